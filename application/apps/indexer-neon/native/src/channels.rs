@@ -1,9 +1,11 @@
+use dlt::dlt_net;
 use dlt::filtering;
 use indexer_base::chunks::serialize_chunks;
 use indexer_base::chunks::Chunk;
-use indexer_base::config::IndexingConfig;
+use indexer_base::config::{IndexMapConfig, IndexingConfig};
 use indexer_base::progress::IndexingProgress;
 use neon::prelude::*;
+use search::searcher::{self, SearchItem, SearchQuery};
 use std::fs;
 use std::path;
 use std::sync::mpsc::{self, RecvTimeoutError};
@@ -14,19 +16,78 @@ use std::time::Duration;
 // Reading from a channel `Receiver` is a blocking operation. This struct
 // wraps the data required to perform a read asynchronously from a libuv
 // thread.
-pub struct EventEmitterTask(Arc<Mutex<mpsc::Receiver<IndexingProgress<Chunk>>>>);
+pub struct EventEmitterTask<T>(Arc<Mutex<mpsc::Receiver<IndexingProgress<T>>>>);
 
-impl EventEmitterTask {
+impl<T> EventEmitterTask<T> {
     pub fn new(
-        event_stream: Arc<Mutex<mpsc::Receiver<IndexingProgress<Chunk>>>>,
-    ) -> EventEmitterTask {
+        event_stream: Arc<Mutex<mpsc::Receiver<IndexingProgress<T>>>>,
+    ) -> EventEmitterTask<T> {
         EventEmitterTask(event_stream)
     }
 }
+
+/// items that are sent to js with a `GotItem` (or similar) event
+pub trait EmittedItem: std::fmt::Debug + Send + 'static {
+    /// sets the event name and the fields of the item
+    fn set_event_fields<'a>(
+        self,
+        cx: &mut TaskContext<'a>,
+        o: Handle<'a, JsObject>,
+    ) -> NeonResult<()>;
+}
+impl EmittedItem for Chunk {
+    fn set_event_fields<'a>(
+        self,
+        cx: &mut TaskContext<'a>,
+        o: Handle<'a, JsObject>,
+    ) -> NeonResult<()> {
+        let event_name = cx.string("GotItem");
+        let rows_start = cx.number(self.r.0 as f64);
+        let rows_end = cx.number(self.r.1 as f64);
+        let bytes_start = cx.number(self.b.0 as f64);
+        let bytes_end = cx.number(self.b.1 as f64);
+
+        o.set(cx, "event", event_name)?;
+        o.set(cx, "rows_start", rows_start)?;
+        o.set(cx, "rows_end", rows_end)?;
+        o.set(cx, "bytes_start", bytes_start)?;
+        o.set(cx, "bytes_end", bytes_end)?;
+        Ok(())
+    }
+}
+impl EmittedItem for SearchItem {
+    fn set_event_fields<'a>(
+        self,
+        cx: &mut TaskContext<'a>,
+        o: Handle<'a, JsObject>,
+    ) -> NeonResult<()> {
+        match self {
+            // chunks of the results file are reported like the ones of an index
+            SearchItem::Chunk(chunk) => chunk.set_event_fields(cx, o),
+            SearchItem::Match(m) => {
+                let event_name = cx.string("GotMatch");
+                let row = cx.number(m.row as f64);
+                let line = cx.number(m.line as f64);
+                let queries = JsArray::new(cx, m.queries.len() as u32);
+                for (i, q) in m.queries.iter().enumerate() {
+                    let query = cx.number(*q as f64);
+                    queries.set(cx, i as u32, query)?;
+                }
+
+                o.set(cx, "event", event_name)?;
+                o.set(cx, "row", row)?;
+                o.set(cx, "line", line)?;
+                o.set(cx, "queries", queries)?;
+                Ok(())
+            }
+        }
+    }
+}
+
 // Implementation of a neon `Task` for `EventEmitterTask`. This task reads
 // from the events channel and calls a JS callback with the data.
-impl Task for EventEmitterTask {
-    type Output = Option<IndexingProgress<Chunk>>;
+impl<T: EmittedItem> Task for EventEmitterTask<T> {
+    type Output = Option<IndexingProgress<T>>;
     type Error = String;
     type JsEvent = JsValue;
 
@@ -70,10 +131,10 @@ impl Task for EventEmitterTask {
     ) -> JsResult<Self::JsEvent> {
         // debug!("complete rs");
         // Receive the event or return early with the error
-        let event: Option<IndexingProgress<Chunk>> = event.or_else(|err| cx.throw_error(&err))?;
+        let event: Option<IndexingProgress<T>> = event.or_else(|err| cx.throw_error(&err))?;
 
         // Timeout occured, return early with `undefined
-        let event: IndexingProgress<Chunk> = match event {
+        let event: IndexingProgress<T> = match event {
             Some(event) => event,
             None => return Ok(JsUndefined::new().upcast()),
         };
@@ -89,18 +150,8 @@ impl Task for EventEmitterTask {
                 o.set(&mut cx, "ellapsed", ticked)?;
                 o.set(&mut cx, "total", total)?;
             }
-            IndexingProgress::GotItem { item: chunk } => {
-                let event_name = cx.string("GotItem");
-                let rows_start = cx.number(chunk.r.0 as f64);
-                let rows_end = cx.number(chunk.r.1 as f64);
-                let bytes_start = cx.number(chunk.b.0 as f64);
-                let bytes_end = cx.number(chunk.b.1 as f64);
-
-                o.set(&mut cx, "event", event_name)?;
-                o.set(&mut cx, "rows_start", rows_start)?;
-                o.set(&mut cx, "rows_end", rows_end)?;
-                o.set(&mut cx, "bytes_start", bytes_start)?;
-                o.set(&mut cx, "bytes_end", bytes_end)?;
+            IndexingProgress::GotItem { item } => {
+                item.set_event_fields(&mut cx, o)?;
             }
             IndexingProgress::Stopped => {
                 trace!("rust: propagate stopped event");
@@ -131,6 +182,8 @@ pub struct IndexingThreadConfig {
     pub append: bool,
     pub tag: String,
     pub timestamps: bool,
+    /// path of `in_file` if it is followed while it grows
+    pub follow: Option<path::PathBuf>,
 }
 impl IndexingEventEmitter {
     pub fn start_indexing_in_thread(
@@ -146,14 +199,23 @@ impl IndexingEventEmitter {
 
         // Spawn a thead to continue running after this method has returned.
         self.task_thread = Some(thread::spawn(move || {
+            // binary index map next to the json map (which the frontend reads)
+            let index_map_path = mapping_out_path.with_extension("idx");
             index_file_with_progress(
                 IndexingConfig {
                     tag: thread_conf.tag.as_str(),
                     chunk_size,
                     in_file: thread_conf.in_file,
+                    zip_entry: None,
                     out_path: &thread_conf.out_path,
                     append,
                     to_stdout: false,
+                    index_map: Some(IndexMapConfig {
+                        path: &index_map_path,
+                        line_offsets: false,
+                    }),
+                    token_index: None,
+                    follow: thread_conf.follow.as_ref().map(path::PathBuf::as_path),
                 },
                 thread_conf.timestamps,
                 mapping_out_path,
@@ -183,14 +245,23 @@ impl IndexingDltEventEmitter {
 
         // Spawn a thead to continue running after this method has returned.
         self.task_thread = Some(thread::spawn(move || {
+            // binary index map next to the json map (which the frontend reads)
+            let index_map_path = mapping_out_path.with_extension("idx");
             index_dlt_file_with_progress(
                 IndexingConfig {
                     tag: thread_conf.tag.as_str(),
                     chunk_size,
                     in_file: thread_conf.in_file,
+                    zip_entry: None,
                     out_path: &thread_conf.out_path,
                     append: thread_conf.append,
                     to_stdout: false,
+                    index_map: Some(IndexMapConfig {
+                        path: &index_map_path,
+                        line_offsets: false,
+                    }),
+                    token_index: None,
+                    follow: thread_conf.follow.as_ref().map(path::PathBuf::as_path),
                 },
                 filter_conf,
                 mapping_out_path,
@@ -202,6 +273,59 @@ impl IndexingDltEventEmitter {
     }
 }
 
+pub struct IndexingDltUdpEventEmitter {
+    pub event_receiver: Arc<Mutex<mpsc::Receiver<IndexingProgress<Chunk>>>>,
+    pub shutdown_sender: mpsc::Sender<()>,
+    pub task_thread: Option<std::thread::JoinHandle<()>>,
+}
+#[derive(Debug)]
+pub struct UdpThreadConfig {
+    pub source: dlt_net::UdpSourceConfig,
+    pub out_path: path::PathBuf,
+    pub append: bool,
+    pub tag: String,
+}
+impl IndexingDltUdpEventEmitter {
+    pub fn start_indexing_dlt_udp_in_thread(
+        self: &mut IndexingDltUdpEventEmitter,
+        shutdown_rx: mpsc::Receiver<()>,
+        chunk_result_sender: mpsc::Sender<IndexingProgress<Chunk>>,
+        mapping_out_path: path::PathBuf,
+        chunk_size: usize,
+        thread_conf: UdpThreadConfig,
+        filter_conf: Option<filtering::DltFilterConfig>,
+    ) {
+        info!("call udp event_thread with chunk size: {}", chunk_size);
+
+        // Spawn a thead to continue running after this method has returned.
+        self.task_thread = Some(thread::spawn(move || {
+            match dlt_net::index_from_udp(
+                &thread_conf.source,
+                dlt_net::SocketIndexingConfig {
+                    tag: thread_conf.tag.as_str(),
+                    chunk_size,
+                    out_path: &thread_conf.out_path,
+                    append: thread_conf.append,
+                    to_stdout: false,
+                },
+                filter_conf,
+                Some(chunk_result_sender.clone()),
+                Some(shutdown_rx),
+                None,
+            ) {
+                Err(why) => {
+                    error!("couldn't process: {}", why);
+                    std::process::exit(2)
+                }
+                Ok(chunks) => {
+                    let _ = serialize_chunks(&chunks, &mapping_out_path);
+                }
+            }
+            debug!("back after DLT UDP indexing finished!",);
+        }));
+    }
+}
+
 pub fn index_dlt_file_with_progress(
     config: IndexingConfig,
     filter_conf: Option<filtering::DltFilterConfig>,
@@ -223,6 +347,9 @@ pub fn index_dlt_file_with_progress(
         filter_conf,
         tx,
         shutdown_receiver,
+        None,
+        1,
+        false,
     ) {
         Err(why) => {
             error!("couldn't process: {}", why);
@@ -251,6 +378,7 @@ pub fn index_file_with_progress(
     match processor::processor::create_index_and_mapping(
         config,
         timestamps,
+        None,
         source_file_size,
         tx,
         shutdown_receiver,
@@ -264,3 +392,54 @@ pub fn index_file_with_progress(
         }
     }
 }
+
+pub struct SearchEventEmitter {
+    pub event_receiver: Arc<Mutex<mpsc::Receiver<IndexingProgress<SearchItem>>>>,
+    pub shutdown_sender: mpsc::Sender<()>,
+    pub task_thread: Option<std::thread::JoinHandle<()>>,
+}
+#[derive(Debug)]
+pub struct SearchThreadConfig {
+    pub in_path: path::PathBuf,
+    pub out_path: path::PathBuf,
+    pub queries: Vec<SearchQuery>,
+}
+impl SearchEventEmitter {
+    pub fn start_search_in_thread(
+        self: &mut SearchEventEmitter,
+        shutdown_rx: mpsc::Receiver<()>,
+        result_sender: mpsc::Sender<IndexingProgress<SearchItem>>,
+        mapping_out_path: path::PathBuf,
+        chunk_size: usize,
+        thread_conf: SearchThreadConfig,
+    ) {
+        info!("call search thread with chunk size: {}", chunk_size);
+
+        // Spawn a thead to continue running after this method has returned.
+        self.task_thread = Some(thread::spawn(move || {
+            match searcher::search_indexed_file(
+                searcher::SearchConfig {
+                    in_path: &thread_conf.in_path,
+                    out_path: &thread_conf.out_path,
+                    queries: &thread_conf.queries,
+                    chunk_size,
+                    to_stdout: false,
+                    index_map: None,
+                    map_path: None,
+                    source_path: None,
+                },
+                Some(result_sender),
+                Some(shutdown_rx),
+            ) {
+                Err(why) => {
+                    error!("couldn't search: {}", why);
+                    std::process::exit(2)
+                }
+                Ok(result) => {
+                    let _ = serialize_chunks(&result.chunks, &mapping_out_path);
+                }
+            }
+            debug!("back after search finished!",);
+        }));
+    }
+}
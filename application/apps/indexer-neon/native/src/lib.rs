@@ -6,13 +6,15 @@ extern crate indexer_base;
 extern crate log;
 extern crate merging;
 extern crate processor;
+extern crate search;
 extern crate serde;
 
 mod channels;
 mod logging;
 use crate::logging::SimpleLogger;
 use channels::{
-    EventEmitterTask, IndexingDltEventEmitter, IndexingEventEmitter, IndexingThreadConfig,
+    EventEmitterTask, IndexingDltEventEmitter, IndexingDltUdpEventEmitter, IndexingEventEmitter,
+    IndexingThreadConfig, SearchEventEmitter, SearchThreadConfig, UdpThreadConfig,
 };
 use neon::prelude::*;
 use processor::parse;
@@ -190,7 +192,7 @@ fn dlt_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
             std::process::exit(2)
         }
     };
-    match dlt::dlt_parse::get_dlt_file_info(&f) {
+    match dlt::dlt_parse::get_dlt_file_info(&f, None) {
         Err(why) => {
             error!("couldn't collect statistics: {}", why);
             std::process::exit(2)
@@ -201,6 +203,21 @@ fn dlt_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
         }
     }
 }
+fn export_dlt(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let file_name = cx.argument::<JsString>(0)?.value();
+    let out_path = path::PathBuf::from(cx.argument::<JsString>(1)?.value().as_str());
+    let arg_export_conf = cx.argument::<JsValue>(2)?;
+    let export_conf: dlt::export::ExportConfig = neon_serde::from_value(&mut cx, arg_export_conf)?;
+    trace!("{:?}", export_conf);
+    let f = match fs::File::open(&file_name) {
+        Ok(file) => file,
+        Err(e) => return cx.throw_error(format!("could not open {}: {}", file_name, e)),
+    };
+    match dlt::export::export_dlt_file(f, &out_path, export_conf, None) {
+        Ok(exported) => Ok(cx.number(exported as f64)),
+        Err(e) => cx.throw_error(format!("couldn't export: {}", e)),
+    }
+}
 
 // interface of the Rust code for js, exposes the `poll` and `shutdown` methods
 declare_types! {
@@ -212,6 +229,7 @@ declare_types! {
             let append: bool = cx.argument::<JsBoolean>(3)?.value();
             let timestamps: bool = cx.argument::<JsBoolean>(4)?.value();
             let chunk_size: usize = cx.argument::<JsNumber>(5)?.value() as usize;
+            let follow: bool = cx.argument::<JsBoolean>(6)?.value();
             let mapping_out_path: path::PathBuf = path::PathBuf::from(file.to_string() + ".map.json");
             let (shutdown_sender, shutdown_receiver) = mpsc::channel();
 
@@ -239,6 +257,7 @@ declare_types! {
                     append,
                     tag,
                     timestamps,
+                    follow: if follow { Some(path::PathBuf::from(&file)) } else { None },
                 }
             );
             Ok(emitter)
@@ -285,7 +304,14 @@ declare_types! {
             let chunk_size: usize = cx.argument::<JsNumber>(4)?.value() as usize;
             let arg_filter_conf = cx.argument::<JsValue>(5)?;
             let filter_conf: dlt::filtering::DltFilterConfig = neon_serde::from_value(&mut cx, arg_filter_conf)?;
+            let follow: bool = cx.argument::<JsBoolean>(6)?.value();
             trace!("{:?}", filter_conf);
+            if let Some(expression) = &filter_conf.expression {
+                // report invalid expressions right away instead of in the indexing thread
+                if let Err(e) = dlt::filtering::expression::FilterExpression::parse(expression) {
+                    return cx.throw_error(format!("{}", e));
+                }
+            }
 
             let mapping_out_path: path::PathBuf = path::PathBuf::from(file.to_string() + ".map.json");
             let shutdown_channel = mpsc::channel();
@@ -314,6 +340,7 @@ declare_types! {
                     append,
                     tag,
                     timestamps: false,
+                    follow: if follow { Some(path::PathBuf::from(&file)) } else { None },
                 },
                 Some(filter_conf)
             );
@@ -335,6 +362,139 @@ declare_types! {
             Ok(JsUndefined::new().upcast())
         }
 
+        // The shutdown method may be called to stop the Rust thread. It
+        // will error if the thread has already been destroyed.
+        method shutdown(mut cx) {
+            trace!("shutdown called");
+            let this = cx.this();
+
+            // Unwrap the shutdown channel and send a shutdown command
+            cx.borrow(&this, |emitter| {
+                match emitter.shutdown_sender.send(()) {
+                    Err(e) => trace!("error happened when sending: {}", e),
+                    Ok(()) => trace!("sent command Shutdown")
+                }
+            });
+            Ok(JsUndefined::new().upcast())
+        }
+    }
+    pub class JsDltUdpIndexerEventEmitter for IndexingDltUdpEventEmitter {
+        init(mut cx) {
+            trace!("Rust: JsDltUdpIndexerEventEmitter");
+            let arg_source = cx.argument::<JsValue>(0)?;
+            let source: dlt::dlt_net::UdpSourceConfig = neon_serde::from_value(&mut cx, arg_source)?;
+            let tag = cx.argument::<JsString>(1)?.value();
+            let out_path = path::PathBuf::from(cx.argument::<JsString>(2)?.value().as_str());
+            let append: bool = cx.argument::<JsBoolean>(3)?.value();
+            let chunk_size: usize = cx.argument::<JsNumber>(4)?.value() as usize;
+            let arg_filter_conf = cx.argument::<JsValue>(5)?;
+            let filter_conf: dlt::filtering::DltFilterConfig = neon_serde::from_value(&mut cx, arg_filter_conf)?;
+            trace!("{:?}, {:?}", source, filter_conf);
+
+            let mapping_out_path: path::PathBuf = path::PathBuf::from(out_path.to_string_lossy().to_string() + ".map.json");
+            let shutdown_channel = mpsc::channel();
+            let chunk_result_channel = mpsc::channel();
+            let mut emitter = IndexingDltUdpEventEmitter {
+                event_receiver: Arc::new(Mutex::new(chunk_result_channel.1)),
+                shutdown_sender: shutdown_channel.0,
+                task_thread: None,
+            };
+            emitter.start_indexing_dlt_udp_in_thread(shutdown_channel.1,
+                chunk_result_channel.0,
+                mapping_out_path,
+                chunk_size,
+                UdpThreadConfig {
+                    source,
+                    out_path,
+                    append,
+                    tag,
+                },
+                Some(filter_conf)
+            );
+            Ok(emitter)
+        }
+
+        // will be called by JS to receive data in a loop, but care should be taken to only call it once at a time.
+        method poll(mut cx) {
+            // The callback to be executed when data is available
+            let cb = cx.argument::<JsFunction>(0)?;
+            let this = cx.this();
+
+            // Create an asynchronously `EventEmitterTask` to receive data
+            let events = cx.borrow(&this, |emitter| Arc::clone(&emitter.event_receiver));
+            let emitter = EventEmitterTask::new(events);
+
+            // Schedule the task on the `libuv` thread pool
+            emitter.schedule(cb);
+            Ok(JsUndefined::new().upcast())
+        }
+
+        // The shutdown method may be called to stop the Rust thread. It
+        // will error if the thread has already been destroyed.
+        method shutdown(mut cx) {
+            trace!("shutdown called");
+            let this = cx.this();
+
+            // Unwrap the shutdown channel and send a shutdown command
+            cx.borrow(&this, |emitter| {
+                match emitter.shutdown_sender.send(()) {
+                    Err(e) => trace!("error happened when sending: {}", e),
+                    Ok(()) => trace!("sent command Shutdown")
+                }
+            });
+            Ok(JsUndefined::new().upcast())
+        }
+    }
+    pub class JsSearchEventEmitter for SearchEventEmitter {
+        init(mut cx) {
+            trace!("Rust: JsSearchEventEmitter");
+            let in_path = path::PathBuf::from(cx.argument::<JsString>(0)?.value().as_str());
+            let out_path = path::PathBuf::from(cx.argument::<JsString>(1)?.value().as_str());
+            let arg_queries = cx.argument::<JsValue>(2)?;
+            let queries: Vec<search::searcher::SearchQuery> = neon_serde::from_value(&mut cx, arg_queries)?;
+            let chunk_size: usize = cx.argument::<JsNumber>(3)?.value() as usize;
+            trace!("{:?}", queries);
+            // report invalid queries right away instead of in the search thread
+            if let Err(e) = search::searcher::query_set(&queries) {
+                return cx.throw_error(format!("{}", e));
+            }
+
+            let mapping_out_path: path::PathBuf = path::PathBuf::from(out_path.to_string_lossy().to_string() + ".map.json");
+            let shutdown_channel = mpsc::channel();
+            let result_channel = mpsc::channel();
+            let mut emitter = SearchEventEmitter {
+                event_receiver: Arc::new(Mutex::new(result_channel.1)),
+                shutdown_sender: shutdown_channel.0,
+                task_thread: None,
+            };
+            emitter.start_search_in_thread(shutdown_channel.1,
+                result_channel.0,
+                mapping_out_path,
+                chunk_size,
+                SearchThreadConfig {
+                    in_path,
+                    out_path,
+                    queries,
+                }
+            );
+            Ok(emitter)
+        }
+
+        // will be called by JS to receive data in a loop, but care should be taken to only call it once at a time.
+        method poll(mut cx) {
+            // The callback to be executed when data is available
+            let cb = cx.argument::<JsFunction>(0)?;
+            let this = cx.this();
+
+            // Create an asynchronously `EventEmitterTask` to receive data
+            let events = cx.borrow(&this, |emitter| Arc::clone(&emitter.event_receiver));
+            let emitter = EventEmitterTask::new(events);
+
+            // Schedule the task on the `libuv` thread pool
+            emitter.schedule(cb);
+            Ok(JsUndefined::new().upcast())
+        }
+
         // The shutdown method may be called to stop the Rust thread. It
         // will error if the thread has already been destroyed.
         method shutdown(mut cx) {
@@ -368,7 +528,10 @@ register_module!(mut cx, {
     cx.export_function("mergeFiles", merge_files)?;
     cx.export_function("concatFiles", concat_files)?;
     cx.export_function("dltStats", dlt_stats)?;
+    cx.export_function("exportDlt", export_dlt)?;
     cx.export_class::<JsIndexerEventEmitter>("RustIndexerEventEmitter")?;
     cx.export_class::<JsDltIndexerEventEmitter>("RustDltIndexerEventEmitter")?;
+    cx.export_class::<JsDltUdpIndexerEventEmitter>("RustDltUdpIndexerEventEmitter")?;
+    cx.export_class::<JsSearchEventEmitter>("RustSearchEventEmitter")?;
     Ok(())
 });
@@ -2,21 +2,37 @@ const addon = require("../native");
 import { log } from "./logging";
 import { DltFilterConf } from "./dlt";
 import { AsyncResult, ITicks, IChunk } from "./progress";
-import { NativeEventEmitter, RustDltIndexerChannel } from "./emitter";
+import { NativeEventEmitter, RustDltIndexerChannel, RustDltUdpIndexerChannel } from "./emitter";
 
+export interface IPayloadPattern {
+    pattern: String;
+    is_regex?: boolean;
+    ignore_case?: boolean;
+}
 export interface DltFilterConf {
     min_log_level?: DltLogLevel;
+    // minimum level per app id / per app id and context id (same values as DltLogLevel)
+    app_min_log_levels?: { [app_id: string]: number };
+    context_min_log_levels?: { [app_id: string]: { [context_id: string]: number } };
     app_ids?: Array<String>;
     ecu_ids?: Array<String>;
     context_ids?: Array<String>;
+    include_payload?: Array<IPayloadPattern>;
+    exclude_payload?: Array<IPayloadPattern>;
+    // boolean filter expression, e.g. `(ecu == "ECU1" && level <= WARN) || apid in ["DIAG", "SYS"]`
+    expression?: String;
+    // inclusive time window, posix timestamps in ms (or ecu uptime in 0.1 ms)
+    from?: number;
+    to?: number;
+    time_base?: "storage" | "uptime";
 }
 export enum DltLogLevel {
-    Fatal = 0x1 << 4,
-    Error = 0x2 << 4,
-    Warn = 0x3 << 4,
-    Info = 0x4 << 4,
-    Debug = 0x5 << 4,
-    Verbose = 0x6 << 4,
+    Fatal = 1,
+    Error = 2,
+    Warn = 3,
+    Info = 4,
+    Debug = 5,
+    Verbose = 6,
 }
 export interface IIndexDltParams {
     dltFile: String;
@@ -28,9 +44,44 @@ export interface IIndexDltParams {
     stdout: boolean;
     statusUpdates: boolean;
 }
+export interface IMulticastInfo {
+    multiaddr: String;
+    interface?: String;
+}
+export interface IUdpSourceConfig {
+    bind_addr: String;
+    multicast: Array<IMulticastInfo>;
+}
+export interface IIndexDltUdpParams {
+    source: IUdpSourceConfig;
+    filterConfig?: DltFilterConf;
+    tag: String;
+    out: String;
+    chunk_size?: number;
+    append: boolean;
+}
+export interface ILineRange {
+    first: number;
+    last: number;
+}
+export interface ITimeRange {
+    start?: number;
+    end?: number;
+}
+export interface IExportDltParams {
+    dltFile: String;
+    out: String;
+    filterConfig?: DltFilterConf;
+    lines?: ILineRange;
+    time?: ITimeRange;
+}
 export function dltStats(dltFile: String) {
     return addon.dltStats(dltFile);
 }
+// returns the number of exported messages
+export function exportDlt({ dltFile, out, filterConfig, lines, time }: IExportDltParams): number {
+    return addon.exportDlt(dltFile, out, { filter: filterConfig, lines, time });
+}
 export function indexDltFile({
     dltFile,
     filterConfig,
@@ -72,6 +123,7 @@ export function indexDltAsync(
             append,
             chunk_size,
             filterConfig,
+            false,
         );
         const emitter = new NativeEventEmitter(channel);
         let timeout = setTimeout(function() {
@@ -103,3 +155,65 @@ export function indexDltAsync(
         });
     });
 }
+// indexes the dlt file and the messages appended to it (also after truncation
+// or rotation) until the returned emitter is asked to shut down
+export function followDltAsync(
+    { dltFile, filterConfig, tag, out, chunk_size, append }: IIndexDltParams,
+    onChunk: (chunk: IChunk) => any,
+): [NativeEventEmitter, Promise<AsyncResult>] {
+    const channel = new RustDltIndexerChannel(
+        dltFile,
+        tag,
+        out,
+        append,
+        chunk_size !== undefined ? chunk_size : 5000,
+        filterConfig !== undefined ? filterConfig : {},
+        true,
+    );
+    const emitter = new NativeEventEmitter(channel);
+    const done = new Promise<AsyncResult>((resolve, reject) => {
+        emitter.on(NativeEventEmitter.EVENTS.GotItem, onChunk);
+        emitter.on(NativeEventEmitter.EVENTS.Error, (e: any) => {
+            log("we got an error: " + e);
+            emitter.requestShutdown();
+        });
+        emitter.on(NativeEventEmitter.EVENTS.Finished, () => {
+            log("stopped following " + dltFile);
+            emitter.shutdownAcknowledged(() => {
+                log("shutdown completed");
+                resolve(AsyncResult.Completed);
+            });
+        });
+    });
+    return [emitter, done];
+}
+// receives dlt messages until the returned emitter is asked to shut down
+export function indexDltUdpAsync(
+    { source, filterConfig, tag, out, chunk_size, append }: IIndexDltUdpParams,
+    onChunk: (chunk: IChunk) => any,
+): [NativeEventEmitter, Promise<AsyncResult>] {
+    const channel = new RustDltUdpIndexerChannel(
+        source,
+        tag,
+        out,
+        append,
+        chunk_size !== undefined ? chunk_size : 500,
+        filterConfig !== undefined ? filterConfig : {},
+    );
+    const emitter = new NativeEventEmitter(channel);
+    const done = new Promise<AsyncResult>((resolve, reject) => {
+        emitter.on(NativeEventEmitter.EVENTS.GotItem, onChunk);
+        emitter.on(NativeEventEmitter.EVENTS.Error, (e: any) => {
+            log("we got an error: " + e);
+            emitter.requestShutdown();
+        });
+        emitter.on(NativeEventEmitter.EVENTS.Finished, () => {
+            log("udp receiver finished");
+            emitter.shutdownAcknowledged(() => {
+                log("shutdown completed");
+                resolve(AsyncResult.Completed);
+            });
+        });
+    });
+    return [emitter, done];
+}
@@ -1,15 +1,25 @@
 const {
     RustIndexerEventEmitter: RustIndexerChannel,
     RustDltIndexerEventEmitter: RustDltIndexerChannel,
+    RustDltUdpIndexerEventEmitter: RustDltUdpIndexerChannel,
+    RustSearchEventEmitter: RustSearchChannel,
 } = require("../native/index.node");
 const { EventEmitter } = require("events");
-export { EventEmitter, RustIndexerChannel, RustDltIndexerChannel };
+export {
+    EventEmitter,
+    RustIndexerChannel,
+    RustDltIndexerChannel,
+    RustDltUdpIndexerChannel,
+    RustSearchChannel,
+};
 const { promisify } = require("util");
 import { log } from "./logging";
 
 export enum ChannelType {
     IndexingChannel,
     DltIndexingChannel,
+    DltUdpIndexingChannel,
+    SearchChannel,
 }
 export interface IChannel {
     poll: () => void;
@@ -20,6 +30,7 @@ export interface IChannel {
 export class NativeEventEmitter extends EventEmitter {
     public static EVENTS = {
         GotItem: "GotItem",
+        GotMatch: "GotMatch",
         Progress: "Progress",
         Stopped: "Stopped",
         Finished: "Finished",
@@ -1,6 +1,7 @@
 import {
     indexFile,
     indexAsync,
+    followAsync,
     IIndexerParams,
     detectTimestampInString,
     detectTimestampFormatInFile,
@@ -8,9 +9,22 @@ import {
     IFilePath,
 } from "./processor";
 import { IConcatFilesParams, IMergeParams, mergeFiles, concatFiles } from "./merger";
-import { IIndexDltParams, dltStats, indexDltFile, indexDltAsync, DltFilterConf } from "./dlt";
+import {
+    IIndexDltParams,
+    IIndexDltUdpParams,
+    IExportDltParams,
+    dltStats,
+    exportDlt,
+    indexDltFile,
+    indexDltAsync,
+    followDltAsync,
+    indexDltUdpAsync,
+    DltFilterConf,
+} from "./dlt";
+import { ISearchParams, ISearchMatch, ISearchQuery, searchAsync } from "./search";
+import { NativeEventEmitter } from "./emitter";
 import { ITicks, AsyncResult, IChunk } from "./progress";
-export { ITicks, DltFilterConf };
+export { ITicks, DltFilterConf, ISearchParams, ISearchMatch, ISearchQuery };
 
 export interface Foo {
   todo: number;
@@ -26,10 +40,59 @@ export interface LevelDistribution {
     log_verbose: number;
     log_invalid: number;
 }
+export interface ILifecycle {
+    id: number;
+    ecu_id: String;
+    // posix timestamps in ms, null if unknown
+    start: number | null;
+    end: number | null;
+    message_count: number;
+}
+export interface IIdCombination {
+    ecu_id: String;
+    app_id: String;
+    context_id: String;
+    count: number;
+    levels: LevelDistribution;
+}
+// first and last storage time, posix timestamps in ms
+export interface ITimeSpan {
+    first: number;
+    last: number;
+}
+export interface IMessageRate {
+    start: number;
+    bucket_ms: number;
+    counts: Array<number>;
+}
+export interface IMessageTypeDistribution {
+    log: number;
+    app_trace: number;
+    network_trace: number;
+    control: number;
+    unknown: number;
+    no_type: number;
+}
+export interface IPayloadSizes {
+    min: number;
+    max: number;
+    average: number;
+    total: number;
+}
 export interface StatisticInfo {
     app_ids: Array<[String, LevelDistribution]>;
     context_ids: Array<[String, LevelDistribution]>;
     ecu_ids: Array<[String, LevelDistribution]>;
+    lifecycles: Array<ILifecycle>;
+    ecu_app_context_ids: Array<IIdCombination>;
+    app_id_times: Array<[String, ITimeSpan]>;
+    context_id_times: Array<[String, ITimeSpan]>;
+    ecu_id_times: Array<[String, ITimeSpan]>;
+    message_rate: IMessageRate | null;
+    verbose: number;
+    non_verbose: number;
+    message_types: IMessageTypeDistribution;
+    payload_sizes: IPayloadSizes | null;
 }
 
 export interface IChipmunkIndexer {
@@ -43,9 +106,17 @@ export interface IChipmunkIndexer {
         onChunk: (chunk: IChunk) => any,
         tag: string,
     ) => Promise<AsyncResult>;
+    followAsync: (
+        chunkSize: number,
+        fileToFollow: string,
+        outPath: string,
+        onChunk: (chunk: IChunk) => any,
+        tag: string,
+    ) => [NativeEventEmitter, Promise<AsyncResult>];
     mergeFiles: (params: IMergeParams) => boolean;
     concatFiles: (params: IConcatFilesParams) => boolean;
     dltStats: (dltFile: String) => StatisticInfo;
+    exportDlt: (params: IExportDltParams) => number;
     indexDltFile: (params: IIndexDltParams) => boolean;
     indexDltAsync: (
         params: IIndexDltParams,
@@ -53,20 +124,40 @@ export interface IChipmunkIndexer {
         onProgress: (ticks: ITicks) => any,
         onChunk: (chunk: IChunk) => any,
     ) => Promise<AsyncResult>;
+    followDltAsync: (
+        params: IIndexDltParams,
+        onChunk: (chunk: IChunk) => any,
+    ) => [NativeEventEmitter, Promise<AsyncResult>];
+    indexDltUdpAsync: (
+        params: IIndexDltUdpParams,
+        onChunk: (chunk: IChunk) => any,
+    ) => [NativeEventEmitter, Promise<AsyncResult>];
     detectTimestampInString: (input: string) => string;
     detectTimestampFormatInFile: (input: string) => string;
     detectTimestampFormatsInFiles: (conf: Array<IFilePath>) => string;
+    searchAsync: (
+        params: ISearchParams,
+        maxTime: number,
+        onProgress: (ticks: ITicks) => any,
+        onChunk: (chunk: IChunk) => any,
+        onMatch: (match: ISearchMatch) => any,
+    ) => Promise<AsyncResult>;
 }
 
 export const library: IChipmunkIndexer = {
     indexFile,
     indexAsync,
+    followAsync,
     mergeFiles,
     concatFiles,
     dltStats,
+    exportDlt,
     indexDltFile,
     indexDltAsync,
+    followDltAsync,
+    indexDltUdpAsync,
     detectTimestampInString,
     detectTimestampFormatInFile,
     detectTimestampFormatsInFiles,
+    searchAsync,
 };
@@ -31,7 +31,7 @@ export function indexAsync(
         let chunks: number = 0;
         const append = false; // TODO support append option
         const timestamps = false; // TODO support timestamps option
-        const channel = new RustIndexerChannel(fileToIndex, tag, outPath, append, timestamps, chunkSize);
+        const channel = new RustIndexerChannel(fileToIndex, tag, outPath, append, timestamps, chunkSize, false);
         const emitter = new NativeEventEmitter(channel);
         let timeout = setTimeout(function() {
             log("TIMED OUT ====> shutting down");
@@ -62,6 +62,35 @@ export function indexAsync(
         });
     });
 }
+// indexes the file and what is appended to it (also after truncation or
+// rotation) until the returned emitter is asked to shut down
+export function followAsync(
+    chunkSize: number,
+    fileToFollow: string,
+    outPath: string,
+    onChunk: (chunk: IChunk) => any,
+    tag: string,
+): [NativeEventEmitter, Promise<AsyncResult>] {
+    const append = false;
+    const timestamps = false;
+    const channel = new RustIndexerChannel(fileToFollow, tag, outPath, append, timestamps, chunkSize, true);
+    const emitter = new NativeEventEmitter(channel);
+    const done = new Promise<AsyncResult>((resolve, reject) => {
+        emitter.on(NativeEventEmitter.EVENTS.GotItem, onChunk);
+        emitter.on(NativeEventEmitter.EVENTS.Error, (e: any) => {
+            log("we got an error: " + e);
+            emitter.requestShutdown();
+        });
+        emitter.on(NativeEventEmitter.EVENTS.Finished, () => {
+            log("stopped following " + fileToFollow);
+            emitter.shutdownAcknowledged(() => {
+                log("shutdown completed");
+                resolve(AsyncResult.Completed);
+            });
+        });
+    });
+    return [emitter, done];
+}
 export function indexFile({
     file,
     tag,
@@ -0,0 +1,66 @@
+import { log } from "./logging";
+import { AsyncResult, ITicks, IChunk } from "./progress";
+import { NativeEventEmitter, RustSearchChannel } from "./emitter";
+
+export interface ISearchQuery {
+    pattern: string;
+    literal?: boolean;
+    ignore_case?: boolean;
+}
+export interface ISearchParams {
+    file: string;
+    out: string;
+    queries: Array<ISearchQuery>;
+    chunk_size?: number;
+}
+export interface ISearchMatch {
+    row: number;
+    line: number;
+    queries: Array<number>;
+}
+
+export function searchAsync(
+    { file, out, queries, chunk_size }: ISearchParams,
+    maxTime: number,
+    onProgress: (ticks: ITicks) => any,
+    onChunk: (chunk: IChunk) => any,
+    onMatch: (match: ISearchMatch) => any,
+): Promise<AsyncResult> {
+    return new Promise<AsyncResult>((resolve, reject) => {
+        const channel = new RustSearchChannel(
+            file,
+            out,
+            queries,
+            chunk_size !== undefined ? chunk_size : 500,
+        );
+        const emitter = new NativeEventEmitter(channel);
+        let timeout = setTimeout(function() {
+            log("TIMED OUT ====> shutting down");
+            emitter.requestShutdown();
+        }, maxTime);
+        emitter.on(NativeEventEmitter.EVENTS.GotItem, onChunk);
+        emitter.on(NativeEventEmitter.EVENTS.GotMatch, onMatch);
+        emitter.on(NativeEventEmitter.EVENTS.Progress, onProgress);
+        emitter.on(NativeEventEmitter.EVENTS.Stopped, () => {
+            log("search was stopped");
+            clearTimeout(timeout);
+            emitter.shutdownAcknowledged(() => {
+                log("shutdown completed");
+                resolve(AsyncResult.Aborted);
+            });
+        });
+        emitter.on(NativeEventEmitter.EVENTS.Error, (e: any) => {
+            log("we got an error: " + e);
+            clearTimeout(timeout);
+            emitter.requestShutdown();
+        });
+        emitter.on(NativeEventEmitter.EVENTS.Finished, () => {
+            log("search finished");
+            clearTimeout(timeout);
+            emitter.shutdownAcknowledged(() => {
+                log("shutdown completed");
+                resolve(AsyncResult.Completed);
+            });
+        });
+    });
+}
@@ -5,6 +5,11 @@ extern crate dlt;
 use dlt::dlt::*;
 use bytes::{BytesMut};
 use criterion::Criterion;
+use dlt::message_ref::{dlt_message_ref, MappedDltFile, ValueRef};
+use std::fs;
+use tempdir::TempDir;
+
+const FILE_MESSAGE_COUNT: u32 = 100_000;
 
 fn dlt_benchmark(c: &mut Criterion) {
     c.bench_function("format header", |b| {
@@ -50,6 +55,7 @@ fn dlt_benchmark(c: &mut Criterion) {
             type_info,
             name: Some("foo".to_string()),
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::Bool(true),
         };
@@ -72,5 +78,113 @@ fn dlt_parse_benchmark(c: &mut Criterion) {
     });
 }
 
+fn verbose_message(counter: u32) -> Message {
+    let argument = |kind, value| Argument {
+        type_info: TypeInfo {
+            kind,
+            coding: StringCoding::UTF8,
+            has_variable_info: false,
+            has_trace_info: false,
+        },
+        name: None,
+        unit: None,
+        trace_info: None,
+        fixed_point: None,
+        value,
+    };
+    let mut message = Message {
+        storage_header: Some(StorageHeader {
+            timestamp: DltTimeStamp {
+                seconds: 0x4DC9_2C26 + counter / 1000,
+                microseconds: (counter % 1000) * 1000,
+            },
+            ecu_id: "ECU1".to_string(),
+        }),
+        header: StandardHeader {
+            version: 1,
+            has_extended_header: true,
+            big_endian: true,
+            message_counter: (counter % 256) as u8,
+            overall_length: 0,
+            ecu_id: Some("ECU1".to_string()),
+            session_id: None,
+            timestamp: Some(counter * 10),
+        },
+        extended_header: Some(ExtendedHeader {
+            argument_count: 2,
+            verbose: true,
+            message_type: MessageType::Log(LogLevel::Info),
+            application_id: "APP".to_string(),
+            context_id: format!("C{}", counter % 8),
+        }),
+        payload: Payload::Verbose(vec![
+            argument(
+                TypeInfoKind::StringType,
+                Value::StringVal(format!("benchmark message number {}", counter)),
+            ),
+            argument(
+                TypeInfoKind::Unsigned(TypeLength::BitLength32, false),
+                Value::U32(counter),
+            ),
+        ]),
+    };
+    message.header.overall_length = message.as_bytes_without_storage_header().len() as u16;
+    message
+}
+/// compares the owned parser with the zero-copy `MessageRef`
+fn dlt_message_ref_benchmark(c: &mut Criterion) {
+    let bytes = verbose_message(42).as_bytes();
+    let owned_bytes = bytes.clone();
+    c.bench_function("parse message (owned)", move |b| {
+        b.iter(|| dlt::dlt_parse::dlt_message(&owned_bytes, None, None, None))
+    });
+    c.bench_function("parse message (ref)", move |b| {
+        b.iter(|| {
+            let (_, msg) = dlt_message_ref(&bytes).expect("could not parse message");
+            msg.arguments()
+                .filter_map(Result::ok)
+                .map(|a| match a.value {
+                    ValueRef::StringVal(s) => s.len(),
+                    _ => 0,
+                })
+                .sum::<usize>()
+        })
+    });
+
+    let tmp_dir = TempDir::new("dlt_bench").expect("could not create temp dir");
+    let path = tmp_dir.path().join("bench.dlt");
+    let content: Vec<u8> = (0..FILE_MESSAGE_COUNT)
+        .flat_map(|i| verbose_message(i).as_bytes())
+        .collect();
+    fs::write(&path, &content).expect("could not write dlt file");
+    let in_path = path.clone();
+    c.bench_function("file info (buffered)", move |b| {
+        b.iter(|| {
+            let file = fs::File::open(&in_path).expect("could not open dlt file");
+            dlt::dlt_parse::get_dlt_file_info(&file, None).expect("could not get info")
+        })
+    });
+    let in_path = path.clone();
+    c.bench_function("file info (memory mapped)", move |b| {
+        b.iter(|| {
+            let dlt_file = MappedDltFile::open(&in_path).expect("could not map dlt file");
+            dlt::dlt_parse::get_mapped_dlt_file_info(&dlt_file, None).expect("could not get info")
+        })
+    });
+    c.bench_function("iterate messages (memory mapped)", move |b| {
+        // keep the temp dir alive as long as the benchmark runs
+        let _dir = &tmp_dir;
+        b.iter(|| {
+            let dlt_file = MappedDltFile::open(&path).expect("could not map dlt file");
+            dlt_file.messages().count()
+        })
+    });
+}
+
 criterion_group!(benches, dlt_benchmark, dlt_parse_benchmark);
-criterion_main!(benches);
+criterion_group! {
+    name = message_ref_benches;
+    config = Criterion::default().sample_size(10);
+    targets = dlt_message_ref_benchmark
+}
+criterion_main!(benches, message_ref_benches);
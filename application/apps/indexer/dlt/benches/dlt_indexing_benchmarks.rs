@@ -0,0 +1,139 @@
+#[macro_use]
+extern crate criterion;
+extern crate dlt;
+
+use criterion::Criterion;
+use dlt::dlt::*;
+use indexer_base::config::IndexingConfig;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+
+const MESSAGE_COUNT: u32 = 200_000;
+
+fn message(counter: u32) -> Message {
+    let mut message = Message {
+        storage_header: Some(StorageHeader {
+            timestamp: DltTimeStamp {
+                seconds: 0x4DC9_2C26 + counter / 1000,
+                microseconds: (counter % 1000) * 1000,
+            },
+            ecu_id: "ECU1".to_string(),
+        }),
+        header: StandardHeader {
+            version: 1,
+            has_extended_header: true,
+            big_endian: true,
+            message_counter: (counter % 256) as u8,
+            overall_length: 0,
+            ecu_id: Some("ECU1".to_string()),
+            session_id: None,
+            timestamp: Some(counter * 10),
+        },
+        extended_header: Some(ExtendedHeader {
+            argument_count: 2,
+            verbose: true,
+            message_type: MessageType::Log(LogLevel::Info),
+            application_id: "APP".to_string(),
+            context_id: format!("C{}", counter % 8),
+        }),
+        payload: Payload::Verbose(vec![
+            Argument {
+                type_info: TypeInfo {
+                    kind: TypeInfoKind::StringType,
+                    coding: StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: Value::StringVal(format!("benchmark message number {}", counter)),
+            },
+            Argument {
+                type_info: TypeInfo {
+                    kind: TypeInfoKind::Unsigned(TypeLength::BitLength32, false),
+                    coding: StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: Value::U32(counter),
+            },
+        ]),
+    };
+    message.header.overall_length = message.as_bytes_without_storage_header().len() as u16;
+    message
+}
+
+fn indexing_config<'a>(in_path: &Path, out_path: &'a PathBuf) -> IndexingConfig<'a> {
+    IndexingConfig {
+        tag: "TAG",
+        chunk_size: 500,
+        in_file: fs::File::open(in_path).expect("could not open dlt file"),
+        zip_entry: None,
+        out_path,
+        append: false,
+        to_stdout: false,
+        index_map: None,
+        token_index: None,
+        follow: None,
+    }
+}
+
+fn dlt_indexing_benchmark(c: &mut Criterion) {
+    let tmp_dir = TempDir::new("dlt_bench").expect("could not create temp dir");
+    let in_path = tmp_dir.path().join("bench.dlt");
+    let content: Vec<u8> = (0..MESSAGE_COUNT)
+        .flat_map(|i| message(i).as_bytes())
+        .collect();
+    fs::write(&in_path, &content).expect("could not write dlt file");
+    let out_path = tmp_dir.path().join("bench.out");
+
+    let paths = (in_path.clone(), out_path.clone());
+    c.bench_function("index dlt file", move |b| {
+        b.iter(|| {
+            dlt::dlt_parse::index_dlt_file(
+                indexing_config(&paths.0, &paths.1),
+                None,
+                0,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+        })
+    });
+    for &threads in &[2, 4, 0] {
+        let paths = (in_path.clone(), out_path.clone());
+        c.bench_function(
+            &format!("index dlt file parallel ({} threads)", threads),
+            move |b| {
+                b.iter(|| {
+                    dlt::parallel::index_dlt_file_parallel(
+                        indexing_config(&paths.0, &paths.1),
+                        None,
+                        0,
+                        None,
+                        None,
+                        None,
+                        None,
+                        threads,
+                    )
+                })
+            },
+        );
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = dlt_indexing_benchmark
+}
+criterion_main!(benches);
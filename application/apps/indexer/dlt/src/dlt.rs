@@ -12,7 +12,7 @@
 #![allow(clippy::unit_arg)]
 
 use indexer_base::error_reporter::*;
-use bytes::{ByteOrder, BytesMut, BufMut};
+use bytes::{BigEndian, ByteOrder, BytesMut, BufMut, LittleEndian};
 use chrono::{NaiveDateTime};
 use chrono::prelude::{Utc, DateTime};
 use std::fmt;
@@ -25,6 +25,7 @@ use proptest_derive::Arbitrary;
 use proptest::prelude::*;
 
 use std::str;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, PartialEq, Arbitrary)]
 pub struct DltTimeStamp {
@@ -32,6 +33,12 @@ pub struct DltTimeStamp {
     #[proptest(strategy = "0..=1_000_000u32")]
     pub microseconds: u32,
 }
+impl DltTimeStamp {
+    /// posix timestamp in ms
+    pub fn as_millis(&self) -> i64 {
+        i64::from(self.seconds) * 1000 + i64::from(self.microseconds) / 1000
+    }
+}
 impl fmt::Display for DltTimeStamp {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         let naive: Option<NaiveDateTime> =
@@ -82,6 +89,18 @@ impl BytesMutExt for BytesMut {
     }
 }
 impl StorageHeader {
+    /// storage header for a message that was not read from a file but
+    /// received (e.g. over the network) at `time`
+    pub fn received_at(time: SystemTime, ecu_id: Option<&String>) -> StorageHeader {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        StorageHeader {
+            timestamp: DltTimeStamp {
+                seconds: since_epoch.as_secs() as u32,
+                microseconds: since_epoch.subsec_micros(),
+            },
+            ecu_id: ecu_id.cloned().unwrap_or_default(),
+        }
+    }
     #[allow(dead_code)]
     pub fn as_bytes(self: &StorageHeader) -> Vec<u8> {
         let mut buf = BytesMut::with_capacity(STORAGE_HEADER_LENGTH);
@@ -226,12 +245,15 @@ impl fmt::Display for NetworkTraceType {
 pub enum ControlType {
     Request,
     Response,
+    /// timing packet sent periodically by the dlt-daemon
+    Time,
 }
 impl fmt::Display for ControlType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         match self {
             ControlType::Request => f.write_str("REQ"),
             ControlType::Response => f.write_str("RES"),
+            ControlType::Time => f.write_str("TIME"),
         }
     }
 }
@@ -245,10 +267,21 @@ pub enum MessageType {
     #[proptest(strategy = "((0b100u8..0b111u8),(0..0b1111u8)).prop_map(MessageType::Unknown)")]
     Unknown((u8, u8)),
 }
+impl MessageType {
+    /// true for log messages less severe than `level`
+    pub fn skip_with_level(&self, level: LogLevel) -> bool {
+        match self {
+            MessageType::Log(n) => level < *n,
+            _ => false,
+        }
+    }
+}
 pub const DLT_TYPE_LOG: u8 = 0b000;
 pub const DLT_TYPE_APP_TRACE: u8 = 0b001;
 pub const DLT_TYPE_NW_TRACE: u8 = 0b010;
 pub const DLT_TYPE_CONTROL: u8 = 0b011;
+/// structs nested deeper than this are treated as corrupted data
+pub const MAX_STRUCT_DEPTH: usize = 16;
 
 #[derive(Debug, Clone, PartialEq, Arbitrary)]
 pub struct ExtendedHeader {
@@ -273,10 +306,7 @@ impl ExtendedHeader {
         buf.to_vec()
     }
     pub fn skip_with_level(self: &ExtendedHeader, level: LogLevel) -> bool {
-        match self.message_type {
-            MessageType::Log(n) => level < n,
-            _ => false,
-        }
+        self.message_type.skip_with_level(level)
     }
 }
 
@@ -310,6 +340,11 @@ pub enum Value {
     F64(f64),
     StringVal(String),
     Raw(Vec<u8>),
+    /// multi-dimensional array: the number of entries for each dimension
+    /// and all entries in row-major order
+    Array(Vec<u16>, Vec<Value>),
+    /// struct with the arguments as its (possibly named) fields
+    Struct(Vec<Argument>),
 }
 
 #[derive(Debug, Clone, PartialEq, Arbitrary)]
@@ -339,6 +374,65 @@ pub enum TypeLength {
 //         )
 // }
 
+/// arrays can only hold bool and numeric values
+pub fn array_strategy() -> impl Strategy<Value = TypeInfoKind> {
+    prop_oneof![
+        Just(TypeInfoKind::Bool),
+        signed_strategy(),
+        unsigned_strategy(),
+        any::<FloatWidth>().prop_map(TypeInfoKind::Float),
+    ]
+    .prop_map(|kind| TypeInfoKind::Array(Box::new(kind)))
+}
+/// creates a `Value::Array` with up to 3 dimensions that holds
+/// entries of `element_kind`
+pub fn array_value_strategy(element_kind: &TypeInfoKind) -> BoxedStrategy<Value> {
+    let element_strategy: BoxedStrategy<Value> = match element_kind {
+        TypeInfoKind::Bool => any::<bool>().prop_map(Value::Bool).boxed(),
+        TypeInfoKind::Signed(TypeLength::BitLength8, _) => any::<i8>().prop_map(Value::I8).boxed(),
+        TypeInfoKind::Signed(TypeLength::BitLength16, _) => {
+            any::<i16>().prop_map(Value::I16).boxed()
+        }
+        TypeInfoKind::Signed(TypeLength::BitLength32, _) => {
+            any::<i32>().prop_map(Value::I32).boxed()
+        }
+        TypeInfoKind::Signed(TypeLength::BitLength64, _) => {
+            any::<i64>().prop_map(Value::I64).boxed()
+        }
+        TypeInfoKind::Signed(TypeLength::BitLength128, _) => {
+            any::<i128>().prop_map(Value::I128).boxed()
+        }
+        TypeInfoKind::Unsigned(TypeLength::BitLength8, _) => {
+            any::<u8>().prop_map(Value::U8).boxed()
+        }
+        TypeInfoKind::Unsigned(TypeLength::BitLength16, _) => {
+            any::<u16>().prop_map(Value::U16).boxed()
+        }
+        TypeInfoKind::Unsigned(TypeLength::BitLength32, _) => {
+            any::<u32>().prop_map(Value::U32).boxed()
+        }
+        TypeInfoKind::Unsigned(TypeLength::BitLength64, _) => {
+            any::<u64>().prop_map(Value::U64).boxed()
+        }
+        TypeInfoKind::Unsigned(TypeLength::BitLength128, _) => {
+            any::<u128>().prop_map(Value::U128).boxed()
+        }
+        TypeInfoKind::Float(FloatWidth::Width32) => any::<f32>().prop_map(Value::F32).boxed(),
+        TypeInfoKind::Float(FloatWidth::Width64) => any::<f64>().prop_map(Value::F64).boxed(),
+        _ => Just(Value::Bool(false)).boxed(),
+    };
+    prop::collection::vec(0..4u16, 1..=3)
+        .prop_flat_map(move |dimensions| {
+            let entries = array_entry_count(&dimensions).unwrap_or(0);
+            (
+                Just(dimensions),
+                prop::collection::vec(element_strategy.clone(), entries),
+            )
+        })
+        .prop_map(|(dimensions, values)| Value::Array(dimensions, values))
+        .boxed()
+}
+
 pub fn signed_strategy() -> impl Strategy<Value = TypeInfoKind> {
     (any::<TypeLength>(), any::<bool>()).prop_filter_map(
         "only permit fixed point for 32 and 64 bit",
@@ -371,9 +465,13 @@ pub enum TypeInfoKind {
     #[proptest(strategy = "unsigned_strategy()")]
     Unsigned(TypeLength, bool), // FIXP
     Float(FloatWidth),
-    // Array,
+    /// array of bool, signed, unsigned or float values
+    #[proptest(strategy = "array_strategy()")]
+    Array(Box<TypeInfoKind>),
     StringType,
     Raw,
+    /// struct of nested arguments
+    Struct,
 }
 
 #[derive(Debug, Clone, PartialEq, Arbitrary)]
@@ -383,7 +481,6 @@ pub struct TypeInfo {
     pub has_variable_info: bool,
     pub has_trace_info: bool,
     // TraceInfo,
-    // TypeStruct,
 }
 impl TypeInfo {
     pub fn type_length_bits_float(len: FloatWidth) -> u32 {
@@ -410,31 +507,43 @@ impl TypeInfo {
         }
     }
     pub fn is_fixed_point(self: &TypeInfo) -> bool {
-        match self.kind {
-            TypeInfoKind::Signed(_, fp) => fp,
-            TypeInfoKind::Unsigned(_, fp) => fp,
-            _ => false,
+        fn kind_is_fixed_point(kind: &TypeInfoKind) -> bool {
+            match kind {
+                TypeInfoKind::Signed(_, fp) => *fp,
+                TypeInfoKind::Unsigned(_, fp) => *fp,
+                TypeInfoKind::Array(element_kind) => kind_is_fixed_point(element_kind),
+                _ => false,
+            }
         }
+        kind_is_fixed_point(&self.kind)
     }
     pub fn as_bytes<T: ByteOrder>(self: &TypeInfo) -> Vec<u8> {
-        let mut info: u32 = 0;
-        // encode length
-        match self.kind {
-            TypeInfoKind::Float(len) => info |= TypeInfo::type_length_bits_float(len),
-            TypeInfoKind::Signed(len, _) => info |= TypeInfo::type_length_bits(len),
-            TypeInfoKind::Unsigned(len, _) => info |= TypeInfo::type_length_bits(len),
-            TypeInfoKind::Bool => info |= TypeInfo::type_length_bits(TypeLength::BitLength8),
-            _ => (),
+        fn length_bits(kind: &TypeInfoKind) -> u32 {
+            match kind {
+                TypeInfoKind::Float(len) => TypeInfo::type_length_bits_float(*len),
+                TypeInfoKind::Signed(len, _) => TypeInfo::type_length_bits(*len),
+                TypeInfoKind::Unsigned(len, _) => TypeInfo::type_length_bits(*len),
+                TypeInfoKind::Bool => TypeInfo::type_length_bits(TypeLength::BitLength8),
+                TypeInfoKind::Array(element_kind) => length_bits(element_kind),
+                _ => 0,
+            }
         }
-        match self.kind {
-            TypeInfoKind::Bool => info |= TYPE_INFO_BOOL_FLAG,
-            TypeInfoKind::Signed(_, _) => info |= TYPE_INFO_SINT_FLAG,
-            TypeInfoKind::Unsigned(_, _) => info |= TYPE_INFO_UINT_FLAG,
-            TypeInfoKind::Float(_) => info |= TYPE_INFO_FLOAT_FLAG,
-            // TypeInfoKind::Array => info |= TYPE_INFO_ARRAY_FLAG,
-            TypeInfoKind::StringType => info |= TYPE_INFO_STRING_FLAG,
-            TypeInfoKind::Raw => info |= TYPE_INFO_RAW_FLAG,
+        fn kind_bits(kind: &TypeInfoKind) -> u32 {
+            match kind {
+                TypeInfoKind::Bool => TYPE_INFO_BOOL_FLAG,
+                TypeInfoKind::Signed(_, _) => TYPE_INFO_SINT_FLAG,
+                TypeInfoKind::Unsigned(_, _) => TYPE_INFO_UINT_FLAG,
+                TypeInfoKind::Float(_) => TYPE_INFO_FLOAT_FLAG,
+                TypeInfoKind::Array(element_kind) => TYPE_INFO_ARRAY_FLAG | kind_bits(element_kind),
+                TypeInfoKind::StringType => TYPE_INFO_STRING_FLAG,
+                TypeInfoKind::Raw => TYPE_INFO_RAW_FLAG,
+                TypeInfoKind::Struct => TYPE_INFO_STRUCT_FLAG,
+            }
         }
+        let mut info: u32 = 0;
+        // encode length
+        info |= length_bits(&self.kind);
+        info |= kind_bits(&self.kind);
         if self.has_variable_info {
             info |= TYPE_INFO_VARIABLE_INFO
         }
@@ -498,12 +607,25 @@ impl TryFrom<u32> for TypeInfo {
         }
 
         let is_fixed_point = (info & TYPE_INFO_FIXED_POINT_FLAG) != 0;
+        let is_struct = (info & TYPE_INFO_STRUCT_FLAG) != 0;
         let kind = match (info >> 4) & 0b111_1111 {
+            0b000_0000 if is_struct => Ok(TypeInfoKind::Struct),
             0b000_0001 => Ok(TypeInfoKind::Bool),
             0b000_0010 => Ok(TypeInfoKind::Signed(type_len(info)?, is_fixed_point)),
             0b000_0100 => Ok(TypeInfoKind::Unsigned(type_len(info)?, is_fixed_point)),
             0b000_1000 => Ok(TypeInfoKind::Float(type_len_float(info)?)),
-            // 0b001_0000 => Ok(TypeInfoKind::Array),
+            0b001_0001 => Ok(TypeInfoKind::Array(Box::new(TypeInfoKind::Bool))),
+            0b001_0010 => Ok(TypeInfoKind::Array(Box::new(TypeInfoKind::Signed(
+                type_len(info)?,
+                is_fixed_point,
+            )))),
+            0b001_0100 => Ok(TypeInfoKind::Array(Box::new(TypeInfoKind::Unsigned(
+                type_len(info)?,
+                is_fixed_point,
+            )))),
+            0b001_1000 => Ok(TypeInfoKind::Array(Box::new(TypeInfoKind::Float(
+                type_len_float(info)?,
+            )))),
             0b010_0000 => Ok(TypeInfoKind::StringType),
             0b100_0000 => Ok(TypeInfoKind::Raw),
             v => {
@@ -541,11 +663,82 @@ fn my_enum_strategy() -> impl Strategy<Value = Value> {
         any::<u32>().prop_map(Value::U32),
     ]
 }
+/// writes a bool or numeric value, other values are ignored
+fn put_value<T: ByteOrder>(value: &Value, buf: &mut BytesMut) {
+    match value {
+        Value::Bool(v) => buf.put_u8(if *v { 0x1 } else { 0x0 }),
+        Value::U8(v) => buf.put_u8(*v),
+        Value::U16(v) => {
+            let mut b = [0; 2];
+            T::write_u16(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::U32(v) => {
+            let mut b = [0; 4];
+            T::write_u32(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::U64(v) => {
+            let mut b = [0; 8];
+            T::write_u64(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::U128(v) => {
+            let mut b = [0; 16];
+            T::write_u128(&mut b, *v);
+            buf.put_slice(&b);
+        }
+        Value::I8(v) => buf.put_i8(*v),
+        Value::I16(v) => {
+            let mut b = [0; 2];
+            T::write_i16(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::I32(v) => {
+            let mut b = [0; 4];
+            T::write_i32(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::I64(v) => {
+            let mut b = [0; 8];
+            T::write_i64(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::I128(v) => {
+            let mut b = [0; 16];
+            T::write_i128(&mut b, *v);
+            buf.put_slice(&b);
+        }
+        Value::F32(v) => {
+            let mut b = [0; 4];
+            T::write_f32(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        Value::F64(v) => {
+            let mut b = [0; 8];
+            T::write_f64(&mut b, *v);
+            buf.put_slice(&b)
+        }
+        _ => (),
+    }
+}
+/// number of entries in an array with the given dimensions
+/// (`None` if the count does not fit into `usize`)
+pub fn array_entry_count(dimensions: &[u16]) -> Option<usize> {
+    if dimensions.is_empty() {
+        return Some(0);
+    }
+    dimensions
+        .iter()
+        .try_fold(1usize, |acc, d| acc.checked_mul(*d as usize))
+}
 #[derive(Debug, Clone, PartialEq, Arbitrary)]
 pub struct Argument {
     pub type_info: TypeInfo,
     pub name: Option<String>,
     pub unit: Option<String>,
+    /// only present if the type info has the trace info flag set
+    pub trace_info: Option<String>,
     pub fixed_point: Option<FixedPoint>,
     // #[proptest(strategy = "Just(Value::U8(22))")]
     #[proptest(strategy = "my_enum_strategy()")]
@@ -554,6 +747,23 @@ pub struct Argument {
 impl Argument {
     #[allow(dead_code)]
     pub fn as_bytes<T: ByteOrder>(self: &Argument) -> Vec<u8> {
+        let bytes = self.as_bytes_without_trace_info::<T>();
+        match (self.type_info.has_trace_info, &self.trace_info) {
+            (true, Some(info)) if bytes.len() >= TYPE_INFO_LENGTH => {
+                // the trace info block directly follows the type info
+                let mut buf = BytesMut::with_capacity(bytes.len() + 2 + info.len() + 1);
+                buf.extend_from_slice(&bytes[..TYPE_INFO_LENGTH]);
+                #[allow(deprecated)]
+                buf.put_u16::<T>(info.len() as u16 + 1);
+                buf.extend_from_slice(info.as_bytes());
+                buf.put_u8(0x0); // null termination
+                buf.extend_from_slice(&bytes[TYPE_INFO_LENGTH..]);
+                buf.to_vec()
+            }
+            _ => bytes,
+        }
+    }
+    fn as_bytes_without_trace_info<T: ByteOrder>(self: &Argument) -> Vec<u8> {
         fn mut_buf_with_typeinfo_name_unit<T: ByteOrder>(
             info: &TypeInfo,
             name: &Option<String>,
@@ -620,105 +830,78 @@ impl Argument {
                 });
                 buf.to_vec()
             }
-            TypeInfoKind::Signed(_, _) => {
-                fn write_value<T: ByteOrder>(value: &Value, buf: &mut BytesMut) {
-                    match value {
-                        Value::I8(v) => buf.put_i8(*v),
-                        Value::I16(v) => {
-                            let mut b = [0; 2];
-                            T::write_i16(&mut b, *v);
-                            buf.put_slice(&b)
-                        }
-                        Value::I32(v) => {
-                            let mut b = [0; 4];
-                            T::write_i32(&mut b, *v);
-                            buf.put_slice(&b)
-                        }
-                        Value::I64(v) => {
-                            let mut b = [0; 8];
-                            T::write_i64(&mut b, *v);
-                            buf.put_slice(&b)
-                        }
-                        Value::I128(v) => {
-                            let mut b = [0; 16];
-                            T::write_i128(&mut b, *v);
-                            buf.put_slice(&b);
-                        }
-                        _ => (),
-                    }
-                }
+            TypeInfoKind::Signed(_, _) | TypeInfoKind::Unsigned(_, _) | TypeInfoKind::Float(_) => {
                 let mut buf = mut_buf_with_typeinfo_name_unit::<T>(
                     &self.type_info,
                     &self.name,
                     &self.unit,
                     &self.fixed_point,
                 );
-                write_value::<T>(&self.value, &mut buf);
+                put_value::<T>(&self.value, &mut buf);
                 buf.to_vec()
             }
-            TypeInfoKind::Unsigned(_, _) => {
-                let mut buf = mut_buf_with_typeinfo_name_unit::<T>(
-                    &self.type_info,
-                    &self.name,
-                    &self.unit,
-                    &self.fixed_point,
-                );
-                match self.value {
-                    Value::U8(v) => buf.put_u8(v),
-                    Value::U16(v) => {
-                        let mut b = [0; 2];
-                        T::write_u16(&mut b, v);
-                        buf.put_slice(&b)
+            TypeInfoKind::Array(_) => match &self.value {
+                Value::Array(dimensions, values) => {
+                    // name, unit and fixed point follow the dimensions
+                    let name_unit_fp = mut_buf_with_typeinfo_name_unit::<T>(
+                        &self.type_info,
+                        &self.name,
+                        &self.unit,
+                        &self.fixed_point,
+                    );
+                    let mut buf = BytesMut::with_capacity(
+                        name_unit_fp.len() +
+                        2 /* number of dimensions */ +
+                        2 * dimensions.len() +
+                        16 * values.len(), /* at most 128 bit per entry */
+                    );
+                    buf.extend_from_slice(&self.type_info.as_bytes::<T>()[..]);
+                    #[allow(deprecated)]
+                    buf.put_u16::<T>(dimensions.len() as u16);
+                    for d in dimensions {
+                        #[allow(deprecated)]
+                        buf.put_u16::<T>(*d);
                     }
-                    Value::U32(v) => {
-                        let mut b = [0; 4];
-                        T::write_u32(&mut b, v);
-                        buf.put_slice(&b)
+                    buf.extend_from_slice(&name_unit_fp[TYPE_INFO_LENGTH..]);
+                    for v in values {
+                        put_value::<T>(v, &mut buf);
                     }
-                    Value::U64(v) => {
-                        let mut b = [0; 8];
-                        T::write_u64(&mut b, v);
-                        buf.put_slice(&b)
+                    buf.to_vec()
+                }
+                _ => {
+                    report_error(format!("found invalid dlt entry ({:?}", self));
+                    BytesMut::with_capacity(0).to_vec()
+                }
+            },
+            TypeInfoKind::Struct => match &self.value {
+                Value::Struct(fields) => {
+                    let field_bytes: Vec<Vec<u8>> =
+                        fields.iter().map(|arg| arg.as_bytes::<T>()).collect();
+                    let mut capacity = TYPE_INFO_LENGTH + 2 /* number of entries */;
+                    if let Some(n) = &self.name {
+                        capacity += 2 /* length name */ + n.len() + 1;
                     }
-                    Value::U128(v) => {
-                        let mut b = [0; 16];
-                        T::write_u128(&mut b, v);
-                        buf.put_slice(&b);
+                    capacity += field_bytes.iter().map(Vec::len).sum::<usize>();
+                    let mut buf = BytesMut::with_capacity(capacity);
+                    buf.extend_from_slice(&self.type_info.as_bytes::<T>()[..]);
+                    #[allow(deprecated)]
+                    buf.put_u16::<T>(fields.len() as u16);
+                    if let (true, Some(n)) = (self.type_info.has_variable_info, &self.name) {
+                        #[allow(deprecated)]
+                        buf.put_u16::<T>(n.len() as u16 + 1);
+                        buf.extend_from_slice(n.as_bytes());
+                        buf.put_u8(0x0); // null termination
                     }
-                    _ => (),
-                }
-                buf.to_vec()
-            }
-            TypeInfoKind::Float(_) => {
-                fn write_value<T: ByteOrder>(value: &Value, buf: &mut BytesMut) {
-                    match value {
-                        Value::F32(v) => {
-                            let mut b = [0; 4];
-                            T::write_f32(&mut b, *v);
-                            buf.put_slice(&b)
-                        }
-                        Value::F64(v) => {
-                            let mut b = [0; 8];
-                            T::write_f64(&mut b, *v);
-                            buf.put_slice(&b)
-                        }
-                        _ => (),
+                    for bytes in field_bytes {
+                        buf.extend_from_slice(&bytes);
                     }
+                    buf.to_vec()
                 }
-                let mut buf = mut_buf_with_typeinfo_name_unit::<T>(
-                    &self.type_info,
-                    &self.name,
-                    &self.unit,
-                    &self.fixed_point,
-                );
-                write_value::<T>(&self.value, &mut buf);
-                buf.to_vec()
-            }
-            // TypeInfoKind::Array => {
-            //     // TODO dlt array type not yet implemented NYI
-            //     eprintln!("found dlt array type...not yet supported");
-            //     BytesMut::with_capacity(STORAGE_HEADER_LENGTH).to_vec()
-            // }
+                _ => {
+                    report_error(format!("found invalid dlt entry ({:?}", self));
+                    BytesMut::with_capacity(0).to_vec()
+                }
+            },
             TypeInfoKind::StringType => {
                 match (self.type_info.has_variable_info, &self.name) {
                     (true, Some(var_name)) => {
@@ -842,6 +1025,13 @@ pub enum Payload {
         strategy = "(0..10u32, prop::collection::vec(any::<u8>(), 0..20)).prop_map(|(a, b)| Payload::NonVerbose(a,b))"
     )]
     NonVerbose(u32, Vec<u8>),
+    /// non-verbose payload that was decoded with the help of FIBEX metadata:
+    /// message id, raw data and the decoded arguments
+    #[proptest(skip)]
+    NonVerboseDecoded(u32, Vec<u8>, Vec<Argument>),
+    /// control message and the raw payload it was parsed from
+    #[proptest(skip)]
+    Control(ControlMessage, Vec<u8>),
 }
 impl Payload {
     #[allow(dead_code)]
@@ -853,14 +1043,28 @@ impl Payload {
                     buf.extend_from_slice(&arg.as_bytes::<T>());
                 }
             }
-            Payload::NonVerbose(msg_id, payload) => {
+            Payload::NonVerbose(msg_id, payload)
+            | Payload::NonVerboseDecoded(msg_id, payload, _) => {
                 #[allow(deprecated)]
                 buf.put_u32::<T>(*msg_id);
                 buf.extend_from_slice(payload);
             }
+            Payload::Control(_, payload) => buf.extend_from_slice(payload),
         }
         buf.to_vec()
     }
+    /// the payload as plain text (arguments separated by spaces), used to search in it
+    pub fn as_text(&self) -> String {
+        match self {
+            Payload::Verbose(args) | Payload::NonVerboseDecoded(_, _, args) => args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+            Payload::NonVerbose(_, data) => String::from_utf8_lossy(data).to_string(),
+            Payload::Control(control_message, _) => control_message.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -870,6 +1074,41 @@ pub struct Message {
     pub extended_header: Option<ExtendedHeader>,
     pub payload: Payload,
 }
+impl Message {
+    /// serialize the message including the storage header (if present)
+    /// the overall length of the standard header is calculated from the content
+    pub fn as_bytes(self: &Message) -> Vec<u8> {
+        let mut buf = match &self.storage_header {
+            Some(storage_header) => storage_header.as_bytes(),
+            None => vec![],
+        };
+        buf.extend_from_slice(&self.as_bytes_without_storage_header());
+        buf
+    }
+    /// serialize the message the way it is sent over the wire (without storage header)
+    pub fn as_bytes_without_storage_header(self: &Message) -> Vec<u8> {
+        let payload_bytes = if self.header.big_endian {
+            self.payload.as_bytes::<BigEndian>()
+        } else {
+            self.payload.as_bytes::<LittleEndian>()
+        };
+        let extended_header_bytes = match &self.extended_header {
+            // an extended header that was only derived from a fibex is not part of the message
+            Some(ext) if self.header.has_extended_header => ext.as_bytes(),
+            _ => vec![],
+        };
+        let header_length = calculate_standard_header_length(self.header.header_type());
+        let overall_length = header_length + extended_header_bytes.len() + payload_bytes.len();
+        let header = StandardHeader {
+            overall_length: overall_length as u16,
+            ..self.header.clone()
+        };
+        let mut buf = header.as_bytes();
+        buf.extend_from_slice(&extended_header_bytes);
+        buf.extend_from_slice(&payload_bytes);
+        buf
+    }
+}
 pub const DLT_COLUMN_SENTINAL: char = '\u{0004}';
 pub const DLT_ARGUMENT_SENTINAL: char = '\u{0005}';
 pub const DLT_NEWLINE_SENTINAL_SLICE: &[u8] = &[0x6];
@@ -948,9 +1187,12 @@ impl fmt::Display for Message {
             )?,
         }
         match &self.payload {
-            Payload::Verbose(arguments) => arguments
+            Payload::Verbose(arguments) | Payload::NonVerboseDecoded(_, _, arguments) => arguments
                 .iter()
                 .try_for_each(|arg| write!(f, "{}{}", DLT_ARGUMENT_SENTINAL, arg)),
+            Payload::Control(control_message, _) => {
+                write!(f, "{}{}", DLT_ARGUMENT_SENTINAL, control_message)
+            }
             Payload::NonVerbose(id, data) => {
                 let as_string = str::from_utf8(&data).unwrap_or("").trim();
                 f.write_str(
@@ -1022,12 +1264,52 @@ impl fmt::Display for Value {
                     .join(&DLT_NEWLINE_SENTINAL_STR)
             ),
             Value::Raw(value) => write!(f, "{:02X?}", value),
+            Value::Array(dimensions, values) => fmt_array(f, dimensions, values),
+            Value::Struct(fields) => {
+                f.write_str("{")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", field)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+/// formats array entries nested by dimension, e.g. `[[1, 2], [3, 4]]`
+fn fmt_array(f: &mut Formatter, dimensions: &[u16], values: &[Value]) -> fmt::Result {
+    f.write_str("[")?;
+    match dimensions.split_first() {
+        Some((_, [])) | None => {
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", v)?;
+            }
+        }
+        Some((&outer, inner)) => {
+            let inner_count = array_entry_count(inner).unwrap_or(0);
+            for i in 0..outer as usize {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                let start = std::cmp::min(i * inner_count, values.len());
+                let end = std::cmp::min(start + inner_count, values.len());
+                fmt_array(f, inner, &values[start..end])?;
+            }
         }
     }
+    f.write_str("]")
 }
 
 impl fmt::Display for Argument {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        if let Some(t) = &self.trace_info {
+            write!(f, "[{}] ", t)?;
+        }
         if let Some(n) = &self.name {
             write!(f, "{}: ", n)?;
         }
@@ -1050,6 +1332,7 @@ pub trait TryFrom<T>: Sized {
 }
 
 // StorageHeader
+pub const STORAGE_HEADER_PATTERN: &[u8] = &[0x44, 0x4C, 0x54, 0x01];
 pub const STORAGE_HEADER_PATTERN_LENGTH: usize = 4;
 pub const STORAGE_HEADER_LENGTH: usize = 16;
 
@@ -1073,13 +1356,12 @@ pub const TYPE_INFO_BOOL_FLAG: u32 = 1 << 4;
 pub const TYPE_INFO_SINT_FLAG: u32 = 1 << 5;
 pub const TYPE_INFO_UINT_FLAG: u32 = 1 << 6;
 pub const TYPE_INFO_FLOAT_FLAG: u32 = 1 << 7;
-pub const _TYPE_INFO_ARRAY_FLAG: u32 = 1 << 8;
+pub const TYPE_INFO_ARRAY_FLAG: u32 = 1 << 8;
 pub const TYPE_INFO_STRING_FLAG: u32 = 1 << 9;
 pub const TYPE_INFO_RAW_FLAG: u32 = 1 << 10;
 pub const TYPE_INFO_VARIABLE_INFO: u32 = 1 << 11;
 pub const TYPE_INFO_FIXED_POINT_FLAG: u32 = 1 << 12;
 pub const TYPE_INFO_TRACE_INFO_FLAG: u32 = 1 << 13;
-#[allow(dead_code)]
 pub const TYPE_INFO_STRUCT_FLAG: u32 = 1 << 14;
 
 pub fn calculate_standard_header_length(header_type: u8) -> usize {
@@ -1230,6 +1512,7 @@ impl From<&ControlType> for u8 {
         match t {
             ControlType::Request => res |= 0x1 << 4,
             ControlType::Response => res |= 0x2 << 4,
+            ControlType::Time => res |= 0x3 << 4,
         }
         res
     }
@@ -1240,6 +1523,7 @@ impl TryFrom<u8> for ControlType {
         match message_info >> 4 {
             1 => Ok(ControlType::Request),
             2 => Ok(ControlType::Response),
+            3 => Ok(ControlType::Time),
             _ => {
                 report_warning_ln(format!("Unknown control type {}", message_info >> 4), index);
                 Err(Error::new(
@@ -1251,6 +1535,295 @@ impl TryFrom<u8> for ControlType {
     }
 }
 
+/// service id of a control message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ServiceId(pub u32);
+impl ServiceId {
+    pub const SET_LOG_LEVEL: ServiceId = ServiceId(0x01);
+    pub const SET_TRACE_STATUS: ServiceId = ServiceId(0x02);
+    pub const GET_LOG_INFO: ServiceId = ServiceId(0x03);
+    pub const GET_DEFAULT_LOG_LEVEL: ServiceId = ServiceId(0x04);
+    pub const STORE_CONFIG: ServiceId = ServiceId(0x05);
+    pub const RESET_TO_FACTORY_DEFAULT: ServiceId = ServiceId(0x06);
+    pub const SET_COM_INTERFACE_STATUS: ServiceId = ServiceId(0x07);
+    pub const SET_COM_INTERFACE_MAX_BANDWIDTH: ServiceId = ServiceId(0x08);
+    pub const SET_VERBOSE_MODE: ServiceId = ServiceId(0x09);
+    pub const SET_MESSAGE_FILTERING: ServiceId = ServiceId(0x0A);
+    pub const SET_TIMING_PACKETS: ServiceId = ServiceId(0x0B);
+    pub const GET_LOCAL_TIME: ServiceId = ServiceId(0x0C);
+    pub const USE_ECU_ID: ServiceId = ServiceId(0x0D);
+    pub const USE_SESSION_ID: ServiceId = ServiceId(0x0E);
+    pub const USE_TIMESTAMP: ServiceId = ServiceId(0x0F);
+    pub const USE_EXTENDED_HEADER: ServiceId = ServiceId(0x10);
+    pub const SET_DEFAULT_LOG_LEVEL: ServiceId = ServiceId(0x11);
+    pub const SET_DEFAULT_TRACE_STATUS: ServiceId = ServiceId(0x12);
+    pub const GET_SOFTWARE_VERSION: ServiceId = ServiceId(0x13);
+    pub const MESSAGE_BUFFER_OVERFLOW: ServiceId = ServiceId(0x14);
+    pub const GET_DEFAULT_TRACE_STATUS: ServiceId = ServiceId(0x15);
+    pub const GET_COM_INTERFACE_STATUS: ServiceId = ServiceId(0x16);
+    pub const GET_LOG_CHANNEL_NAMES: ServiceId = ServiceId(0x17);
+    pub const GET_COM_INTERFACE_MAX_BANDWIDTH: ServiceId = ServiceId(0x18);
+    pub const GET_VERBOSE_MODE_STATUS: ServiceId = ServiceId(0x19);
+    pub const GET_MESSAGE_FILTERING_STATUS: ServiceId = ServiceId(0x1A);
+    pub const GET_USE_ECU_ID: ServiceId = ServiceId(0x1B);
+    pub const GET_USE_SESSION_ID: ServiceId = ServiceId(0x1C);
+    pub const GET_USE_TIMESTAMP: ServiceId = ServiceId(0x1D);
+    pub const GET_USE_EXTENDED_HEADER: ServiceId = ServiceId(0x1E);
+    pub const GET_TRACE_STATUS: ServiceId = ServiceId(0x1F);
+    pub const UNREGISTER_CONTEXT: ServiceId = ServiceId(0xF01);
+    pub const CONNECTION_INFO: ServiceId = ServiceId(0xF02);
+    pub const TIMEZONE: ServiceId = ServiceId(0xF03);
+    pub const MARKER: ServiceId = ServiceId(0xF04);
+
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            ServiceId::SET_LOG_LEVEL => Some("set_log_level"),
+            ServiceId::SET_TRACE_STATUS => Some("set_trace_status"),
+            ServiceId::GET_LOG_INFO => Some("get_log_info"),
+            ServiceId::GET_DEFAULT_LOG_LEVEL => Some("get_default_log_level"),
+            ServiceId::STORE_CONFIG => Some("store_config"),
+            ServiceId::RESET_TO_FACTORY_DEFAULT => Some("reset_to_factory_default"),
+            ServiceId::SET_COM_INTERFACE_STATUS => Some("set_com_interface_status"),
+            ServiceId::SET_COM_INTERFACE_MAX_BANDWIDTH => Some("set_com_interface_max_bandwidth"),
+            ServiceId::SET_VERBOSE_MODE => Some("set_verbose_mode"),
+            ServiceId::SET_MESSAGE_FILTERING => Some("set_message_filtering"),
+            ServiceId::SET_TIMING_PACKETS => Some("set_timing_packets"),
+            ServiceId::GET_LOCAL_TIME => Some("get_local_time"),
+            ServiceId::USE_ECU_ID => Some("use_ecu_id"),
+            ServiceId::USE_SESSION_ID => Some("use_session_id"),
+            ServiceId::USE_TIMESTAMP => Some("use_timestamp"),
+            ServiceId::USE_EXTENDED_HEADER => Some("use_extended_header"),
+            ServiceId::SET_DEFAULT_LOG_LEVEL => Some("set_default_log_level"),
+            ServiceId::SET_DEFAULT_TRACE_STATUS => Some("set_default_trace_status"),
+            ServiceId::GET_SOFTWARE_VERSION => Some("get_software_version"),
+            ServiceId::MESSAGE_BUFFER_OVERFLOW => Some("message_buffer_overflow"),
+            ServiceId::GET_DEFAULT_TRACE_STATUS => Some("get_default_trace_status"),
+            ServiceId::GET_COM_INTERFACE_STATUS => Some("get_com_interface_status"),
+            ServiceId::GET_LOG_CHANNEL_NAMES => Some("get_log_channel_names"),
+            ServiceId::GET_COM_INTERFACE_MAX_BANDWIDTH => Some("get_com_interface_max_bandwidth"),
+            ServiceId::GET_VERBOSE_MODE_STATUS => Some("get_verbose_mode_status"),
+            ServiceId::GET_MESSAGE_FILTERING_STATUS => Some("get_message_filtering_status"),
+            ServiceId::GET_USE_ECU_ID => Some("get_use_ecu_id"),
+            ServiceId::GET_USE_SESSION_ID => Some("get_use_session_id"),
+            ServiceId::GET_USE_TIMESTAMP => Some("get_use_timestamp"),
+            ServiceId::GET_USE_EXTENDED_HEADER => Some("get_use_extended_header"),
+            ServiceId::GET_TRACE_STATUS => Some("get_trace_status"),
+            ServiceId::UNREGISTER_CONTEXT => Some("unregister_context"),
+            ServiceId::CONNECTION_INFO => Some("connection_info"),
+            ServiceId::TIMEZONE => Some("timezone"),
+            ServiceId::MARKER => Some("marker"),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for ServiceId {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "service_0x{:X}", self.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseStatus {
+    Ok,
+    NotSupported,
+    Error,
+    /// get_log_info specific status codes (3..9) and unknown codes
+    Other(u8),
+}
+impl From<u8> for ResponseStatus {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => ResponseStatus::Ok,
+            1 => ResponseStatus::NotSupported,
+            2 => ResponseStatus::Error,
+            v => ResponseStatus::Other(v),
+        }
+    }
+}
+impl From<&ResponseStatus> for u8 {
+    fn from(status: &ResponseStatus) -> Self {
+        match status {
+            ResponseStatus::Ok => 0,
+            ResponseStatus::NotSupported => 1,
+            ResponseStatus::Error => 2,
+            ResponseStatus::Other(v) => *v,
+        }
+    }
+}
+impl fmt::Display for ResponseStatus {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ResponseStatus::Ok => f.write_str("ok"),
+            ResponseStatus::NotSupported => f.write_str("not_supported"),
+            ResponseStatus::Error => f.write_str("error"),
+            ResponseStatus::Other(8) => f.write_str("no_matching_context_id"),
+            ResponseStatus::Other(9) => f.write_str("response_data_overflow"),
+            ResponseStatus::Other(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// log level or trace status as used in control messages (-1 means default)
+fn fmt_control_level(f: &mut Formatter, level: i8) -> Result<(), fmt::Error> {
+    match level {
+        -1 => f.write_str("default"),
+        0 => f.write_str("off"),
+        l => match u8_to_log_level(l as u8) {
+            Some(log_level) => write!(f, "{}", log_level),
+            None => write!(f, "{}", l),
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextLogInfo {
+    pub context_id: String,
+    pub log_level: i8,
+    pub trace_status: i8,
+    pub description: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplicationLogInfo {
+    pub application_id: String,
+    pub contexts: Vec<ContextLogInfo>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlRequest {
+    SetLogLevel {
+        application_id: String,
+        context_id: String,
+        log_level: i8,
+    },
+    SetTraceStatus {
+        application_id: String,
+        context_id: String,
+        trace_status: i8,
+    },
+    GetLogInfo {
+        options: u8,
+        application_id: String,
+        context_id: String,
+    },
+    SetDefaultLogLevel(i8),
+    SetTimingPackets(bool),
+    /// requests without or with unsupported parameters
+    Other(Vec<u8>),
+}
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlResponse {
+    LogInfo(Vec<ApplicationLogInfo>),
+    DefaultLogLevel(i8),
+    SoftwareVersion(String),
+    MessageBufferOverflow {
+        overflow: bool,
+        counter: u32,
+    },
+    /// responses without or with unsupported data
+    Other(Vec<u8>),
+}
+
+/// payload of a (non-verbose) control message
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlMessage {
+    Request(ServiceId, ControlRequest),
+    Response(ServiceId, ResponseStatus, ControlResponse),
+    /// timing packets carry no payload
+    Time,
+}
+impl ControlMessage {
+    pub fn service_id(&self) -> Option<ServiceId> {
+        match self {
+            ControlMessage::Request(id, _) | ControlMessage::Response(id, _, _) => Some(*id),
+            ControlMessage::Time => None,
+        }
+    }
+}
+impl fmt::Display for ControlMessage {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ControlMessage::Request(id, request) => {
+                write!(f, "[{}]", id)?;
+                match request {
+                    ControlRequest::SetLogLevel {
+                        application_id,
+                        context_id,
+                        log_level,
+                    } => {
+                        write!(f, " {} {} ", application_id, context_id)?;
+                        fmt_control_level(f, *log_level)
+                    }
+                    ControlRequest::SetTraceStatus {
+                        application_id,
+                        context_id,
+                        trace_status,
+                    } => {
+                        write!(f, " {} {} ", application_id, context_id)?;
+                        fmt_control_level(f, *trace_status)
+                    }
+                    ControlRequest::GetLogInfo {
+                        options,
+                        application_id,
+                        context_id,
+                    } => write!(f, " {} {} options: {}", application_id, context_id, options),
+                    ControlRequest::SetDefaultLogLevel(level) => {
+                        f.write_str(" ")?;
+                        fmt_control_level(f, *level)
+                    }
+                    ControlRequest::SetTimingPackets(on) => {
+                        f.write_str(if *on { " on" } else { " off" })
+                    }
+                    ControlRequest::Other(data) if data.is_empty() => Ok(()),
+                    ControlRequest::Other(data) => write!(f, " {:02X?}", data),
+                }
+            }
+            ControlMessage::Response(id, status, response) => {
+                write!(f, "[{} {}]", id, status)?;
+                match response {
+                    ControlResponse::LogInfo(applications) => {
+                        for app in applications {
+                            write!(f, " {}", app.application_id)?;
+                            if let Some(d) = &app.description {
+                                write!(f, " \"{}\"", d)?;
+                            }
+                            f.write_str(" (")?;
+                            for (i, ctx) in app.contexts.iter().enumerate() {
+                                if i > 0 {
+                                    f.write_str(", ")?;
+                                }
+                                write!(f, "{} ", ctx.context_id)?;
+                                fmt_control_level(f, ctx.log_level)?;
+                                f.write_str(" ")?;
+                                fmt_control_level(f, ctx.trace_status)?;
+                                if let Some(d) = &ctx.description {
+                                    write!(f, " \"{}\"", d)?;
+                                }
+                            }
+                            f.write_str(")")?;
+                        }
+                        Ok(())
+                    }
+                    ControlResponse::DefaultLogLevel(level) => {
+                        f.write_str(" ")?;
+                        fmt_control_level(f, *level)
+                    }
+                    ControlResponse::SoftwareVersion(version) => write!(f, " {}", version),
+                    ControlResponse::MessageBufferOverflow { overflow, counter } => {
+                        write!(f, " overflow: {} counter: {}", overflow, counter)
+                    }
+                    ControlResponse::Other(data) if data.is_empty() => Ok(()),
+                    ControlResponse::Other(data) => write!(f, " {:02X?}", data),
+                }
+            }
+            ControlMessage::Time => f.write_str("[timing packet]"),
+        }
+    }
+}
+
 impl fmt::Display for MessageType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         match self {
@@ -1544,6 +2117,7 @@ mod tests {
             type_info: type_info.clone(),
             name: Some("foo".to_string()),
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::Bool(true),
         };
@@ -1565,6 +2139,7 @@ mod tests {
             type_info: type_info2,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::Bool(true),
         };
@@ -1584,6 +2159,7 @@ mod tests {
             type_info,
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: Value::U32(0x33),
         };
@@ -1608,6 +2184,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::U32(0x33),
         };
@@ -1630,6 +2207,7 @@ mod tests {
             type_info,
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: Value::I32(-0x33),
         };
@@ -1654,6 +2232,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::I32(-0x33),
         };
@@ -1676,6 +2255,7 @@ mod tests {
             type_info,
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: Value::F32(123.98f32),
         };
@@ -1700,6 +2280,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::F64(123.98f64),
         };
@@ -1721,6 +2302,7 @@ mod tests {
             type_info,
             name: Some("speed".to_string()),
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::StringVal("foo".to_string()),
         };
@@ -1742,6 +2324,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: Value::StringVal("foo".to_string()),
         };
@@ -1765,6 +2348,7 @@ mod tests {
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
             value: Value::I32(-44),
+            trace_info: None,
             fixed_point: Some(FixedPoint {
                 quantization: 1.5,
                 offset: FixedPointValue::I32(-200),
@@ -1796,6 +2380,7 @@ mod tests {
             name: None,
             unit: None,
             value: Value::I32(-44),
+            trace_info: None,
             fixed_point: Some(FixedPoint {
                 quantization: 1.5,
                 offset: FixedPointValue::I32(-200),
@@ -1824,6 +2409,7 @@ mod tests {
             name: Some("foo".to_string()),
             unit: None,
             value: Value::Raw(vec![0xD, 0xE, 0xA, 0xD]),
+            trace_info: None,
             fixed_point: Some(FixedPoint {
                 quantization: 1.5,
                 offset: FixedPointValue::I32(-200),
@@ -1848,6 +2434,7 @@ mod tests {
             name: None,
             unit: None,
             value: Value::Raw(vec![0xD, 0xE, 0xA, 0xD]),
+            trace_info: None,
             fixed_point: Some(FixedPoint {
                 quantization: 1.5,
                 offset: FixedPointValue::I32(-200),
@@ -1857,4 +2444,97 @@ mod tests {
         expected.extend(vec![0xD, 0xE, 0xA, 0xD]);
         assert_eq!(expected, argument.as_bytes::<BigEndian>());
     }
+    #[test]
+    fn test_convert_array_argument_to_bytes() {
+        let type_info = TypeInfo {
+            kind: TypeInfoKind::Array(Box::new(TypeInfoKind::Unsigned(
+                TypeLength::BitLength16,
+                false,
+            ))),
+            coding: StringCoding::ASCII,
+            has_variable_info: true,
+            has_trace_info: false,
+        };
+        let mut expected = type_info.as_bytes::<BigEndian>();
+        let argument = Argument {
+            type_info,
+            name: Some("rpm".to_string()),
+            unit: Some("1/s".to_string()),
+            trace_info: None,
+            fixed_point: None,
+            value: Value::Array(
+                vec![2, 2],
+                vec![Value::U16(1), Value::U16(2), Value::U16(3), Value::U16(4)],
+            ),
+        };
+        expected.extend(vec![0x0, 0x2]); // number of dimensions
+        expected.extend(vec![0x0, 0x2, 0x0, 0x2]); // entries per dimension
+        expected.extend(vec![0x0, 0x4]); // length of name + zero
+        expected.extend(vec![0x0, 0x4]); // length of unit + zero
+        expected.extend(b"rpm\0");
+        expected.extend(b"1/s\0");
+        expected.extend(vec![0x0, 0x1, 0x0, 0x2, 0x0, 0x3, 0x0, 0x4]); // values
+        assert_eq!(expected, argument.as_bytes::<BigEndian>());
+        assert_eq!("rpm: 1/s[[1, 2], [3, 4]]", format!("{}", argument));
+    }
+    #[test]
+    fn test_convert_array_type_info_to_bytes() {
+        let type_info = TypeInfo {
+            kind: TypeInfoKind::Array(Box::new(TypeInfoKind::Float(FloatWidth::Width64))),
+            coding: StringCoding::ASCII,
+            has_variable_info: false,
+            has_trace_info: false,
+        };
+        let expected: u32 = 0b0000_0000_0000_0000_0000_0001_1000_0100;
+        assert_eq!(
+            expected,
+            BigEndian::read_u32(&type_info.as_bytes::<BigEndian>()[..])
+        );
+        assert_eq!(type_info, TypeInfo::try_from(expected, None).unwrap());
+    }
+    #[test]
+    fn test_convert_struct_argument_to_bytes() {
+        let type_info = TypeInfo {
+            kind: TypeInfoKind::Struct,
+            coding: StringCoding::ASCII,
+            has_variable_info: true,
+            has_trace_info: false,
+        };
+        let field_type_info = TypeInfo {
+            kind: TypeInfoKind::Unsigned(TypeLength::BitLength8, false),
+            coding: StringCoding::ASCII,
+            has_variable_info: false,
+            has_trace_info: false,
+        };
+        let field = Argument {
+            type_info: field_type_info,
+            name: None,
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: Value::U8(0x2A),
+        };
+        let mut expected = type_info.as_bytes::<BigEndian>();
+        expected.extend(vec![0x0, 0x2]); // number of entries
+        expected.extend(vec![0x0, 0x4]); // length of name + zero
+        expected.extend(b"pos\0");
+        expected.extend(field.as_bytes::<BigEndian>());
+        expected.extend(field.as_bytes::<BigEndian>());
+        let argument = Argument {
+            type_info,
+            name: Some("pos".to_string()),
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: Value::Struct(vec![field.clone(), field]),
+        };
+        assert_eq!(expected, argument.as_bytes::<BigEndian>());
+        assert_eq!("pos: {42, 42}", format!("{}", argument));
+        let info: u32 = 0b0000_0000_0000_0000_0100_1000_0000_0000;
+        assert_eq!(
+            info,
+            BigEndian::read_u32(&argument.type_info.as_bytes::<BigEndian>()[..])
+        );
+        assert_eq!(argument.type_info, TypeInfo::try_from(info, None).unwrap());
+    }
 }
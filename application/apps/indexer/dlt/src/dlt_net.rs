@@ -0,0 +1,432 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+use crate::dlt;
+use crate::dlt_parse::bytes_to_next_standard_header;
+use crate::dlt_tokio::Codec;
+use crate::fibex;
+use crate::filtering;
+use indexer_base::chunks::{Chunk, ChunkFactory};
+use indexer_base::error_reporter::*;
+use indexer_base::index_map::IndexMapWriter;
+use indexer_base::progress::*;
+use indexer_base::utils;
+
+use bytes::BytesMut;
+use failure::{err_msg, Error};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpStream, UdpSocket};
+use std::path;
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, SystemTime};
+use tokio::codec::Decoder;
+
+/// default port of the dlt-daemon
+pub const DLT_DAEMON_PORT: u16 = 3490;
+/// how long we block on a socket before checking for a shutdown request
+const SOCKET_POLL_TIMEOUT: Duration = Duration::from_millis(200);
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TcpSourceConfig {
+    /// e.g. "192.168.1.5:3490"
+    pub address: String,
+}
+
+/// like `IndexingConfig` but for sources that are not a file
+pub struct SocketIndexingConfig<'a> {
+    pub tag: &'a str,
+    pub chunk_size: usize,
+    pub out_path: &'a path::PathBuf,
+    pub append: bool,
+    pub to_stdout: bool,
+}
+
+/// writes received messages as tagged lines and keeps track of the chunks
+pub(crate) struct MessageIndexer<'a> {
+    tag: &'a str,
+    out_path: &'a path::PathBuf,
+    buf_writer: BufWriter<fs::File>,
+    chunk_factory: ChunkFactory,
+    line_nr: usize,
+    chunks: Vec<Chunk>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+}
+impl<'a> MessageIndexer<'a> {
+    pub(crate) fn new(
+        tag: &'a str,
+        out_path: &'a path::PathBuf,
+        chunk_size: usize,
+        append: bool,
+        to_stdout: bool,
+        update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    ) -> Result<MessageIndexer<'a>, Error> {
+        let line_nr = utils::next_line_nr(out_path).ok_or_else(|| {
+            err_msg(format!(
+                "could not determine last line number of {:?}",
+                out_path
+            ))
+        })?;
+        let (out_file, current_out_file_size) = utils::get_out_file_and_size(append, out_path)?;
+        Ok(MessageIndexer {
+            tag,
+            out_path,
+            buf_writer: BufWriter::new(out_file),
+            chunk_factory: ChunkFactory::new(chunk_size, to_stdout, current_out_file_size),
+            line_nr,
+            chunks: vec![],
+            update_channel,
+        })
+    }
+    /// also write an index map of the output
+    pub(crate) fn with_index_writer(mut self, writer: IndexMapWriter) -> MessageIndexer<'a> {
+        self.chunk_factory = self.chunk_factory.with_index_writer(writer);
+        self
+    }
+    pub(crate) fn write_message(&mut self, msg: &dlt::Message) -> Result<(), Error> {
+        let written_bytes_len =
+            utils::create_tagged_line_d(self.tag, &mut self.buf_writer, msg, self.line_nr, true)?;
+        self.line_nr += 1;
+        if let Some(chunk) = self
+            .chunk_factory
+            .create_chunk_if_needed(self.line_nr, written_bytes_len)
+        {
+            self.buf_writer.flush()?;
+            self.report_chunk(chunk);
+        }
+        Ok(())
+    }
+    fn report_chunk(&mut self, chunk: Chunk) {
+        self.update_channel.as_ref().map(|c| {
+            c.send(IndexingProgress::GotItem {
+                item: chunk.clone(),
+            })
+        });
+        self.chunks.push(chunk);
+    }
+    pub(crate) fn current_byte_index(&self) -> usize {
+        self.chunk_factory.get_current_byte_index()
+    }
+    /// make the lines written so far visible and report them as a chunk,
+    /// even if it is not full yet
+    pub(crate) fn flush(&mut self) -> Result<(), Error> {
+        self.buf_writer.flush()?;
+        if let Some(chunk) = self.chunk_factory.create_pending_chunk(self.line_nr) {
+            self.report_chunk(chunk);
+        }
+        Ok(())
+    }
+    pub(crate) fn finish(mut self) -> Result<Vec<Chunk>, Error> {
+        self.buf_writer.flush()?;
+        if let Some(chunk) = self
+            .chunk_factory
+            .create_last_chunk(self.line_nr, self.chunks.is_empty())
+        {
+            self.report_chunk(chunk);
+        }
+        self.chunk_factory.finish_index()?;
+        match self.chunks.last() {
+            Some(last_chunk) => {
+                let last_expected_byte_index =
+                    fs::metadata(self.out_path).map(|md| md.len() as usize)?;
+                if last_expected_byte_index != last_chunk.b.1 {
+                    report_error(format!(
+                        "error in computation! last byte in chunks is {} but should be {}",
+                        last_chunk.b.1, last_expected_byte_index
+                    ));
+                }
+            }
+            None => report_warning("output was empty!"),
+        }
+        if let Some(tx) = self.update_channel {
+            trace!("sending IndexingProgress::Finished");
+            tx.send(IndexingProgress::Finished)?;
+        }
+        Ok(self.chunks)
+    }
+}
+
+/// messages without storage header get one with the receive time
+fn with_storage_header(mut msg: dlt::Message, received: SystemTime) -> dlt::Message {
+    if msg.storage_header.is_none() {
+        msg.storage_header = Some(dlt::StorageHeader::received_at(
+            received,
+            msg.header.ecu_id.as_ref(),
+        ));
+    }
+    msg
+}
+
+pub(crate) fn shutdown_requested(shutdown_receiver: Option<&mpsc::Receiver<()>>) -> bool {
+    match shutdown_receiver.map(mpsc::Receiver::try_recv) {
+        // Shutdown if we have received a command or if there is
+        // nothing to send it.
+        Some(Ok(_)) | Some(Err(TryRecvError::Disconnected)) => {
+            info!("shutdown received in socket indexer");
+            true
+        }
+        // No shutdown command, continue
+        Some(Err(TryRecvError::Empty)) | None => false,
+    }
+}
+
+/// connect to a dlt-daemon and index all messages that are received
+/// until the connection is closed or a shutdown is requested
+/// fibex: used to decode non-verbose messages
+pub fn index_from_tcp(
+    source: &TcpSourceConfig,
+    config: SocketIndexingConfig,
+    dlt_filter: Option<filtering::DltFilterConfig>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<Vec<Chunk>, Error> {
+    trace!("index_from_tcp {}", source.address);
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let mut codec = Codec::new(
+        dlt_filter
+            .map(filtering::process_filter_config)
+            .transpose()?,
+        fibex_metadata,
+    );
+    let mut stream = TcpStream::connect(&source.address)
+        .map_err(|e| err_msg(format!("could not connect to {}: {}", source.address, e)))?;
+    stream.set_read_timeout(Some(SOCKET_POLL_TIMEOUT))?;
+
+    let mut indexer = MessageIndexer::new(
+        config.tag,
+        config.out_path,
+        config.chunk_size,
+        config.append,
+        config.to_stdout,
+        update_channel,
+    )?;
+    let mut received = BytesMut::with_capacity(READ_BUFFER_SIZE);
+    let mut read_buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        if shutdown_requested(shutdown_receiver.as_ref()) {
+            break;
+        }
+        match stream.read(&mut read_buf) {
+            Ok(0) => {
+                info!("connection to {} was closed", source.address);
+                break;
+            }
+            Ok(n) => {
+                let now = SystemTime::now();
+                received.extend_from_slice(&read_buf[..n]);
+                loop {
+                    match codec.decode(&mut received) {
+                        Ok(Some(msg)) => indexer.write_message(&with_storage_header(msg, now))?,
+                        Ok(None) => break,
+                        Err(e) => {
+                            // skip the corrupted message and continue with the next one
+                            let skipped = bytes_to_next_standard_header(&received);
+                            report_warning(format!(
+                                "skipped {} bytes of a corrupted message: {}",
+                                skipped, e
+                            ));
+                            received.split_to(skipped);
+                        }
+                    }
+                }
+            }
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                // nothing received for a while, report what we have
+                indexer.flush()?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => {
+                return Err(err_msg(format!(
+                    "error while receiving dlt messages: {}",
+                    e
+                )))
+            }
+        }
+    }
+    if !received.is_empty() {
+        report_warning(format!(
+            "{} bytes of an incomplete message were discarded",
+            received.len()
+        ));
+    }
+    indexer.finish()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MulticastInfo {
+    /// multicast group to join, e.g. "239.255.42.99"
+    pub multiaddr: String,
+    /// ipv4: address of the interface, ipv6: index of the interface
+    /// the default interface is used if not present
+    pub interface: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UdpSourceConfig {
+    /// local address to receive on, e.g. "0.0.0.0:3490"
+    pub bind_addr: String,
+    pub multicast: Vec<MulticastInfo>,
+}
+
+fn join_multicast(socket: &UdpSocket, info: &MulticastInfo) -> Result<(), Error> {
+    let multiaddr: IpAddr = info.multiaddr.parse().map_err(|e| {
+        err_msg(format!(
+            "invalid multicast address {}: {}",
+            info.multiaddr, e
+        ))
+    })?;
+    match multiaddr {
+        IpAddr::V4(addr) => {
+            let interface = match &info.interface {
+                Some(interface) => interface.parse::<Ipv4Addr>().map_err(|e| {
+                    err_msg(format!("invalid interface address {}: {}", interface, e))
+                })?,
+                None => Ipv4Addr::UNSPECIFIED,
+            };
+            socket.join_multicast_v4(&addr, &interface)?
+        }
+        IpAddr::V6(addr) => {
+            let interface = match &info.interface {
+                Some(interface) => interface.parse::<u32>().map_err(|e| {
+                    err_msg(format!("invalid interface index {}: {}", interface, e))
+                })?,
+                None => 0,
+            };
+            socket.join_multicast_v6(&addr, interface)?
+        }
+    }
+    Ok(())
+}
+
+/// all dlt messages contained in one datagram
+/// whatever cannot be decoded is dropped with a warning
+pub(crate) fn decode_datagram(
+    codec: &mut Codec,
+    datagram: &[u8],
+    origin: &dyn fmt::Display,
+) -> Vec<dlt::Message> {
+    let mut messages = vec![];
+    let mut datagram = BytesMut::from(datagram);
+    loop {
+        match codec.decode(&mut datagram) {
+            Ok(Some(msg)) => messages.push(msg),
+            Ok(None) => {
+                if !datagram.is_empty() {
+                    report_warning(format!(
+                        "datagram from {} ended with {} bytes of an incomplete message",
+                        origin,
+                        datagram.len()
+                    ));
+                }
+                break;
+            }
+            Err(e) => {
+                report_warning(format!("dropping rest of datagram from {}: {}", origin, e));
+                break;
+            }
+        }
+    }
+    messages
+}
+
+/// receive dlt messages over UDP (optionally from multicast groups) and index
+/// them until a shutdown is requested
+/// a datagram may contain multiple dlt messages but messages are not split
+/// across datagrams
+/// fibex: used to decode non-verbose messages
+pub fn index_from_udp(
+    source: &UdpSourceConfig,
+    config: SocketIndexingConfig,
+    dlt_filter: Option<filtering::DltFilterConfig>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<Vec<Chunk>, Error> {
+    trace!("index_from_udp {}", source.bind_addr);
+    let socket = UdpSocket::bind(&source.bind_addr)
+        .map_err(|e| err_msg(format!("could not bind to {}: {}", source.bind_addr, e)))?;
+    for info in &source.multicast {
+        join_multicast(&socket, info)?;
+    }
+    index_from_udp_socket(
+        socket,
+        config,
+        dlt_filter,
+        update_channel,
+        shutdown_receiver,
+        fibex,
+    )
+}
+pub(crate) fn index_from_udp_socket(
+    socket: UdpSocket,
+    config: SocketIndexingConfig,
+    dlt_filter: Option<filtering::DltFilterConfig>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<Vec<Chunk>, Error> {
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let mut codec = Codec::new(
+        dlt_filter
+            .map(filtering::process_filter_config)
+            .transpose()?,
+        fibex_metadata,
+    );
+    socket.set_read_timeout(Some(SOCKET_POLL_TIMEOUT))?;
+
+    let mut indexer = MessageIndexer::new(
+        config.tag,
+        config.out_path,
+        config.chunk_size,
+        config.append,
+        config.to_stdout,
+        update_channel,
+    )?;
+    let mut read_buf = vec![0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        if shutdown_requested(shutdown_receiver.as_ref()) {
+            break;
+        }
+        match socket.recv_from(&mut read_buf) {
+            Ok((n, from)) => {
+                let now = SystemTime::now();
+                for msg in decode_datagram(&mut codec, &read_buf[..n], &from) {
+                    indexer.write_message(&with_storage_header(msg, now))?;
+                }
+            }
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                // nothing received for a while, report what we have
+                indexer.flush()?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => {
+                return Err(err_msg(format!(
+                    "error while receiving dlt messages: {}",
+                    e
+                )))
+            }
+        }
+    }
+    indexer.finish()
+}
@@ -11,53 +11,47 @@
 // from E.S.R.Labs.
 use crate::dlt;
 use crate::dlt::TryFrom;
+use crate::fibex;
 use crate::filtering;
+use crate::lifecycle::{self, AnnotatedMessage, Lifecycle, LifecycleAnnotator, LifecycleDetector};
+use crate::message_ref::{self, MessageRef};
+use crate::parallel;
 use indexer_base::chunks::{Chunk, ChunkFactory};
-use indexer_base::config::IndexingConfig;
+use indexer_base::compression;
+use indexer_base::config::{IndexingConfig, TimeWindowEnd};
 use indexer_base::error_reporter::*;
+use indexer_base::follow;
+use indexer_base::index_map;
 use indexer_base::progress::*;
+use indexer_base::token_index;
 use indexer_base::utils;
 use serde::Serialize;
 use std::sync::mpsc::{self, TryRecvError};
 
 use buf_redux::policy::MinBuffered;
 use buf_redux::BufReader as ReduxReader;
-use byteorder::{BigEndian, LittleEndian};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use failure::{err_msg, Error};
-use nom::bytes::streaming::{tag, take, take_while_m_n};
+use nom::bytes::streaming::{take, take_while_m_n};
 use nom::{combinator::map, multi::count, number::streaming, sequence::tuple, IResult};
 use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::io::{BufRead, BufWriter, Read, Write};
 
 use std::str;
 
-const REPORT_PROGRESS_LINE_BLOCK: usize = 250_000;
+pub(crate) const REPORT_PROGRESS_LINE_BLOCK: usize = 250_000;
 
-fn parse_ecu_id(input: &[u8]) -> IResult<&[u8], &str> {
+pub(crate) fn parse_ecu_id(input: &[u8]) -> IResult<&[u8], &str> {
     dlt_zero_terminated_string(input, 4)
 }
 fn dlt_storage_header(input: &[u8]) -> IResult<&[u8], Option<dlt::StorageHeader>> {
-    let (i, (_, _, seconds, microseconds)) = tuple((
-        tag("DLT"),
-        tag(&[0x01]),
-        streaming::le_u32,
-        streaming::le_u32,
-    ))(input)?;
-    let (rest, ecu_id) = dlt_zero_terminated_string(i, 4)?;
-    Ok((
-        rest,
-        Some(dlt::StorageHeader {
-            timestamp: dlt::DltTimeStamp {
-                seconds,
-                microseconds,
-            },
-            ecu_id: ecu_id.to_string(),
-        }),
-    ))
+    map(message_ref::storage_header_ref, |h| Some(h.to_owned()))(input)
 }
 
-fn maybe_parse_ecu_id(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<&str>> {
+pub(crate) fn maybe_parse_ecu_id(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<&str>> {
     fn parse_ecu_id_to_option(input: &[u8]) -> IResult<&[u8], Option<&str>> {
         map(parse_ecu_id, Some)(input)
     }
@@ -70,7 +64,7 @@ fn maybe_parse_ecu_id(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<&str>>
         parse_nothing_str
     }
 }
-fn maybe_parse_u32(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<u32>> {
+pub(crate) fn maybe_parse_u32(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<u32>> {
     fn parse_u32_to_option(input: &[u8]) -> IResult<&[u8], Option<u32>> {
         map(streaming::be_u32, Some)(input)
     }
@@ -87,58 +81,14 @@ fn maybe_parse_u32(a: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Option<u32>> {
 /// The standard header is part of every DLT message
 /// all big endian format [PRS_Dlt_00091]
 fn dlt_standard_header(input: &[u8]) -> IResult<&[u8], dlt::StandardHeader> {
-    let (rest, header_type) = streaming::be_u8(input)?;
-    let has_ecu_id = (header_type & dlt::WITH_ECU_ID_FLAG) != 0;
-    let has_session_id = (header_type & dlt::WITH_SESSION_ID_FLAG) != 0;
-    let has_timestamp = (header_type & dlt::WITH_TIMESTAMP_FLAG) != 0;
-    let (i, (message_counter, len, ecu_id, session_id, timestamp)) = tuple((
-        streaming::be_u8,
-        streaming::be_u16,
-        maybe_parse_ecu_id(has_ecu_id),
-        maybe_parse_u32(has_session_id),
-        maybe_parse_u32(has_timestamp),
-    ))(rest)?;
-
-    Ok((
-        i,
-        dlt::StandardHeader {
-            version: header_type >> 5 & 0b111,
-            has_extended_header: (header_type & dlt::WITH_EXTENDED_HEADER_FLAG) != 0,
-            big_endian: (header_type & dlt::BIG_ENDIAN_FLAG) != 0,
-            message_counter,
-            overall_length: len,
-            ecu_id: ecu_id.map(|r| r.to_string()),
-            session_id,
-            timestamp,
-        },
-    ))
+    map(message_ref::standard_header_ref, |h| h.to_owned())(input)
 }
 
 fn dlt_extended_header(input: &[u8], index: Option<usize>) -> IResult<&[u8], dlt::ExtendedHeader> {
-    let (i, (message_info, argument_count, app_id, context_id)) = tuple((
-        streaming::be_u8,
-        streaming::be_u8,
-        parse_ecu_id,
-        parse_ecu_id,
-    ))(input)?;
-
-    let verbose = (message_info & dlt::VERBOSE_FLAG) != 0;
-    match dlt::MessageType::try_from(message_info, index) {
-        Ok(message_type) => Ok((
-            i,
-            dlt::ExtendedHeader {
-                verbose,
-                argument_count,
-                message_type,
-                application_id: app_id.to_string(),
-                context_id: context_id.to_string(),
-            },
-        )),
-        Err(e) => {
-            report_error_ln(format!("Invalid message type: {}", e), index);
-            Err(nom::Err::Error((i, nom::error::ErrorKind::Verify)))
-        }
-    }
+    map(
+        |i| message_ref::extended_header_ref(i, index),
+        |h| h.to_owned(),
+    )(input)
 }
 #[inline]
 pub fn is_not_null(chr: u8) -> bool {
@@ -179,6 +129,7 @@ fn dlt_variable_name<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], String> {
     Ok((i2, name.to_string()))
 }
 pub trait NomByteOrder: Clone + Copy + Eq + Ord + PartialEq + PartialOrd {
+    fn is_big_endian() -> bool;
     fn parse_u16(i: &[u8]) -> IResult<&[u8], u16>;
     fn parse_i16(i: &[u8]) -> IResult<&[u8], i16>;
     fn parse_u32(i: &[u8]) -> IResult<&[u8], u32>;
@@ -192,6 +143,10 @@ pub trait NomByteOrder: Clone + Copy + Eq + Ord + PartialEq + PartialOrd {
 }
 
 impl NomByteOrder for BigEndian {
+    #[inline]
+    fn is_big_endian() -> bool {
+        true
+    }
     #[inline]
     fn parse_u16(i: &[u8]) -> IResult<&[u8], u16> {
         streaming::be_u16(i)
@@ -235,6 +190,10 @@ impl NomByteOrder for BigEndian {
 }
 
 impl NomByteOrder for LittleEndian {
+    #[inline]
+    fn is_big_endian() -> bool {
+        false
+    }
     #[inline]
     fn parse_u16(i: &[u8]) -> IResult<&[u8], u16> {
         streaming::le_u16(i)
@@ -277,7 +236,9 @@ impl NomByteOrder for LittleEndian {
     }
 }
 
-fn dlt_uint<T: NomByteOrder>(width: dlt::TypeLength) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
+pub(crate) fn dlt_uint<T: NomByteOrder>(
+    width: dlt::TypeLength,
+) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
     match width {
         dlt::TypeLength::BitLength8 => |i| map(streaming::be_u8, dlt::Value::U8)(i),
         dlt::TypeLength::BitLength16 => |i| map(T::parse_u16, dlt::Value::U16)(i),
@@ -286,7 +247,9 @@ fn dlt_uint<T: NomByteOrder>(width: dlt::TypeLength) -> fn(&[u8]) -> IResult<&[u
         dlt::TypeLength::BitLength128 => |i| map(T::parse_u128, dlt::Value::U128)(i),
     }
 }
-fn dlt_sint<T: NomByteOrder>(width: dlt::TypeLength) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
+pub(crate) fn dlt_sint<T: NomByteOrder>(
+    width: dlt::TypeLength,
+) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
     match width {
         dlt::TypeLength::BitLength8 => |i| map(streaming::be_i8, dlt::Value::I8)(i),
         dlt::TypeLength::BitLength16 => |i| map(T::parse_i16, dlt::Value::I16)(i),
@@ -295,13 +258,31 @@ fn dlt_sint<T: NomByteOrder>(width: dlt::TypeLength) -> fn(&[u8]) -> IResult<&[u
         dlt::TypeLength::BitLength128 => |i| map(T::parse_i128, dlt::Value::I128)(i),
     }
 }
-fn dlt_fint<T: NomByteOrder>(width: dlt::FloatWidth) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
+pub(crate) fn dlt_fint<T: NomByteOrder>(
+    width: dlt::FloatWidth,
+) -> fn(&[u8]) -> IResult<&[u8], dlt::Value> {
     match width {
         dlt::FloatWidth::Width32 => |i| map(T::parse_f32, dlt::Value::F32)(i),
         dlt::FloatWidth::Width64 => |i| map(T::parse_f64, dlt::Value::F64)(i),
     }
 }
-fn dlt_type_info<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::TypeInfo> {
+fn dlt_bool(input: &[u8]) -> IResult<&[u8], dlt::Value> {
+    map(streaming::be_u8, |v| dlt::Value::Bool(v != 0))(input)
+}
+/// parser and width in bytes for a single array entry
+#[allow(clippy::type_complexity)]
+pub(crate) fn dlt_array_entry<T: NomByteOrder>(
+    element_kind: &dlt::TypeInfoKind,
+) -> Option<(fn(&[u8]) -> IResult<&[u8], dlt::Value>, usize)> {
+    match element_kind {
+        dlt::TypeInfoKind::Bool => Some((dlt_bool, 1)),
+        dlt::TypeInfoKind::Signed(width, _) => Some((dlt_sint::<T>(*width), *width as usize / 8)),
+        dlt::TypeInfoKind::Unsigned(width, _) => Some((dlt_uint::<T>(*width), *width as usize / 8)),
+        dlt::TypeInfoKind::Float(width) => Some((dlt_fint::<T>(*width), *width as usize / 8)),
+        _ => None,
+    }
+}
+pub(crate) fn dlt_type_info<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::TypeInfo> {
     let (i, info) = T::parse_u32(input)?;
     match dlt::TypeInfo::try_from(info, None) {
         Ok(type_info) => Ok((i, type_info)),
@@ -311,7 +292,7 @@ fn dlt_type_info<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::TypeInfo>
         }
     }
 }
-fn dlt_fixed_point<T: NomByteOrder>(
+pub(crate) fn dlt_fixed_point<T: NomByteOrder>(
     input: &[u8],
     width: dlt::TypeLength,
 ) -> IResult<&[u8], dlt::FixedPoint> {
@@ -343,16 +324,78 @@ fn dlt_fixed_point<T: NomByteOrder>(
     }
 }
 fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument> {
+    dlt_nested_argument::<T>(input, 0)
+}
+/// `depth` is the number of structs the argument is part of
+fn dlt_nested_argument<T: NomByteOrder>(
+    input: &[u8],
+    depth: usize,
+) -> IResult<&[u8], dlt::Argument> {
     // println!("before dlt_argument, input: \t{:02X?}", input);
     let (i, type_info) = dlt_type_info::<T>(input)?;
     // println!(
     //     "after dlt_type_info, input: \t{:02X?}, type_info: {:?}",
     //     i, type_info
     // );
+    let (i, trace_info) = if type_info.has_trace_info {
+        map(dlt_variable_name::<T>, Some)(i)?
+    } else {
+        (i, None)
+    };
+    dlt_argument_value::<T>(i, type_info, trace_info, depth)
+}
+/// parses everything that follows the type info (and trace info) of an argument
+fn dlt_argument_value<T: NomByteOrder>(
+    i: &[u8],
+    type_info: dlt::TypeInfo,
+    trace_info: Option<String>,
+    depth: usize,
+) -> IResult<&[u8], dlt::Argument> {
     match type_info.kind {
-        // dlt::TypeInfoKind::Array => {
-        //     panic!("TODO: array not yet implemented"); // not yet impemented
-        // }
+        dlt::TypeInfoKind::Array(ref element_kind) => {
+            let (entry_parser, entry_width) = match dlt_array_entry::<T>(element_kind) {
+                Some(p) => p,
+                None => {
+                    report_error(format!("unsupported array type: {:?}", element_kind));
+                    return Err(nom::Err::Error((i, nom::error::ErrorKind::Verify)));
+                }
+            };
+            let (i2, dimension_cnt) = T::parse_u16(i)?;
+            let (i3, dimensions) = count(T::parse_u16, dimension_cnt as usize)(i2)?;
+            let (before_fixed_point, (name, unit)) =
+                dlt_variable_name_and_unit::<T>(&type_info)(i3)?;
+            let (before_val, fixed_point) = match **element_kind {
+                dlt::TypeInfoKind::Signed(width, true)
+                | dlt::TypeInfoKind::Unsigned(width, true) => {
+                    let (r, fp) = dlt_fixed_point::<T>(before_fixed_point, width)?;
+                    (r, Some(fp))
+                }
+                _ => (before_fixed_point, None),
+            };
+            let (entry_cnt, byte_cnt) = match dlt::array_entry_count(&dimensions)
+                .and_then(|cnt| cnt.checked_mul(entry_width).map(|bytes| (cnt, bytes)))
+            {
+                Some(cnt_and_bytes) => cnt_and_bytes,
+                None => {
+                    report_error(format!("array too large: {:?}", dimensions));
+                    return Err(nom::Err::Error((before_val, nom::error::ErrorKind::Verify)));
+                }
+            };
+            // make sure all entries are available before collecting them
+            let (rest, entry_bytes) = take(byte_cnt)(before_val)?;
+            let (_, values) = count(entry_parser, entry_cnt)(entry_bytes)?;
+            Ok((
+                rest,
+                dlt::Argument {
+                    trace_info,
+                    name,
+                    unit,
+                    value: dlt::Value::Array(dimensions, values),
+                    fixed_point,
+                    type_info,
+                },
+            ))
+        }
         dlt::TypeInfoKind::Signed(width, fixed_point) => {
             let (before_val, (name, unit)) = dlt_variable_name_and_unit::<T>(&type_info)(i)?;
             let (after_fixed_point, fixed_point) = if fixed_point {
@@ -365,6 +408,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     name,
                     unit,
                     value,
@@ -385,6 +429,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     name,
                     unit,
                     value,
@@ -401,6 +446,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     name,
                     unit,
                     value,
@@ -421,6 +467,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     name,
                     unit: None,
                     value,
@@ -439,6 +486,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     type_info,
                     name,
                     unit: None,
@@ -459,6 +507,7 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
             Ok((
                 rest,
                 dlt::Argument {
+                    trace_info,
                     name,
                     unit: None,
                     fixed_point: None,
@@ -467,6 +516,32 @@ fn dlt_argument<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], dlt::Argument>
                 },
             ))
         }
+        dlt::TypeInfoKind::Struct => {
+            if depth >= dlt::MAX_STRUCT_DEPTH {
+                return Err(nom::Err::Error((i, nom::error::ErrorKind::TooLarge)));
+            }
+            let (i2, field_cnt) = T::parse_u16(i)?;
+            let (i3, name) = if type_info.has_variable_info {
+                map(dlt_variable_name::<T>, Some)(i2)?
+            } else {
+                (i2, None)
+            };
+            let (rest, fields) = count(
+                |input| dlt_nested_argument::<T>(input, depth + 1),
+                field_cnt as usize,
+            )(i3)?;
+            Ok((
+                rest,
+                dlt::Argument {
+                    trace_info,
+                    name,
+                    unit: None,
+                    fixed_point: None,
+                    value: dlt::Value::Struct(fields),
+                    type_info,
+                },
+            ))
+        }
     }
 }
 
@@ -474,6 +549,245 @@ struct DltArgumentParser {
     current_index: Option<usize>,
 }
 
+/// decode a non-verbose payload (without message id) using the signals of the frame
+fn dlt_fibex_payload<'a, T: NomByteOrder>(
+    input: &'a [u8],
+    frame: &fibex::FrameMetadata,
+) -> IResult<&'a [u8], Vec<dlt::Argument>> {
+    let mut arguments = vec![];
+    let mut rest = input;
+    for pdu in &frame.pdus {
+        if pdu.signal_types.is_empty() {
+            if let Some(description) = &pdu.description {
+                arguments.push(dlt::Argument {
+                    type_info: dlt::TypeInfo {
+                        kind: dlt::TypeInfoKind::StringType,
+                        coding: dlt::StringCoding::UTF8,
+                        has_variable_info: false,
+                        has_trace_info: false,
+                    },
+                    name: None,
+                    unit: None,
+                    trace_info: None,
+                    fixed_point: None,
+                    value: dlt::Value::StringVal(description.clone()),
+                });
+            }
+        }
+        for signal_type in &pdu.signal_types {
+            let (r, argument) = dlt_argument_value::<T>(rest, signal_type.clone(), None, 0)?;
+            arguments.push(argument);
+            rest = r;
+        }
+    }
+    Ok((rest, arguments))
+}
+/// uses the FIBEX metadata to turn a non-verbose payload into arguments.
+/// If the message has no extended header, the frame can supply one.
+fn decode_non_verbose<T: NomByteOrder>(
+    payload: dlt::Payload,
+    extended_header: Option<dlt::ExtendedHeader>,
+    fibex_metadata: &fibex::FibexMetadata,
+    index: Option<usize>,
+) -> (dlt::Payload, Option<dlt::ExtendedHeader>) {
+    let (message_id, data) = match payload {
+        dlt::Payload::NonVerbose(message_id, data) => (message_id, data),
+        _ => return (payload, extended_header),
+    };
+    let frame = match fibex_metadata.frame(
+        message_id,
+        extended_header
+            .as_ref()
+            .map(|h| (h.application_id.as_ref(), h.context_id.as_ref())),
+    ) {
+        Some(frame) => frame,
+        None => return (dlt::Payload::NonVerbose(message_id, data), extended_header),
+    };
+    let arguments = match dlt_fibex_payload::<T>(&data, frame) {
+        Ok((_, arguments)) => arguments,
+        Err(e) => {
+            report_warning_ln(
+                format!(
+                    "non-verbose message {} does not match fibex frame {}: {:?}",
+                    message_id, frame.short_name, e
+                ),
+                index,
+            );
+            return (dlt::Payload::NonVerbose(message_id, data), extended_header);
+        }
+    };
+    let extended_header = match (extended_header, &frame.application_id, &frame.context_id) {
+        (None, Some(app_id), Some(context_id)) => Some(dlt::ExtendedHeader {
+            verbose: false,
+            argument_count: arguments.len() as u8,
+            message_type: frame
+                .message_type
+                .clone()
+                .unwrap_or(dlt::MessageType::Unknown((0, 0))),
+            application_id: app_id.clone(),
+            context_id: context_id.clone(),
+        }),
+        (h, _, _) => h,
+    };
+    (
+        dlt::Payload::NonVerboseDecoded(message_id, data, arguments),
+        extended_header,
+    )
+}
+fn dlt_control_id(input: &[u8]) -> IResult<&[u8], String> {
+    map(parse_ecu_id, |id| id.to_string())(input)
+}
+fn dlt_control_description<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], String> {
+    let (i, len) = T::parse_u16(input)?;
+    let (rest, description) = take(len)(i)?;
+    Ok((
+        rest,
+        String::from_utf8_lossy(description)
+            .trim_end_matches('\0')
+            .to_string(),
+    ))
+}
+/// log info as sent in a get_log_info response with status 6 or 7
+/// (7 additionally includes descriptions)
+fn dlt_log_info<T: NomByteOrder>(
+    input: &[u8],
+    with_descriptions: bool,
+) -> IResult<&[u8], Vec<dlt::ApplicationLogInfo>> {
+    let (mut rest, app_cnt) = T::parse_u16(input)?;
+    let mut applications = Vec::with_capacity(app_cnt as usize);
+    for _ in 0..app_cnt {
+        let (i, (application_id, context_cnt)) = tuple((dlt_control_id, T::parse_u16))(rest)?;
+        rest = i;
+        let mut contexts = Vec::with_capacity(context_cnt as usize);
+        for _ in 0..context_cnt {
+            let (i, (context_id, log_level, trace_status)) =
+                tuple((dlt_control_id, streaming::be_i8, streaming::be_i8))(rest)?;
+            let (i, description) = if with_descriptions {
+                map(dlt_control_description::<T>, Some)(i)?
+            } else {
+                (i, None)
+            };
+            rest = i;
+            contexts.push(dlt::ContextLogInfo {
+                context_id,
+                log_level,
+                trace_status,
+                description,
+            });
+        }
+        let (i, description) = if with_descriptions {
+            map(dlt_control_description::<T>, Some)(rest)?
+        } else {
+            (rest, None)
+        };
+        rest = i;
+        applications.push(dlt::ApplicationLogInfo {
+            application_id,
+            contexts,
+            description,
+        });
+    }
+    Ok((rest, applications))
+}
+/// parses the complete payload of a request or response control message
+fn dlt_control_message<'a, T: NomByteOrder>(
+    input: &'a [u8],
+    control_type: &dlt::ControlType,
+) -> IResult<&'a [u8], dlt::ControlMessage> {
+    if *control_type == dlt::ControlType::Time {
+        return Ok((input, dlt::ControlMessage::Time));
+    }
+    let (i, service_id) = map(T::parse_u32, dlt::ServiceId)(input)?;
+    if *control_type == dlt::ControlType::Request {
+        let (rest, request) = match service_id {
+            dlt::ServiceId::SET_LOG_LEVEL => map(
+                tuple((dlt_control_id, dlt_control_id, streaming::be_i8)),
+                |(application_id, context_id, log_level)| dlt::ControlRequest::SetLogLevel {
+                    application_id,
+                    context_id,
+                    log_level,
+                },
+            )(i)?,
+            dlt::ServiceId::SET_TRACE_STATUS => map(
+                tuple((dlt_control_id, dlt_control_id, streaming::be_i8)),
+                |(application_id, context_id, trace_status)| dlt::ControlRequest::SetTraceStatus {
+                    application_id,
+                    context_id,
+                    trace_status,
+                },
+            )(i)?,
+            dlt::ServiceId::GET_LOG_INFO => map(
+                tuple((streaming::be_u8, dlt_control_id, dlt_control_id)),
+                |(options, application_id, context_id)| dlt::ControlRequest::GetLogInfo {
+                    options,
+                    application_id,
+                    context_id,
+                },
+            )(i)?,
+            dlt::ServiceId::SET_DEFAULT_LOG_LEVEL => {
+                map(streaming::be_i8, dlt::ControlRequest::SetDefaultLogLevel)(i)?
+            }
+            dlt::ServiceId::SET_TIMING_PACKETS => map(streaming::be_u8, |v| {
+                dlt::ControlRequest::SetTimingPackets(v != 0)
+            })(i)?,
+            _ => (&i[i.len()..], dlt::ControlRequest::Other(i.to_vec())),
+        };
+        return Ok((rest, dlt::ControlMessage::Request(service_id, request)));
+    }
+    let (i, status) = map(streaming::be_u8, dlt::ResponseStatus::from)(i)?;
+    let (rest, response) = match (service_id, status) {
+        (dlt::ServiceId::GET_LOG_INFO, dlt::ResponseStatus::Other(v)) if v == 6 || v == 7 => {
+            map(
+                |i| dlt_log_info::<T>(i, v == 7),
+                dlt::ControlResponse::LogInfo,
+            )(i)?
+        }
+        (dlt::ServiceId::GET_DEFAULT_LOG_LEVEL, dlt::ResponseStatus::Ok) => {
+            map(streaming::be_i8, dlt::ControlResponse::DefaultLogLevel)(i)?
+        }
+        (dlt::ServiceId::GET_SOFTWARE_VERSION, dlt::ResponseStatus::Ok) => {
+            let (i2, len) = T::parse_u32(i)?;
+            map(take(len), |v: &[u8]| {
+                dlt::ControlResponse::SoftwareVersion(
+                    String::from_utf8_lossy(v)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                )
+            })(i2)?
+        }
+        (dlt::ServiceId::MESSAGE_BUFFER_OVERFLOW, dlt::ResponseStatus::Ok) => map(
+            tuple((streaming::be_u8, T::parse_u32)),
+            |(overflow, counter)| dlt::ControlResponse::MessageBufferOverflow {
+                overflow: overflow != 0,
+                counter,
+            },
+        )(i)?,
+        _ => (&i[i.len()..], dlt::ControlResponse::Other(i.to_vec())),
+    };
+    Ok((
+        rest,
+        dlt::ControlMessage::Response(service_id, status, response),
+    ))
+}
+/// control messages are always non-verbose, the payload starts with the service id
+fn dlt_control_payload<'a, T: NomByteOrder>(
+    input: &'a [u8],
+    control_type: &dlt::ControlType,
+    payload_length: usize,
+    index: Option<usize>,
+) -> IResult<&'a [u8], dlt::Payload> {
+    let (rest, payload) = take(payload_length)(input)?;
+    match dlt_control_message::<T>(payload, control_type) {
+        Ok((_, control_message)) => Ok((
+            rest,
+            dlt::Payload::Control(control_message, payload.to_vec()),
+        )),
+        Err(e) => {
+            report_warning_ln(format!("could not parse control message: {:?}", e), index);
+            dlt_payload::<T>(input, false, payload_length, 0)
+        }
+    }
+}
 fn dlt_payload<T: NomByteOrder>(
     input: &[u8],
     verbose: bool,
@@ -481,6 +795,9 @@ fn dlt_payload<T: NomByteOrder>(
     arg_cnt: u8,
 ) -> IResult<&[u8], dlt::Payload> {
     if !verbose {
+        if payload_length < 4 {
+            return Err(nom::Err::Error((input, nom::error::ErrorKind::Verify)));
+        }
         let (rest, (message_id, payload)) = tuple((T::parse_u32, take(payload_length - 4)))(input)?;
         Ok((rest, dlt::Payload::NonVerbose(message_id, payload.to_vec())))
     } else {
@@ -527,19 +844,46 @@ pub fn dlt_message<'a>(
     input: &'a [u8],
     filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
     index: Option<usize>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> IResult<&'a [u8], Option<dlt::Message>> {
+    let (after_storage_header, storage_header) = dlt_storage_header(input)?;
+    dlt_message_with_storage_header(
+        after_storage_header,
+        storage_header,
+        filter_config_opt,
+        index,
+        fibex_metadata,
+    )
+}
+/// parse a dlt message that was not stored in a file and thus
+/// starts right with the standard header (e.g. received over the network)
+pub fn dlt_message_without_storage_header<'a>(
+    input: &'a [u8],
+    filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
+    index: Option<usize>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> IResult<&'a [u8], Option<dlt::Message>> {
+    dlt_message_with_storage_header(input, None, filter_config_opt, index, fibex_metadata)
+}
+fn dlt_message_with_storage_header<'a>(
+    input: &'a [u8],
+    storage_header: Option<dlt::StorageHeader>,
+    filter_config_opt: Option<&filtering::ProcessedDltFilterConfig>,
+    index: Option<usize>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
 ) -> IResult<&'a [u8], Option<dlt::Message>> {
-    let (after_storage_and_normal_header, (storage_header, header)) =
-        tuple((dlt_storage_header, dlt_standard_header))(input)?;
+    let (after_storage_and_normal_header, header) = dlt_standard_header(input)?;
 
-    let payload_length = match validated_payload_length(&header, index) {
-        Some(length) => length,
-        None => {
-            return Err(nom::Err::Error((
-                after_storage_and_normal_header,
-                nom::error::ErrorKind::Verify,
-            )));
-        }
-    };
+    let payload_length =
+        match validated_payload_length(header.overall_length, header.header_type(), index) {
+            Some(length) => length,
+            None => {
+                return Err(nom::Err::Error((
+                    after_storage_and_normal_header,
+                    nom::error::ErrorKind::Verify,
+                )));
+            }
+        };
 
     let mut verbose: bool = false;
     let mut arg_count = 0;
@@ -552,165 +896,409 @@ pub fn dlt_message<'a>(
         (after_storage_and_normal_header, None)
     };
     if let Some(filter_config) = filter_config_opt {
-        if let Some(h) = &extended_header {
-            if let Some(min_filter_level) = filter_config.min_log_level {
-                if h.skip_with_level(min_filter_level) {
-                    // no need to parse further, skip payload
-                    let (after_message, _) = take(payload_length)(after_headers)?;
-                    return Ok((after_message, None));
-                }
-            }
-            if let Some(only_these_components) = &filter_config.app_ids {
-                if !only_these_components.contains(&h.application_id) {
-                    // no need to parse further, skip payload
-                    let (after_message, _) = take(payload_length)(after_headers)?;
-                    return Ok((after_message, None));
-                }
-            }
-            if let Some(only_these_context_ids) = &filter_config.context_ids {
-                if !only_these_context_ids.contains(&h.context_id) {
-                    // no need to parse further, skip payload
-                    let (after_message, _) = take(payload_length)(after_headers)?;
-                    return Ok((after_message, None));
-                }
-            }
-            if let Some(only_these_ecu_ids) = &filter_config.ecu_ids {
-                if let Some(ecu_id) = &header.ecu_id {
-                    if !only_these_ecu_ids.contains(ecu_id) {
-                        // no need to parse further, skip payload
-                        let (after_message, _) = take(payload_length)(after_headers)?;
-                        return Ok((after_message, None));
-                    }
+        let filtered_out = filter_config.is_outside_time_window(storage_header.as_ref(), &header)
+            || extended_header
+                .as_ref()
+                .map_or(false, |h| is_filtered_out(filter_config, h, &header));
+        if filtered_out {
+            // no need to parse further, skip payload
+            let (after_message, _) = take(payload_length)(after_headers)?;
+            return Ok((after_message, None));
+        }
+    }
+    let control_type = match &extended_header {
+        Some(dlt::ExtendedHeader {
+            message_type: dlt::MessageType::Control(control_type),
+            verbose: false,
+            ..
+        }) => Some(control_type),
+        _ => None,
+    };
+    // the payload is parsed only from the bytes announced in the header so
+    // a corrupted argument cannot swallow the messages that follow
+    let (i, payload_bytes) = take(payload_length)(after_headers)?;
+    let payload_res = match (control_type, header.big_endian) {
+        (Some(ct), true) => {
+            dlt_control_payload::<BigEndian>(payload_bytes, ct, payload_length, index)
+        }
+        (Some(ct), false) => {
+            dlt_control_payload::<LittleEndian>(payload_bytes, ct, payload_length, index)
+        }
+        (None, true) => dlt_payload::<BigEndian>(payload_bytes, verbose, payload_length, arg_count),
+        (None, false) => {
+            dlt_payload::<LittleEndian>(payload_bytes, verbose, payload_length, arg_count)
+        }
+    };
+    let (_, payload) = payload_res.map_err(|e| match e {
+        nom::Err::Incomplete(_) => nom::Err::Error((payload_bytes, nom::error::ErrorKind::Eof)),
+        e => e,
+    })?;
+    let (payload, extended_header) = match fibex_metadata {
+        Some(fibex) if !verbose => {
+            let had_extended_header = extended_header.is_some();
+            let (payload, extended_header) = if header.big_endian {
+                decode_non_verbose::<BigEndian>(payload, extended_header, fibex, index)
+            } else {
+                decode_non_verbose::<LittleEndian>(payload, extended_header, fibex, index)
+            };
+            // app and context id might only be known now
+            if let (false, Some(filter_config), Some(h)) =
+                (had_extended_header, filter_config_opt, &extended_header)
+            {
+                if is_filtered_out(filter_config, h, &header) {
+                    return Ok((i, None));
                 }
             }
+            (payload, extended_header)
+        }
+        _ => (payload, extended_header),
+    };
+    if let Some(filter_config) = filter_config_opt {
+        if filter_config.is_payload_filtered_out(&payload) {
+            return Ok((i, None));
         }
     }
-    let (i, payload) = if header.big_endian {
-        dlt_payload::<BigEndian>(after_headers, verbose, payload_length, arg_count)?
-    } else {
-        dlt_payload::<LittleEndian>(after_headers, verbose, payload_length, arg_count)?
+    let message = dlt::Message {
+        storage_header,
+        header,
+        extended_header,
+        payload,
     };
-    Ok((
-        i,
-        Some(dlt::Message {
-            storage_header,
-            header,
-            extended_header,
-            payload,
-        }),
-    ))
+    if let Some(expression) = filter_config_opt.and_then(|f| f.expression.as_ref()) {
+        if !expression.matches(&message) {
+            return Ok((i, None));
+        }
+    }
+    Ok((i, Some(message)))
+}
+fn is_filtered_out(
+    filter_config: &filtering::ProcessedDltFilterConfig,
+    extended_header: &dlt::ExtendedHeader,
+    header: &dlt::StandardHeader,
+) -> bool {
+    is_filtered_out_by_ids(
+        filter_config,
+        &extended_header.application_id,
+        &extended_header.context_id,
+        &extended_header.message_type,
+        header.ecu_id.as_ref().map(String::as_str),
+    )
+}
+/// checks the log level and id filters
+pub(crate) fn is_filtered_out_by_ids(
+    filter_config: &filtering::ProcessedDltFilterConfig,
+    app_id: &str,
+    context_id: &str,
+    message_type: &dlt::MessageType,
+    ecu_id: Option<&str>,
+) -> bool {
+    if let Some(min_filter_level) = filter_config.min_log_level_for(app_id, context_id) {
+        if message_type.skip_with_level(min_filter_level) {
+            return true;
+        }
+    }
+    if let Some(only_these_components) = &filter_config.app_ids {
+        if !only_these_components.contains(app_id) {
+            return true;
+        }
+    }
+    if let Some(only_these_context_ids) = &filter_config.context_ids {
+        if !only_these_context_ids.contains(context_id) {
+            return true;
+        }
+    }
+    if let Some(only_these_ecu_ids) = &filter_config.ecu_ids {
+        if let Some(ecu_id) = ecu_id {
+            if !only_these_ecu_ids.contains(ecu_id) {
+                return true;
+            }
+        }
+    }
+    false
 }
-fn validated_payload_length(header: &dlt::StandardHeader, index: Option<usize>) -> Option<usize> {
-    let message_length = header.overall_length as usize;
-    let headers_length = dlt::calculate_all_headers_length(header.header_type());
+pub(crate) fn validated_payload_length(
+    overall_length: u16,
+    header_type: u8,
+    index: Option<usize>,
+) -> Option<usize> {
+    let message_length = overall_length as usize;
+    let headers_length = dlt::calculate_all_headers_length(header_type);
     if message_length < headers_length {
         report_error_ln("Invalid header length", index);
         return None;
     }
     Some(message_length - headers_length)
 }
-pub fn dlt_app_id_context_id(
-    input: &[u8],
+pub fn dlt_app_id_context_id<'a>(
+    input: &'a [u8],
     index: Option<usize>,
-) -> IResult<&[u8], StatisticRowInfo> {
-    let (after_storage_and_normal_header, (_, header)) =
-        tuple((dlt_storage_header, dlt_standard_header))(input)?;
-
-    let payload_length = match validated_payload_length(&header, index) {
-        Some(length) => length,
-        None => {
-            return Err(nom::Err::Error((
-                after_storage_and_normal_header,
-                nom::error::ErrorKind::Verify,
-            )));
-        }
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> IResult<&'a [u8], StatisticRowInfo> {
+    let (after_message, msg) = message_ref::dlt_message_ref_at(input, index)?;
+    Ok((after_message, statistic_row_info(&msg, fibex_metadata)))
+}
+/// what the statistics need to know about a message
+pub fn statistic_row_info(
+    msg: &MessageRef,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> StatisticRowInfo {
+    let header = &msg.header;
+    let times = MessageTimes {
+        ecu_id: msg.ecu_id().map(str::to_string),
+        uptime: header.timestamp,
+        storage_time: msg.storage_time(),
     };
-    if !header.has_extended_header {
-        // no app id, skip rest
-        let (after_message, _) = take(payload_length)(after_storage_and_normal_header)?;
-        return Ok((
-            after_message,
-            StatisticRowInfo {
+    let payload = msg.payload;
+    let ecu_id = header.ecu_id.map(str::to_string);
+    let extended_header = match &msg.extended_header {
+        Some(extended_header) => extended_header,
+        None => {
+            // for non-verbose messages the fibex might know app and context id
+            if let Some(frame) = fibex_metadata.and_then(|fibex| {
+                let message_id = if header.big_endian {
+                    BigEndian::parse_u32(payload)
+                } else {
+                    LittleEndian::parse_u32(payload)
+                };
+                message_id
+                    .ok()
+                    .and_then(|(_, message_id)| fibex.frame(message_id, None))
+            }) {
+                if let (Some(app_id), Some(context_id)) = (&frame.application_id, &frame.context_id)
+                {
+                    let level = match frame.message_type {
+                        Some(dlt::MessageType::Log(level)) => Some(level),
+                        _ => None,
+                    };
+                    return StatisticRowInfo {
+                        app_id_context_id: Some((app_id.clone(), context_id.clone())),
+                        ecu_id,
+                        level,
+                        control_service: None,
+                        times,
+                        message_type: frame.message_type.clone(),
+                        verbose: false,
+                        payload_length: payload.len(),
+                    };
+                }
+            }
+            return StatisticRowInfo {
                 app_id_context_id: None,
-                ecu_id: header.ecu_id,
+                ecu_id,
                 level: None,
-            },
-        ));
-    }
-
-    let (after_headers, extended_header) =
-        dlt_extended_header(after_storage_and_normal_header, index)?;
-    // skip payload
-    let (after_message, _) = take(payload_length)(after_headers)?;
-    let level = match extended_header.message_type {
-        dlt::MessageType::Log(level) => Some(level),
+                control_service: None,
+                times,
+                message_type: None,
+                verbose: false,
+                payload_length: payload.len(),
+            };
+        }
+    };
+    // only the service id of control messages is of interest
+    let control_service = match (&extended_header.message_type, extended_header.verbose) {
+        (dlt::MessageType::Control(dlt::ControlType::Time), _) | (_, true) => None,
+        (dlt::MessageType::Control(_), false) => {
+            let service_id = if header.big_endian {
+                BigEndian::parse_u32(payload)
+            } else {
+                LittleEndian::parse_u32(payload)
+            };
+            service_id.ok().map(|(_, id)| dlt::ServiceId(id))
+        }
         _ => None,
     };
-    Ok((
-        after_message,
-        StatisticRowInfo {
-            app_id_context_id: Some((extended_header.application_id, extended_header.context_id)),
-            ecu_id: header.ecu_id,
-            level,
-        },
-    ))
+    StatisticRowInfo {
+        app_id_context_id: Some((
+            extended_header.application_id.to_string(),
+            extended_header.context_id.to_string(),
+        )),
+        ecu_id,
+        level: msg.log_level(),
+        control_service,
+        times,
+        verbose: extended_header.verbose,
+        message_type: Some(extended_header.message_type.clone()),
+        payload_length: payload.len(),
+    }
 }
 
-fn read_one_dlt_message<T: Read>(
+/// result of reading from a dlt file that might contain corrupted data
+pub(crate) enum ReadResult<T> {
+    /// a message was parsed from the given number of bytes
+    Parsed(usize, T),
+    /// the given number of bytes did not contain a valid message and has to be skipped
+    Skipped(usize),
+}
+pub(crate) fn read_one_dlt_message<T: Read>(
     reader: &mut ReduxReader<T, MinBuffered>,
     filter_config: Option<&filtering::ProcessedDltFilterConfig>,
     index: Option<usize>,
-) -> Result<Option<(usize, Option<dlt::Message>)>, Error> {
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> Result<Option<ReadResult<Option<dlt::Message>>>, Error> {
+    read_with_resync(reader, |content| {
+        dlt_message(content, filter_config, index, fibex_metadata)
+    })
+}
+/// read the next item using `parse`
+///
+/// corrupted data is not treated as an error: the bytes up to the next position
+/// that looks like the start of a message are reported as skipped instead.
+/// The same happens for a message that is cut off by the end of the file.
+fn read_with_resync<T, R, F>(
+    reader: &mut ReduxReader<R, MinBuffered>,
+    parse: F,
+) -> Result<Option<ReadResult<T>>, Error>
+where
+    R: Read,
+    F: Fn(&[u8]) -> IResult<&[u8], T>,
+{
     loop {
-        match reader.fill_buf() {
-            Ok(content) => {
-                if content.is_empty() {
-                    return Ok(None);
-                }
-                let available = content.len();
-
-                let res: nom::IResult<&[u8], Option<dlt::Message>> =
-                    dlt_message(content, filter_config, index);
-                match res {
-                    Ok(r) => {
-                        let consumed = available - r.0.len();
-                        break Ok(Some((consumed, r.1)));
+        if let Err(e) = reader.fill_buf() {
+            // a followed file was replaced, what is left of the old one
+            // (a message that was cut off) can not be completed anymore
+            if follow::is_reopened(&e) && !reader.buffer().is_empty() {
+                return Ok(Some(ReadResult::Skipped(reader.buffer().len())));
+            }
+            // a file that is followed has no new content yet, parse what we have
+            if !follow::is_waiting(&e) {
+                return Err(err_msg(format!("error while reading dlt messages: {}", e)));
+            }
+        }
+        let content = reader.buffer();
+        if content.is_empty() {
+            return Ok(None);
+        }
+        let available = content.len();
+        match parse(content) {
+            Ok((rest, item)) => return Ok(Some(ReadResult::Parsed(available - rest.len(), item))),
+            Err(nom::Err::Incomplete(_)) => {
+                // the buffer is way bigger than any dlt message, so not being
+                // able to read more means we hit the end of the file
+                reader.make_room();
+                let read = match reader.read_into_buf() {
+                    Ok(read) => read,
+                    // the rest of the message is not in the file that replaced it
+                    Err(ref e) if follow::is_reopened(e) => {
+                        return Ok(Some(ReadResult::Skipped(reader.buffer().len())))
                     }
-                    e => match e {
-                        Err(nom::Err::Incomplete(_)) => continue,
-                        Err(nom::Err::Error(_e)) => {
-                            return Err(err_msg(format!(
-                                "parsing error for dlt messages: {:?}",
-                                _e
-                            )));
-                        }
-                        Err(nom::Err::Failure(_e)) => {
-                            return Err(err_msg(format!(
-                                "parsing failure for dlt messages: {:?}",
-                                _e
-                            )));
-                        }
-                        _ => {
-                            return Err(err_msg(format!(
-                                "error while parsing dlt messages: {:?}",
-                                e
-                            )))
-                        }
-                    },
+                    // the rest of the message is not written yet
+                    Err(ref e) if follow::is_waiting(e) => return Ok(None),
+                    Err(e) => {
+                        return Err(err_msg(format!("error while reading dlt messages: {}", e)))
+                    }
+                };
+                if read == 0 {
+                    let skipped = bytes_to_next_message(reader.buffer());
+                    return Ok(Some(ReadResult::Skipped(skipped)));
                 }
             }
-            Err(e) => {
-                return Err(err_msg(format!("parsing error for dlt messages: {:?}", e)));
+            Err(_) => return Ok(Some(ReadResult::Skipped(bytes_to_next_message(content)))),
+        }
+    }
+}
+/// number of bytes that can be skipped to get to the next position that
+/// looks like the start of a dlt message: a storage header pattern followed
+/// by a standard header with a valid length
+pub(crate) fn bytes_to_next_message(content: &[u8]) -> usize {
+    let pattern_length = dlt::STORAGE_HEADER_PATTERN_LENGTH;
+    let mut pos = 1;
+    while pos + pattern_length <= content.len() {
+        if content[pos..].starts_with(dlt::STORAGE_HEADER_PATTERN)
+            && plausible_standard_header(&content[pos + pattern_length..])
+        {
+            return pos;
+        }
+        pos += 1;
+    }
+    // the end of the content could be the beginning of a storage header pattern
+    std::cmp::max(1, content.len().saturating_sub(pattern_length - 1))
+}
+/// `input` starts right after a storage header pattern
+fn plausible_standard_header(input: &[u8]) -> bool {
+    let header_start = dlt::STORAGE_HEADER_LENGTH - dlt::STORAGE_HEADER_PATTERN_LENGTH;
+    match input.get(header_start..header_start + dlt::HEADER_MIN_LENGTH) {
+        Some(header) => {
+            let overall_length = BigEndian::read_u16(&header[2..]) as usize;
+            overall_length >= dlt::calculate_all_headers_length(header[0])
+        }
+        // not enough data yet to tell, let the parser decide
+        None => true,
+    }
+}
+/// like `bytes_to_next_message` but for streams without storage headers
+/// (e.g. received from a dlt-daemon): skips to the next position that
+/// looks like a version 1 standard header with a valid length
+pub(crate) fn bytes_to_next_standard_header(content: &[u8]) -> usize {
+    let mut pos = 1;
+    while pos + dlt::HEADER_MIN_LENGTH <= content.len() {
+        let header_type = content[pos];
+        let overall_length = BigEndian::read_u16(&content[pos + 2..]) as usize;
+        if header_type >> 5 == 1 && overall_length >= dlt::calculate_all_headers_length(header_type)
+        {
+            return pos;
+        }
+        pos += 1;
+    }
+    // not enough data yet to tell if the rest starts a message
+    std::cmp::min(pos, content.len())
+}
+/// keeps track of byte ranges that had to be skipped because they
+/// did not contain valid dlt messages
+#[derive(Default)]
+pub(crate) struct SkippedRanges {
+    current: Option<(usize, usize)>,
+    range_count: usize,
+    skipped_bytes: usize,
+}
+impl SkippedRanges {
+    pub(crate) fn skipped(&mut self, offset: usize, len: usize) {
+        self.skipped_bytes += len;
+        match self.current {
+            Some((start, end)) if end == offset => self.current = Some((start, offset + len)),
+            _ => {
+                self.resynced();
+                self.current = Some((offset, offset + len));
             }
         }
     }
+    /// valid data was found again
+    pub(crate) fn resynced(&mut self) {
+        if let Some(range) = self.current.take() {
+            self.range_count += 1;
+            SkippedRanges::report(range);
+        }
+    }
+    pub(crate) fn finish(mut self) {
+        self.resynced();
+        if self.range_count > 1 {
+            report_warning(format!(
+                "skipped {} bytes of corrupted data in {} ranges",
+                self.skipped_bytes, self.range_count
+            ));
+        }
+    }
+    fn report((start, end): (usize, usize)) {
+        report_warning(format!(
+            "skipped {} bytes of corrupted data at offset {}..{}",
+            end - start,
+            start,
+            end
+        ));
+    }
 }
+/// threads: 1 to index on the current thread, otherwise the number of worker
+/// threads used by `index_dlt_file_parallel` (0 for one per cpu)
+/// annotate_lifecycles: see `index_dlt_file`
+#[allow(clippy::too_many_arguments)]
 pub fn create_index_and_mapping_dlt(
     config: IndexingConfig,
     source_file_size: Option<usize>,
     filter_conf: Option<filtering::DltFilterConfig>,
     update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
     shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+    threads: usize,
+    annotate_lifecycles: bool,
 ) -> Result<Vec<Chunk>, Error> {
     trace!("create_index_and_mapping_dlt");
     let initial_line_nr = match utils::next_line_nr(config.out_path) {
@@ -723,17 +1311,39 @@ pub fn create_index_and_mapping_dlt(
             std::process::exit(2)
         }
     };
-    index_dlt_file(
-        config,
-        filter_conf,
-        initial_line_nr,
-        source_file_size,
-        update_channel,
-        shutdown_receiver,
-    )
+    // a file that is followed is read as it grows, there is nothing to split
+    // and lifecycles can only be annotated in the order of the file
+    if threads == 1 || config.follow.is_some() || annotate_lifecycles {
+        index_dlt_file(
+            config,
+            filter_conf,
+            initial_line_nr,
+            source_file_size,
+            update_channel,
+            shutdown_receiver,
+            fibex,
+            annotate_lifecycles,
+        )
+    } else {
+        parallel::index_dlt_file_parallel(
+            config,
+            filter_conf,
+            initial_line_nr,
+            source_file_size,
+            update_channel,
+            shutdown_receiver,
+            fibex,
+            threads,
+        )
+    }
 }
 /// create index for a dlt file
 /// source_file_size: if progress updates should be made, add this value
+/// fibex: used to decode non-verbose messages
+/// annotate_lifecycles: append the lifecycle id and the absolute time of each
+/// message as extra columns (see `lifecycle::AnnotatedMessage`), the file is
+/// read twice for this
+#[allow(clippy::too_many_arguments)]
 pub fn index_dlt_file(
     config: IndexingConfig,
     dlt_filter: Option<filtering::DltFilterConfig>,
@@ -741,37 +1351,122 @@ pub fn index_dlt_file(
     source_file_size: Option<usize>,
     update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
     shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+    annotate_lifecycles: bool,
 ) -> Result<Vec<Chunk>, Error> {
     trace!("index_dlt_file");
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let filter_config: Option<filtering::ProcessedDltFilterConfig> = dlt_filter
+        .map(filtering::process_filter_config)
+        .transpose()?;
+    let lifecycles = if annotate_lifecycles {
+        if config.follow.is_some() {
+            return Err(err_msg(
+                "lifecycles can not be annotated while following a file",
+            ));
+        }
+        Some(lifecycle::detect_lifecycles(
+            compression::open_input(config.in_file.try_clone()?, config.zip_entry)?,
+            filter_config.as_ref(),
+            fibex_metadata.as_ref(),
+        )?)
+    } else {
+        None
+    };
+    let mut annotator = lifecycles.as_ref().map(|l| LifecycleAnnotator::new(l));
     let (out_file, current_out_file_size) =
         utils::get_out_file_and_size(config.append, &config.out_path)?;
 
     let mut chunks = vec![];
     let mut chunk_factory =
         ChunkFactory::new(config.chunk_size, config.to_stdout, current_out_file_size);
+    if let Some(index_map) = &config.index_map {
+        chunk_factory = chunk_factory.with_index_writer(index_map::writer_for(
+            index_map,
+            &config.in_file,
+            config.append,
+        )?);
+    }
+    if let Some(token_index) = config.token_index {
+        chunk_factory = chunk_factory.with_token_writer(token_index::writer_for(
+            token_index,
+            &config.in_file,
+            config.append,
+            config.chunk_size,
+        )?);
+    }
 
-    let mut reader = ReduxReader::with_capacity(10 * 1024 * 1024, config.in_file)
-        .set_policy(MinBuffered(10 * 1024));
+    let compression::Input { reader, progress } = match config.follow {
+        Some(path) => follow::open_input(config.in_file, path)?,
+        None => compression::open_input(config.in_file, config.zip_entry)?,
+    };
+    let mut reader =
+        ReduxReader::with_capacity(10 * 1024 * 1024, reader).set_policy(MinBuffered(10 * 1024));
     let mut line_nr = initial_line_nr;
     let mut processed_lines = 0usize;
     let mut buf_writer = BufWriter::with_capacity(10 * 1024 * 1024, out_file);
 
     let mut processed_bytes = utils::get_processed_bytes(config.append, &config.out_path) as usize;
     let mut stopped = false;
-    let filter_config: Option<filtering::ProcessedDltFilterConfig> =
-        dlt_filter.map(filtering::process_filter_config);
+    let mut offset = 0usize;
+    let mut skipped_ranges = SkippedRanges::default();
+    let mut time_window_end = filter_config
+        .as_ref()
+        .and_then(|f| f.storage_time_end())
+        .map(TimeWindowEnd::new);
     loop {
         // println!("line index: {}", line_nr);
         if stopped {
             info!("we where stopped in dlt-indexer",);
             break;
         };
-        match read_one_dlt_message(&mut reader, filter_config.as_ref(), Some(line_nr)) {
-            Ok(Some((consumed, Some(msg)))) => {
+        let res = read_one_dlt_message(
+            &mut reader,
+            filter_config.as_ref(),
+            Some(line_nr),
+            fibex_metadata.as_ref(),
+        );
+        if let (Ok(Some(ReadResult::Parsed(..))), Some(window_end)) = (&res, &mut time_window_end) {
+            // the message is still in the buffer
+            if let Ok((_, Some(storage_header))) = dlt_storage_header(reader.buffer()) {
+                if window_end.is_passed(storage_header.timestamp.as_millis()) {
+                    info!("end of time window reached at line {}", line_nr);
+                    break;
+                }
+            }
+        }
+        match res {
+            Ok(Some(ReadResult::Parsed(consumed, Some(msg)))) => {
                 // println!("consumed: {}", consumed);
                 reader.consume(consumed);
-                let written_bytes_len =
-                    utils::create_tagged_line_d(config.tag, &mut buf_writer, &msg, line_nr, true)?;
+                skipped_ranges.resynced();
+                offset += consumed;
+                let annotated;
+                let line: &dyn fmt::Display = match annotator.as_mut() {
+                    Some(annotator) => {
+                        annotated = AnnotatedMessage {
+                            message: &msg,
+                            annotation: annotator.annotate_message(&msg),
+                        };
+                        &annotated
+                    }
+                    None => &msg,
+                };
+                let written_bytes_len = if chunk_factory.indexes_tokens() {
+                    let formatted = line.to_string();
+                    chunk_factory.add_tokens(formatted.as_bytes());
+                    utils::create_tagged_line_d(
+                        config.tag,
+                        &mut buf_writer,
+                        formatted,
+                        line_nr,
+                        true,
+                    )?
+                } else {
+                    utils::create_tagged_line_d(config.tag, &mut buf_writer, line, line_nr, true)?
+                };
                 processed_bytes += consumed;
                 line_nr += 1;
                 processed_lines += 1;
@@ -804,7 +1499,7 @@ pub fn index_dlt_file(
                     utils::report_progress(
                         processed_lines,
                         chunk_factory.get_current_byte_index(),
-                        processed_bytes,
+                        progress.bytes(processed_bytes),
                         file_size,
                         REPORT_PROGRESS_LINE_BLOCK,
                     );
@@ -812,26 +1507,50 @@ pub fn index_dlt_file(
                         trace!("dlt progress");
                         update_channel.as_ref().map(|c| {
                             c.send(IndexingProgress::Progress {
-                                ticks: (processed_bytes, file_size),
+                                ticks: (progress.bytes(processed_bytes), file_size),
                             })
                         });
                     }
                 }
             }
-            Ok(Some((consumed, None))) => {
+            Ok(Some(ReadResult::Parsed(consumed, None))) => {
                 reader.consume(consumed);
+                skipped_ranges.resynced();
+                offset += consumed;
                 processed_bytes += consumed;
                 processed_lines += 1;
                 if let Some(file_size) = source_file_size {
                     utils::report_progress(
                         processed_lines,
                         chunk_factory.get_current_byte_index(),
-                        processed_bytes,
+                        progress.bytes(processed_bytes),
                         file_size,
                         REPORT_PROGRESS_LINE_BLOCK,
                     );
                 }
             }
+            Ok(Some(ReadResult::Skipped(skipped))) => {
+                reader.consume(skipped);
+                skipped_ranges.skipped(offset, skipped);
+                offset += skipped;
+                processed_bytes += skipped;
+            }
+            Ok(None) if config.follow.is_some() => {
+                // all of the file is indexed, report the messages so far
+                buf_writer.flush()?;
+                if let Some(chunk) = chunk_factory.create_pending_chunk(line_nr) {
+                    update_channel.as_ref().map(|c| {
+                        c.send(IndexingProgress::GotItem {
+                            item: chunk.clone(),
+                        })
+                    });
+                    chunks.push(chunk);
+                }
+                if follow::wait(shutdown_receiver.as_ref()) {
+                    info!("stopped following {:?}", config.follow);
+                    break;
+                }
+            }
             Ok(None) => {
                 // println!("nothing more to parse");
                 break;
@@ -839,8 +1558,25 @@ pub fn index_dlt_file(
             Err(e) => return Err(err_msg(format!("error while parsing dlt messages: {}", e))),
         }
     }
+    skipped_ranges.finish();
 
     buf_writer.flush()?;
+    finish_chunks(
+        chunk_factory,
+        line_nr,
+        chunks,
+        config.out_path,
+        update_channel,
+    )
+}
+/// add the chunk for the last lines and check it against the size of the output
+pub(crate) fn finish_chunks(
+    mut chunk_factory: ChunkFactory,
+    line_nr: usize,
+    mut chunks: Vec<Chunk>,
+    out_path: &std::path::PathBuf,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+) -> Result<Vec<Chunk>, Error> {
     if let Some(chunk) = chunk_factory.create_last_chunk(line_nr, chunks.is_empty()) {
         update_channel.as_ref().map(|c| {
             c.send(IndexingProgress::GotItem {
@@ -849,10 +1585,10 @@ pub fn index_dlt_file(
         });
         chunks.push(chunk);
     }
+    chunk_factory.finish_index()?;
     match chunks.last() {
         Some(last_chunk) => {
-            let last_expected_byte_index =
-                fs::metadata(config.out_path).map(|md| md.len() as usize)?;
+            let last_expected_byte_index = fs::metadata(out_path).map(|md| md.len() as usize)?;
             if last_expected_byte_index != last_chunk.b.1 {
                 report_error(format!(
                     "error in computation! last byte in chunks is {} but should be {}",
@@ -917,114 +1653,340 @@ impl LevelDistribution {
             },
         }
     }
+    pub fn count(&self) -> usize {
+        self.non_log
+            + self.log_fatal
+            + self.log_error
+            + self.log_warning
+            + self.log_info
+            + self.log_debug
+            + self.log_verbose
+            + self.log_invalid
+    }
 }
 type IdMap = FxHashMap<String, LevelDistribution>;
 
-fn add_for_level(level: Option<dlt::LogLevel>, ids: &mut IdMap, id: String) {
-    if let Some(n) = ids.get_mut(&id) {
-        match level {
-            Some(dlt::LogLevel::Fatal) => {
-                *n = LevelDistribution {
-                    log_fatal: n.log_fatal + 1,
-                    ..*n
-                }
-            }
-            Some(dlt::LogLevel::Error) => {
-                *n = LevelDistribution {
-                    log_error: n.log_error + 1,
-                    ..*n
-                }
-            }
-            Some(dlt::LogLevel::Warn) => {
-                *n = LevelDistribution {
-                    log_warning: n.log_warning + 1,
-                    ..*n
-                }
-            }
-            Some(dlt::LogLevel::Info) => {
-                *n = LevelDistribution {
-                    log_info: n.log_info + 1,
-                    ..*n
-                }
-            }
-            Some(dlt::LogLevel::Debug) => {
-                *n = LevelDistribution {
-                    log_debug: n.log_debug + 1,
-                    ..*n
-                };
+fn add_level(n: &mut LevelDistribution, level: Option<dlt::LogLevel>) {
+    match level {
+        Some(dlt::LogLevel::Fatal) => {
+            *n = LevelDistribution {
+                log_fatal: n.log_fatal + 1,
+                ..*n
             }
-            Some(dlt::LogLevel::Verbose) => {
-                *n = LevelDistribution {
-                    log_verbose: n.log_verbose + 1,
-                    ..*n
-                };
+        }
+        Some(dlt::LogLevel::Error) => {
+            *n = LevelDistribution {
+                log_error: n.log_error + 1,
+                ..*n
             }
-            Some(dlt::LogLevel::Invalid(_)) => {
-                *n = LevelDistribution {
-                    log_invalid: n.log_invalid + 1,
-                    ..*n
-                };
+        }
+        Some(dlt::LogLevel::Warn) => {
+            *n = LevelDistribution {
+                log_warning: n.log_warning + 1,
+                ..*n
             }
-            None => {
-                *n = LevelDistribution {
-                    non_log: n.non_log + 1,
-                    ..*n
-                };
+        }
+        Some(dlt::LogLevel::Info) => {
+            *n = LevelDistribution {
+                log_info: n.log_info + 1,
+                ..*n
             }
         }
+        Some(dlt::LogLevel::Debug) => {
+            *n = LevelDistribution {
+                log_debug: n.log_debug + 1,
+                ..*n
+            };
+        }
+        Some(dlt::LogLevel::Verbose) => {
+            *n = LevelDistribution {
+                log_verbose: n.log_verbose + 1,
+                ..*n
+            };
+        }
+        Some(dlt::LogLevel::Invalid(_)) => {
+            *n = LevelDistribution {
+                log_invalid: n.log_invalid + 1,
+                ..*n
+            };
+        }
+        None => {
+            *n = LevelDistribution {
+                non_log: n.non_log + 1,
+                ..*n
+            };
+        }
+    }
+}
+fn add_for_level(level: Option<dlt::LogLevel>, ids: &mut IdMap, id: String) {
+    if let Some(n) = ids.get_mut(&id) {
+        add_level(n, level);
     } else {
         ids.insert(id, LevelDistribution::new(level));
     }
 }
+/// first and last storage time (posix timestamps in ms)
+#[derive(Serialize, Debug, Clone, Copy)]
+struct TimeSpan {
+    first: i64,
+    last: i64,
+}
+fn add_time(spans: &mut FxHashMap<String, TimeSpan>, id: &str, time: i64) {
+    match spans.get_mut(id) {
+        Some(span) => {
+            span.first = span.first.min(time);
+            span.last = span.last.max(time);
+        }
+        None => {
+            spans.insert(
+                id.to_string(),
+                TimeSpan {
+                    first: time,
+                    last: time,
+                },
+            );
+        }
+    }
+}
+fn sorted_by_id(spans: FxHashMap<String, TimeSpan>) -> Vec<(String, TimeSpan)> {
+    let mut spans: Vec<(String, TimeSpan)> = spans.into_iter().collect();
+    spans.sort_by(|a, b| a.0.cmp(&b.0));
+    spans
+}
+#[derive(Serialize, Debug, Default)]
+struct MessageTypeDistribution {
+    log: usize,
+    app_trace: usize,
+    network_trace: usize,
+    control: usize,
+    unknown: usize,
+    /// messages without extended header (and no type from a fibex)
+    no_type: usize,
+}
+impl MessageTypeDistribution {
+    fn add(&mut self, message_type: Option<&dlt::MessageType>) {
+        match message_type {
+            Some(dlt::MessageType::Log(_)) => self.log += 1,
+            Some(dlt::MessageType::ApplicationTrace(_)) => self.app_trace += 1,
+            Some(dlt::MessageType::NetworkTrace(_)) => self.network_trace += 1,
+            Some(dlt::MessageType::Control(_)) => self.control += 1,
+            Some(dlt::MessageType::Unknown(_)) => self.unknown += 1,
+            None => self.no_type += 1,
+        }
+    }
+}
+/// sizes of the payloads in bytes
+#[derive(Serialize, Debug)]
+struct PayloadSizes {
+    min: usize,
+    max: usize,
+    average: f64,
+    total: usize,
+}
+/// number of messages per time bucket, the first bucket starts at `start`
+/// (posix timestamp in ms)
+#[derive(Serialize, Debug)]
+struct MessageRate {
+    start: i64,
+    bucket_ms: i64,
+    counts: Vec<usize>,
+}
+/// the buckets of the message rate are chosen so there are no more than this
+const MAX_RATE_BUCKETS: i64 = 100;
+#[derive(Serialize, Debug)]
+struct IdCombination {
+    ecu_id: String,
+    app_id: String,
+    context_id: String,
+    count: usize,
+    levels: LevelDistribution,
+}
 #[derive(Serialize, Debug)]
 pub struct StatisticInfo {
     app_ids: Vec<(String, LevelDistribution)>,
     context_ids: Vec<(String, LevelDistribution)>,
     ecu_ids: Vec<(String, LevelDistribution)>,
+    /// number of request/response control messages per service
+    control_services: Vec<(String, usize)>,
+    lifecycles: Vec<Lifecycle>,
+    /// messages per combination of ecu, app and context id
+    ecu_app_context_ids: Vec<IdCombination>,
+    app_id_times: Vec<(String, TimeSpan)>,
+    context_id_times: Vec<(String, TimeSpan)>,
+    ecu_id_times: Vec<(String, TimeSpan)>,
+    /// `None` if there was no message
+    message_rate: Option<MessageRate>,
+    verbose: usize,
+    non_verbose: usize,
+    message_types: MessageTypeDistribution,
+    /// `None` if there was no message
+    payload_sizes: Option<PayloadSizes>,
+}
+#[derive(Default)]
+struct StatisticCollector {
+    app_ids: IdMap,
+    context_ids: IdMap,
+    ecu_ids: IdMap,
+    control_services: FxHashMap<String, usize>,
+    lifecycles: LifecycleDetector,
+    id_combinations: FxHashMap<(String, String, String), LevelDistribution>,
+    app_id_times: FxHashMap<String, TimeSpan>,
+    context_id_times: FxHashMap<String, TimeSpan>,
+    ecu_id_times: FxHashMap<String, TimeSpan>,
+    /// number of messages per second
+    messages_per_second: BTreeMap<i64, usize>,
+    verbose: usize,
+    non_verbose: usize,
+    message_types: MessageTypeDistribution,
+    min_payload: Option<usize>,
+    max_payload: usize,
+    total_payload: usize,
+    message_count: usize,
+}
+impl StatisticCollector {
+    fn add(&mut self, row: StatisticRowInfo) {
+        let times = &row.times;
+        self.lifecycles
+            .add(times.ecu_id.as_deref(), times.uptime, times.storage_time);
+        if let Some(service) = &row.control_service {
+            *self
+                .control_services
+                .entry(service.to_string())
+                .or_insert(0) += 1;
+        }
+        let (app_id, context_id) = row
+            .app_id_context_id
+            .unwrap_or_else(|| ("NONE".to_string(), "NONE".to_string()));
+        let ecu_id = row.ecu_id.unwrap_or_else(|| "NONE".to_string());
+        let level = row.level;
+        if let Some(time) = times.storage_time {
+            add_time(&mut self.app_id_times, &app_id, time);
+            add_time(&mut self.context_id_times, &context_id, time);
+            add_time(&mut self.ecu_id_times, &ecu_id, time);
+            *self.messages_per_second.entry(time / 1000).or_insert(0) += 1;
+        }
+        add_for_level(level, &mut self.app_ids, app_id.clone());
+        add_for_level(level, &mut self.context_ids, context_id.clone());
+        add_for_level(level, &mut self.ecu_ids, ecu_id.clone());
+        let key = (ecu_id, app_id, context_id);
+        match self.id_combinations.get_mut(&key) {
+            Some(levels) => add_level(levels, level),
+            None => {
+                self.id_combinations
+                    .insert(key, LevelDistribution::new(level));
+            }
+        }
+        if row.verbose {
+            self.verbose += 1;
+        } else {
+            self.non_verbose += 1;
+        }
+        self.message_types.add(row.message_type.as_ref());
+        let payload_length = row.payload_length;
+        self.min_payload = Some(
+            self.min_payload
+                .map_or(payload_length, |min| min.min(payload_length)),
+        );
+        self.max_payload = self.max_payload.max(payload_length);
+        self.total_payload += payload_length;
+        self.message_count += 1;
+    }
+    fn message_rate(&self) -> Option<MessageRate> {
+        let first = *self.messages_per_second.keys().next()?;
+        let last = *self.messages_per_second.keys().next_back()?;
+        let span = last - first + 1;
+        let bucket_s = (span + MAX_RATE_BUCKETS - 1) / MAX_RATE_BUCKETS;
+        let mut counts = vec![0usize; ((span + bucket_s - 1) / bucket_s) as usize];
+        for (second, count) in &self.messages_per_second {
+            counts[((second - first) / bucket_s) as usize] += count;
+        }
+        Some(MessageRate {
+            start: first * 1000,
+            bucket_ms: bucket_s * 1000,
+            counts,
+        })
+    }
+    fn finish(self) -> StatisticInfo {
+        let message_rate = self.message_rate();
+        let payload_sizes = self.min_payload.map(|min| PayloadSizes {
+            min,
+            max: self.max_payload,
+            average: self.total_payload as f64 / self.message_count as f64,
+            total: self.total_payload,
+        });
+        let mut ecu_app_context_ids: Vec<IdCombination> = self
+            .id_combinations
+            .into_iter()
+            .map(|((ecu_id, app_id, context_id), levels)| IdCombination {
+                ecu_id,
+                app_id,
+                context_id,
+                count: levels.count(),
+                levels,
+            })
+            .collect();
+        ecu_app_context_ids.sort_by(|a, b| {
+            (&a.ecu_id, &a.app_id, &a.context_id).cmp(&(&b.ecu_id, &b.app_id, &b.context_id))
+        });
+        StatisticInfo {
+            app_ids: self
+                .app_ids
+                .into_iter()
+                .collect::<Vec<(String, LevelDistribution)>>(),
+            context_ids: self
+                .context_ids
+                .into_iter()
+                .collect::<Vec<(String, LevelDistribution)>>(),
+            ecu_ids: self
+                .ecu_ids
+                .into_iter()
+                .collect::<Vec<(String, LevelDistribution)>>(),
+            control_services: self
+                .control_services
+                .into_iter()
+                .collect::<Vec<(String, usize)>>(),
+            lifecycles: self.lifecycles.finish(),
+            ecu_app_context_ids,
+            app_id_times: sorted_by_id(self.app_id_times),
+            context_id_times: sorted_by_id(self.context_id_times),
+            ecu_id_times: sorted_by_id(self.ecu_id_times),
+            message_rate,
+            verbose: self.verbose,
+            non_verbose: self.non_verbose,
+            message_types: self.message_types,
+            payload_sizes,
+        }
+    }
 }
 #[allow(dead_code)]
-pub fn get_dlt_file_info(in_file: &fs::File) -> Result<StatisticInfo, Error> {
+pub fn get_dlt_file_info(
+    in_file: &fs::File,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<StatisticInfo, Error> {
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
     let mut reader =
         ReduxReader::with_capacity(10 * 1024 * 1024, in_file).set_policy(MinBuffered(10 * 1024));
 
-    let mut app_ids: IdMap = FxHashMap::default();
-    let mut context_ids: IdMap = FxHashMap::default();
-    let mut ecu_ids: IdMap = FxHashMap::default();
+    let mut statistics = StatisticCollector::default();
     let mut index = 0usize;
+    let mut offset = 0usize;
+    let mut skipped_ranges = SkippedRanges::default();
     loop {
         // println!("line index: {}", line_nr);
-        match read_one_dlt_message_info(&mut reader, Some(index)) {
-            Ok(Some((
-                consumed,
-                StatisticRowInfo {
-                    app_id_context_id: Some((app_id, context_id)),
-                    ecu_id: ecu,
-                    level,
-                },
-            ))) => {
+        match read_one_dlt_message_info(&mut reader, Some(index), fibex_metadata.as_ref()) {
+            Ok(Some(ReadResult::Parsed(consumed, row))) => {
                 reader.consume(consumed);
-                add_for_level(level, &mut app_ids, app_id);
-                add_for_level(level, &mut context_ids, context_id);
-                match ecu {
-                    Some(id) => add_for_level(level, &mut ecu_ids, id),
-                    None => add_for_level(level, &mut ecu_ids, "NONE".to_string()),
-                };
+                skipped_ranges.resynced();
+                offset += consumed;
+                statistics.add(row);
             }
-            Ok(Some((
-                consumed,
-                StatisticRowInfo {
-                    app_id_context_id: None,
-                    ecu_id: ecu,
-                    level,
-                },
-            ))) => {
-                reader.consume(consumed);
-                add_for_level(level, &mut app_ids, "NONE".to_string());
-                add_for_level(level, &mut context_ids, "NONE".to_string());
-                match ecu {
-                    Some(id) => add_for_level(level, &mut ecu_ids, id),
-                    None => add_for_level(level, &mut ecu_ids, "NONE".to_string()),
-                };
+            Ok(Some(ReadResult::Skipped(skipped))) => {
+                reader.consume(skipped);
+                skipped_ranges.skipped(offset, skipped);
+                offset += skipped;
+                continue;
             }
             Ok(None) => {
                 break;
@@ -1033,17 +1995,22 @@ pub fn get_dlt_file_info(in_file: &fs::File) -> Result<StatisticInfo, Error> {
         }
         index += 1;
     }
-    Ok(StatisticInfo {
-        app_ids: app_ids
-            .into_iter()
-            .collect::<Vec<(String, LevelDistribution)>>(),
-        context_ids: context_ids
-            .into_iter()
-            .collect::<Vec<(String, LevelDistribution)>>(),
-        ecu_ids: ecu_ids
-            .into_iter()
-            .collect::<Vec<(String, LevelDistribution)>>(),
-    })
+    skipped_ranges.finish();
+    Ok(statistics.finish())
+}
+/// statistics of a memory mapped dlt file, see `get_dlt_file_info`
+pub fn get_mapped_dlt_file_info(
+    dlt_file: &message_ref::MappedDltFile,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<StatisticInfo, Error> {
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let mut statistics = StatisticCollector::default();
+    for msg in dlt_file.messages() {
+        statistics.add(statistic_row_info(&msg, fibex_metadata.as_ref()));
+    }
+    Ok(statistics.finish())
 }
 
 #[derive(Serialize, Debug)]
@@ -1051,60 +2018,35 @@ pub struct StatisticRowInfo {
     app_id_context_id: Option<(String, String)>,
     ecu_id: Option<String>,
     level: Option<dlt::LogLevel>,
+    control_service: Option<dlt::ServiceId>,
+    times: MessageTimes,
+    #[serde(skip)]
+    message_type: Option<dlt::MessageType>,
+    verbose: bool,
+    payload_length: usize,
+}
+/// what is needed to detect the lifecycle of a message
+#[derive(Serialize, Debug)]
+struct MessageTimes {
+    ecu_id: Option<String>,
+    uptime: Option<u32>,
+    storage_time: Option<i64>,
 }
 fn read_one_dlt_message_info<T: Read>(
     reader: &mut ReduxReader<T, MinBuffered>,
     index: Option<usize>,
-) -> Result<Option<(usize, StatisticRowInfo)>, Error> {
-    loop {
-        match reader.fill_buf() {
-            Ok(content) => {
-                if content.is_empty() {
-                    return Ok(None);
-                }
-                let available = content.len();
-                let res: nom::IResult<&[u8], StatisticRowInfo> =
-                    dlt_app_id_context_id(content, index);
-                match res {
-                    Ok(r) => {
-                        let consumed = available - r.0.len();
-                        break Ok(Some((consumed, r.1)));
-                    }
-                    e => match e {
-                        Err(nom::Err::Incomplete(_)) => continue,
-                        Err(nom::Err::Error(_e)) => {
-                            return Err(err_msg(format!(
-                                "parsing error for dlt messages: {:?}",
-                                _e
-                            )));
-                        }
-                        Err(nom::Err::Failure(_e)) => {
-                            return Err(err_msg(format!(
-                                "parsing failure for dlt messages: {:?}",
-                                _e
-                            )));
-                        }
-                        _ => {
-                            return Err(err_msg(format!(
-                                "error while parsing dlt messages: {:?}",
-                                e
-                            )))
-                        }
-                    },
-                }
-            }
-            Err(e) => {
-                return Err(err_msg(format!("error while parsing dlt messages: {}", e)));
-            }
-        }
-    }
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> Result<Option<ReadResult<StatisticRowInfo>>, Error> {
+    read_with_resync(reader, |content| {
+        dlt_app_id_context_id(content, index, fibex_metadata)
+    })
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
-    use byteorder::BigEndian;
+    use byteorder::{BigEndian, LittleEndian};
     use bytes::BytesMut;
     use pretty_assertions::assert_eq;
     use proptest::prelude::*;
@@ -1145,7 +2087,11 @@ mod tests {
     fn fixedpoint_strategy(
         type_info: dlt::TypeInfo,
     ) -> impl Strategy<Value = Option<dlt::FixedPoint>> {
-        let (is32_bit, is_fp) = match type_info.kind {
+        let kind = match type_info.kind {
+            dlt::TypeInfoKind::Array(element_kind) => *element_kind,
+            kind => kind,
+        };
+        let (is32_bit, is_fp) = match kind {
             dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength32, fp) => (true, fp),
             dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength32, fp) => (true, fp),
             dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength64, fp) => (false, fp),
@@ -1164,12 +2110,13 @@ mod tests {
         })
     }
 
-    fn argument_strategy() -> impl Strategy<Value = dlt::Argument> {
+    pub(crate) fn argument_strategy() -> impl Strategy<Value = dlt::Argument> {
         fn has_unit_info(kind: &dlt::TypeInfoKind) -> bool {
             match kind {
                 dlt::TypeInfoKind::Unsigned(_, _) => true,
                 dlt::TypeInfoKind::Signed(_, _) => true,
                 dlt::TypeInfoKind::Float(_) => true,
+                dlt::TypeInfoKind::Array(_) => true,
                 _ => false,
             }
         }
@@ -1178,13 +2125,19 @@ mod tests {
                 fixedpoint_strategy(type_info.clone()),
                 "[a-zA-Z0-9]{1,4}",
                 "[a-zA-Z0-9]{1,4}",
+                "[a-zA-Z0-9]{1,8}",
             )
-                .prop_map(move |(fp, name_val, unit_val)| dlt::Argument {
+                .prop_map(move |(fp, name_val, unit_val, trace_val)| dlt::Argument {
                     name: if type_info.has_variable_info {
                         Some(name_val)
                     } else {
                         None
                     },
+                    trace_info: if type_info.has_trace_info {
+                        Some(trace_val)
+                    } else {
+                        None
+                    },
                     fixed_point: fp,
                     unit: if type_info.has_variable_info && has_unit_info(&type_info.kind) {
                         Some(unit_val)
@@ -1216,7 +2169,7 @@ mod tests {
                 ("[a-zA-Z]{1,10}", any::<Vec<u8>>()),
             ),
         )
-            .prop_map(
+            .prop_flat_map(
                 |(
                     ti,
                     b,
@@ -1226,37 +2179,50 @@ mod tests {
                     u64val,
                     u128val,
                     (i8val, i16val, i32val, i64val, i128val, f32val, f64val, (stringval, vecval)),
-                )| match ti.kind {
-                    dlt::TypeInfoKind::Bool => (ti, dlt::Value::Bool(b)),
-                    dlt::TypeInfoKind::Signed(s, _) => (
-                        ti,
-                        match s {
-                            dlt::TypeLength::BitLength8 => dlt::Value::I8(i8val),
-                            dlt::TypeLength::BitLength16 => dlt::Value::I16(i16val),
-                            dlt::TypeLength::BitLength32 => dlt::Value::I32(i32val),
-                            dlt::TypeLength::BitLength64 => dlt::Value::I64(i64val),
-                            dlt::TypeLength::BitLength128 => dlt::Value::I128(i128val),
-                        },
-                    ),
-                    dlt::TypeInfoKind::Unsigned(s, _) => (
-                        ti,
-                        match s {
-                            dlt::TypeLength::BitLength8 => dlt::Value::U8(u8val),
-                            dlt::TypeLength::BitLength16 => dlt::Value::U16(u16val),
-                            dlt::TypeLength::BitLength32 => dlt::Value::U32(u32val),
-                            dlt::TypeLength::BitLength64 => dlt::Value::U64(u64val),
-                            dlt::TypeLength::BitLength128 => dlt::Value::U128(u128val),
-                        },
-                    ),
-                    dlt::TypeInfoKind::Float(w) => (
-                        ti,
-                        match w {
-                            dlt::FloatWidth::Width32 => dlt::Value::F32(f32val),
-                            dlt::FloatWidth::Width64 => dlt::Value::F64(f64val),
-                        },
-                    ),
-                    dlt::TypeInfoKind::StringType => (ti, dlt::Value::StringVal(stringval)),
-                    dlt::TypeInfoKind::Raw => (ti, dlt::Value::Raw(vecval)),
+                )| {
+                    let value = match ti.kind {
+                        dlt::TypeInfoKind::Bool => (ti, dlt::Value::Bool(b)),
+                        dlt::TypeInfoKind::Signed(s, _) => (
+                            ti,
+                            match s {
+                                dlt::TypeLength::BitLength8 => dlt::Value::I8(i8val),
+                                dlt::TypeLength::BitLength16 => dlt::Value::I16(i16val),
+                                dlt::TypeLength::BitLength32 => dlt::Value::I32(i32val),
+                                dlt::TypeLength::BitLength64 => dlt::Value::I64(i64val),
+                                dlt::TypeLength::BitLength128 => dlt::Value::I128(i128val),
+                            },
+                        ),
+                        dlt::TypeInfoKind::Unsigned(s, _) => (
+                            ti,
+                            match s {
+                                dlt::TypeLength::BitLength8 => dlt::Value::U8(u8val),
+                                dlt::TypeLength::BitLength16 => dlt::Value::U16(u16val),
+                                dlt::TypeLength::BitLength32 => dlt::Value::U32(u32val),
+                                dlt::TypeLength::BitLength64 => dlt::Value::U64(u64val),
+                                dlt::TypeLength::BitLength128 => dlt::Value::U128(u128val),
+                            },
+                        ),
+                        dlt::TypeInfoKind::Float(w) => (
+                            ti,
+                            match w {
+                                dlt::FloatWidth::Width32 => dlt::Value::F32(f32val),
+                                dlt::FloatWidth::Width64 => dlt::Value::F64(f64val),
+                            },
+                        ),
+                        dlt::TypeInfoKind::StringType => (ti, dlt::Value::StringVal(stringval)),
+                        dlt::TypeInfoKind::Raw => (ti, dlt::Value::Raw(vecval)),
+                        dlt::TypeInfoKind::Array(ref element_kind) => {
+                            let array_strategy = dlt::array_value_strategy(element_kind);
+                            return (Just(ti), array_strategy).boxed();
+                        }
+                        dlt::TypeInfoKind::Struct => {
+                            let fields_strategy =
+                                prop::collection::vec(argument_strategy().boxed(), 0..3)
+                                    .prop_map(dlt::Value::Struct);
+                            return (Just(ti), fields_strategy).boxed();
+                        }
+                    };
+                    Just(value).boxed()
                 },
             )
     }
@@ -1326,6 +2292,7 @@ mod tests {
             type_info,
             name: Some("a".to_string()),
             unit: Some("a".to_string()),
+            trace_info: None,
             fixed_point: Some(dlt::FixedPoint {
                 quantization: 1.0,
                 offset: dlt::FixedPointValue::I64(1),
@@ -1350,6 +2317,7 @@ mod tests {
             },
             name: Some("a".to_string()),
             unit: Some("A".to_string()),
+            trace_info: None,
             fixed_point: Some(dlt::FixedPoint {
                 quantization: 0.1,
                 offset: dlt::FixedPointValue::I32(0),
@@ -1375,6 +2343,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::Bool(true),
         };
@@ -1395,6 +2364,7 @@ mod tests {
             type_info,
             name: Some("varname_foo".to_string()),
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::Bool(true),
         };
@@ -1417,6 +2387,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::U32(0x123),
         };
@@ -1437,6 +2408,7 @@ mod tests {
             type_info,
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::U32(0x123),
         };
@@ -1459,6 +2431,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::I16(-23),
         };
@@ -1479,6 +2452,7 @@ mod tests {
             type_info,
             name: Some("temperature".to_string()),
             unit: Some("celcius".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::I32(-23),
         };
@@ -1501,6 +2475,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::F32(123.98f32),
         };
@@ -1521,6 +2496,7 @@ mod tests {
             type_info,
             name: Some("temperature".to_string()),
             unit: Some("celcius".to_string()),
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::F64(28.3),
         };
@@ -1543,6 +2519,7 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::Raw(vec![0xD, 0xE, 0xA, 0xD]),
         };
@@ -1562,6 +2539,7 @@ mod tests {
             type_info,
             name: Some("payload".to_string()),
             unit: None,
+            trace_info: None,
             fixed_point: None,
             value: dlt::Value::Raw(vec![0xD, 0xE, 0xA, 0xD]),
         };
@@ -1584,15 +2562,345 @@ mod tests {
             type_info,
             name: None,
             unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::StringVal("foo".to_string()),
+        };
+        let mut argument_bytes = argument.as_bytes::<BigEndian>();
+        println!("argument bytes: {:02X?}", argument_bytes);
+        argument_bytes.extend(b"----");
+        let res: IResult<&[u8], dlt::Argument> = dlt_argument::<BigEndian>(&argument_bytes);
+        let expected: IResult<&[u8], dlt::Argument> = Ok((b"----", argument));
+        assert_eq!(expected, res);
+    }
+    #[test]
+    fn test_parse_string_argument_with_trace_info() {
+        let type_info = dlt::TypeInfo {
+            kind: dlt::TypeInfoKind::StringType,
+            coding: dlt::StringCoding::UTF8,
+            has_variable_info: true,
+            has_trace_info: true,
+        };
+        #[rustfmt::skip]
+        let mut argument_bytes: Vec<u8> = vec![
+            0x00, 0x00, 0xAA, 0x00, // type info: utf8, trace info, variable info, string
+            0x00, 0x05, b'm', b'o', b'd', b'1', 0x00, // trace info
+            0x00, 0x04, // length of string
+            0x00, 0x03, b'i', b'd', 0x00, // variable name
+            b'f', b'o', b'o', 0x00, // value
+        ];
+        let argument = dlt::Argument {
+            type_info,
+            name: Some("id".to_string()),
+            unit: None,
+            trace_info: Some("mod1".to_string()),
             fixed_point: None,
             value: dlt::Value::StringVal("foo".to_string()),
         };
+        assert_eq!(argument_bytes, argument.as_bytes::<BigEndian>());
+        assert_eq!("[mod1] id: foo", format!("{}", argument));
+        argument_bytes.extend(b"----");
+        let res: IResult<&[u8], dlt::Argument> = dlt_argument::<BigEndian>(&argument_bytes);
+        let expected: IResult<&[u8], dlt::Argument> = Ok((b"----", argument));
+        assert_eq!(expected, res);
+    }
+    #[test]
+    fn test_parse_control_request() {
+        #[rustfmt::skip]
+        let payload: Vec<u8> = vec![
+            0x01, 0x00, 0x00, 0x00, // service id: set_log_level
+            b'A', b'P', b'P', 0x00, // app id
+            b'C', b'T', b'X', b'1', // context id
+            0x05, // debug
+            b'r', b'e', b'm', b'o', // com interface
+        ];
+        let (_, msg) =
+            dlt_control_message::<LittleEndian>(&payload, &dlt::ControlType::Request).unwrap();
+        assert_eq!(
+            dlt::ControlMessage::Request(
+                dlt::ServiceId::SET_LOG_LEVEL,
+                dlt::ControlRequest::SetLogLevel {
+                    application_id: "APP".to_string(),
+                    context_id: "CTX1".to_string(),
+                    log_level: 5,
+                }
+            ),
+            msg
+        );
+        assert_eq!("[set_log_level] APP CTX1 DEBUG", format!("{}", msg));
+    }
+    #[test]
+    fn test_parse_control_responses() {
+        #[rustfmt::skip]
+        let payload: Vec<u8> = vec![
+            0x00, 0x00, 0x00, 0x13, // service id: get_software_version
+            0x00, // ok
+            0x00, 0x00, 0x00, 0x04, // length
+            b'v', b'1', b'.', b'2',
+        ];
+        let (_, msg) =
+            dlt_control_message::<BigEndian>(&payload, &dlt::ControlType::Response).unwrap();
+        assert_eq!(
+            dlt::ControlMessage::Response(
+                dlt::ServiceId::GET_SOFTWARE_VERSION,
+                dlt::ResponseStatus::Ok,
+                dlt::ControlResponse::SoftwareVersion("v1.2".to_string())
+            ),
+            msg
+        );
+        assert_eq!("[get_software_version ok] v1.2", format!("{}", msg));
+
+        #[rustfmt::skip]
+        let payload: Vec<u8> = vec![
+            0x03, 0x00, 0x00, 0x00, // service id: get_log_info
+            0x07, // with descriptions
+            0x01, 0x00, // one application
+            b'A', b'P', b'P', 0x00,
+            0x02, 0x00, // two contexts
+            b'C', b'T', b'X', b'1', 0x04, 0x00, 0x03, 0x00, b'c', b't', b'x',
+            b'C', b'T', b'X', b'2', 0xFF, 0xFF, 0x00, 0x00,
+            0x03, 0x00, b'a', b'p', b'p', // application description
+            b'r', b'e', b'm', b'o', // com interface
+        ];
+        let (_, msg) =
+            dlt_control_message::<LittleEndian>(&payload, &dlt::ControlType::Response).unwrap();
+        assert_eq!(
+            "[get_log_info 7] APP \"app\" (CTX1 INFO off \"ctx\", CTX2 default default \"\")",
+            format!("{}", msg)
+        );
+
+        let payload: Vec<u8> = vec![0x00, 0x00, 0x00, 0x04, 0x01];
+        let (_, msg) =
+            dlt_control_message::<BigEndian>(&payload, &dlt::ControlType::Response).unwrap();
+        assert_eq!(
+            dlt::ControlMessage::Response(
+                dlt::ServiceId::GET_DEFAULT_LOG_LEVEL,
+                dlt::ResponseStatus::NotSupported,
+                dlt::ControlResponse::Other(vec![])
+            ),
+            msg
+        );
+    }
+    #[test]
+    fn test_parse_control_message() {
+        let extended_header = dlt::ExtendedHeader {
+            verbose: false,
+            argument_count: 0,
+            message_type: dlt::MessageType::Control(dlt::ControlType::Request),
+            application_id: "APP".to_string(),
+            context_id: "CON".to_string(),
+        };
+        let mut raw: Vec<u8> = vec![
+            0x44, 0x4C, 0x54, 0x01, 0x56, 0xA2, 0x91, 0x5C, 0x9C, 0x91, 0x0B, 0x00, 0x45, 0x43,
+            0x55, 0x31, // storage header
+            0x21, // header type: version 1, extended header, little endian
+            0x00, // message counter
+            0x00, 0x12, // length
+        ];
+        raw.extend(extended_header.as_bytes());
+        raw.extend(vec![0x04, 0x0F, 0x00, 0x00]); // marker
+        let (rest, msg) = dlt_message(&raw, None, None, None).expect("should parse");
+        assert!(rest.is_empty());
+        let msg = msg.expect("not filtered");
+        assert_eq!(
+            dlt::Payload::Control(
+                dlt::ControlMessage::Request(
+                    dlt::ServiceId::MARKER,
+                    dlt::ControlRequest::Other(vec![])
+                ),
+                vec![0x04, 0x0F, 0x00, 0x00]
+            ),
+            msg.payload
+        );
+        assert!(format!("{}", msg).ends_with("[marker]"));
+
+        // timing packets have no payload at all
+        let extended_header = dlt::ExtendedHeader {
+            message_type: dlt::MessageType::Control(dlt::ControlType::Time),
+            ..extended_header
+        };
+        raw.truncate(16);
+        raw.extend(vec![0x21, 0x00, 0x00, 0x0E]);
+        raw.extend(extended_header.as_bytes());
+        let (rest, msg) = dlt_message(&raw, None, None, None).expect("should parse");
+        assert!(rest.is_empty());
+        assert_eq!(
+            dlt::Payload::Control(dlt::ControlMessage::Time, vec![]),
+            msg.expect("not filtered").payload
+        );
+    }
+    #[test]
+    fn test_parse_args_after_trace_info_stay_aligned() {
+        #[rustfmt::skip]
+        let payload: Vec<u8> = vec![
+            0x41, 0x20, 0x00, 0x00, // type info: trace info, unsigned, 8 bit
+            0x04, 0x00, b'a', b'b', b'c', 0x00, // trace info
+            0x2A, // value
+            0x11, 0x00, 0x00, 0x00, // type info: bool
+            0x01, // value
+        ];
+        let res = dlt_payload::<LittleEndian>(&payload, true, payload.len(), 2);
+        let (rest, parsed) = res.expect("payload should parse");
+        assert!(rest.is_empty());
+        match parsed {
+            dlt::Payload::Verbose(args) => {
+                assert_eq!(2, args.len());
+                assert_eq!(Some("abc".to_string()), args[0].trace_info);
+                assert_eq!(dlt::Value::U8(42), args[0].value);
+                assert_eq!(None, args[1].trace_info);
+                assert_eq!(dlt::Value::Bool(true), args[1].value);
+            }
+            _ => panic!("expected verbose payload"),
+        }
+    }
+    #[test]
+    fn test_parse_array_argument() {
+        let type_info = dlt::TypeInfo {
+            kind: dlt::TypeInfoKind::Array(Box::new(dlt::TypeInfoKind::Bool)),
+            coding: dlt::StringCoding::UTF8,
+            has_variable_info: false,
+            has_trace_info: false,
+        };
+        let argument = dlt::Argument {
+            type_info,
+            name: None,
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::Array(
+                vec![3],
+                vec![
+                    dlt::Value::Bool(true),
+                    dlt::Value::Bool(false),
+                    dlt::Value::Bool(true),
+                ],
+            ),
+        };
         let mut argument_bytes = argument.as_bytes::<BigEndian>();
         println!("argument bytes: {:02X?}", argument_bytes);
         argument_bytes.extend(b"----");
         let res: IResult<&[u8], dlt::Argument> = dlt_argument::<BigEndian>(&argument_bytes);
         let expected: IResult<&[u8], dlt::Argument> = Ok((b"----", argument));
         assert_eq!(expected, res);
+        // now with variable info and fixed point
+        let type_info = dlt::TypeInfo {
+            kind: dlt::TypeInfoKind::Array(Box::new(dlt::TypeInfoKind::Signed(
+                dlt::TypeLength::BitLength32,
+                true,
+            ))),
+            coding: dlt::StringCoding::UTF8,
+            has_variable_info: true,
+            has_trace_info: false,
+        };
+        let argument = dlt::Argument {
+            type_info,
+            name: Some("temperatures".to_string()),
+            unit: Some("celcius".to_string()),
+            trace_info: None,
+            fixed_point: Some(dlt::FixedPoint {
+                quantization: 0.5,
+                offset: dlt::FixedPointValue::I32(-40),
+            }),
+            value: dlt::Value::Array(vec![2, 1], vec![dlt::Value::I32(-23), dlt::Value::I32(42)]),
+        };
+        let mut argument_bytes = argument.as_bytes::<LittleEndian>();
+        println!("argument bytes: {:02X?}", argument_bytes);
+        argument_bytes.extend(b"----");
+        let res: IResult<&[u8], dlt::Argument> = dlt_argument::<LittleEndian>(&argument_bytes);
+        let expected: IResult<&[u8], dlt::Argument> = Ok((b"----", argument));
+        assert_eq!(expected, res);
+    }
+    #[test]
+    fn test_parse_struct_argument() {
+        let string_field = dlt::Argument {
+            type_info: dlt::TypeInfo {
+                kind: dlt::TypeInfoKind::StringType,
+                coding: dlt::StringCoding::UTF8,
+                has_variable_info: true,
+                has_trace_info: false,
+            },
+            name: Some("id".to_string()),
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::StringVal("abc".to_string()),
+        };
+        let nested = dlt::Argument {
+            type_info: dlt::TypeInfo {
+                kind: dlt::TypeInfoKind::Struct,
+                coding: dlt::StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::Struct(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::Float(dlt::FloatWidth::Width32),
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: true,
+                    has_trace_info: false,
+                },
+                name: Some("x".to_string()),
+                unit: Some("m".to_string()),
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::F32(1.5),
+            }]),
+        };
+        let argument = dlt::Argument {
+            type_info: dlt::TypeInfo {
+                kind: dlt::TypeInfoKind::Struct,
+                coding: dlt::StringCoding::UTF8,
+                has_variable_info: true,
+                has_trace_info: false,
+            },
+            name: Some("obj".to_string()),
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::Struct(vec![string_field, nested]),
+        };
+        let mut argument_bytes = argument.as_bytes::<BigEndian>();
+        println!("argument bytes: {:02X?}", argument_bytes);
+        argument_bytes.extend(b"----");
+        let res: IResult<&[u8], dlt::Argument> = dlt_argument::<BigEndian>(&argument_bytes);
+        let expected: IResult<&[u8], dlt::Argument> = Ok((b"----", argument));
+        assert_eq!(expected, res);
+    }
+    /// `depth` structs, each one the only field of the one before
+    pub(crate) fn nested_struct_bytes(depth: usize) -> Vec<u8> {
+        let type_info = dlt::TypeInfo {
+            kind: dlt::TypeInfoKind::Struct,
+            coding: dlt::StringCoding::ASCII,
+            has_variable_info: false,
+            has_trace_info: false,
+        }
+        .as_bytes::<BigEndian>();
+        let mut bytes = vec![];
+        for level in 0..depth {
+            bytes.extend_from_slice(&type_info);
+            let field_cnt: u16 = if level + 1 < depth { 1 } else { 0 };
+            bytes.extend_from_slice(&field_cnt.to_be_bytes());
+        }
+        bytes
+    }
+    #[test]
+    fn test_parse_deeply_nested_structs() {
+        assert!(dlt_argument::<BigEndian>(&nested_struct_bytes(dlt::MAX_STRUCT_DEPTH)).is_ok());
+        assert!(
+            dlt_argument::<BigEndian>(&nested_struct_bytes(dlt::MAX_STRUCT_DEPTH + 1)).is_err()
+        );
+        // would overflow the stack without a limit
+        match dlt_argument::<BigEndian>(&nested_struct_bytes(100_000)) {
+            Err(nom::Err::Error(_)) => (),
+            other => panic!(
+                "expected an error, got {:?}",
+                other.map(|(rest, _)| rest.len())
+            ),
+        }
     }
     // #[test]
     // fn test_dlt_message_parsing() {
@@ -1664,6 +2972,7 @@ mod tests {
             name: Some("speed".to_string()),
             unit: Some("mph".to_string()),
             value: dlt::Value::I32(-44),
+            trace_info: None,
             fixed_point: Some(dlt::FixedPoint {
                 quantization: 1.5,
                 offset: dlt::FixedPointValue::I32(-200),
@@ -10,29 +10,64 @@
 // is strictly forbidden unless prior written permission is obtained
 // from E.S.R.Labs.
 use crate::dlt;
+use crate::dlt_parse::{dlt_message, dlt_message_without_storage_header};
+use crate::fibex;
+use crate::filtering;
 
-use dlt::TryFrom;
-use tokio::codec::{Decoder, Encoder};
-use bytes::{Buf, ByteOrder, Bytes, BytesMut, IntoBuf};
-use std::io::{Error, Read};
-use std::io;
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
 use log::warn;
-use std::fmt::{Write};
-use std::str;
+use std::io;
+use std::io::Error;
+use std::time::SystemTime;
+use tokio::codec::{Decoder, Encoder};
 
+/// codec for dlt messages as they are sent over the wire (no storage header)
 #[derive(Default)]
-pub struct Codec;
+pub struct Codec {
+    filter_config: Option<filtering::ProcessedDltFilterConfig>,
+    fibex_metadata: Option<fibex::FibexMetadata>,
+}
+impl Codec {
+    /// messages that do not match the filter config are silently dropped
+    /// fibex: used to decode non-verbose messages
+    pub fn new(
+        filter_config: Option<filtering::ProcessedDltFilterConfig>,
+        fibex_metadata: Option<fibex::FibexMetadata>,
+    ) -> Self {
+        Codec {
+            filter_config,
+            fibex_metadata,
+        }
+    }
+}
 
+/// codec for dlt messages as they are stored in a dlt file: every message
+/// is prefixed with a storage header
 #[derive(Default)]
 pub struct DltFileCodec {
     dlt_codec: Codec,
 }
+impl DltFileCodec {
+    pub fn new(
+        filter_config: Option<filtering::ProcessedDltFilterConfig>,
+        fibex_metadata: Option<fibex::FibexMetadata>,
+    ) -> Self {
+        DltFileCodec {
+            dlt_codec: Codec::new(filter_config, fibex_metadata),
+        }
+    }
+}
 impl Decoder for DltFileCodec {
     type Item = dlt::Message;
     type Error = Error;
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        unimplemented!()
+        decode_message(
+            src,
+            dlt::STORAGE_HEADER_LENGTH,
+            self.dlt_codec.filter_config.as_ref(),
+            self.dlt_codec.fibex_metadata.as_ref(),
+        )
     }
 }
 impl Encoder for DltFileCodec {
@@ -40,48 +75,16 @@ impl Encoder for DltFileCodec {
     type Error = Error;
 
     fn encode(&mut self, msg: Self::Item, dest: &mut BytesMut) -> Result<(), Self::Error> {
-        // Messages without extended header (non-verbose) are unimplemented
-        if let Some(ext) = msg.extended_header {
-            let _level = match ext.message_type {
-                dlt::MessageType::Log(level) => level.into(),
-                dlt::MessageType::ApplicationTrace(_) | dlt::MessageType::NetworkTrace(_) => {
-                    log::Level::Trace
-                }
-                // Ignore everything else
-                _ => return Ok(()),
-            };
-
-            dest.reserve(1024); // TODO reserve correct amount
-                                // Format message: Join arguments as strings
-            if let dlt::Payload::Verbose(arguments) = msg.payload {
-                // Format tag by concatenating ecu_id, application_id and context_id
-                write!(
-                    dest,
-                    "{}{}{}-{}",
-                    msg.storage_header
-                        .map_or(String::new(), |storage_header| format!(
-                            "{}: ",
-                            storage_header.timestamp
-                        )),
-                    msg.header
-                        .ecu_id
-                        .map(|id| format!("{}-", id))
-                        .unwrap_or_else(|| "".into()),
-                    ext.application_id,
-                    ext.context_id
-                )
-                .map_err(|e| Error::new(io::ErrorKind::Other, e))?;
-
-                // Format payload
-                arguments
-                    .iter()
-                    .try_for_each(|arg| write!(dest, " {}", arg))
-                    .map_err(|e| Error::new(io::ErrorKind::Other, e))?;
-            }
-            writeln!(dest).map_err(|e| Error::new(io::ErrorKind::Other, e))
+        if msg.storage_header.is_some() {
+            dest.extend_from_slice(&msg.as_bytes());
         } else {
-            Ok(())
+            // messages that were not read from a file get stamped with the current time
+            let storage_header =
+                dlt::StorageHeader::received_at(SystemTime::now(), msg.header.ecu_id.as_ref());
+            dest.extend_from_slice(&storage_header.as_bytes());
+            self.dlt_codec.encode(msg, dest)?;
         }
+        Ok(())
     }
 }
 impl Decoder for Codec {
@@ -89,56 +92,205 @@ impl Decoder for Codec {
     type Error = Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut buf = Bytes::from(src.as_ref()).into_buf();
+        decode_message(
+            src,
+            0,
+            self.filter_config.as_ref(),
+            self.fibex_metadata.as_ref(),
+        )
+    }
+}
+
+impl Encoder for Codec {
+    type Item = dlt::Message;
+    type Error = Error;
 
-        if buf.remaining() < dlt::HEADER_MIN_LENGTH {
+    fn encode(&mut self, msg: Self::Item, dest: &mut BytesMut) -> Result<(), Self::Error> {
+        dest.extend_from_slice(&msg.as_bytes_without_storage_header());
+        Ok(())
+    }
+}
+
+/// take the next complete message out of `src`
+///
+/// returns `Ok(None)` if more data is needed. Messages that cannot be parsed
+/// or are filtered out are consumed and skipped.
+fn decode_message(
+    src: &mut BytesMut,
+    storage_header_length: usize,
+    filter_config: Option<&filtering::ProcessedDltFilterConfig>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> Result<Option<dlt::Message>, Error> {
+    loop {
+        if src.len() < storage_header_length + dlt::HEADER_MIN_LENGTH {
             return Ok(None);
         }
-        let header_type = buf.get_u8();
-        let message_counter = buf.get_u8();
-        let overall_message_length = buf.get_u16_be() as usize;
-
+        if storage_header_length > 0 && !src.starts_with(dlt::STORAGE_HEADER_PATTERN) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing storage header pattern",
+            ));
+        }
+        let header_type = src[storage_header_length];
+        let overall_message_length =
+            BigEndian::read_u16(&src[storage_header_length + 2..]) as usize;
         let headers_length = dlt::calculate_all_headers_length(header_type);
         if overall_message_length < headers_length {
             return Err(Error::new(io::ErrorKind::Other, "Invalid header length"));
         }
-
-        if buf.remaining() + dlt::HEADER_MIN_LENGTH < overall_message_length {
+        let message_length = storage_header_length + overall_message_length;
+        if src.len() < message_length {
+            src.reserve(message_length - src.len());
             return Ok(None);
         }
+        let raw = src.split_to(message_length);
+        let res = if storage_header_length > 0 {
+            dlt_message(&raw, filter_config, None, fibex_metadata)
+        } else {
+            dlt_message_without_storage_header(&raw, filter_config, None, fibex_metadata)
+        };
+        match res {
+            Ok((_, Some(message))) => return Ok(Some(message)),
+            Ok((_, None)) => (), // filtered out
+            Err(e) => warn!("Failed to parse message: {:?}", e),
+        }
+    }
+}
 
-        let message = decode_message(
-            &mut buf,
-            header_type,
-            message_counter,
-            overall_message_length,
-            headers_length,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlt_parse::tests::argument_strategy;
+    use futures::Stream;
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+    use tokio::codec::FramedRead;
+
+    fn payload_strategy() -> impl Strategy<Value = (bool, dlt::Payload)> {
+        prop_oneof![
+            prop::collection::vec(argument_strategy(), 0..4)
+                .prop_map(|args| (true, dlt::Payload::Verbose(args))),
+            (any::<u32>(), prop::collection::vec(any::<u8>(), 0..32))
+                .prop_map(|(id, data)| (false, dlt::Payload::NonVerbose(id, data))),
+        ]
+    }
+
+    fn message_strategy() -> impl Strategy<Value = dlt::Message> {
+        (
+            any::<Option<dlt::StorageHeader>>(),
+            any::<dlt::StandardHeader>(),
+            any::<Option<dlt::ExtendedHeader>>(),
+            payload_strategy(),
         )
-        .map(Option::Some)
-        .unwrap_or_else(|e| {
-            warn!("Failed to parse message: {}", e);
-            None
-        });
-        src.advance(overall_message_length);
-        Ok(message)
+            .prop_filter(
+                "verbose messages need an extended header",
+                |(_, _, ext, (verbose, _))| ext.is_some() || !verbose,
+            )
+            .prop_map(|(storage_header, header, ext, (verbose, payload))| {
+                let extended_header = ext.map(|ext| dlt::ExtendedHeader {
+                    verbose,
+                    argument_count: match &payload {
+                        dlt::Payload::Verbose(args) => args.len() as u8,
+                        _ => 0,
+                    },
+                    message_type: match ext.message_type {
+                        // non-verbose control messages have a payload of their own
+                        dlt::MessageType::Control(_) => dlt::MessageType::Log(dlt::LogLevel::Info),
+                        t => t,
+                    },
+                    ..ext
+                });
+                let mut message = dlt::Message {
+                    storage_header,
+                    header: dlt::StandardHeader {
+                        has_extended_header: extended_header.is_some(),
+                        ..header
+                    },
+                    extended_header,
+                    payload,
+                };
+                message.header.overall_length =
+                    message.as_bytes_without_storage_header().len() as u16;
+                message
+            })
     }
-}
 
-impl Encoder for Codec {
-    type Item = dlt::Message;
-    type Error = Error;
+    fn decode_all<C: Decoder<Item = dlt::Message, Error = Error>>(
+        codec: C,
+        bytes: &[u8],
+    ) -> Vec<dlt::Message> {
+        FramedRead::new(bytes, codec)
+            .wait()
+            .collect::<Result<Vec<dlt::Message>, Error>>()
+            .expect("decoding failed")
+    }
 
-    fn encode(&mut self, _: Self::Item, _: &mut BytesMut) -> Result<(), Self::Error> {
-        unimplemented!()
+    proptest! {
+        #[test]
+        fn test_codec_round_trip(messages in prop::collection::vec(message_strategy(), 1..5)) {
+            let mut codec = Codec::default();
+            let mut bytes = BytesMut::new();
+            let expected: Vec<dlt::Message> = messages
+                .into_iter()
+                .map(|m| dlt::Message { storage_header: None, ..m })
+                .collect();
+            for m in &expected {
+                codec.encode(m.clone(), &mut bytes).expect("encoding failed");
+            }
+            assert_eq!(expected, decode_all(Codec::default(), &bytes));
+        }
+        #[test]
+        fn test_file_codec_round_trip(messages in prop::collection::vec(message_strategy(), 1..5)) {
+            let mut codec = DltFileCodec::default();
+            let mut bytes = BytesMut::new();
+            for m in &messages {
+                codec.encode(m.clone(), &mut bytes).expect("encoding failed");
+            }
+            let decoded = decode_all(DltFileCodec::default(), &bytes);
+            assert_eq!(messages.len(), decoded.len());
+            for (expected, actual) in messages.into_iter().zip(decoded) {
+                assert!(actual.storage_header.is_some());
+                if expected.storage_header.is_some() {
+                    assert_eq!(expected, actual);
+                } else {
+                    assert_eq!(expected, dlt::Message { storage_header: None, ..actual });
+                }
+            }
+        }
     }
-}
 
-fn decode_message(
-    buf: &mut dyn Buf,
-    header_type: u8,
-    message_counter: u8,
-    overall_message_length: usize,
-    headers_length: usize,
-) -> Result<dlt::Message, Error> {
-    unimplemented!()
-}
\ No newline at end of file
+    #[test]
+    fn test_decode_partial_message() {
+        let message = dlt::Message {
+            storage_header: None,
+            header: dlt::StandardHeader {
+                has_extended_header: false,
+                version: 1,
+                big_endian: true,
+                message_counter: 7,
+                overall_length: 14,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+            },
+            extended_header: None,
+            payload: dlt::Payload::NonVerbose(0x42, vec![0x1, 0x2]),
+        };
+        let bytes = message.as_bytes_without_storage_header();
+        let mut codec = Codec::default();
+        let mut buf = BytesMut::from(&bytes[..5]);
+        assert_eq!(None, codec.decode(&mut buf).expect("decoding failed"));
+        buf.extend_from_slice(&bytes[5..]);
+        assert_eq!(
+            Some(message),
+            codec.decode(&mut buf).expect("decoding failed")
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_file_codec_rejects_missing_storage_header() {
+        let mut buf = BytesMut::from(&b"this is not a dlt file at all"[..]);
+        assert!(DltFileCodec::default().decode(&mut buf).is_err());
+    }
+}
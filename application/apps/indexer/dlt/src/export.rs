@@ -0,0 +1,137 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+use crate::dlt;
+use crate::dlt_parse::{read_one_dlt_message, ReadResult, SkippedRanges};
+use crate::fibex;
+use crate::filtering;
+
+use buf_redux::policy::MinBuffered;
+use buf_redux::BufReader as ReduxReader;
+use failure::{err_msg, Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// inclusive range of line numbers
+///
+/// line numbers are counted the same way `index_dlt_file` does: only
+/// messages that pass the filter of the export get a line number
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LineRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+/// inclusive range of storage header timestamps (posix timestamps in ms)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TimeRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+impl TimeRange {
+    fn contains(&self, timestamp: i64) -> bool {
+        self.start.map_or(true, |start| timestamp >= start)
+            && self.end.map_or(true, |end| timestamp <= end)
+    }
+}
+
+/// selects the messages of a dlt file that get exported
+///
+/// a message is exported if it matches all of the given criteria
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ExportConfig {
+    pub filter: Option<filtering::DltFilterConfig>,
+    pub lines: Option<LineRange>,
+    pub time: Option<TimeRange>,
+}
+
+fn storage_time_ms(msg: &dlt::Message) -> Option<i64> {
+    msg.storage_header.as_ref().map(|h| h.timestamp.as_millis())
+}
+
+/// write the selected messages of a dlt file to a new binary dlt file
+///
+/// every message is written with its storage header so the result can be
+/// opened with any dlt viewer. Corrupted parts of the input are skipped.
+/// Returns the number of exported messages.
+pub fn export_dlt_file(
+    in_file: fs::File,
+    out_path: &Path,
+    config: ExportConfig,
+    fibex: Option<fibex::FibexConfig>,
+) -> Result<usize, Error> {
+    trace!("export_dlt_file to {:?}", out_path);
+    if let Some(LineRange { first, last }) = config.lines {
+        if first > last {
+            return Err(err_msg(format!("invalid line range {}..{}", first, last)));
+        }
+    }
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let filter_config: Option<filtering::ProcessedDltFilterConfig> = config
+        .filter
+        .map(filtering::process_filter_config)
+        .transpose()?;
+    let mut reader =
+        ReduxReader::with_capacity(10 * 1024 * 1024, in_file).set_policy(MinBuffered(10 * 1024));
+    let mut writer = BufWriter::with_capacity(10 * 1024 * 1024, fs::File::create(out_path)?);
+
+    let mut line_nr = 0usize;
+    let mut exported = 0usize;
+    let mut offset = 0usize;
+    let mut skipped_ranges = SkippedRanges::default();
+    loop {
+        match read_one_dlt_message(
+            &mut reader,
+            filter_config.as_ref(),
+            Some(line_nr),
+            fibex_metadata.as_ref(),
+        )? {
+            Some(ReadResult::Parsed(consumed, msg)) => {
+                reader.consume(consumed);
+                skipped_ranges.resynced();
+                offset += consumed;
+                if let Some(msg) = msg {
+                    if let Some(lines) = &config.lines {
+                        if line_nr > lines.last {
+                            break;
+                        }
+                        if line_nr < lines.first {
+                            line_nr += 1;
+                            continue;
+                        }
+                    }
+                    line_nr += 1;
+                    if let Some(time) = &config.time {
+                        match storage_time_ms(&msg) {
+                            Some(t) if time.contains(t) => (),
+                            _ => continue,
+                        }
+                    }
+                    writer.write_all(&msg.as_bytes())?;
+                    exported += 1;
+                }
+            }
+            Some(ReadResult::Skipped(skipped)) => {
+                reader.consume(skipped);
+                skipped_ranges.skipped(offset, skipped);
+                offset += skipped;
+            }
+            None => break,
+        }
+    }
+    skipped_ranges.finish();
+    writer.flush()?;
+    Ok(exported)
+}
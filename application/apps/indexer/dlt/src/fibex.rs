@@ -0,0 +1,511 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+use crate::dlt;
+use failure::{err_msg, Error};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// FIBEX files that describe the non-verbose messages of a trace
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FibexConfig {
+    pub fibex_file_paths: Vec<String>,
+}
+
+/// one PDU of a frame: either a static text or a list of signals
+#[derive(Debug, Clone, PartialEq)]
+pub struct PduMetadata {
+    pub description: Option<String>,
+    pub signal_types: Vec<dlt::TypeInfo>,
+}
+
+/// describes one non-verbose message (a frame in FIBEX terms)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameMetadata {
+    pub short_name: String,
+    pub pdus: Vec<Arc<PduMetadata>>,
+    pub application_id: Option<String>,
+    pub context_id: Option<String>,
+    pub message_type: Option<dlt::MessageType>,
+}
+
+/// all frames of one or more FIBEX files, accessible by message id
+#[derive(Debug, Default)]
+pub struct FibexMetadata {
+    frames_by_key: FxHashMap<(String, String, u32), Arc<FrameMetadata>>,
+    frames: FxHashMap<u32, Arc<FrameMetadata>>,
+}
+
+impl FibexMetadata {
+    /// find the frame for a message id. If the message carries app and context id,
+    /// frames defined for exactly this app and context are preferred.
+    pub fn frame(
+        &self,
+        message_id: u32,
+        app_and_context_id: Option<(&str, &str)>,
+    ) -> Option<&FrameMetadata> {
+        app_and_context_id
+            .and_then(|(app_id, context_id)| {
+                self.frames_by_key
+                    .get(&(app_id.to_string(), context_id.to_string(), message_id))
+            })
+            .or_else(|| self.frames.get(&message_id))
+            .map(|frame| frame.as_ref())
+    }
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+pub fn read_fibexes(config: &FibexConfig) -> Result<FibexMetadata, Error> {
+    let paths: Vec<PathBuf> = config.fibex_file_paths.iter().map(PathBuf::from).collect();
+    let mut parts = FibexParts::default();
+    for path in paths {
+        let reader = Reader::from_file(&path)
+            .map_err(|e| err_msg(format!("could not open fibex file {:?}: {}", path, e)))?;
+        parse_fibex(reader, &mut parts)
+            .map_err(|e| err_msg(format!("error in fibex file {:?}: {}", path, e)))?;
+    }
+    parts.resolve()
+}
+
+pub fn read_fibex<B: BufRead>(input: B) -> Result<FibexMetadata, Error> {
+    let mut parts = FibexParts::default();
+    parse_fibex(Reader::from_reader(input), &mut parts)?;
+    parts.resolve()
+}
+
+/// frame as found in the xml, PDUs are only known by reference
+#[derive(Debug, Default)]
+struct RawFrame {
+    id: String,
+    short_name: Option<String>,
+    pdu_refs: Vec<(u32, String)>,
+    application_id: Option<String>,
+    context_id: Option<String>,
+    message_type: Option<String>,
+    message_info: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct RawPdu {
+    id: String,
+    description: Option<String>,
+    signal_refs: Vec<(u32, String)>,
+}
+
+/// reference to a PDU or signal together with its sequence number
+#[derive(Debug, Default)]
+struct RawInstance {
+    sequence_number: u32,
+    id_ref: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct FibexParts {
+    frames: Vec<RawFrame>,
+    pdus: FxHashMap<String, RawPdu>,
+}
+
+impl FibexParts {
+    fn resolve(self) -> Result<FibexMetadata, Error> {
+        let mut pdus: FxHashMap<String, Arc<PduMetadata>> = FxHashMap::default();
+        for (id, raw_pdu) in self.pdus {
+            let mut signal_refs = raw_pdu.signal_refs;
+            signal_refs.sort_by_key(|(seq, _)| *seq);
+            let signal_types = signal_refs
+                .iter()
+                .map(|(_, signal_ref)| {
+                    signal_type(signal_ref).ok_or_else(|| {
+                        err_msg(format!("unknown signal {} in PDU {}", signal_ref, id))
+                    })
+                })
+                .collect::<Result<Vec<dlt::TypeInfo>, Error>>()?;
+            pdus.insert(
+                id,
+                Arc::new(PduMetadata {
+                    description: raw_pdu.description,
+                    signal_types,
+                }),
+            );
+        }
+        let mut metadata = FibexMetadata::default();
+        for raw_frame in self.frames {
+            let RawFrame {
+                id,
+                short_name,
+                mut pdu_refs,
+                application_id,
+                context_id,
+                message_type: msg_type,
+                message_info: msg_info,
+            } = raw_frame;
+            let message_id = match message_id(&id) {
+                Some(message_id) => message_id,
+                None => {
+                    warn!("ignoring fibex frame with unexpected id {}", id);
+                    continue;
+                }
+            };
+            pdu_refs.sort_by_key(|(seq, _)| *seq);
+            let frame_pdus = pdu_refs
+                .iter()
+                .map(|(_, pdu_ref)| {
+                    pdus.get(pdu_ref).cloned().ok_or_else(|| {
+                        err_msg(format!("frame {} references unknown PDU {}", id, pdu_ref))
+                    })
+                })
+                .collect::<Result<Vec<Arc<PduMetadata>>, Error>>()?;
+            let message_type = match (msg_type, msg_info) {
+                (Some(t), Some(i)) => message_type(&t, &i),
+                _ => None,
+            };
+            let frame = Arc::new(FrameMetadata {
+                short_name: short_name.unwrap_or(id),
+                pdus: frame_pdus,
+                application_id,
+                context_id,
+                message_type,
+            });
+            if let (Some(app_id), Some(context_id)) = (&frame.application_id, &frame.context_id) {
+                metadata.frames_by_key.insert(
+                    (app_id.clone(), context_id.clone(), message_id),
+                    frame.clone(),
+                );
+            }
+            metadata.frames.insert(message_id, frame);
+        }
+        Ok(metadata)
+    }
+}
+
+/// frame ids look like "ID_1234" where 1234 is the message id
+fn message_id(frame_id: &str) -> Option<u32> {
+    let digits = frame_id.strip_prefix("ID_").unwrap_or(frame_id);
+    digits.parse::<u32>().ok()
+}
+
+fn signal_type(signal_ref: &str) -> Option<dlt::TypeInfo> {
+    let (kind, coding) = match signal_ref {
+        "S_BOOL" => (dlt::TypeInfoKind::Bool, dlt::StringCoding::ASCII),
+        "S_SINT8" => (
+            dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength8, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_SINT16" => (
+            dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength16, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_SINT32" => (
+            dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength32, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_SINT64" => (
+            dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength64, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_UINT8" => (
+            dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength8, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_UINT16" => (
+            dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength16, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_UINT32" => (
+            dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength32, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_UINT64" => (
+            dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength64, false),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_FLOA32" => (
+            dlt::TypeInfoKind::Float(dlt::FloatWidth::Width32),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_FLOA64" => (
+            dlt::TypeInfoKind::Float(dlt::FloatWidth::Width64),
+            dlt::StringCoding::ASCII,
+        ),
+        "S_STRG_ASCII" => (dlt::TypeInfoKind::StringType, dlt::StringCoding::ASCII),
+        "S_STRG_UTF8" => (dlt::TypeInfoKind::StringType, dlt::StringCoding::UTF8),
+        "S_RAW" | "S_RAWD" => (dlt::TypeInfoKind::Raw, dlt::StringCoding::ASCII),
+        _ => return None,
+    };
+    Some(dlt::TypeInfo {
+        kind,
+        coding,
+        has_variable_info: false,
+        has_trace_info: false,
+    })
+}
+
+fn message_type(message_type: &str, message_info: &str) -> Option<dlt::MessageType> {
+    match (message_type, message_info) {
+        ("DLT_TYPE_LOG", "DLT_LOG_FATAL") => Some(dlt::MessageType::Log(dlt::LogLevel::Fatal)),
+        ("DLT_TYPE_LOG", "DLT_LOG_ERROR") => Some(dlt::MessageType::Log(dlt::LogLevel::Error)),
+        ("DLT_TYPE_LOG", "DLT_LOG_WARN") => Some(dlt::MessageType::Log(dlt::LogLevel::Warn)),
+        ("DLT_TYPE_LOG", "DLT_LOG_INFO") => Some(dlt::MessageType::Log(dlt::LogLevel::Info)),
+        ("DLT_TYPE_LOG", "DLT_LOG_DEBUG") => Some(dlt::MessageType::Log(dlt::LogLevel::Debug)),
+        ("DLT_TYPE_LOG", "DLT_LOG_VERBOSE") => Some(dlt::MessageType::Log(dlt::LogLevel::Verbose)),
+        ("DLT_TYPE_APP_TRACE", "DLT_TRACE_VARIABLE") => Some(dlt::MessageType::ApplicationTrace(
+            dlt::ApplicationTraceType::Variable,
+        )),
+        ("DLT_TYPE_APP_TRACE", "DLT_TRACE_FUNCTION_IN") => Some(
+            dlt::MessageType::ApplicationTrace(dlt::ApplicationTraceType::FunctionIn),
+        ),
+        ("DLT_TYPE_APP_TRACE", "DLT_TRACE_FUNCTION_OUT") => Some(
+            dlt::MessageType::ApplicationTrace(dlt::ApplicationTraceType::FunctionOut),
+        ),
+        ("DLT_TYPE_APP_TRACE", "DLT_TRACE_STATE") => Some(dlt::MessageType::ApplicationTrace(
+            dlt::ApplicationTraceType::State,
+        )),
+        ("DLT_TYPE_APP_TRACE", "DLT_TRACE_VFB") => Some(dlt::MessageType::ApplicationTrace(
+            dlt::ApplicationTraceType::Vfb,
+        )),
+        ("DLT_TYPE_CONTROL", "DLT_CONTROL_REQUEST") => {
+            Some(dlt::MessageType::Control(dlt::ControlType::Request))
+        }
+        ("DLT_TYPE_CONTROL", "DLT_CONTROL_RESPONSE") => {
+            Some(dlt::MessageType::Control(dlt::ControlType::Response))
+        }
+        _ => None,
+    }
+}
+
+fn id_attribute<B: BufRead>(
+    e: &BytesStart,
+    reader: &Reader<B>,
+    key: &[u8],
+) -> Result<Option<String>, Error> {
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| err_msg(format!("invalid attribute: {}", e)))?;
+        if attr.key == key {
+            let value = attr
+                .unescape_and_decode_value(reader)
+                .map_err(|e| err_msg(format!("invalid attribute value: {}", e)))?;
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_fibex<B: BufRead>(mut reader: Reader<B>, parts: &mut FibexParts) -> Result<(), Error> {
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut text_buf = Vec::new();
+    let mut frame: Option<RawFrame> = None;
+    let mut pdu: Option<RawPdu> = None;
+    let mut instance: Option<RawInstance> = None;
+    loop {
+        let event = reader.read_event(&mut buf).map_err(|e| {
+            err_msg(format!(
+                "xml error at position {}: {}",
+                reader.buffer_position(),
+                e
+            ))
+        })?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match e.local_name() {
+                    b"FRAME" => {
+                        frame = Some(RawFrame {
+                            id: id_attribute(e, &reader, b"ID")?.unwrap_or_default(),
+                            ..Default::default()
+                        })
+                    }
+                    b"PDU" => {
+                        pdu = Some(RawPdu {
+                            id: id_attribute(e, &reader, b"ID")?.unwrap_or_default(),
+                            ..Default::default()
+                        })
+                    }
+                    b"PDU-INSTANCE" | b"SIGNAL-INSTANCE" => instance = Some(RawInstance::default()),
+                    b"PDU-REF" | b"SIGNAL-REF" => {
+                        let id_ref = id_attribute(e, &reader, b"ID-REF")?;
+                        if let Some(i) = instance.as_mut() {
+                            i.id_ref = id_ref;
+                        }
+                    }
+                    b"SHORT-NAME" | b"DESC" | b"SEQUENCE-NUMBER" | b"MESSAGE_TYPE"
+                    | b"MESSAGE_INFO" | b"APPLICATION_ID" | b"CONTEXT_ID"
+                        if !is_empty =>
+                    {
+                        let name = e.local_name().to_vec();
+                        let end = e.name().to_vec();
+                        let value = reader
+                            .read_text(&end, &mut text_buf)
+                            .map_err(|e| err_msg(format!("invalid text in element: {}", e)))?;
+                        text_buf.clear();
+                        match (&name[..], instance.as_mut(), pdu.as_mut(), frame.as_mut()) {
+                            (b"SEQUENCE-NUMBER", Some(i), _, _) => {
+                                i.sequence_number = value.parse().map_err(|_| {
+                                    err_msg(format!("invalid sequence number {}", value))
+                                })?
+                            }
+                            (b"DESC", None, Some(p), _) => p.description = Some(value),
+                            (b"SHORT-NAME", None, None, Some(f)) => f.short_name = Some(value),
+                            (b"MESSAGE_TYPE", _, None, Some(f)) => f.message_type = Some(value),
+                            (b"MESSAGE_INFO", _, None, Some(f)) => f.message_info = Some(value),
+                            (b"APPLICATION_ID", _, None, Some(f)) => f.application_id = Some(value),
+                            (b"CONTEXT_ID", _, None, Some(f)) => f.context_id = Some(value),
+                            _ => (),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::End(ref e) => match e.local_name() {
+                b"FRAME" => {
+                    if let Some(f) = frame.take() {
+                        parts.frames.push(f);
+                    }
+                }
+                b"PDU" => {
+                    if let Some(p) = pdu.take() {
+                        parts.pdus.insert(p.id.clone(), p);
+                    }
+                }
+                b"PDU-INSTANCE" => {
+                    if let (
+                        Some(RawInstance {
+                            sequence_number,
+                            id_ref: Some(id_ref),
+                        }),
+                        Some(f),
+                    ) = (instance.take(), frame.as_mut())
+                    {
+                        f.pdu_refs.push((sequence_number, id_ref));
+                    }
+                }
+                b"SIGNAL-INSTANCE" => {
+                    if let (
+                        Some(RawInstance {
+                            sequence_number,
+                            id_ref: Some(id_ref),
+                        }),
+                        Some(p),
+                    ) = (instance.take(), pdu.as_mut())
+                    {
+                        p.signal_refs.push((sequence_number, id_ref));
+                    }
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    pub static FIBEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<fx:FIBEX xmlns:fx="http://www.asam.net/xml/fbx" xmlns:ho="http://www.asam.net/xml">
+  <fx:ELEMENTS>
+    <fx:FRAMES>
+      <fx:FRAME ID="ID_10">
+        <ho:SHORT-NAME>ID_10</ho:SHORT-NAME>
+        <fx:BYTE-LENGTH>5</fx:BYTE-LENGTH>
+        <fx:FRAME-TYPE>OTHER</fx:FRAME-TYPE>
+        <fx:MANUFACTURER-EXTENSION>
+          <MESSAGE_TYPE>DLT_TYPE_LOG</MESSAGE_TYPE>
+          <MESSAGE_INFO>DLT_LOG_WARN</MESSAGE_INFO>
+          <APPLICATION_ID>APP1</APPLICATION_ID>
+          <CONTEXT_ID>CTX1</CONTEXT_ID>
+        </fx:MANUFACTURER-EXTENSION>
+        <fx:PDU-INSTANCES>
+          <fx:PDU-INSTANCE ID="P_10_1">
+            <fx:PDU-REF ID-REF="PDU_10_1"/>
+            <fx:SEQUENCE-NUMBER>1</fx:SEQUENCE-NUMBER>
+          </fx:PDU-INSTANCE>
+          <fx:PDU-INSTANCE ID="P_10_0">
+            <fx:PDU-REF ID-REF="PDU_10_0"/>
+            <fx:SEQUENCE-NUMBER>0</fx:SEQUENCE-NUMBER>
+          </fx:PDU-INSTANCE>
+        </fx:PDU-INSTANCES>
+      </fx:FRAME>
+    </fx:FRAMES>
+    <fx:PDUS>
+      <fx:PDU ID="PDU_10_0">
+        <ho:SHORT-NAME>PDU_10_0</ho:SHORT-NAME>
+        <ho:DESC>temperature:</ho:DESC>
+        <fx:BYTE-LENGTH>0</fx:BYTE-LENGTH>
+        <fx:PDU-TYPE>OTHER</fx:PDU-TYPE>
+      </fx:PDU>
+      <fx:PDU ID="PDU_10_1">
+        <ho:SHORT-NAME>PDU_10_1</ho:SHORT-NAME>
+        <fx:BYTE-LENGTH>5</fx:BYTE-LENGTH>
+        <fx:PDU-TYPE>OTHER</fx:PDU-TYPE>
+        <fx:SIGNAL-INSTANCES>
+          <fx:SIGNAL-INSTANCE ID="S_10_1_1">
+            <fx:SEQUENCE-NUMBER>1</fx:SEQUENCE-NUMBER>
+            <fx:SIGNAL-REF ID-REF="S_BOOL"/>
+          </fx:SIGNAL-INSTANCE>
+          <fx:SIGNAL-INSTANCE ID="S_10_1_0">
+            <fx:SEQUENCE-NUMBER>0</fx:SEQUENCE-NUMBER>
+            <fx:SIGNAL-REF ID-REF="S_SINT32"/>
+          </fx:SIGNAL-INSTANCE>
+        </fx:SIGNAL-INSTANCES>
+      </fx:PDU>
+    </fx:PDUS>
+  </fx:ELEMENTS>
+</fx:FIBEX>
+"#;
+
+    #[test]
+    fn test_read_fibex() {
+        let metadata = read_fibex(FIBEX.as_bytes()).expect("fibex should be valid");
+        assert_eq!(1, metadata.frame_count());
+        let frame = metadata.frame(10, None).expect("frame 10 should exist");
+        assert_eq!("ID_10", frame.short_name);
+        assert_eq!(Some("APP1".to_string()), frame.application_id);
+        assert_eq!(Some("CTX1".to_string()), frame.context_id);
+        assert_eq!(
+            Some(dlt::MessageType::Log(dlt::LogLevel::Warn)),
+            frame.message_type
+        );
+        assert_eq!(2, frame.pdus.len());
+        assert_eq!(Some("temperature:".to_string()), frame.pdus[0].description);
+        assert!(frame.pdus[0].signal_types.is_empty());
+        let kinds: Vec<dlt::TypeInfoKind> = frame.pdus[1]
+            .signal_types
+            .iter()
+            .map(|t| t.kind.clone())
+            .collect();
+        assert_eq!(
+            vec![
+                dlt::TypeInfoKind::Signed(dlt::TypeLength::BitLength32, false),
+                dlt::TypeInfoKind::Bool
+            ],
+            kinds
+        );
+        assert!(metadata.frame(10, Some(("APP1", "CTX1"))).is_some());
+        assert!(metadata.frame(11, None).is_none());
+    }
+    #[test]
+    fn test_read_fibex_with_unknown_pdu() {
+        let broken = FIBEX.replace("ID-REF=\"PDU_10_1\"", "ID-REF=\"PDU_99\"");
+        assert!(read_fibex(broken.as_bytes()).is_err());
+    }
+}
@@ -12,9 +12,16 @@
 use serde::{Deserialize, Serialize};
 use std::io::{Read};
 use std::fs;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use crate::dlt;
 use std::iter::FromIterator;
+use failure::{err_msg, Error};
+use indexer_base::config::TimeWindow;
+use indexer_base::error_reporter::report_warning;
+use regex::{Regex, RegexBuilder};
+
+pub mod expression;
+use expression::FilterExpression;
 
 /// only select log entries with level MIN_LEVEL and more severe
 ///  1 => FATAL
@@ -23,33 +30,290 @@ use std::iter::FromIterator;
 ///  4 => INFO
 ///  5 => DEBUG
 ///  6 => VERBOSE
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// `app_min_log_levels` overrides the minimum level for single applications
+/// (app id => level), `context_min_log_levels` for single contexts of an
+/// application (app id => context id => level). The most specific setting wins.
+///
+/// payload patterns are matched against the formatted arguments of a message:
+/// with `include_payload` only messages matching at least one of the patterns
+/// are selected, messages matching any of the `exclude_payload` patterns are dropped
+///
+/// `expression` is a boolean filter expression (see `expression`) that has to be
+/// true in addition to all other settings
+///
+/// `from` and `to` restrict the messages to an inclusive time window. With the
+/// default `time_base` ("storage") they are posix timestamps in ms compared with the
+/// storage header, with "uptime" they are compared with the ecu uptime of the
+/// standard header (in 0.1 ms). Messages without that timestamp are dropped.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct DltFilterConfig {
     pub min_log_level: Option<u8>,
+    pub app_min_log_levels: Option<HashMap<String, u8>>,
+    pub context_min_log_levels: Option<HashMap<String, HashMap<String, u8>>>,
     pub app_ids: Option<Vec<String>>,
     pub ecu_ids: Option<Vec<String>>,
     pub context_ids: Option<Vec<String>>,
+    pub include_payload: Option<Vec<PayloadPattern>>,
+    pub exclude_payload: Option<Vec<PayloadPattern>>,
+    pub expression: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub time_base: Option<TimeBase>,
+}
+/// the timestamp the time window of a filter refers to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBase {
+    Storage,
+    Uptime,
+}
+/// a substring or regular expression to look for in the payload
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayloadPattern {
+    pub pattern: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
 }
 pub struct ProcessedDltFilterConfig {
     pub min_log_level: Option<dlt::LogLevel>,
+    pub app_min_log_levels: Option<HashMap<String, dlt::LogLevel>>,
+    pub context_min_log_levels: Option<HashMap<String, HashMap<String, dlt::LogLevel>>>,
     pub app_ids: Option<HashSet<String>>,
     pub ecu_ids: Option<HashSet<String>>,
     pub context_ids: Option<HashSet<String>>,
+    pub include_payload: Option<Vec<PayloadMatcher>>,
+    pub exclude_payload: Option<Vec<PayloadMatcher>>,
+    pub expression: Option<FilterExpression>,
+    pub time_window: Option<TimeWindow>,
+    pub time_base: TimeBase,
+}
+impl ProcessedDltFilterConfig {
+    /// the minimum log level that applies to messages of a context
+    pub fn min_log_level_for(&self, app_id: &str, context_id: &str) -> Option<dlt::LogLevel> {
+        self.context_min_log_levels
+            .as_ref()
+            .and_then(|apps| apps.get(app_id))
+            .and_then(|contexts| contexts.get(context_id))
+            .or_else(|| {
+                self.app_min_log_levels
+                    .as_ref()
+                    .and_then(|apps| apps.get(app_id))
+            })
+            .cloned()
+            .or(self.min_log_level)
+    }
+    /// the timestamp of a message that is compared with the time window
+    pub fn message_time(
+        &self,
+        storage_header: Option<&dlt::StorageHeader>,
+        header: &dlt::StandardHeader,
+    ) -> Option<i64> {
+        self.time_of(
+            storage_header.map(|h| h.timestamp.as_millis()),
+            header.timestamp,
+        )
+    }
+    /// the time of the configured time base out of storage time (ms) and uptime
+    pub fn time_of(&self, storage_time: Option<i64>, uptime: Option<u32>) -> Option<i64> {
+        match self.time_base {
+            TimeBase::Storage => storage_time,
+            TimeBase::Uptime => uptime.map(i64::from),
+        }
+    }
+    /// true if a time window is configured and the message is not inside of it
+    pub fn is_outside_time_window(
+        &self,
+        storage_header: Option<&dlt::StorageHeader>,
+        header: &dlt::StandardHeader,
+    ) -> bool {
+        self.is_time_outside_window(self.message_time(storage_header, header))
+    }
+    /// true if a time window is configured and `time` is not inside of it
+    pub fn is_time_outside_window(&self, time: Option<i64>) -> bool {
+        match &self.time_window {
+            Some(window) => time.map_or(true, |time| !window.contains(time)),
+            None => false,
+        }
+    }
+    /// end of the time window if it refers to the storage time
+    ///
+    /// only storage timestamps can be expected to grow over a whole file,
+    /// the ecu uptime starts over with every lifecycle
+    pub fn storage_time_end(&self) -> Option<i64> {
+        match (self.time_base, &self.time_window) {
+            (TimeBase::Storage, Some(window)) => window.to,
+            _ => None,
+        }
+    }
+    /// true if payload patterns are configured and the payload does not satisfy them
+    pub fn is_payload_filtered_out(&self, payload: &dlt::Payload) -> bool {
+        if self.include_payload.is_none() && self.exclude_payload.is_none() {
+            return false;
+        }
+        let text = payload.as_text();
+        if let Some(include) = &self.include_payload {
+            if !include.iter().any(|m| m.is_match(&text)) {
+                return true;
+            }
+        }
+        if let Some(exclude) = &self.exclude_payload {
+            if exclude.iter().any(|m| m.is_match(&text)) {
+                return true;
+            }
+        }
+        false
+    }
+}
+pub enum PayloadMatcher {
+    /// the needle is already lowercase if case is ignored
+    Substring {
+        needle: String,
+        ignore_case: bool,
+    },
+    Regex(Regex),
+}
+impl PayloadMatcher {
+    pub fn new(pattern: PayloadPattern) -> Result<PayloadMatcher, Error> {
+        if pattern.is_regex {
+            let regex = RegexBuilder::new(&pattern.pattern)
+                .case_insensitive(pattern.ignore_case)
+                .build()
+                .map_err(|e| {
+                    err_msg(format!("invalid payload regex {}: {}", pattern.pattern, e))
+                })?;
+            Ok(PayloadMatcher::Regex(regex))
+        } else if pattern.ignore_case {
+            Ok(PayloadMatcher::Substring {
+                needle: pattern.pattern.to_lowercase(),
+                ignore_case: true,
+            })
+        } else {
+            Ok(PayloadMatcher::Substring {
+                needle: pattern.pattern,
+                ignore_case: false,
+            })
+        }
+    }
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            PayloadMatcher::Substring {
+                needle,
+                ignore_case: false,
+            } => text.contains(needle.as_str()),
+            PayloadMatcher::Substring {
+                needle,
+                ignore_case: true,
+            } => text.to_lowercase().contains(needle.as_str()),
+            PayloadMatcher::Regex(regex) => regex.is_match(text),
+        }
+    }
 }
 
-pub fn process_filter_config(cfg: DltFilterConfig) -> ProcessedDltFilterConfig {
-    ProcessedDltFilterConfig {
-        min_log_level: cfg.min_log_level.and_then(dlt::u8_to_log_level),
+fn payload_matchers(
+    patterns: Option<Vec<PayloadPattern>>,
+) -> Result<Option<Vec<PayloadMatcher>>, Error> {
+    patterns
+        .map(|patterns| patterns.into_iter().map(PayloadMatcher::new).collect())
+        .transpose()
+}
+
+fn log_level(level: u8, id: &str) -> Result<dlt::LogLevel, Error> {
+    dlt::u8_to_log_level(level)
+        .ok_or_else(|| err_msg(format!("invalid log level {} for {}", level, id)))
+}
+
+/// an invalid global level only disables level filtering (as it always did),
+/// existing configurations keep working
+fn global_log_level(level: Option<u8>) -> Option<dlt::LogLevel> {
+    let level = level?;
+    let log_level = dlt::u8_to_log_level(level);
+    if log_level.is_none() {
+        report_warning(format!(
+            "invalid log level {} for min_log_level, messages are not filtered by level",
+            level
+        ));
+    }
+    log_level
+}
+
+fn app_log_levels(
+    levels: Option<HashMap<String, u8>>,
+) -> Result<Option<HashMap<String, dlt::LogLevel>>, Error> {
+    levels
+        .map(|levels| {
+            levels
+                .into_iter()
+                .map(|(app_id, level)| Ok((app_id.clone(), log_level(level, &app_id)?)))
+                .collect()
+        })
+        .transpose()
+}
+
+/// app id => context id => level
+type ContextLogLevels<L> = HashMap<String, HashMap<String, L>>;
+
+fn context_log_levels(
+    levels: Option<ContextLogLevels<u8>>,
+) -> Result<Option<ContextLogLevels<dlt::LogLevel>>, Error> {
+    levels
+        .map(|levels| {
+            levels
+                .into_iter()
+                .map(|(app_id, contexts)| {
+                    let contexts = contexts
+                        .into_iter()
+                        .map(|(context_id, level)| {
+                            let id = format!("{}/{}", app_id, context_id);
+                            Ok((context_id, log_level(level, &id)?))
+                        })
+                        .collect::<Result<HashMap<String, dlt::LogLevel>, Error>>()?;
+                    Ok((app_id, contexts))
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+pub fn process_filter_config(cfg: DltFilterConfig) -> Result<ProcessedDltFilterConfig, Error> {
+    let time_window = match (cfg.from, cfg.to) {
+        (None, None) => None,
+        (Some(from), Some(to)) if from > to => {
+            return Err(err_msg(format!("invalid time window {}..{}", from, to)));
+        }
+        (from, to) => Some(TimeWindow { from, to }),
+    };
+    Ok(ProcessedDltFilterConfig {
+        min_log_level: global_log_level(cfg.min_log_level),
+        app_min_log_levels: app_log_levels(cfg.app_min_log_levels)?,
+        context_min_log_levels: context_log_levels(cfg.context_min_log_levels)?,
         app_ids: cfg.app_ids.map(HashSet::from_iter),
         ecu_ids: cfg.ecu_ids.map(HashSet::from_iter),
         context_ids: cfg.context_ids.map(HashSet::from_iter),
-    }
+        include_payload: payload_matchers(cfg.include_payload)?,
+        exclude_payload: payload_matchers(cfg.exclude_payload)?,
+        expression: cfg
+            .expression
+            .map(|e| FilterExpression::parse(&e))
+            .transpose()?,
+        time_window,
+        time_base: cfg.time_base.unwrap_or(TimeBase::Storage),
+    })
 }
 
 pub fn read_filter_options(f: &mut fs::File) -> Result<DltFilterConfig, failure::Error> {
     let mut contents = String::new();
     f.read_to_string(&mut contents)
         .expect("something went wrong reading the file");
+    if !contents.trim_start().starts_with('{') {
+        // the file contains just a filter expression
+        return Ok(DltFilterConfig {
+            expression: Some(contents.trim().to_string()),
+            ..Default::default()
+        });
+    }
     let v: DltFilterConfig = serde_json::from_str(&contents[..])?;
     Ok(v)
 }
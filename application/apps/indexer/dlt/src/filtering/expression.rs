@@ -0,0 +1,606 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! boolean filter expressions over dlt messages, e.g.
+//!
+//! `(ecu == "ECU1" && level <= WARN) || apid in ["DIAG", "SYS"]`
+//!
+//! fields:
+//! * `ecu`, `apid`, `ctid`, `payload`: text, compared with `==`, `!=`, `in`,
+//!   `contains` and `~` (regular expression)
+//! * `level` (`FATAL` < `ERROR` < `WARN` < `INFO` < `DEBUG` < `VERBOSE`),
+//!   `type` (`LOG`, `APP_TRACE`, `NW_TRACE`, `CONTROL`), `session`, `counter`,
+//!   `timestamp` (ecu uptime in 0.1 ms) and `time` (storage header time as posix
+//!   timestamp in ms or RFC 3339 string): numbers, compared with `==`, `!=`,
+//!   `<`, `<=`, `>`, `>=` and `in`
+//!
+//! expressions are combined with `&&`, `||`, `!` and parentheses.
+//! A comparison on a field the message does not have (e.g. the level of
+//! a message without extended header) is false.
+use crate::dlt;
+use failure::{err_msg, Error};
+use regex::Regex;
+use std::collections::HashSet;
+
+/// parentheses and negations nested deeper than this are rejected
+pub const MAX_EXPRESSION_DEPTH: usize = 64;
+
+/// a compiled filter expression
+#[derive(Debug)]
+pub enum FilterExpression {
+    And(Box<FilterExpression>, Box<FilterExpression>),
+    Or(Box<FilterExpression>, Box<FilterExpression>),
+    Not(Box<FilterExpression>),
+    Text(TextField, TextTest),
+    Number(NumberField, NumberTest),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextField {
+    Ecu,
+    AppId,
+    ContextId,
+    Payload,
+}
+
+#[derive(Debug)]
+pub enum TextTest {
+    Equals(String),
+    NotEquals(String),
+    In(HashSet<String>),
+    Contains(String),
+    Matches(Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberField {
+    Level,
+    MessageType,
+    SessionId,
+    Counter,
+    Timestamp,
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug)]
+pub enum NumberTest {
+    Compare(Comparison, i64),
+    In(Vec<i64>),
+}
+
+impl FilterExpression {
+    pub fn parse(input: &str) -> Result<FilterExpression, Error> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+            input_length: input.chars().count(),
+        };
+        let expression = parser.expression()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some((token, column)) => Err(parse_error(
+                *column,
+                format!("unexpected {} after the end of the expression", token),
+            )),
+        }
+    }
+
+    pub fn matches(&self, msg: &dlt::Message) -> bool {
+        let mut payload_text: Option<String> = None;
+        self.evaluate(msg, &mut payload_text)
+    }
+
+    /// `payload_text` caches the formatted payload so it is created at most once
+    fn evaluate(&self, msg: &dlt::Message, payload_text: &mut Option<String>) -> bool {
+        match self {
+            FilterExpression::And(left, right) => {
+                left.evaluate(msg, payload_text) && right.evaluate(msg, payload_text)
+            }
+            FilterExpression::Or(left, right) => {
+                left.evaluate(msg, payload_text) || right.evaluate(msg, payload_text)
+            }
+            FilterExpression::Not(expression) => !expression.evaluate(msg, payload_text),
+            FilterExpression::Text(field, test) => {
+                let value: Option<&str> = match field {
+                    TextField::Ecu => msg
+                        .header
+                        .ecu_id
+                        .as_ref()
+                        .or_else(|| msg.storage_header.as_ref().map(|h| &h.ecu_id))
+                        .map(|id| id.as_str()),
+                    TextField::AppId => msg
+                        .extended_header
+                        .as_ref()
+                        .map(|h| h.application_id.as_str()),
+                    TextField::ContextId => {
+                        msg.extended_header.as_ref().map(|h| h.context_id.as_str())
+                    }
+                    TextField::Payload => {
+                        if payload_text.is_none() {
+                            *payload_text = Some(msg.payload.as_text());
+                        }
+                        payload_text.as_ref().map(|text| text.as_str())
+                    }
+                };
+                value.map_or(false, |v| test.matches(v))
+            }
+            FilterExpression::Number(field, test) => {
+                number_value(*field, msg).map_or(false, |v| test.matches(v))
+            }
+        }
+    }
+}
+
+impl TextTest {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            TextTest::Equals(s) => value == s,
+            TextTest::NotEquals(s) => value != s,
+            TextTest::In(set) => set.contains(value),
+            TextTest::Contains(s) => value.contains(s.as_str()),
+            TextTest::Matches(regex) => regex.is_match(value),
+        }
+    }
+}
+
+impl NumberTest {
+    fn matches(&self, value: i64) -> bool {
+        match self {
+            NumberTest::Compare(comparison, n) => match comparison {
+                Comparison::Equal => value == *n,
+                Comparison::NotEqual => value != *n,
+                Comparison::Less => value < *n,
+                Comparison::LessOrEqual => value <= *n,
+                Comparison::Greater => value > *n,
+                Comparison::GreaterOrEqual => value >= *n,
+            },
+            NumberTest::In(values) => values.contains(&value),
+        }
+    }
+}
+
+fn number_value(field: NumberField, msg: &dlt::Message) -> Option<i64> {
+    match field {
+        NumberField::Level => match msg.extended_header.as_ref().map(|h| &h.message_type) {
+            Some(dlt::MessageType::Log(level)) => Some(i64::from(u8::from(level) >> 4)),
+            _ => None,
+        },
+        NumberField::MessageType => match msg.extended_header.as_ref().map(|h| &h.message_type) {
+            Some(dlt::MessageType::Log(_)) => Some(i64::from(dlt::DLT_TYPE_LOG)),
+            Some(dlt::MessageType::ApplicationTrace(_)) => Some(i64::from(dlt::DLT_TYPE_APP_TRACE)),
+            Some(dlt::MessageType::NetworkTrace(_)) => Some(i64::from(dlt::DLT_TYPE_NW_TRACE)),
+            Some(dlt::MessageType::Control(_)) => Some(i64::from(dlt::DLT_TYPE_CONTROL)),
+            _ => None,
+        },
+        NumberField::SessionId => msg.header.session_id.map(i64::from),
+        NumberField::Counter => Some(i64::from(msg.header.message_counter)),
+        NumberField::Timestamp => msg.header.timestamp.map(i64::from),
+        NumberField::Time => msg.storage_header.as_ref().map(|h| h.timestamp.as_millis()),
+    }
+}
+
+/// combines a chain of `&&` or `||` operands (in order) into a balanced tree,
+/// so long chains can be evaluated and dropped without overflowing the stack
+fn balanced(
+    mut operands: Vec<FilterExpression>,
+    combine: fn(Box<FilterExpression>, Box<FilterExpression>) -> FilterExpression,
+) -> FilterExpression {
+    if operands.len() == 1 {
+        return operands.remove(0);
+    }
+    let right = operands.split_off(operands.len() / 2);
+    combine(
+        Box::new(balanced(operands, combine)),
+        Box::new(balanced(right, combine)),
+    )
+}
+
+fn parse_error(column: usize, message: String) -> Error {
+    err_msg(format!(
+        "invalid filter expression at column {}: {}",
+        column, message
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Str(String),
+    Number(i64),
+    Operator(&'static str),
+}
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Identifier(name) => write!(f, "'{}'", name),
+            Token::Str(s) => write!(f, "string \"{}\"", s),
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Operator(op) => write!(f, "'{}'", op),
+        }
+    }
+}
+
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "~", "(", ")", "[", "]", ",",
+];
+
+/// splits the input into tokens, each with its (1 based) column
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(parse_error(column, "unterminated string".to_string())),
+                    Some('"') => break,
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some(escaped) => s.push(*escaped),
+                            None => {
+                                return Err(parse_error(column, "unterminated string".to_string()))
+                            }
+                        }
+                        i += 2;
+                    }
+                    Some(other) => {
+                        s.push(*other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Str(s), column));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric()) {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let number = if literal.starts_with("0x") || literal.starts_with("0X") {
+                i64::from_str_radix(&literal[2..], 16)
+            } else {
+                literal.parse::<i64>()
+            };
+            match number {
+                Ok(n) => tokens.push((Token::Number(n), column)),
+                Err(_) => return Err(parse_error(column, format!("invalid number {}", literal))),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Identifier(chars[start..i].iter().collect()), column));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => {
+                    tokens.push((Token::Operator(op), column));
+                    i += op.len();
+                }
+                None => return Err(parse_error(column, format!("unexpected character '{}'", c))),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+enum Field {
+    Text(TextField),
+    Number(NumberField),
+}
+
+const FIELD_NAMES: &str =
+    "ecu, apid, ctid, payload, level, type, session, counter, timestamp, time";
+
+fn field(name: &str) -> Option<Field> {
+    match name {
+        "ecu" => Some(Field::Text(TextField::Ecu)),
+        "apid" => Some(Field::Text(TextField::AppId)),
+        "ctid" => Some(Field::Text(TextField::ContextId)),
+        "payload" => Some(Field::Text(TextField::Payload)),
+        "level" => Some(Field::Number(NumberField::Level)),
+        "type" => Some(Field::Number(NumberField::MessageType)),
+        "session" => Some(Field::Number(NumberField::SessionId)),
+        "counter" => Some(Field::Number(NumberField::Counter)),
+        "timestamp" => Some(Field::Number(NumberField::Timestamp)),
+        "time" => Some(Field::Number(NumberField::Time)),
+        _ => None,
+    }
+}
+
+/// recursive descent parser:
+///
+/// ```text
+/// expression := and ("||" and)*
+/// and        := unary ("&&" unary)*
+/// unary      := "!" unary | "(" expression ")" | comparison
+/// comparison := field operator value | field "in" "[" value ("," value)* "]"
+/// ```
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// nesting level of the expression that is currently parsed
+    depth: usize,
+    input_length: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<&(Token, usize)> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self, expected: &str) -> Result<(Token, usize), Error> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t.clone())
+            }
+            None => Err(parse_error(
+                self.input_length + 1,
+                format!("expected {} but the expression ended", expected),
+            )),
+        }
+    }
+    fn next_is(&mut self, operator: &str) -> bool {
+        match self.peek() {
+            Some((Token::Operator(op), _)) if *op == operator => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn expect(&mut self, operator: &'static str) -> Result<(), Error> {
+        match self.next(&format!("'{}'", operator))? {
+            (Token::Operator(op), _) if op == operator => Ok(()),
+            (token, column) => Err(parse_error(
+                column,
+                format!("expected '{}' but found {}", operator, token),
+            )),
+        }
+    }
+    fn expression(&mut self) -> Result<FilterExpression, Error> {
+        let mut operands = vec![self.and()?];
+        while self.next_is("||") {
+            operands.push(self.and()?);
+        }
+        Ok(balanced(operands, FilterExpression::Or))
+    }
+    fn and(&mut self) -> Result<FilterExpression, Error> {
+        let mut operands = vec![self.unary()?];
+        while self.next_is("&&") {
+            operands.push(self.unary()?);
+        }
+        Ok(balanced(operands, FilterExpression::And))
+    }
+    fn unary(&mut self) -> Result<FilterExpression, Error> {
+        if self.next_is("!") {
+            let operand = self.nested(Parser::unary)?;
+            return Ok(FilterExpression::Not(Box::new(operand)));
+        }
+        if self.next_is("(") {
+            let expression = self.nested(Parser::expression)?;
+            self.expect(")")?;
+            return Ok(expression);
+        }
+        self.comparison()
+    }
+    /// parses the operand of the token that was just consumed, the depth
+    /// is limited so deeply nested input can not overflow the stack
+    fn nested<F>(&mut self, parse: F) -> Result<FilterExpression, Error>
+    where
+        F: FnOnce(&mut Parser) -> Result<FilterExpression, Error>,
+    {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return Err(parse_error(
+                self.tokens[self.pos - 1].1,
+                format!("nested deeper than {} levels", MAX_EXPRESSION_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+    fn comparison(&mut self) -> Result<FilterExpression, Error> {
+        let (name, column) = match self.next("a field name")? {
+            (Token::Identifier(name), column) => (name, column),
+            (token, column) => {
+                return Err(parse_error(
+                    column,
+                    format!("expected a field name but found {}", token),
+                ))
+            }
+        };
+        let field = field(&name).ok_or_else(|| {
+            parse_error(
+                column,
+                format!("unknown field '{}' (known fields: {})", name, FIELD_NAMES),
+            )
+        })?;
+        let (operator, op_column) = match self.next("an operator")? {
+            (Token::Operator(op), column) => (op, column),
+            (Token::Identifier(ref keyword), column) if keyword == "in" => ("in", column),
+            (Token::Identifier(ref keyword), column) if keyword == "contains" => {
+                ("contains", column)
+            }
+            (Token::Identifier(ref keyword), column) if keyword == "matches" => ("~", column),
+            (token, column) => {
+                return Err(parse_error(
+                    column,
+                    format!("expected an operator but found {}", token),
+                ))
+            }
+        };
+        match field {
+            Field::Text(text_field) => {
+                let test = match operator {
+                    "==" => TextTest::Equals(self.text_value()?),
+                    "!=" => TextTest::NotEquals(self.text_value()?),
+                    "contains" => TextTest::Contains(self.text_value()?),
+                    "~" => {
+                        let (pattern, column) = self.text_value_with_column()?;
+                        TextTest::Matches(Regex::new(&pattern).map_err(|e| {
+                            parse_error(column, format!("invalid regular expression: {}", e))
+                        })?)
+                    }
+                    "in" => {
+                        let mut values = HashSet::new();
+                        self.list(|parser| {
+                            values.insert(parser.text_value()?);
+                            Ok(())
+                        })?;
+                        TextTest::In(values)
+                    }
+                    op => {
+                        return Err(parse_error(
+                            op_column,
+                            format!(
+                                "operator '{}' can not be used with text field '{}'",
+                                op, name
+                            ),
+                        ))
+                    }
+                };
+                Ok(FilterExpression::Text(text_field, test))
+            }
+            Field::Number(number_field) => {
+                let comparison = match operator {
+                    "==" => Comparison::Equal,
+                    "!=" => Comparison::NotEqual,
+                    "<" => Comparison::Less,
+                    "<=" => Comparison::LessOrEqual,
+                    ">" => Comparison::Greater,
+                    ">=" => Comparison::GreaterOrEqual,
+                    "in" => {
+                        let mut values = vec![];
+                        self.list(|parser| {
+                            values.push(parser.number_value(number_field)?);
+                            Ok(())
+                        })?;
+                        return Ok(FilterExpression::Number(
+                            number_field,
+                            NumberTest::In(values),
+                        ));
+                    }
+                    op => {
+                        return Err(parse_error(
+                            op_column,
+                            format!(
+                                "operator '{}' can not be used with numeric field '{}'",
+                                op, name
+                            ),
+                        ))
+                    }
+                };
+                let value = self.number_value(number_field)?;
+                Ok(FilterExpression::Number(
+                    number_field,
+                    NumberTest::Compare(comparison, value),
+                ))
+            }
+        }
+    }
+    /// `"[" item ("," item)* "]"`
+    fn list<F>(&mut self, mut item: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut Parser) -> Result<(), Error>,
+    {
+        self.expect("[")?;
+        loop {
+            item(self)?;
+            if !self.next_is(",") {
+                break;
+            }
+        }
+        self.expect("]")
+    }
+    fn text_value(&mut self) -> Result<String, Error> {
+        self.text_value_with_column().map(|(s, _)| s)
+    }
+    fn text_value_with_column(&mut self) -> Result<(String, usize), Error> {
+        match self.next("a string")? {
+            (Token::Str(s), column) => Ok((s, column)),
+            (token, column) => Err(parse_error(
+                column,
+                format!("expected a string but found {}", token),
+            )),
+        }
+    }
+    fn number_value(&mut self, field: NumberField) -> Result<i64, Error> {
+        match self.next("a value")? {
+            (Token::Number(n), _) => Ok(n),
+            (Token::Identifier(name), column) => {
+                let value = match field {
+                    NumberField::Level => level_value(&name),
+                    NumberField::MessageType => message_type_value(&name),
+                    _ => None,
+                };
+                value.ok_or_else(|| {
+                    parse_error(column, format!("unknown value '{}' for this field", name))
+                })
+            }
+            (Token::Str(s), column) if field == NumberField::Time => {
+                chrono::DateTime::parse_from_rfc3339(&s)
+                    .map(|t| t.timestamp_millis())
+                    .map_err(|e| parse_error(column, format!("invalid time \"{}\": {}", s, e)))
+            }
+            (token, column) => Err(parse_error(
+                column,
+                format!("expected a number but found {}", token),
+            )),
+        }
+    }
+}
+
+fn level_value(name: &str) -> Option<i64> {
+    let level = match name.to_uppercase().as_str() {
+        "FATAL" => dlt::LEVEL_FATAL,
+        "ERROR" => dlt::LEVEL_ERROR,
+        "WARN" | "WARNING" => dlt::LEVEL_WARN,
+        "INFO" => dlt::LEVEL_INFO,
+        "DEBUG" => dlt::LEVEL_DEBUG,
+        "VERBOSE" => dlt::LEVEL_VERBOSE,
+        _ => return None,
+    };
+    Some(i64::from(level))
+}
+
+fn message_type_value(name: &str) -> Option<i64> {
+    let message_type = match name.to_uppercase().as_str() {
+        "LOG" => dlt::DLT_TYPE_LOG,
+        "APP_TRACE" => dlt::DLT_TYPE_APP_TRACE,
+        "NW_TRACE" => dlt::DLT_TYPE_NW_TRACE,
+        "CONTROL" => dlt::DLT_TYPE_CONTROL,
+        _ => return None,
+    };
+    Some(i64::from(message_type))
+}
@@ -18,8 +18,16 @@ extern crate indexer_base;
 extern crate log;
 
 pub mod dlt;
+pub mod dlt_net;
 pub mod dlt_parse;
+pub mod dlt_tokio;
+pub mod export;
+pub mod fibex;
 pub mod filtering;
+pub mod lifecycle;
+pub mod message_ref;
+pub mod parallel;
+pub mod pcap;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,274 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! detection of ecu lifecycles
+//!
+//! every dlt message carries the uptime of the ecu that sent it, the storage
+//! header contains the time the logger received it. A lifecycle of an ecu ends
+//! when its uptime starts over.
+//! The start of a lifecycle in wall clock time is estimated from the message with
+//! the smallest difference between receive time and uptime (the one that was
+//! delayed the least), the absolute time of every message of the lifecycle is
+//! then reconstructed from that start and its uptime.
+use crate::dlt;
+use crate::dlt_parse::{read_one_dlt_message, ReadResult};
+use crate::fibex;
+use crate::filtering;
+use indexer_base::compression;
+
+use buf_redux::policy::MinBuffered;
+use buf_redux::BufReader as ReduxReader;
+use failure::Error;
+
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::fmt;
+use std::io::BufRead;
+
+/// messages of different applications are not strictly ordered by uptime,
+/// the uptime has to go back further than this to count as restart of the ecu
+pub const MAX_UPTIME_JITTER_MS: i64 = 1_000;
+
+const NO_ECU_ID: &str = "NONE";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Lifecycle {
+    pub id: usize,
+    pub ecu_id: String,
+    /// start of the ecu (posix timestamp in ms), unknown without storage headers
+    pub start: Option<i64>,
+    /// reconstructed time of the last message (posix timestamp in ms)
+    pub end: Option<i64>,
+    pub message_count: usize,
+}
+
+/// the lifecycle a message belongs to and its reconstructed time
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub lifecycle_id: usize,
+    /// posix timestamp in ms, only known for messages with an uptime
+    pub absolute_time: Option<i64>,
+}
+
+struct CurrentLifecycle {
+    id: usize,
+    max_uptime_ms: i64,
+}
+
+/// assigns messages to lifecycles as they are read, lifecycle ids are given
+/// out in the order the lifecycles are found
+#[derive(Default)]
+struct LifecycleTracker {
+    current: FxHashMap<String, CurrentLifecycle>,
+    count: usize,
+}
+impl LifecycleTracker {
+    /// returns the id of the lifecycle and true if it was started by this message
+    fn track(&mut self, ecu_id: &str, uptime_ms: Option<i64>) -> (usize, bool) {
+        let next_id = self.count;
+        // messages without uptime stay in the current lifecycle
+        let restarted = |current: &CurrentLifecycle| {
+            uptime_ms.map_or(false, |uptime| {
+                uptime + MAX_UPTIME_JITTER_MS < current.max_uptime_ms
+            })
+        };
+        let uptime = uptime_ms.unwrap_or(0);
+        match self.current.get_mut(ecu_id) {
+            Some(current) if restarted(current) => {
+                // the ecu was restarted
+                *current = CurrentLifecycle {
+                    id: next_id,
+                    max_uptime_ms: uptime,
+                };
+            }
+            Some(current) => {
+                current.max_uptime_ms = current.max_uptime_ms.max(uptime);
+                return (current.id, false);
+            }
+            None => {
+                self.current.insert(
+                    ecu_id.to_string(),
+                    CurrentLifecycle {
+                        id: next_id,
+                        max_uptime_ms: uptime,
+                    },
+                );
+            }
+        }
+        self.count += 1;
+        (next_id, true)
+    }
+}
+
+/// collects the lifecycles of all ecus in a sequence of messages
+#[derive(Default)]
+pub struct LifecycleDetector {
+    tracker: LifecycleTracker,
+    lifecycles: Vec<LifecycleStats>,
+}
+struct LifecycleStats {
+    ecu_id: String,
+    /// smallest difference between storage time and uptime
+    min_offset: Option<i64>,
+    max_uptime_ms: Option<i64>,
+    message_count: usize,
+}
+impl LifecycleDetector {
+    pub fn new() -> LifecycleDetector {
+        Default::default()
+    }
+    /// `uptime` is the timestamp of the standard header (in 0.1 ms),
+    /// `storage_time` the time of the storage header (posix timestamp in ms)
+    /// returns the id of the lifecycle of the message
+    pub fn add(
+        &mut self,
+        ecu_id: Option<&str>,
+        uptime: Option<u32>,
+        storage_time: Option<i64>,
+    ) -> usize {
+        let ecu_id = ecu_id.unwrap_or(NO_ECU_ID);
+        let uptime_ms = uptime.map(uptime_to_ms);
+        let (id, started) = self.tracker.track(ecu_id, uptime_ms);
+        if started {
+            self.lifecycles.push(LifecycleStats {
+                ecu_id: ecu_id.to_string(),
+                min_offset: None,
+                max_uptime_ms: None,
+                message_count: 0,
+            });
+        }
+        let stats = &mut self.lifecycles[id];
+        stats.message_count += 1;
+        if let Some(uptime) = uptime_ms {
+            stats.max_uptime_ms = Some(stats.max_uptime_ms.map_or(uptime, |m| m.max(uptime)));
+            if let Some(time) = storage_time {
+                let offset = time - uptime;
+                stats.min_offset = Some(stats.min_offset.map_or(offset, |m| m.min(offset)));
+            }
+        }
+        id
+    }
+    pub fn add_message(&mut self, msg: &dlt::Message) -> usize {
+        let (ecu_id, uptime, storage_time) = message_times(msg);
+        self.add(ecu_id, uptime, storage_time)
+    }
+    pub fn finish(self) -> Vec<Lifecycle> {
+        self.lifecycles
+            .into_iter()
+            .enumerate()
+            .map(|(id, stats)| {
+                let end = stats
+                    .min_offset
+                    .and_then(|start| stats.max_uptime_ms.map(|uptime| start + uptime));
+                Lifecycle {
+                    id,
+                    ecu_id: stats.ecu_id,
+                    start: stats.min_offset,
+                    end,
+                    message_count: stats.message_count,
+                }
+            })
+            .collect()
+    }
+}
+
+/// annotates messages with their lifecycle once the lifecycles are known
+///
+/// the messages have to be passed in the same order as to the `LifecycleDetector`
+/// that found the lifecycles
+pub struct LifecycleAnnotator<'a> {
+    tracker: LifecycleTracker,
+    lifecycles: &'a [Lifecycle],
+}
+impl<'a> LifecycleAnnotator<'a> {
+    pub fn new(lifecycles: &'a [Lifecycle]) -> LifecycleAnnotator<'a> {
+        LifecycleAnnotator {
+            tracker: LifecycleTracker::default(),
+            lifecycles,
+        }
+    }
+    pub fn annotate(&mut self, ecu_id: Option<&str>, uptime: Option<u32>) -> Option<Annotation> {
+        let uptime_ms = uptime.map(uptime_to_ms);
+        let (id, _) = self.tracker.track(ecu_id.unwrap_or(NO_ECU_ID), uptime_ms);
+        self.lifecycles.get(id).map(|lifecycle| Annotation {
+            lifecycle_id: id,
+            absolute_time: lifecycle
+                .start
+                .and_then(|start| uptime_ms.map(|uptime| start + uptime)),
+        })
+    }
+    pub fn annotate_message(&mut self, msg: &dlt::Message) -> Option<Annotation> {
+        let (ecu_id, uptime, _) = message_times(msg);
+        self.annotate(ecu_id, uptime)
+    }
+}
+
+/// a message with its lifecycle id and absolute time as two extra columns
+/// after the payload (empty if they are not known)
+pub struct AnnotatedMessage<'a> {
+    pub message: &'a dlt::Message,
+    pub annotation: Option<Annotation>,
+}
+impl<'a> fmt::Display for AnnotatedMessage<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.message, dlt::DLT_COLUMN_SENTINAL)?;
+        if let Some(annotation) = &self.annotation {
+            write!(f, "{}", annotation.lifecycle_id)?;
+        }
+        write!(f, "{}", dlt::DLT_COLUMN_SENTINAL)?;
+        if let Some(time) = self.annotation.as_ref().and_then(|a| a.absolute_time) {
+            write!(f, "{}", time)?;
+        }
+        Ok(())
+    }
+}
+
+/// lifecycles of all messages of `input` that pass the filter
+pub(crate) fn detect_lifecycles(
+    input: compression::Input,
+    filter_config: Option<&filtering::ProcessedDltFilterConfig>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> Result<Vec<Lifecycle>, Error> {
+    let mut reader = ReduxReader::with_capacity(10 * 1024 * 1024, input.reader)
+        .set_policy(MinBuffered(10 * 1024));
+    let mut detector = LifecycleDetector::new();
+    loop {
+        match read_one_dlt_message(&mut reader, filter_config, None, fibex_metadata)? {
+            Some(ReadResult::Parsed(consumed, msg)) => {
+                reader.consume(consumed);
+                if let Some(msg) = msg {
+                    detector.add_message(&msg);
+                }
+            }
+            Some(ReadResult::Skipped(skipped)) => reader.consume(skipped),
+            None => break,
+        }
+    }
+    Ok(detector.finish())
+}
+
+fn uptime_to_ms(uptime: u32) -> i64 {
+    i64::from(uptime) / 10
+}
+
+fn message_times(msg: &dlt::Message) -> (Option<&str>, Option<u32>, Option<i64>) {
+    let ecu_id = msg
+        .header
+        .ecu_id
+        .as_ref()
+        .or_else(|| msg.storage_header.as_ref().map(|h| &h.ecu_id));
+    (
+        ecu_id.map(String::as_str),
+        msg.header.timestamp,
+        msg.storage_header.as_ref().map(|h| h.timestamp.as_millis()),
+    )
+}
@@ -0,0 +1,705 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! borrowed views of dlt messages
+//!
+//! a `MessageRef` points into the bytes it was parsed from, ids and string
+//! arguments are not copied. Together with a memory mapped file
+//! (`MappedDltFile`) messages can be inspected without copying them into
+//! a read buffer first.
+//! Only the type info of arrays (which boxes the element type) allocates.
+use crate::dlt::{self, TryFrom};
+use crate::dlt_parse::{
+    bytes_to_next_message, dlt_array_entry, dlt_fint, dlt_fixed_point, dlt_message, dlt_sint,
+    dlt_type_info, dlt_uint, dlt_zero_terminated_string, is_filtered_out_by_ids,
+    maybe_parse_ecu_id, maybe_parse_u32, parse_ecu_id, validated_payload_length, NomByteOrder,
+    SkippedRanges,
+};
+use crate::filtering;
+
+use byteorder::{BigEndian, LittleEndian};
+use failure::{err_msg, Error};
+use indexer_base::error_reporter::*;
+use memmap::Mmap;
+use nom::bytes::streaming::{tag, take};
+use nom::{combinator::map, number::streaming, sequence::tuple, IResult};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageHeaderRef<'a> {
+    pub timestamp: dlt::DltTimeStamp,
+    pub ecu_id: &'a str,
+}
+impl<'a> StorageHeaderRef<'a> {
+    pub fn to_owned(&self) -> dlt::StorageHeader {
+        dlt::StorageHeader {
+            timestamp: self.timestamp.clone(),
+            ecu_id: self.ecu_id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandardHeaderRef<'a> {
+    pub version: u8,
+    pub has_extended_header: bool,
+    pub big_endian: bool,
+    pub message_counter: u8,
+    pub overall_length: u16,
+    pub ecu_id: Option<&'a str>,
+    pub session_id: Option<u32>,
+    pub timestamp: Option<u32>,
+}
+impl<'a> StandardHeaderRef<'a> {
+    pub fn header_type(&self) -> u8 {
+        let mut header_type = self.version << 5;
+        if self.has_extended_header {
+            header_type |= dlt::WITH_EXTENDED_HEADER_FLAG
+        }
+        if self.big_endian {
+            header_type |= dlt::BIG_ENDIAN_FLAG
+        }
+        if self.ecu_id.is_some() {
+            header_type |= dlt::WITH_ECU_ID_FLAG
+        }
+        if self.session_id.is_some() {
+            header_type |= dlt::WITH_SESSION_ID_FLAG
+        }
+        if self.timestamp.is_some() {
+            header_type |= dlt::WITH_TIMESTAMP_FLAG
+        }
+        header_type
+    }
+    pub fn to_owned(&self) -> dlt::StandardHeader {
+        dlt::StandardHeader {
+            version: self.version,
+            has_extended_header: self.has_extended_header,
+            big_endian: self.big_endian,
+            message_counter: self.message_counter,
+            overall_length: self.overall_length,
+            ecu_id: self.ecu_id.map(str::to_string),
+            session_id: self.session_id,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedHeaderRef<'a> {
+    pub verbose: bool,
+    pub argument_count: u8,
+    pub message_type: dlt::MessageType,
+    pub application_id: &'a str,
+    pub context_id: &'a str,
+}
+impl<'a> ExtendedHeaderRef<'a> {
+    pub fn to_owned(&self) -> dlt::ExtendedHeader {
+        dlt::ExtendedHeader {
+            verbose: self.verbose,
+            argument_count: self.argument_count,
+            message_type: self.message_type.clone(),
+            application_id: self.application_id.to_string(),
+            context_id: self.context_id.to_string(),
+        }
+    }
+}
+
+/// a stored dlt message, the payload is not decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageRef<'a> {
+    pub storage_header: Option<StorageHeaderRef<'a>>,
+    pub header: StandardHeaderRef<'a>,
+    pub extended_header: Option<ExtendedHeaderRef<'a>>,
+    pub payload: &'a [u8],
+    /// all bytes of the message including the storage header
+    pub bytes: &'a [u8],
+}
+impl<'a> MessageRef<'a> {
+    /// ecu id of the standard header, the one of the storage header if there is none
+    pub fn ecu_id(&self) -> Option<&'a str> {
+        self.header
+            .ecu_id
+            .or_else(|| self.storage_header.as_ref().map(|h| h.ecu_id))
+    }
+    /// storage time (posix timestamp in ms)
+    pub fn storage_time(&self) -> Option<i64> {
+        self.storage_header
+            .as_ref()
+            .map(|h| h.timestamp.as_millis())
+    }
+    pub fn is_verbose(&self) -> bool {
+        self.extended_header.as_ref().map_or(false, |h| h.verbose)
+    }
+    pub fn log_level(&self) -> Option<dlt::LogLevel> {
+        match self.extended_header {
+            Some(ExtendedHeaderRef {
+                message_type: dlt::MessageType::Log(level),
+                ..
+            }) => Some(level),
+            _ => None,
+        }
+    }
+    /// the arguments of a verbose message, nothing for non-verbose messages
+    pub fn arguments(&self) -> Arguments<'a> {
+        Arguments {
+            input: self.payload,
+            remaining: self.extended_header.as_ref().map_or(0, |h| {
+                if h.verbose {
+                    h.argument_count as usize
+                } else {
+                    0
+                }
+            }),
+            big_endian: self.header.big_endian,
+            depth: 0,
+        }
+    }
+    /// decode the complete message
+    pub fn to_message(&self) -> Result<dlt::Message, Error> {
+        match dlt_message(self.bytes, None, None, None) {
+            Ok((_, Some(message))) => Ok(message),
+            Ok((_, None)) => Err(err_msg("message was filtered")),
+            Err(e) => Err(err_msg(format!("could not decode message: {:?}", e))),
+        }
+    }
+}
+
+/// iterates over the arguments of a verbose payload (or the fields of a struct)
+///
+/// iteration stops after the first argument that could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arguments<'a> {
+    input: &'a [u8],
+    remaining: usize,
+    big_endian: bool,
+    /// number of structs the arguments are part of
+    depth: usize,
+}
+impl<'a> Iterator for Arguments<'a> {
+    type Item = Result<ArgumentRef<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let res = if self.big_endian {
+            argument_ref::<BigEndian>(self.input, self.depth)
+        } else {
+            argument_ref::<LittleEndian>(self.input, self.depth)
+        };
+        match res {
+            Ok((rest, argument)) => {
+                self.input = rest;
+                self.remaining -= 1;
+                Some(Ok(argument))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(err_msg(format!("could not parse argument: {:?}", e))))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentRef<'a> {
+    pub type_info: dlt::TypeInfo,
+    pub trace_info: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub unit: Option<&'a str>,
+    pub fixed_point: Option<dlt::FixedPoint>,
+    pub value: ValueRef<'a>,
+}
+impl<'a> ArgumentRef<'a> {
+    pub fn to_argument(&self) -> dlt::Argument {
+        dlt::Argument {
+            type_info: self.type_info.clone(),
+            trace_info: self.trace_info.map(str::to_string),
+            name: self.name.map(str::to_string),
+            unit: self.unit.map(str::to_string),
+            fixed_point: self.fixed_point.clone(),
+            value: self.value.to_value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    /// bool, integer and float values
+    Scalar(dlt::Value),
+    StringVal(&'a str),
+    Raw(&'a [u8]),
+    Array(ArrayRef<'a>),
+    Struct(Arguments<'a>),
+}
+impl<'a> ValueRef<'a> {
+    pub fn to_value(&self) -> dlt::Value {
+        match self {
+            ValueRef::Scalar(value) => value.clone(),
+            ValueRef::StringVal(s) => dlt::Value::StringVal(s.to_string()),
+            ValueRef::Raw(bytes) => dlt::Value::Raw(bytes.to_vec()),
+            ValueRef::Array(array) => {
+                dlt::Value::Array(array.dimensions().collect(), array.values().collect())
+            }
+            // the fields were already parsed once to find the end of the struct
+            ValueRef::Struct(fields) => dlt::Value::Struct(
+                fields
+                    .clone()
+                    .filter_map(Result::ok)
+                    .map(|field| field.to_argument())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+type EntryParser = fn(&[u8]) -> IResult<&[u8], dlt::Value>;
+
+/// entries of an array argument
+#[derive(Debug, Clone)]
+pub struct ArrayRef<'a> {
+    dimensions: &'a [u8],
+    entries: &'a [u8],
+    parse_dimension: fn(&[u8]) -> IResult<&[u8], u16>,
+    parse_entry: EntryParser,
+    entry_width: usize,
+}
+impl<'a> ArrayRef<'a> {
+    pub fn dimensions(&self) -> impl Iterator<Item = u16> + 'a {
+        let parse = self.parse_dimension;
+        self.dimensions
+            .chunks(2)
+            .filter_map(move |bytes| parse(bytes).ok().map(|(_, d)| d))
+    }
+    pub fn values(&self) -> impl Iterator<Item = dlt::Value> + 'a {
+        let parse = self.parse_entry;
+        self.entries
+            .chunks(self.entry_width)
+            .filter_map(move |bytes| parse(bytes).ok().map(|(_, v)| v))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len() / self.entry_width
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+impl<'a> PartialEq for ArrayRef<'a> {
+    fn eq(&self, other: &ArrayRef) -> bool {
+        self.dimensions().eq(other.dimensions()) && self.values().eq(other.values())
+    }
+}
+
+pub(crate) fn storage_header_ref(input: &[u8]) -> IResult<&[u8], StorageHeaderRef<'_>> {
+    let (i, (_, _, seconds, microseconds)) = tuple((
+        tag("DLT"),
+        tag(&[0x01]),
+        streaming::le_u32,
+        streaming::le_u32,
+    ))(input)?;
+    let (rest, ecu_id) = dlt_zero_terminated_string(i, 4)?;
+    Ok((
+        rest,
+        StorageHeaderRef {
+            timestamp: dlt::DltTimeStamp {
+                seconds,
+                microseconds,
+            },
+            ecu_id,
+        },
+    ))
+}
+
+/// The standard header is part of every DLT message
+/// all big endian format [PRS_Dlt_00091]
+pub(crate) fn standard_header_ref(input: &[u8]) -> IResult<&[u8], StandardHeaderRef<'_>> {
+    let (rest, header_type) = streaming::be_u8(input)?;
+    let has_ecu_id = (header_type & dlt::WITH_ECU_ID_FLAG) != 0;
+    let has_session_id = (header_type & dlt::WITH_SESSION_ID_FLAG) != 0;
+    let has_timestamp = (header_type & dlt::WITH_TIMESTAMP_FLAG) != 0;
+    let (i, (message_counter, len, ecu_id, session_id, timestamp)) = tuple((
+        streaming::be_u8,
+        streaming::be_u16,
+        maybe_parse_ecu_id(has_ecu_id),
+        maybe_parse_u32(has_session_id),
+        maybe_parse_u32(has_timestamp),
+    ))(rest)?;
+    Ok((
+        i,
+        StandardHeaderRef {
+            version: header_type >> 5 & 0b111,
+            has_extended_header: (header_type & dlt::WITH_EXTENDED_HEADER_FLAG) != 0,
+            big_endian: (header_type & dlt::BIG_ENDIAN_FLAG) != 0,
+            message_counter,
+            overall_length: len,
+            ecu_id,
+            session_id,
+            timestamp,
+        },
+    ))
+}
+
+pub(crate) fn extended_header_ref(
+    input: &[u8],
+    index: Option<usize>,
+) -> IResult<&[u8], ExtendedHeaderRef<'_>> {
+    let (i, (message_info, argument_count, application_id, context_id)) = tuple((
+        streaming::be_u8,
+        streaming::be_u8,
+        parse_ecu_id,
+        parse_ecu_id,
+    ))(input)?;
+    let verbose = (message_info & dlt::VERBOSE_FLAG) != 0;
+    match dlt::MessageType::try_from(message_info, index) {
+        Ok(message_type) => Ok((
+            i,
+            ExtendedHeaderRef {
+                verbose,
+                argument_count,
+                message_type,
+                application_id,
+                context_id,
+            },
+        )),
+        Err(e) => {
+            report_error_ln(format!("Invalid message type: {}", e), index);
+            Err(nom::Err::Error((i, nom::error::ErrorKind::Verify)))
+        }
+    }
+}
+
+/// parse a stored dlt message without decoding its payload
+pub fn dlt_message_ref(input: &[u8]) -> IResult<&[u8], MessageRef<'_>> {
+    dlt_message_ref_at(input, None)
+}
+/// like `dlt_message_ref`, errors are reported for the message at `index`
+pub(crate) fn dlt_message_ref_at(
+    input: &[u8],
+    index: Option<usize>,
+) -> IResult<&[u8], MessageRef<'_>> {
+    let (after_storage_header, storage_header) = storage_header_ref(input)?;
+    let (after_standard_header, header) = standard_header_ref(after_storage_header)?;
+    let payload_length =
+        match validated_payload_length(header.overall_length, header.header_type(), index) {
+            Some(length) => length,
+            None => {
+                return Err(nom::Err::Error((
+                    after_standard_header,
+                    nom::error::ErrorKind::Verify,
+                )));
+            }
+        };
+    let (after_headers, extended_header) = if header.has_extended_header {
+        map(|i| extended_header_ref(i, index), Some)(after_standard_header)?
+    } else {
+        (after_standard_header, None)
+    };
+    let (rest, payload) = take(payload_length)(after_headers)?;
+    Ok((
+        rest,
+        MessageRef {
+            storage_header: Some(storage_header),
+            header,
+            extended_header,
+            payload,
+            bytes: &input[..input.len() - rest.len()],
+        },
+    ))
+}
+
+fn variable_name_ref<T: NomByteOrder>(input: &[u8]) -> IResult<&[u8], &str> {
+    let (i, size) = T::parse_u16(input)?;
+    dlt_zero_terminated_string(i, size as usize)
+}
+
+#[allow(clippy::type_complexity)]
+fn variable_name_and_unit_ref<'a, T: NomByteOrder>(
+    type_info: &dlt::TypeInfo,
+    input: &'a [u8],
+) -> IResult<&'a [u8], (Option<&'a str>, Option<&'a str>)> {
+    if type_info.has_variable_info {
+        let (i2, (name_size, unit_size)) = tuple((T::parse_u16, T::parse_u16))(input)?;
+        let (i3, name) = dlt_zero_terminated_string(i2, name_size as usize)?;
+        let (rest, unit) = dlt_zero_terminated_string(i3, unit_size as usize)?;
+        Ok((rest, (Some(name), Some(unit))))
+    } else {
+        Ok((input, (None, None)))
+    }
+}
+
+fn optional_name_ref<'a, T: NomByteOrder>(
+    type_info: &dlt::TypeInfo,
+    input: &'a [u8],
+) -> IResult<&'a [u8], Option<&'a str>> {
+    if type_info.has_variable_info {
+        map(variable_name_ref::<T>, Some)(input)
+    } else {
+        Ok((input, None))
+    }
+}
+
+fn fixed_point_ref<'a, T: NomByteOrder>(
+    input: &'a [u8],
+    kind: &dlt::TypeInfoKind,
+) -> IResult<&'a [u8], Option<dlt::FixedPoint>> {
+    match kind {
+        dlt::TypeInfoKind::Signed(width, true) | dlt::TypeInfoKind::Unsigned(width, true) => {
+            map(|i| dlt_fixed_point::<T>(i, *width), Some)(input)
+        }
+        _ => Ok((input, None)),
+    }
+}
+
+/// `depth` is the number of structs the argument is part of
+fn argument_ref<T: NomByteOrder>(input: &[u8], depth: usize) -> IResult<&[u8], ArgumentRef<'_>> {
+    let (i, type_info) = dlt_type_info::<T>(input)?;
+    let (i, trace_info) = if type_info.has_trace_info {
+        map(variable_name_ref::<T>, Some)(i)?
+    } else {
+        (i, None)
+    };
+    let (rest, (name, unit, fixed_point, value)) = match type_info.kind {
+        dlt::TypeInfoKind::Array(ref element_kind) => {
+            let (parse_entry, entry_width) = match dlt_array_entry::<T>(element_kind) {
+                Some(p) => p,
+                None => {
+                    report_error(format!("unsupported array type: {:?}", element_kind));
+                    return Err(nom::Err::Error((i, nom::error::ErrorKind::Verify)));
+                }
+            };
+            let (i2, dimension_cnt) = T::parse_u16(i)?;
+            let (i3, dimensions) = take(dimension_cnt as usize * 2)(i2)?;
+            let (before_fixed_point, (name, unit)) =
+                variable_name_and_unit_ref::<T>(&type_info, i3)?;
+            let (before_val, fixed_point) = fixed_point_ref::<T>(before_fixed_point, element_kind)?;
+            let entry_cnt = dimensions.chunks(2).try_fold(
+                if dimensions.is_empty() { 0 } else { 1usize },
+                |acc, d| {
+                    T::parse_u16(d)
+                        .ok()
+                        .and_then(|(_, d)| acc.checked_mul(d as usize))
+                },
+            );
+            let byte_cnt = match entry_cnt.and_then(|cnt| cnt.checked_mul(entry_width)) {
+                Some(bytes) => bytes,
+                None => {
+                    report_error("array too large");
+                    return Err(nom::Err::Error((before_val, nom::error::ErrorKind::Verify)));
+                }
+            };
+            let (rest, entries) = take(byte_cnt)(before_val)?;
+            let array = ArrayRef {
+                dimensions,
+                entries,
+                parse_dimension: T::parse_u16,
+                parse_entry,
+                entry_width,
+            };
+            (rest, (name, unit, fixed_point, ValueRef::Array(array)))
+        }
+        dlt::TypeInfoKind::Signed(width, _) => {
+            let (before_val, (name, unit)) = variable_name_and_unit_ref::<T>(&type_info, i)?;
+            let (after_fixed_point, fixed_point) =
+                fixed_point_ref::<T>(before_val, &type_info.kind)?;
+            let (rest, value) = dlt_sint::<T>(width)(after_fixed_point)?;
+            (rest, (name, unit, fixed_point, ValueRef::Scalar(value)))
+        }
+        dlt::TypeInfoKind::Unsigned(width, _) => {
+            let (before_val, (name, unit)) = variable_name_and_unit_ref::<T>(&type_info, i)?;
+            let (after_fixed_point, fixed_point) =
+                fixed_point_ref::<T>(before_val, &type_info.kind)?;
+            let (rest, value) = dlt_uint::<T>(width)(after_fixed_point)?;
+            (rest, (name, unit, fixed_point, ValueRef::Scalar(value)))
+        }
+        dlt::TypeInfoKind::Float(width) => {
+            let (before_val, (name, unit)) = variable_name_and_unit_ref::<T>(&type_info, i)?;
+            let (rest, value) = dlt_fint::<T>(width)(before_val)?;
+            (rest, (name, unit, None, ValueRef::Scalar(value)))
+        }
+        dlt::TypeInfoKind::Raw => {
+            let (i2, raw_byte_cnt) = T::parse_u16(i)?;
+            let (i3, name) = optional_name_ref::<T>(&type_info, i2)?;
+            let (rest, bytes) = take(raw_byte_cnt)(i3)?;
+            (rest, (name, None, None, ValueRef::Raw(bytes)))
+        }
+        dlt::TypeInfoKind::Bool => {
+            let (i2, name) = optional_name_ref::<T>(&type_info, i)?;
+            let (rest, value) = streaming::be_u8(i2)?;
+            (
+                rest,
+                (
+                    name,
+                    None,
+                    None,
+                    ValueRef::Scalar(dlt::Value::Bool(value != 0)),
+                ),
+            )
+        }
+        dlt::TypeInfoKind::StringType => {
+            let (i2, size) = T::parse_u16(i)?;
+            let (i3, name) = optional_name_ref::<T>(&type_info, i2)?;
+            let (rest, value) = dlt_zero_terminated_string(i3, size as usize)?;
+            (rest, (name, None, None, ValueRef::StringVal(value)))
+        }
+        dlt::TypeInfoKind::Struct => {
+            if depth >= dlt::MAX_STRUCT_DEPTH {
+                return Err(nom::Err::Error((i, nom::error::ErrorKind::TooLarge)));
+            }
+            let (i2, field_cnt) = T::parse_u16(i)?;
+            let (fields_start, name) = optional_name_ref::<T>(&type_info, i2)?;
+            // walk over the fields once to find the end of the struct
+            let mut rest = fields_start;
+            for _ in 0..field_cnt {
+                let (r, _) = argument_ref::<T>(rest, depth + 1)?;
+                rest = r;
+            }
+            let fields = Arguments {
+                input: &fields_start[..fields_start.len() - rest.len()],
+                remaining: field_cnt as usize,
+                big_endian: T::is_big_endian(),
+                depth: depth + 1,
+            };
+            (rest, (name, None, None, ValueRef::Struct(fields)))
+        }
+    };
+    Ok((
+        rest,
+        ArgumentRef {
+            type_info,
+            trace_info,
+            name,
+            unit,
+            fixed_point,
+            value,
+        },
+    ))
+}
+
+/// true if the message does not pass the filter
+///
+/// payload and expression filters need the decoded message, it is only
+/// decoded if one of them is configured. Non-verbose messages are not
+/// decoded with a fibex.
+pub fn is_filtered_out(
+    filter_config: &filtering::ProcessedDltFilterConfig,
+    msg: &MessageRef,
+) -> bool {
+    if filter_config
+        .is_time_outside_window(filter_config.time_of(msg.storage_time(), msg.header.timestamp))
+    {
+        return true;
+    }
+    if let Some(h) = &msg.extended_header {
+        if is_filtered_out_by_ids(
+            filter_config,
+            h.application_id,
+            h.context_id,
+            &h.message_type,
+            msg.header.ecu_id,
+        ) {
+            return true;
+        }
+    }
+    let needs_message = filter_config.include_payload.is_some()
+        || filter_config.exclude_payload.is_some()
+        || filter_config.expression.is_some();
+    if needs_message {
+        let message = match msg.to_message() {
+            Ok(message) => message,
+            Err(_) => return true,
+        };
+        if filter_config.is_payload_filtered_out(&message.payload) {
+            return true;
+        }
+        if let Some(expression) = &filter_config.expression {
+            return !expression.matches(&message);
+        }
+    }
+    false
+}
+
+/// a dlt file mapped into memory
+pub struct MappedDltFile {
+    /// empty files cannot be mapped
+    mmap: Option<Mmap>,
+}
+impl MappedDltFile {
+    pub fn open(path: &Path) -> Result<MappedDltFile, Error> {
+        let file = fs::File::open(path)?;
+        let mmap = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            // the file must not be changed while it is mapped, dlt files
+            // are only appended to by loggers which does not affect the mapped part
+            Some(unsafe { Mmap::map(&file)? })
+        };
+        Ok(MappedDltFile { mmap })
+    }
+    pub fn bytes(&self) -> &[u8] {
+        self.mmap.as_ref().map_or(&[], |m| &m[..])
+    }
+    /// all messages of the file, corrupted parts are skipped
+    pub fn messages(&self) -> Messages<'_> {
+        Messages::new(self.bytes())
+    }
+}
+
+/// iterates over the messages in a byte slice
+///
+/// data that does not contain valid messages is skipped like
+/// `index_dlt_file` does
+pub struct Messages<'a> {
+    input: &'a [u8],
+    offset: usize,
+    skipped_ranges: Option<SkippedRanges>,
+}
+impl<'a> Messages<'a> {
+    pub fn new(input: &'a [u8]) -> Messages<'a> {
+        Messages {
+            input,
+            offset: 0,
+            skipped_ranges: Some(SkippedRanges::default()),
+        }
+    }
+    /// offset of the next message in the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+impl<'a> Iterator for Messages<'a> {
+    type Item = MessageRef<'a>;
+    fn next(&mut self) -> Option<MessageRef<'a>> {
+        while self.offset < self.input.len() {
+            let content = &self.input[self.offset..];
+            match dlt_message_ref(content) {
+                Ok((rest, msg)) => {
+                    self.offset += content.len() - rest.len();
+                    if let Some(ranges) = self.skipped_ranges.as_mut() {
+                        ranges.resynced();
+                    }
+                    return Some(msg);
+                }
+                Err(_) => {
+                    let skipped = bytes_to_next_message(content);
+                    if let Some(ranges) = self.skipped_ranges.as_mut() {
+                        ranges.skipped(self.offset, skipped);
+                    }
+                    self.offset += skipped;
+                }
+            }
+        }
+        if let Some(ranges) = self.skipped_ranges.take() {
+            ranges.finish();
+        }
+        None
+    }
+}
@@ -0,0 +1,496 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! indexing of dlt files on multiple threads
+//!
+//! the file is read in batches that are split into slices at message
+//! boundaries. The slices are parsed and formatted on a thread pool, the
+//! results are written in order afterwards so the line numbers and chunks
+//! are the same as the ones of `index_dlt_file`.
+//! A slice that does not end with a complete message (the boundary was just
+//! something that looked like a storage header) is parsed again together with
+//! the following data.
+use crate::dlt;
+use crate::dlt_parse::{
+    bytes_to_next_message, dlt_message, finish_chunks, index_dlt_file, SkippedRanges,
+    REPORT_PROGRESS_LINE_BLOCK,
+};
+use crate::fibex;
+use crate::filtering;
+
+use byteorder::{BigEndian, ByteOrder};
+use failure::{err_msg, Error};
+use indexer_base::chunks::{Chunk, ChunkFactory};
+use indexer_base::compression;
+use indexer_base::config::IndexingConfig;
+use indexer_base::index_map;
+use indexer_base::progress::*;
+use indexer_base::token_index;
+use indexer_base::utils;
+use std::fmt::Write as FmtWrite;
+use std::io::{BufWriter, Read, Write};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// size of the slices that are parsed by one thread
+pub const SLICE_SIZE: usize = 4 * 1024 * 1024;
+
+/// messages of one slice, formatted but without tag and line number
+struct ParsedSlice {
+    formatted: String,
+    /// end of every message in `formatted`
+    line_ends: Vec<usize>,
+    /// ranges (relative to the start of the slice) that were skipped
+    skipped: Vec<(usize, usize)>,
+    /// the slice ended with a complete message
+    complete: bool,
+}
+
+fn parse_slice(
+    content: &[u8],
+    at_end_of_file: bool,
+    filter_config: Option<&filtering::ProcessedDltFilterConfig>,
+    fibex_metadata: Option<&fibex::FibexMetadata>,
+) -> ParsedSlice {
+    let mut parsed = ParsedSlice {
+        formatted: String::new(),
+        line_ends: vec![],
+        skipped: vec![],
+        complete: false,
+    };
+    let mut pos = 0usize;
+    let mut ends_with_message = false;
+    while pos < content.len() {
+        let input = &content[pos..];
+        match dlt_message(input, filter_config, None, fibex_metadata) {
+            Ok((rest, msg)) => {
+                if let Some(msg) = msg {
+                    // writing to a String cannot fail
+                    let _ = write!(parsed.formatted, "{}", msg);
+                    parsed.line_ends.push(parsed.formatted.len());
+                }
+                pos += input.len() - rest.len();
+                ends_with_message = true;
+            }
+            Err(nom::Err::Incomplete(_)) if !at_end_of_file => break,
+            Err(_) => {
+                let skipped = bytes_to_next_message(input);
+                parsed.skipped.push((pos, skipped));
+                pos += skipped;
+                ends_with_message = false;
+            }
+        }
+    }
+    parsed.complete = at_end_of_file || (pos == content.len() && ends_with_message);
+    parsed
+}
+
+/// length of the message starting at `pos` if there is something that looks
+/// like a storage header followed by a standard header
+fn message_length_at(content: &[u8], pos: usize) -> Option<usize> {
+    if !content[pos..].starts_with(dlt::STORAGE_HEADER_PATTERN) {
+        return None;
+    }
+    let header_start = pos + dlt::STORAGE_HEADER_LENGTH;
+    let header = content.get(header_start..header_start + dlt::HEADER_MIN_LENGTH)?;
+    let overall_length = BigEndian::read_u16(&header[2..]) as usize;
+    if overall_length < dlt::calculate_all_headers_length(header[0]) {
+        return None;
+    }
+    Some(dlt::STORAGE_HEADER_LENGTH + overall_length)
+}
+
+/// first position at or after `from` where a message starts that is
+/// followed by another message (or the end of the content)
+fn next_boundary(content: &[u8], from: usize) -> Option<usize> {
+    (from..content.len()).find(|&pos| {
+        message_length_at(content, pos).map_or(false, |length| {
+            let next = pos + length;
+            next == content.len()
+                || (next < content.len() && message_length_at(content, next).is_some())
+        })
+    })
+}
+
+/// ends of the slices `content` is split into
+///
+/// the first slice has to end after `min_first_end`, at the end of the file
+/// the last slice ends with the content
+fn slice_ends(
+    content: &[u8],
+    slice_size: usize,
+    min_first_end: usize,
+    at_end_of_file: bool,
+) -> Vec<usize> {
+    let mut ends = vec![];
+    let mut start = 0usize;
+    loop {
+        let min_end = if ends.is_empty() {
+            std::cmp::max(slice_size, min_first_end + 1)
+        } else {
+            start + slice_size
+        };
+        match next_boundary(content, min_end) {
+            Some(end) if end < content.len() => {
+                ends.push(end);
+                start = end;
+            }
+            _ => break,
+        }
+    }
+    if at_end_of_file && start < content.len() {
+        ends.push(content.len());
+    }
+    ends
+}
+
+/// a slice of a batch that has to be parsed
+struct Job {
+    batch: Arc<Vec<u8>>,
+    index: usize,
+    start: usize,
+    end: usize,
+    at_end_of_file: bool,
+}
+
+/// threads that parse slices, the results are sent back with the index of the slice
+struct WorkerPool {
+    jobs: Option<mpsc::Sender<Job>>,
+    results: mpsc::Receiver<(usize, ParsedSlice)>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+impl WorkerPool {
+    fn new(
+        threads: usize,
+        filter_config: Option<filtering::ProcessedDltFilterConfig>,
+        fibex_metadata: Option<fibex::FibexMetadata>,
+    ) -> WorkerPool {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (result_sender, results) = mpsc::channel();
+        let filter_config = Arc::new(filter_config);
+        let fibex_metadata = Arc::new(fibex_metadata);
+        let workers = (0..threads)
+            .map(|_| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                let filter_config = Arc::clone(&filter_config);
+                let fibex_metadata = Arc::clone(&fibex_metadata);
+                thread::spawn(move || loop {
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    // the pool was dropped
+                    let job = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let parsed = parse_slice(
+                        &job.batch[job.start..job.end],
+                        job.at_end_of_file,
+                        filter_config.as_ref().as_ref(),
+                        fibex_metadata.as_ref().as_ref(),
+                    );
+                    if result_sender.send((job.index, parsed)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        WorkerPool {
+            jobs: Some(jobs),
+            results,
+            workers,
+        }
+    }
+    /// parse the slices of `batch`, the results are in the order of the slices
+    fn parse(
+        &self,
+        batch: &Arc<Vec<u8>>,
+        slices: &[(usize, usize)],
+        at_end_of_file: bool,
+    ) -> Result<Vec<ParsedSlice>, Error> {
+        let jobs = self
+            .jobs
+            .as_ref()
+            .ok_or_else(|| err_msg("worker pool was shut down"))?;
+        for (index, &(start, end)) in slices.iter().enumerate() {
+            jobs.send(Job {
+                batch: Arc::clone(batch),
+                index,
+                start,
+                end,
+                at_end_of_file: at_end_of_file && end == batch.len(),
+            })
+            .map_err(|_| err_msg("worker threads are gone"))?;
+        }
+        let mut results: Vec<Option<ParsedSlice>> = slices.iter().map(|_| None).collect();
+        for _ in slices {
+            let (index, parsed) = self
+                .results
+                .recv()
+                .map_err(|_| err_msg("worker threads are gone"))?;
+            results[index] = Some(parsed);
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+}
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // workers stop once the job channel is closed
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// append data from `reader` to `buffer` until it holds `size` bytes
+///
+/// returns true if the end of the input was reached
+fn fill_buffer<R: Read>(reader: &mut R, buffer: &mut Vec<u8>, size: usize) -> Result<bool, Error> {
+    while buffer.len() < size {
+        let missing = size - buffer.len();
+        let read = reader
+            .by_ref()
+            .take(missing as u64)
+            .read_to_end(buffer)
+            .map_err(|e| err_msg(format!("error while reading dlt messages: {}", e)))?;
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// create index for a dlt file using `threads` worker threads (0 for one per cpu)
+///
+/// the output is the same as the one of `index_dlt_file`. If the filter
+/// ends the indexing early (time window on storage time) the file is
+/// indexed on the current thread.
+#[allow(clippy::too_many_arguments)]
+pub fn index_dlt_file_parallel(
+    config: IndexingConfig,
+    dlt_filter: Option<filtering::DltFilterConfig>,
+    initial_line_nr: usize,
+    source_file_size: Option<usize>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+    threads: usize,
+) -> Result<Vec<Chunk>, Error> {
+    trace!("index_dlt_file_parallel");
+    let filter_config: Option<filtering::ProcessedDltFilterConfig> = dlt_filter
+        .clone()
+        .map(filtering::process_filter_config)
+        .transpose()?;
+    if filter_config
+        .as_ref()
+        .map_or(false, |f| f.storage_time_end().is_some())
+    {
+        return index_dlt_file(
+            config,
+            dlt_filter,
+            initial_line_nr,
+            source_file_size,
+            update_channel,
+            shutdown_receiver,
+            fibex,
+            false,
+        );
+    }
+    index_dlt_file_in_slices(
+        config,
+        filter_config,
+        initial_line_nr,
+        source_file_size,
+        update_channel,
+        shutdown_receiver,
+        fibex,
+        threads,
+        SLICE_SIZE,
+    )
+}
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn index_dlt_file_in_slices(
+    config: IndexingConfig,
+    filter_config: Option<filtering::ProcessedDltFilterConfig>,
+    initial_line_nr: usize,
+    source_file_size: Option<usize>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+    threads: usize,
+    slice_size: usize,
+) -> Result<Vec<Chunk>, Error> {
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let threads = if threads == 0 {
+        num_cpus::get()
+    } else {
+        threads
+    };
+    let pool = WorkerPool::new(threads, filter_config, fibex_metadata);
+    let batch_size = threads * slice_size;
+
+    let (out_file, current_out_file_size) =
+        utils::get_out_file_and_size(config.append, config.out_path)?;
+    let mut chunks = vec![];
+    let mut chunk_factory =
+        ChunkFactory::new(config.chunk_size, config.to_stdout, current_out_file_size);
+    if let Some(index_map) = &config.index_map {
+        chunk_factory = chunk_factory.with_index_writer(index_map::writer_for(
+            index_map,
+            &config.in_file,
+            config.append,
+        )?);
+    }
+    if let Some(token_index) = config.token_index {
+        chunk_factory = chunk_factory.with_token_writer(token_index::writer_for(
+            token_index,
+            &config.in_file,
+            config.append,
+            config.chunk_size,
+        )?);
+    }
+    let mut buf_writer = BufWriter::with_capacity(10 * 1024 * 1024, out_file);
+    let compression::Input {
+        mut reader,
+        progress,
+    } = compression::open_input(config.in_file, config.zip_entry)?;
+    let mut line_nr = initial_line_nr;
+    let mut processed_lines = 0usize;
+    let mut processed_bytes = utils::get_processed_bytes(config.append, config.out_path) as usize;
+    let mut skipped_ranges = SkippedRanges::default();
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(batch_size);
+    // file offset of the start of `buffer`
+    let mut offset = 0usize;
+    // end of a slice that did not end with a complete message
+    let mut min_first_end = 0usize;
+    let mut stopped = false;
+    while !stopped {
+        let at_end_of_file = fill_buffer(
+            &mut reader,
+            &mut buffer,
+            std::cmp::max(batch_size, min_first_end + 2 * slice_size),
+        )?;
+        if buffer.is_empty() {
+            break;
+        }
+        let ends = slice_ends(&buffer, slice_size, min_first_end, at_end_of_file);
+        if ends.is_empty() {
+            // no boundary yet, read more
+            min_first_end = buffer.len();
+            continue;
+        }
+        let slices: Vec<(usize, usize)> = ends
+            .iter()
+            .scan(0usize, |start, &end| {
+                let slice = (*start, end);
+                *start = end;
+                Some(slice)
+            })
+            .collect();
+        let batch = Arc::new(buffer);
+        let results = pool.parse(&batch, &slices, at_end_of_file)?;
+        let mut done = 0usize;
+        min_first_end = 0;
+        for (&(start, end), parsed) in slices.iter().zip(results) {
+            if !parsed.complete {
+                min_first_end = end - done;
+                break;
+            }
+            for (skipped_start, skipped) in parsed.skipped {
+                skipped_ranges.skipped(offset + start + skipped_start, skipped);
+            }
+            let mut line_start = 0usize;
+            for line_end in parsed.line_ends {
+                let formatted = &parsed.formatted[line_start..line_end];
+                chunk_factory.add_tokens(formatted.as_bytes());
+                let written_bytes_len = utils::create_tagged_line_d(
+                    config.tag,
+                    &mut buf_writer,
+                    formatted,
+                    line_nr,
+                    true,
+                )?;
+                line_start = line_end;
+                line_nr += 1;
+                processed_lines += 1;
+                if let Some(chunk) =
+                    chunk_factory.create_chunk_if_needed(line_nr, written_bytes_len)
+                {
+                    if let Some(rx) = shutdown_receiver.as_ref() {
+                        match rx.try_recv() {
+                            Ok(_) | Err(TryRecvError::Disconnected) => {
+                                info!("shutdown received in indexer",);
+                                stopped = true
+                            }
+                            Err(TryRecvError::Empty) => (),
+                        }
+                    };
+                    update_channel.as_ref().map(|c| {
+                        c.send(IndexingProgress::GotItem {
+                            item: chunk.clone(),
+                        })
+                    });
+                    chunks.push(chunk);
+                    buf_writer.flush()?;
+                    if stopped {
+                        break;
+                    }
+                }
+                if let Some(file_size) = source_file_size {
+                    utils::report_progress(
+                        processed_lines,
+                        chunk_factory.get_current_byte_index(),
+                        progress.bytes(processed_bytes),
+                        file_size,
+                        REPORT_PROGRESS_LINE_BLOCK,
+                    );
+                }
+            }
+            processed_bytes += end - start;
+            done = end;
+            if stopped {
+                info!("we where stopped in dlt-indexer",);
+                break;
+            }
+        }
+        if let Some(file_size) = source_file_size {
+            update_channel.as_ref().map(|c| {
+                c.send(IndexingProgress::Progress {
+                    ticks: (progress.bytes(processed_bytes), file_size),
+                })
+            });
+        }
+        buffer = batch[done..].to_vec();
+        offset += done;
+        if at_end_of_file && buffer.is_empty() {
+            break;
+        }
+    }
+    skipped_ranges.finish();
+
+    buf_writer.flush()?;
+    finish_chunks(
+        chunk_factory,
+        line_nr,
+        chunks,
+        config.out_path,
+        update_channel,
+    )
+}
@@ -0,0 +1,787 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+//! extract dlt messages that were sent over UDP or TCP from
+//! pcap and pcapng capture files
+use crate::dlt;
+use crate::dlt_net::{decode_datagram, shutdown_requested, MessageIndexer, DLT_DAEMON_PORT};
+use crate::dlt_parse::bytes_to_next_standard_header;
+use crate::dlt_tokio::Codec;
+use crate::fibex;
+use crate::filtering;
+use indexer_base::chunks::Chunk;
+use indexer_base::config::IndexingConfig;
+use indexer_base::index_map;
+use indexer_base::error_reporter::*;
+use indexer_base::progress::*;
+use indexer_base::utils;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use bytes::BytesMut;
+use failure::{err_msg, Error};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::codec::Decoder;
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION_BLOCK: u32 = 0x1;
+const PCAPNG_SIMPLE_PACKET_BLOCK: u32 = 0x3;
+const PCAPNG_ENHANCED_PACKET_BLOCK: u32 = 0x6;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+/// upper bound for a single record, protects against garbage lengths
+const MAX_RECORD_SIZE: usize = 256 * 1024 * 1024;
+
+const LINKTYPE_NULL: u16 = 0;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+const TCP_FLAG_FIN: u8 = 0x01;
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_FLAG_RST: u8 = 0x04;
+/// segments we keep around waiting for a missing one before we give up on it
+const MAX_PENDING_SEGMENTS: usize = 1024;
+
+const REPORT_PROGRESS_PACKET_BLOCK: usize = 250_000;
+
+/// ports that carry dlt if nothing else is configured
+pub const DEFAULT_DLT_PORTS: &[u16] = &[DLT_DAEMON_PORT];
+
+/// true if the file should be read as packet capture (judged by the extension)
+pub fn is_capture_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_lowercase();
+            ext == "pcap" || ext == "pcapng"
+        }
+        None => false,
+    }
+}
+
+/// one packet as it was recorded in the capture
+#[derive(Debug)]
+pub struct CapturedPacket {
+    pub timestamp: dlt::DltTimeStamp,
+    pub link_type: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    link_type: u16,
+    /// timestamp units per second
+    ts_units: u64,
+}
+
+enum Format {
+    Pcap {
+        big_endian: bool,
+        nanos: bool,
+        link_type: u16,
+    },
+    PcapNg {
+        big_endian: bool,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// reads the packets of a pcap or pcapng file one by one
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    format: Format,
+    bytes_read: usize,
+}
+
+fn read_u16(big_endian: bool, buf: &[u8]) -> u16 {
+    if big_endian {
+        BigEndian::read_u16(buf)
+    } else {
+        LittleEndian::read_u16(buf)
+    }
+}
+fn read_u32(big_endian: bool, buf: &[u8]) -> u32 {
+    if big_endian {
+        BigEndian::read_u32(buf)
+    } else {
+        LittleEndian::read_u32(buf)
+    }
+}
+
+/// read exactly `buf.len()` bytes, `Ok(false)` if the input ended right away
+fn read_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(err_msg(format!(
+                    "capture file is truncated ({} of {} bytes)",
+                    filled,
+                    buf.len()
+                )))
+            }
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+fn timestamp_from_units(ts: u64, units_per_second: u64) -> dlt::DltTimeStamp {
+    let units_per_second = units_per_second.max(1);
+    dlt::DltTimeStamp {
+        seconds: (ts / units_per_second) as u32,
+        microseconds: ((ts % units_per_second) * 1_000_000 / units_per_second) as u32,
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// detects the capture format from the first bytes
+    pub fn new(mut reader: R) -> Result<CaptureReader<R>, Error> {
+        let mut magic = [0u8; 4];
+        if !read_or_eof(&mut reader, &mut magic)? {
+            return Err(err_msg("capture file is empty"));
+        }
+        if BigEndian::read_u32(&magic) == PCAPNG_SECTION_HEADER_BLOCK {
+            let mut capture_reader = CaptureReader {
+                reader,
+                format: Format::PcapNg {
+                    big_endian: false,
+                    interfaces: vec![],
+                },
+                bytes_read: 4,
+            };
+            capture_reader.read_section_header()?;
+            return Ok(capture_reader);
+        }
+        let (big_endian, nanos) =
+            match (BigEndian::read_u32(&magic), LittleEndian::read_u32(&magic)) {
+                (PCAP_MAGIC_MICROS, _) => (true, false),
+                (PCAP_MAGIC_NANOS, _) => (true, true),
+                (_, PCAP_MAGIC_MICROS) => (false, false),
+                (_, PCAP_MAGIC_NANOS) => (false, true),
+                _ => return Err(err_msg("not a pcap or pcapng file")),
+            };
+        let mut header = [0u8; 20];
+        if !read_or_eof(&mut reader, &mut header)? {
+            return Err(err_msg("pcap file header is truncated"));
+        }
+        // version (4), thiszone (4), sigfigs (4), snaplen (4), network (4)
+        let link_type = read_u32(big_endian, &header[16..]) as u16;
+        Ok(CaptureReader {
+            reader,
+            format: Format::Pcap {
+                big_endian,
+                nanos,
+                link_type,
+            },
+            bytes_read: 24,
+        })
+    }
+
+    /// how much of the input was consumed so far
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// next packet or `None` at the end of the capture
+    pub fn next_packet(&mut self) -> Result<Option<CapturedPacket>, Error> {
+        match self.format {
+            Format::Pcap {
+                big_endian,
+                nanos,
+                link_type,
+            } => {
+                let mut record_header = [0u8; 16];
+                if !read_or_eof(&mut self.reader, &mut record_header)? {
+                    return Ok(None);
+                }
+                let seconds = read_u32(big_endian, &record_header[0..]);
+                let fraction = read_u32(big_endian, &record_header[4..]);
+                let included_length = read_u32(big_endian, &record_header[8..]) as usize;
+                let data = self.read_body(included_length)?;
+                self.bytes_read += 16;
+                Ok(Some(CapturedPacket {
+                    timestamp: dlt::DltTimeStamp {
+                        seconds,
+                        microseconds: if nanos { fraction / 1000 } else { fraction },
+                    },
+                    link_type,
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => self.next_pcapng_packet(),
+        }
+    }
+
+    fn read_body(&mut self, length: usize) -> Result<Vec<u8>, Error> {
+        if length > MAX_RECORD_SIZE {
+            return Err(err_msg(format!("invalid record length {}", length)));
+        }
+        let mut data = vec![0u8; length];
+        if !read_or_eof(&mut self.reader, &mut data)? && length > 0 {
+            return Err(err_msg("capture file is truncated"));
+        }
+        self.bytes_read += length;
+        Ok(data)
+    }
+
+    fn pcapng_big_endian(&self) -> bool {
+        match self.format {
+            Format::PcapNg { big_endian, .. } => big_endian,
+            Format::Pcap { big_endian, .. } => big_endian,
+        }
+    }
+
+    /// the block type was already read
+    fn read_section_header(&mut self) -> Result<(), Error> {
+        let mut length_and_magic = [0u8; 8];
+        if !read_or_eof(&mut self.reader, &mut length_and_magic)? {
+            return Err(err_msg("pcapng section header is truncated"));
+        }
+        self.bytes_read += 8;
+        let big_endian = match BigEndian::read_u32(&length_and_magic[4..]) {
+            PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ if LittleEndian::read_u32(&length_and_magic[4..]) == PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ => return Err(err_msg("invalid pcapng byte order magic")),
+        };
+        let block_length = read_u32(big_endian, &length_and_magic) as usize;
+        if block_length < 28 {
+            return Err(err_msg(format!(
+                "invalid pcapng section header length {}",
+                block_length
+            )));
+        }
+        // rest of the section header is not needed
+        self.read_body(block_length - 12)?;
+        // a new section starts with a new set of interfaces
+        self.format = Format::PcapNg {
+            big_endian,
+            interfaces: vec![],
+        };
+        Ok(())
+    }
+
+    fn next_pcapng_packet(&mut self) -> Result<Option<CapturedPacket>, Error> {
+        loop {
+            let mut block_type = [0u8; 4];
+            if !read_or_eof(&mut self.reader, &mut block_type)? {
+                return Ok(None);
+            }
+            self.bytes_read += 4;
+            if BigEndian::read_u32(&block_type) == PCAPNG_SECTION_HEADER_BLOCK {
+                self.read_section_header()?;
+                continue;
+            }
+            let big_endian = self.pcapng_big_endian();
+            let block_type = read_u32(big_endian, &block_type);
+            let mut length = [0u8; 4];
+            if !read_or_eof(&mut self.reader, &mut length)? {
+                return Err(err_msg("pcapng block is truncated"));
+            }
+            self.bytes_read += 4;
+            let block_length = read_u32(big_endian, &length) as usize;
+            if block_length < 12 || block_length % 4 != 0 {
+                return Err(err_msg(format!(
+                    "invalid pcapng block length {}",
+                    block_length
+                )));
+            }
+            // body + trailing block length
+            let block = self.read_body(block_length - 8)?;
+            let body = &block[..block.len() - 4];
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION_BLOCK => {
+                    let interface = parse_interface_description(big_endian, body)?;
+                    if let Format::PcapNg { interfaces, .. } = &mut self.format {
+                        interfaces.push(interface);
+                    }
+                }
+                PCAPNG_ENHANCED_PACKET_BLOCK => {
+                    if body.len() < 20 {
+                        return Err(err_msg("pcapng enhanced packet block is truncated"));
+                    }
+                    let interface = self.interface(read_u32(big_endian, &body[0..]) as usize)?;
+                    let ts = (u64::from(read_u32(big_endian, &body[4..])) << 32)
+                        | u64::from(read_u32(big_endian, &body[8..]));
+                    let captured_length = read_u32(big_endian, &body[12..]) as usize;
+                    let data = body
+                        .get(20..20 + captured_length)
+                        .ok_or_else(|| err_msg("pcapng packet data exceeds its block"))?;
+                    return Ok(Some(CapturedPacket {
+                        timestamp: timestamp_from_units(ts, interface.ts_units),
+                        link_type: interface.link_type,
+                        data: data.to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET_BLOCK => {
+                    if body.len() < 4 {
+                        return Err(err_msg("pcapng simple packet block is truncated"));
+                    }
+                    let interface = self.interface(0)?;
+                    let original_length = read_u32(big_endian, body) as usize;
+                    let data = &body[4..];
+                    // simple packet blocks carry no timestamp
+                    return Ok(Some(CapturedPacket {
+                        timestamp: dlt::DltTimeStamp {
+                            seconds: 0,
+                            microseconds: 0,
+                        },
+                        link_type: interface.link_type,
+                        data: data[..original_length.min(data.len())].to_vec(),
+                    }));
+                }
+                _ => (), // statistics, name resolution,... are not needed
+            }
+        }
+    }
+
+    fn interface(&self, id: usize) -> Result<Interface, Error> {
+        match &self.format {
+            Format::PcapNg { interfaces, .. } => interfaces
+                .get(id)
+                .cloned()
+                .ok_or_else(|| err_msg(format!("packet for unknown interface {}", id))),
+            Format::Pcap { .. } => Err(err_msg("no interfaces in pcap files")),
+        }
+    }
+}
+
+fn parse_interface_description(big_endian: bool, body: &[u8]) -> Result<Interface, Error> {
+    if body.len() < 8 {
+        return Err(err_msg("pcapng interface description block is truncated"));
+    }
+    let link_type = read_u16(big_endian, body);
+    let mut ts_units = 1_000_000u64;
+    let mut options = &body[8..];
+    while options.len() >= 4 {
+        let code = read_u16(big_endian, options);
+        let length = read_u16(big_endian, &options[2..]) as usize;
+        let padded_length = (length + 3) & !3;
+        if code == 0 || options.len() < 4 + length {
+            break;
+        }
+        if code == PCAPNG_OPTION_IF_TSRESOL && length >= 1 {
+            let resolution = options[4];
+            let exponent = u32::from(resolution & 0x7F);
+            ts_units = if resolution & 0x80 == 0 {
+                10u64.checked_pow(exponent)
+            } else {
+                2u64.checked_pow(exponent)
+            }
+            .ok_or_else(|| err_msg(format!("unsupported timestamp resolution {}", resolution)))?;
+        }
+        options = &options[(4 + padded_length).min(options.len())..];
+    }
+    Ok(Interface {
+        link_type,
+        ts_units,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamKey {
+    source: (IpAddr, u16),
+    destination: (IpAddr, u16),
+}
+impl fmt::Display for StreamKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} -> {}:{}",
+            self.source.0, self.source.1, self.destination.0, self.destination.1
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Transport<'a> {
+    Udp {
+        key: StreamKey,
+        payload: &'a [u8],
+    },
+    Tcp {
+        key: StreamKey,
+        sequence_number: u32,
+        flags: u8,
+        payload: &'a [u8],
+    },
+}
+
+impl<'a> Transport<'a> {
+    fn key(&self) -> &StreamKey {
+        match self {
+            Transport::Udp { key, .. } | Transport::Tcp { key, .. } => key,
+        }
+    }
+}
+
+/// network layer packet contained in the link layer frame
+fn link_payload(link_type: u16, data: &[u8]) -> Option<&[u8]> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            loop {
+                let ether_type = BigEndian::read_u16(data.get(offset..offset + 2)?);
+                match ether_type {
+                    ETHERTYPE_VLAN | ETHERTYPE_QINQ => offset += 4,
+                    ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => return data.get(offset + 2..),
+                    _ => return None,
+                }
+            }
+        }
+        LINKTYPE_LINUX_SLL => match BigEndian::read_u16(data.get(14..16)?) {
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(16..),
+            _ => None,
+        },
+        LINKTYPE_LINUX_SLL2 => match BigEndian::read_u16(data.get(0..2)?) {
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(20..),
+            _ => None,
+        },
+        // the address family is in the byte order of the capturing host,
+        // the ip version tells us all we need
+        LINKTYPE_NULL => data.get(4..),
+        LINKTYPE_RAW => Some(data),
+        _ => None,
+    }
+}
+
+/// transport protocol and addresses of an ip packet
+fn ip_payload(packet: &[u8]) -> Option<(IpAddr, IpAddr, u8, &[u8])> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_length = usize::from(packet.first()? & 0x0F) * 4;
+            let total_length = usize::from(BigEndian::read_u16(packet.get(2..4)?));
+            let flags_and_offset = BigEndian::read_u16(packet.get(6..8)?);
+            if flags_and_offset & 0x3FFF != 0 {
+                // more fragments flag or fragment offset set
+                report_warning("fragmented ip packets are not supported");
+                return None;
+            }
+            let protocol = *packet.get(9)?;
+            let source: [u8; 4] = to_array4(packet.get(12..16)?);
+            let destination: [u8; 4] = to_array4(packet.get(16..20)?);
+            let end = total_length.min(packet.len());
+            Some((
+                IpAddr::V4(Ipv4Addr::from(source)),
+                IpAddr::V4(Ipv4Addr::from(destination)),
+                protocol,
+                packet.get(header_length..end)?,
+            ))
+        }
+        6 => {
+            let payload_length = usize::from(BigEndian::read_u16(packet.get(4..6)?));
+            let mut next_header = *packet.get(6)?;
+            let source: [u8; 16] = to_array16(packet.get(8..24)?);
+            let destination: [u8; 16] = to_array16(packet.get(24..40)?);
+            let end = (40 + payload_length).min(packet.len());
+            let mut payload = packet.get(40..end)?;
+            loop {
+                match next_header {
+                    // hop-by-hop, routing, destination options
+                    0 | 43 | 60 => {
+                        let length = (usize::from(*payload.get(1)?) + 1) * 8;
+                        next_header = *payload.first()?;
+                        payload = payload.get(length..)?;
+                    }
+                    44 => {
+                        report_warning("fragmented ip packets are not supported");
+                        return None;
+                    }
+                    _ => break,
+                }
+            }
+            Some((
+                IpAddr::V6(Ipv6Addr::from(source)),
+                IpAddr::V6(Ipv6Addr::from(destination)),
+                next_header,
+                payload,
+            ))
+        }
+        _ => None,
+    }
+}
+fn to_array4(s: &[u8]) -> [u8; 4] {
+    let mut a = [0u8; 4];
+    a.copy_from_slice(s);
+    a
+}
+fn to_array16(s: &[u8]) -> [u8; 16] {
+    let mut a = [0u8; 16];
+    a.copy_from_slice(s);
+    a
+}
+
+fn transport(packet: &CapturedPacket) -> Option<Transport<'_>> {
+    let ip_packet = link_payload(packet.link_type, &packet.data)?;
+    let (source_ip, destination_ip, protocol, segment) = ip_payload(ip_packet)?;
+    let source_port = BigEndian::read_u16(segment.get(0..2)?);
+    let destination_port = BigEndian::read_u16(segment.get(2..4)?);
+    let key = StreamKey {
+        source: (source_ip, source_port),
+        destination: (destination_ip, destination_port),
+    };
+    match protocol {
+        IP_PROTOCOL_UDP => {
+            let length = usize::from(BigEndian::read_u16(segment.get(4..6)?));
+            let end = length.max(8).min(segment.len());
+            Some(Transport::Udp {
+                key,
+                payload: segment.get(8..end)?,
+            })
+        }
+        IP_PROTOCOL_TCP => {
+            let data_offset = usize::from(segment.get(12)? >> 4) * 4;
+            Some(Transport::Tcp {
+                key,
+                sequence_number: BigEndian::read_u32(segment.get(4..8)?),
+                flags: *segment.get(13)?,
+                payload: segment.get(data_offset..)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// puts the segments of one direction of a tcp connection back in order
+#[derive(Default)]
+struct TcpStream {
+    next_sequence_number: Option<u32>,
+    /// reassembled data that was not yet decoded
+    data: BytesMut,
+    /// segments that arrived before the ones in front of them
+    pending: BTreeMap<u32, Vec<u8>>,
+}
+impl TcpStream {
+    fn add_segment(&mut self, sequence_number: u32, flags: u8, payload: &[u8], key: &StreamKey) {
+        if flags & TCP_FLAG_SYN != 0 {
+            self.next_sequence_number = Some(sequence_number.wrapping_add(1));
+            self.data.clear();
+            self.pending.clear();
+            return;
+        }
+        if payload.is_empty() {
+            return;
+        }
+        // the capture might start in the middle of a connection
+        let next = *self.next_sequence_number.get_or_insert(sequence_number);
+        let distance = sequence_number.wrapping_sub(next) as i32;
+        if distance > 0 {
+            self.pending.insert(sequence_number, payload.to_vec());
+            if self.pending.len() > MAX_PENDING_SEGMENTS {
+                report_warning(format!(
+                    "segments missing in tcp stream {}, skipping ahead",
+                    key
+                ));
+                // we cannot know where the next message starts in the data we have
+                self.data.clear();
+                let first = *self.pending.keys().next().expect("pending is not empty");
+                self.next_sequence_number = Some(first);
+            }
+        } else {
+            self.append(sequence_number, payload);
+        }
+        self.drain_pending();
+    }
+    /// appends what is new in the segment (retransmissions may overlap)
+    fn append(&mut self, sequence_number: u32, payload: &[u8]) {
+        let next = self.next_sequence_number.unwrap_or(sequence_number);
+        let already_seen = next.wrapping_sub(sequence_number) as usize;
+        if already_seen < payload.len() {
+            self.data.extend_from_slice(&payload[already_seen..]);
+            self.next_sequence_number = Some(sequence_number.wrapping_add(payload.len() as u32));
+        }
+    }
+    fn drain_pending(&mut self) {
+        while let Some(next) = self.next_sequence_number {
+            let first = match self.pending.keys().next() {
+                Some(first) => *first,
+                None => break,
+            };
+            if (first.wrapping_sub(next) as i32) > 0 {
+                break;
+            }
+            let segment = self.pending.remove(&first).expect("key exists");
+            self.append(first, &segment);
+        }
+    }
+}
+
+/// turns the packets of a capture into dlt messages
+pub struct MessageExtractor {
+    codec: Codec,
+    tcp_streams: FxHashMap<StreamKey, TcpStream>,
+    dlt_ports: Vec<u16>,
+}
+impl MessageExtractor {
+    /// dlt_ports: only flows from or to one of these ports are decoded,
+    /// other traffic of the capture is ignored
+    pub fn new(
+        filter_config: Option<filtering::ProcessedDltFilterConfig>,
+        fibex_metadata: Option<fibex::FibexMetadata>,
+        dlt_ports: &[u16],
+    ) -> Self {
+        MessageExtractor {
+            codec: Codec::new(filter_config, fibex_metadata),
+            tcp_streams: FxHashMap::default(),
+            dlt_ports: dlt_ports.to_vec(),
+        }
+    }
+    fn carries_dlt(&self, key: &StreamKey) -> bool {
+        self.dlt_ports.contains(&key.source.1) || self.dlt_ports.contains(&key.destination.1)
+    }
+    /// all dlt messages that were completed by this packet, the capture
+    /// time of the packet is used as storage header
+    pub fn messages(&mut self, packet: &CapturedPacket) -> Vec<dlt::Message> {
+        let transport = transport(packet).filter(|t| self.carries_dlt(t.key()));
+        let mut messages = match transport {
+            Some(Transport::Udp { key, payload }) => {
+                decode_datagram(&mut self.codec, payload, &key)
+            }
+            Some(Transport::Tcp {
+                key,
+                sequence_number,
+                flags,
+                payload,
+            }) => {
+                let codec = &mut self.codec;
+                let stream = self.tcp_streams.entry(key).or_default();
+                stream.add_segment(sequence_number, flags, payload, &key);
+                let mut messages = vec![];
+                loop {
+                    match codec.decode(&mut stream.data) {
+                        Ok(Some(msg)) => messages.push(msg),
+                        Ok(None) => break,
+                        Err(e) => {
+                            // skip the corrupted message and continue with the next one
+                            let skipped = bytes_to_next_standard_header(&stream.data);
+                            report_warning(format!(
+                                "skipped {} bytes of a corrupted message in tcp stream {}: {}",
+                                skipped, key, e
+                            ));
+                            stream.data.split_to(skipped);
+                        }
+                    }
+                }
+                if flags & (TCP_FLAG_FIN | TCP_FLAG_RST) != 0 {
+                    self.tcp_streams.remove(&key);
+                }
+                messages
+            }
+            None => vec![],
+        };
+        let capture_time = UNIX_EPOCH
+            + Duration::new(
+                u64::from(packet.timestamp.seconds),
+                packet.timestamp.microseconds * 1000,
+            );
+        for msg in messages.iter_mut() {
+            msg.storage_header = Some(dlt::StorageHeader::received_at(
+                capture_time,
+                msg.header.ecu_id.as_ref(),
+            ));
+        }
+        messages
+    }
+}
+
+/// create index for the dlt messages contained in a pcap or pcapng file
+/// source_file_size: if progress updates should be made, add this value
+/// fibex: used to decode non-verbose messages
+/// dlt_ports: udp and tcp ports of the dlt traffic (see `DEFAULT_DLT_PORTS`)
+pub fn index_pcap_file(
+    config: IndexingConfig,
+    dlt_filter: Option<filtering::DltFilterConfig>,
+    source_file_size: Option<usize>,
+    update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+    fibex: Option<fibex::FibexConfig>,
+    dlt_ports: &[u16],
+) -> Result<Vec<Chunk>, Error> {
+    trace!("index_pcap_file");
+    if config.follow.is_some() {
+        return Err(err_msg("following capture files is not supported"));
+    }
+    let fibex_metadata: Option<fibex::FibexMetadata> = fibex
+        .map(|config| fibex::read_fibexes(&config))
+        .transpose()?;
+    let mut extractor = MessageExtractor::new(
+        dlt_filter
+            .map(filtering::process_filter_config)
+            .transpose()?,
+        fibex_metadata,
+        dlt_ports,
+    );
+    let index_writer = config
+        .index_map
+        .as_ref()
+        .map(|index_map| index_map::writer_for(index_map, &config.in_file, config.append))
+        .transpose()?;
+    let mut reader =
+        CaptureReader::new(BufReader::with_capacity(10 * 1024 * 1024, config.in_file))?;
+    let mut indexer = MessageIndexer::new(
+        config.tag,
+        config.out_path,
+        config.chunk_size,
+        config.append,
+        config.to_stdout,
+        update_channel.clone(),
+    )?;
+    if let Some(writer) = index_writer {
+        indexer = indexer.with_index_writer(writer);
+    }
+    let mut packet_count = 0usize;
+    while let Some(packet) = reader.next_packet()? {
+        if shutdown_requested(shutdown_receiver.as_ref()) {
+            break;
+        }
+        for msg in extractor.messages(&packet) {
+            indexer.write_message(&msg)?;
+        }
+        packet_count += 1;
+        if let Some(file_size) = source_file_size {
+            utils::report_progress(
+                packet_count,
+                indexer.current_byte_index(),
+                reader.bytes_read(),
+                file_size,
+                REPORT_PROGRESS_PACKET_BLOCK,
+            );
+            if packet_count % REPORT_PROGRESS_PACKET_BLOCK == 0 {
+                update_channel.as_ref().map(|c| {
+                    c.send(IndexingProgress::Progress {
+                        ticks: (reader.bytes_read(), file_size),
+                    })
+                });
+            }
+        }
+    }
+    indexer.finish()
+}
@@ -0,0 +1,334 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_net::*;
+    use crate::filtering;
+    use indexer_base::chunks::Chunk;
+    use indexer_base::progress::IndexingProgress;
+    use std::fs;
+    use std::io::Write;
+    use std::net::{TcpListener, UdpSocket};
+    use std::sync::mpsc;
+    use std::thread;
+    use tempdir::TempDir;
+
+    fn test_message(counter: u8) -> dlt::Message {
+        let argument = dlt::Argument {
+            type_info: dlt::TypeInfo {
+                kind: dlt::TypeInfoKind::StringType,
+                coding: dlt::StringCoding::UTF8,
+                has_variable_info: false,
+                has_trace_info: false,
+            },
+            name: None,
+            unit: None,
+            trace_info: None,
+            fixed_point: None,
+            value: dlt::Value::StringVal(format!("message {}", counter)),
+        };
+        dlt::Message {
+            storage_header: None,
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: false,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 1,
+                message_type: dlt::MessageType::Log(dlt::LogLevel::Info),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![argument]),
+        }
+    }
+
+    /// stand-in for a dlt-daemon: sends the messages in small pieces
+    /// so that messages are split across reads
+    fn serve(messages: Vec<dlt::Message>) -> String {
+        serve_bytes(
+            messages
+                .iter()
+                .flat_map(|m| m.as_bytes_without_storage_header())
+                .collect(),
+        )
+    }
+    fn serve_bytes(bytes: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind");
+        let address = listener.local_addr().expect("no local address").to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("no connection");
+            for piece in bytes.chunks(7) {
+                stream.write_all(piece).expect("could not send");
+                stream.flush().expect("could not flush");
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn test_index_from_tcp() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("tcp.out");
+        let address = serve((0..5).map(test_message).collect());
+        let (tx, rx) = mpsc::channel();
+        let chunks = index_from_tcp(
+            &TcpSourceConfig { address },
+            SocketIndexingConfig {
+                tag: "TCP",
+                chunk_size: 2,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+            },
+            None,
+            Some(tx),
+            None,
+            None,
+        )
+        .expect("indexing failed");
+        assert_eq!(
+            vec![(0, 1), (2, 3), (4, 4)],
+            chunks.iter().map(|c| c.r).collect::<Vec<(usize, usize)>>()
+        );
+        let content = fs::read_to_string(&out_path).expect("could not read output");
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(5, lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            assert!(line.contains(&format!("message {}", i)));
+            // synthesized storage header carries the ecu id
+            assert!(line.contains(&format!("{}ECU1", dlt::DLT_COLUMN_SENTINAL)));
+            assert!(line.contains("TCP"));
+        }
+        assert_eq!(chunks.last().map(|c| c.b.1), Some(content.len()));
+        let progress: Vec<IndexingProgress<Chunk>> = rx.iter().collect();
+        assert_eq!(4, progress.len());
+        match progress.last() {
+            Some(IndexingProgress::Finished) => (),
+            _ => panic!("expected to be finished"),
+        }
+    }
+
+    fn tcp_config(out_path: &std::path::PathBuf, chunk_size: usize) -> SocketIndexingConfig<'_> {
+        SocketIndexingConfig {
+            tag: "TCP",
+            chunk_size,
+            out_path,
+            append: false,
+            to_stdout: false,
+        }
+    }
+
+    #[test]
+    fn test_index_from_tcp_skips_corrupted_messages() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("tcp.out");
+        let mut bytes = test_message(0).as_bytes_without_storage_header();
+        // standard header with a length that is too small for its headers
+        bytes.extend_from_slice(&[0x35, 0x00, 0x00, 0x01]);
+        for counter in 1..3 {
+            bytes.extend(test_message(counter).as_bytes_without_storage_header());
+        }
+        let address = serve_bytes(bytes);
+        let (tx, rx) = mpsc::channel();
+        let chunks = index_from_tcp(
+            &TcpSourceConfig { address },
+            tcp_config(&out_path, 10),
+            None,
+            Some(tx),
+            None,
+            None,
+        )
+        .expect("indexing failed");
+        let content = fs::read_to_string(&out_path).expect("could not read output");
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(3, lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            assert!(line.contains(&format!("message {}", i)));
+        }
+        assert_eq!(chunks.last().map(|c| c.b.1), Some(content.len()));
+        match rx.iter().last() {
+            Some(IndexingProgress::Finished) => (),
+            _ => panic!("expected to be finished"),
+        }
+    }
+
+    #[test]
+    fn test_index_from_tcp_reports_pending_chunks() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("tcp.out");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind");
+        let address = listener.local_addr().expect("no local address").to_string();
+        let (tx, rx) = mpsc::channel::<IndexingProgress<Chunk>>();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("no connection");
+            stream
+                .write_all(&test_message(0).as_bytes_without_storage_header())
+                .expect("could not send");
+            // the first line has to be reported although the chunk is not full
+            match rx.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(IndexingProgress::GotItem { item }) => assert_eq!((0, 0), item.r),
+                _ => panic!("pending chunk was not reported"),
+            }
+            stream
+                .write_all(&test_message(1).as_bytes_without_storage_header())
+                .expect("could not send");
+            rx
+        });
+        let chunks = index_from_tcp(
+            &TcpSourceConfig { address },
+            tcp_config(&out_path, 10),
+            None,
+            Some(tx),
+            None,
+            None,
+        )
+        .expect("indexing failed");
+        assert_eq!(
+            vec![(0, 0), (1, 1)],
+            chunks.iter().map(|c| c.r).collect::<Vec<(usize, usize)>>()
+        );
+        let content = fs::read_to_string(&out_path).expect("could not read output");
+        assert_eq!(2, content.lines().count());
+        assert_eq!(chunks.last().map(|c| c.b.1), Some(content.len()));
+        let rx = server.join().expect("server failed");
+        match rx.iter().last() {
+            Some(IndexingProgress::Finished) => (),
+            _ => panic!("expected to be finished"),
+        }
+    }
+
+    #[test]
+    fn test_index_from_tcp_stops_on_shutdown() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("tcp.out");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind");
+        let address = listener.local_addr().expect("no local address").to_string();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("no connection");
+            stream
+                .write_all(&test_message(0).as_bytes_without_storage_header())
+                .expect("could not send");
+            // keep the connection open until the indexer asked to stop
+            shutdown_tx.send(()).expect("could not request shutdown");
+            thread::sleep(std::time::Duration::from_secs(2));
+        });
+        let chunks = index_from_tcp(
+            &TcpSourceConfig { address },
+            SocketIndexingConfig {
+                tag: "TCP",
+                chunk_size: 10,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+            },
+            None,
+            None,
+            Some(shutdown_rx),
+            None,
+        )
+        .expect("indexing failed");
+        assert!(chunks.len() <= 1);
+    }
+
+    #[test]
+    fn test_index_from_udp_with_multiple_messages_per_datagram() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("udp.out");
+        let receiver = UdpSocket::bind("127.0.0.1:0").expect("could not bind");
+        let receiver_addr = receiver.local_addr().expect("no local address");
+        let sender = UdpSocket::bind("127.0.0.1:0").expect("could not bind");
+        for counter in 0..3 {
+            let mut datagram = test_message(counter * 2).as_bytes_without_storage_header();
+            let mut second = test_message(counter * 2 + 1);
+            if counter == 1 {
+                // will be filtered out
+                second.extended_header = second.extended_header.map(|ext| dlt::ExtendedHeader {
+                    application_id: "FOO".to_string(),
+                    ..ext
+                });
+            }
+            datagram.extend(second.as_bytes_without_storage_header());
+            sender
+                .send_to(&datagram, receiver_addr)
+                .expect("could not send");
+        }
+        // garbage is dropped without affecting other datagrams
+        sender
+            .send_to(&[0x3D, 0x01, 0x00, 0x02], receiver_addr)
+            .expect("could not send");
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(500));
+            let _ = shutdown_tx.send(());
+        });
+        let chunks = index_from_udp_socket(
+            receiver,
+            SocketIndexingConfig {
+                tag: "UDP",
+                chunk_size: 100,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+            },
+            Some(filtering::DltFilterConfig {
+                min_log_level: None,
+                app_min_log_levels: None,
+                context_min_log_levels: None,
+                app_ids: Some(vec!["APP".to_string()]),
+                ecu_ids: None,
+                context_ids: None,
+                include_payload: None,
+                exclude_payload: None,
+                expression: None,
+                from: None,
+                to: None,
+                time_base: None,
+            }),
+            None,
+            Some(shutdown_rx),
+            None,
+        )
+        .expect("indexing failed");
+        assert_eq!(1, chunks.len());
+        assert_eq!((0, 4), chunks[0].r);
+        let content = fs::read_to_string(&out_path).expect("could not read output");
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(5, lines.len());
+        assert!(!content.contains("message 3"));
+    }
+
+    #[test]
+    fn test_index_from_udp_invalid_multicast_address() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("udp.out");
+        let res = index_from_udp(
+            &UdpSourceConfig {
+                bind_addr: "127.0.0.1:0".to_string(),
+                multicast: vec![MulticastInfo {
+                    multiaddr: "not-an-address".to_string(),
+                    interface: None,
+                }],
+            },
+            SocketIndexingConfig {
+                tag: "UDP",
+                chunk_size: 100,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+            },
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(res.is_err());
+    }
+}
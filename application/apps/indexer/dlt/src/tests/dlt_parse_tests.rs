@@ -2,7 +2,14 @@
 mod tests {
     use crate::dlt_parse::*;
     use crate::dlt;
+    use crate::fibex;
+    use crate::filtering;
+    use indexer_base::config::IndexingConfig;
     use nom::IResult;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
 
     #[test]
     fn test_dlt_message_parsing() {
@@ -55,11 +62,738 @@ mod tests {
             0x30, 0x78, 0x00, 0x42, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x82, 0x00, 0x00, 0x02,
             0x00, 0x29, 0x00,
         ];
-        let res1: IResult<&[u8], Option<dlt::Message>> =
-            dlt_message(&raw1[..], None, None);
+        let res1: IResult<&[u8], Option<dlt::Message>> = dlt_message(&raw1[..], None, None, None);
         println!("res1 was: {:?}", res1);
-        let res2: IResult<&[u8], Option<dlt::Message>> = dlt_message(&raw2[..], None, None);
+        let res2: IResult<&[u8], Option<dlt::Message>> = dlt_message(&raw2[..], None, None, None);
         println!("res was: {:?}", res2);
     }
 
+    #[test]
+    fn test_dlt_non_verbose_message_with_fibex() {
+        let metadata = fibex::read_fibex(fibex::tests::FIBEX.as_bytes()).expect("valid fibex");
+        let raw: Vec<u8> = vec![
+            0x44, 0x4C, 0x54, 0x01, // storage header pattern "DLT" + 0x01
+            0x56, 0xA2, 0x91, 0x5C, 0x9C, 0x91, 0x0B, 0x00, // timestamp
+            0x45, 0x43, 0x55, 0x31, // storage header ecu id "ECU1"
+            0x24, // header type: version 1, with ecu id, little endian, no extended header
+            0x00, // message counter
+            0x00, 0x11, // length
+            0x45, 0x43, 0x55, 0x31, // ecu id "ECU1"
+            0x0A, 0x00, 0x00, 0x00, // message id 10
+            0xFB, 0xFF, 0xFF, 0xFF, // -5
+            0x01, // true
+        ];
+        let res: IResult<&[u8], Option<dlt::Message>> =
+            dlt_message(&raw[..], None, None, Some(&metadata));
+        let msg = res
+            .expect("message should parse")
+            .1
+            .expect("message not filtered");
+        let ext = msg
+            .extended_header
+            .clone()
+            .expect("extended header from fibex");
+        assert_eq!("APP1", ext.application_id);
+        assert_eq!("CTX1", ext.context_id);
+        assert_eq!(dlt::MessageType::Log(dlt::LogLevel::Warn), ext.message_type);
+        assert!(!msg.header.has_extended_header);
+        match &msg.payload {
+            dlt::Payload::NonVerboseDecoded(10, _, args) => {
+                let values: Vec<dlt::Value> = args.iter().map(|a| a.value.clone()).collect();
+                assert_eq!(
+                    vec![
+                        dlt::Value::StringVal("temperature:".to_string()),
+                        dlt::Value::I32(-5),
+                        dlt::Value::Bool(true),
+                    ],
+                    values
+                );
+            }
+            p => panic!("payload was not decoded: {:?}", p),
+        }
+
+        // without fibex the payload stays raw
+        let (_, msg) = dlt_message(&raw[..], None, None, None).expect("message should parse");
+        match msg.expect("message not filtered").payload {
+            dlt::Payload::NonVerbose(10, data) => assert_eq!(5, data.len()),
+            p => panic!("unexpected payload: {:?}", p),
+        }
+
+        // app id from the fibex is used for filtering
+        let filter = filtering::process_filter_config(filtering::DltFilterConfig {
+            min_log_level: None,
+            app_min_log_levels: None,
+            context_min_log_levels: None,
+            app_ids: Some(vec!["APP2".to_string()]),
+            ecu_ids: None,
+            context_ids: None,
+            include_payload: None,
+            exclude_payload: None,
+            expression: None,
+            from: None,
+            to: None,
+            time_base: None,
+        })
+        .expect("valid filter");
+        let (rest, msg) =
+            dlt_message(&raw[..], Some(&filter), None, Some(&metadata)).expect("should parse");
+        assert!(rest.is_empty());
+        assert!(msg.is_none());
+    }
+
+    fn stored_message(counter: u8) -> dlt::Message {
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: 1_000 + u32::from(counter),
+                    microseconds: 0,
+                },
+                ecu_id: "ECU1".to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: false,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 1,
+                message_type: dlt::MessageType::Log(dlt::LogLevel::Info),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::StringType,
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::StringVal(format!("message {}", counter)),
+            }]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    /// index `content` as dlt file and return the indexed lines
+    fn index_dlt_content(dir: &Path, content: &[u8]) -> Vec<String> {
+        index_filtered_dlt_content(dir, content, None)
+    }
+
+    fn index_filtered_dlt_content(
+        dir: &Path,
+        content: &[u8],
+        filter: Option<filtering::DltFilterConfig>,
+    ) -> Vec<String> {
+        index_dlt_content_with(dir, content, filter, false)
+    }
+
+    fn index_dlt_content_with(
+        dir: &Path,
+        content: &[u8],
+        filter: Option<filtering::DltFilterConfig>,
+        annotate_lifecycles: bool,
+    ) -> Vec<String> {
+        let in_path = dir.join("trace.dlt");
+        let out_path = dir.join("trace.out");
+        fs::write(&in_path, content).expect("could not write dlt file");
+        index_dlt_file(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 2,
+                in_file: fs::File::open(&in_path).expect("could not open dlt file"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
+            },
+            filter,
+            0,
+            Some(content.len()),
+            None,
+            None,
+            None,
+            annotate_lifecycles,
+        )
+        .expect("indexing failed");
+        fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    fn expected_lines(counters: &[u8]) -> Vec<String> {
+        let dir = TempDir::new("expected").expect("could not create temp dir");
+        let content: Vec<u8> = counters
+            .iter()
+            .flat_map(|c| stored_message(*c).as_bytes())
+            .collect();
+        index_dlt_content(dir.path(), &content)
+    }
+
+    #[test]
+    fn test_index_gzip_compressed_file() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let content = stored_messages(&[0, 1, 2, 3, 4]);
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&content).expect("could not compress");
+        let compressed = encoder.finish().expect("could not compress");
+        assert_eq!(
+            expected_lines(&[0, 1, 2, 3, 4]),
+            index_dlt_content(tmp_dir.path(), &compressed)
+        );
+    }
+
+    #[test]
+    fn test_indexed_file_of_dlt_output() {
+        use indexer_base::config::IndexMapConfig;
+        use indexer_base::indexed_file::{parse_line, IndexedFile};
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let counters: Vec<u8> = (0..9).collect();
+        let content = stored_messages(&counters);
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let out_path = tmp_dir.path().join("trace.out");
+        let map_path = tmp_dir.path().join("trace.map.idx");
+        fs::write(&in_path, &content).expect("could not write dlt file");
+        index_dlt_file(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 2,
+                in_file: fs::File::open(&in_path).expect("could not open dlt file"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: Some(IndexMapConfig {
+                    path: &map_path,
+                    line_offsets: false,
+                }),
+                token_index: None,
+                follow: None,
+            },
+            None,
+            0,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .expect("indexing failed");
+        let output: Vec<String> = fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(String::from)
+            .collect();
+        let mut file = IndexedFile::open(&out_path, &map_path).expect("could not open output");
+        assert_eq!(counters.len(), file.line_count());
+        for row in &[8, 0, 3, 4] {
+            let line = file
+                .get_line(*row)
+                .expect("could not read line")
+                .expect("line is missing");
+            assert_eq!(
+                parse_line(&output[*row]).expect("not an indexed line"),
+                line
+            );
+            assert_eq!("TAG", line.tag);
+            assert_eq!(*row, line.row);
+            assert_eq!(None, line.timestamp);
+            assert!(line.content.contains(&format!("message {}", row)));
+        }
+        let rows: Vec<usize> = file
+            .lines()
+            .expect("no lines")
+            .map(|l| l.expect("could not read line").row)
+            .collect();
+        assert_eq!((0..counters.len()).collect::<Vec<usize>>(), rows);
+        assert_eq!(
+            2,
+            file.get_lines(7..20).expect("could not read lines").len()
+        );
+    }
+
+    #[test]
+    fn test_token_index_of_dlt_output() {
+        use indexer_base::token_index::{self, TokenIndex};
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let counters: Vec<u8> = (0..9).collect();
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let out_path = tmp_dir.path().join("trace.out");
+        let token_path = tmp_dir.path().join("trace.map.tokens");
+        fs::write(&in_path, stored_messages(&counters)).expect("could not write dlt file");
+        let chunks = index_dlt_file(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 2,
+                in_file: fs::File::open(&in_path).expect("could not open dlt file"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: None,
+                token_index: Some(&token_path),
+                follow: None,
+            },
+            None,
+            0,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .expect("indexing failed");
+        let mut index = TokenIndex::open(&token_path).expect("could not open token index");
+        let out_size = fs::metadata(&out_path).expect("no output").len();
+        assert!(index.matches_output(&chunks, out_size));
+        assert!(index.matches_source(&in_path));
+        let candidates = index
+            .candidate_chunks(&[token_index::trigrams(b"message 7")])
+            .expect("lookup failed");
+        assert!(candidates.contains(&3));
+        assert!(candidates.len() < chunks.len());
+    }
+
+    #[test]
+    fn test_follow_growing_dlt_file() {
+        use indexer_base::progress::IndexingProgress;
+        use std::io::Write;
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let out_path = tmp_dir.path().join("trace.out");
+        fs::write(&in_path, stored_messages(&[0, 1, 2])).expect("could not write dlt file");
+        let append = |content: &[u8]| {
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&in_path)
+                .expect("could not open dlt file")
+                .write_all(content)
+                .expect("could not append");
+        };
+
+        let (update_tx, update_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let indexer = {
+            let (in_path, out_path) = (in_path.clone(), out_path.clone());
+            std::thread::spawn(move || {
+                // following is never done in parallel
+                create_index_and_mapping_dlt(
+                    IndexingConfig {
+                        tag: "TAG",
+                        chunk_size: 2,
+                        in_file: fs::File::open(&in_path).expect("could not open dlt file"),
+                        zip_entry: None,
+                        out_path: &out_path,
+                        append: false,
+                        to_stdout: false,
+                        index_map: None,
+                        token_index: None,
+                        follow: Some(&in_path),
+                    },
+                    None,
+                    None,
+                    Some(update_tx),
+                    Some(shutdown_rx),
+                    None,
+                    0,
+                    false,
+                )
+            })
+        };
+        let next_rows = || match update_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(IndexingProgress::GotItem { item }) => item.r,
+            _ => panic!("no chunk while following"),
+        };
+        assert_eq!((0, 1), next_rows());
+        assert_eq!((2, 2), next_rows());
+        // the second message is cut off until the rest of it is written
+        let more = stored_messages(&[3, 4]);
+        let split = more.len() - 5;
+        append(&more[..split]);
+        assert_eq!((3, 3), next_rows());
+        append(&more[split..]);
+        assert_eq!((4, 4), next_rows());
+
+        shutdown_tx.send(()).expect("could not shut down");
+        let chunks = indexer
+            .join()
+            .expect("indexer panicked")
+            .expect("could not follow dlt file");
+        assert_eq!(4, chunks.len());
+        let lines: Vec<String> = fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(expected_lines(&[0, 1, 2, 3, 4]), lines);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotated_dlt_file_with_cut_off_message() {
+        use indexer_base::progress::IndexingProgress;
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let out_path = tmp_dir.path().join("trace.out");
+        let mut content = stored_messages(&[0, 1]);
+        // the second message is never completed
+        content.truncate(content.len() - 5);
+        fs::write(&in_path, content).expect("could not write dlt file");
+
+        let (update_tx, update_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let indexer = {
+            let (in_path, out_path) = (in_path.clone(), out_path.clone());
+            std::thread::spawn(move || {
+                create_index_and_mapping_dlt(
+                    IndexingConfig {
+                        tag: "TAG",
+                        chunk_size: 10,
+                        in_file: fs::File::open(&in_path).expect("could not open dlt file"),
+                        zip_entry: None,
+                        out_path: &out_path,
+                        append: false,
+                        to_stdout: false,
+                        index_map: None,
+                        token_index: None,
+                        follow: Some(&in_path),
+                    },
+                    None,
+                    None,
+                    Some(update_tx),
+                    Some(shutdown_rx),
+                    None,
+                    1,
+                    false,
+                )
+            })
+        };
+        let next_rows = || match update_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(IndexingProgress::GotItem { item }) => item.r,
+            _ => panic!("no chunk while following"),
+        };
+        assert_eq!((0, 0), next_rows());
+        fs::rename(&in_path, tmp_dir.path().join("trace.dlt.1")).expect("could not rotate");
+        fs::write(&in_path, stored_messages(&[2, 3])).expect("could not write rotated file");
+        assert_eq!((1, 2), next_rows());
+
+        shutdown_tx.send(()).expect("could not shut down");
+        indexer
+            .join()
+            .expect("indexer panicked")
+            .expect("could not follow dlt file");
+        let lines: Vec<String> = fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(String::from)
+            .collect();
+        // the cut off message is skipped, the new file is numbered on from the old one
+        assert_eq!(expected_lines(&[0, 2, 3]), lines);
+    }
+
+    #[test]
+    fn test_index_resyncs_after_garbage_between_messages() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let mut content = vec![];
+        content.extend(stored_message(0).as_bytes());
+        content.extend(b"garbage DLT in between");
+        content.extend(stored_message(1).as_bytes());
+        content.extend(&[0xFF; 3]);
+        content.extend(stored_message(2).as_bytes());
+        assert_eq!(
+            expected_lines(&[0, 1, 2]),
+            index_dlt_content(tmp_dir.path(), &content)
+        );
+    }
+
+    #[test]
+    fn test_index_resyncs_after_corrupted_header() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let mut corrupted = stored_message(1).as_bytes();
+        // overall length smaller than the headers
+        corrupted[dlt::STORAGE_HEADER_LENGTH + 2] = 0;
+        corrupted[dlt::STORAGE_HEADER_LENGTH + 3] = 2;
+        let mut content = vec![];
+        content.extend(stored_message(0).as_bytes());
+        content.extend(corrupted);
+        content.extend(stored_message(2).as_bytes());
+        assert_eq!(
+            expected_lines(&[0, 2]),
+            index_dlt_content(tmp_dir.path(), &content)
+        );
+    }
+
+    #[test]
+    fn test_index_resyncs_after_garbled_payload() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let mut garbled = stored_message(1).as_bytes();
+        let len = garbled.len();
+        // the string argument claims to be longer than the payload
+        garbled[len - 12] = 0xFF;
+        let mut content = vec![];
+        content.extend(stored_message(0).as_bytes());
+        content.extend(garbled);
+        content.extend(stored_message(2).as_bytes());
+        content.extend(stored_message(3).as_bytes());
+        assert_eq!(
+            expected_lines(&[0, 2, 3]),
+            index_dlt_content(tmp_dir.path(), &content)
+        );
+    }
+
+    #[test]
+    fn test_index_truncated_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let mut content = vec![];
+        content.extend(stored_message(0).as_bytes());
+        content.extend(stored_message(1).as_bytes());
+        let truncated = stored_message(2).as_bytes();
+        content.extend(&truncated[..truncated.len() - 5]);
+        assert_eq!(
+            expected_lines(&[0, 1]),
+            index_dlt_content(tmp_dir.path(), &content)
+        );
+    }
+
+    #[test]
+    fn test_index_file_without_any_message() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        assert!(index_dlt_content(tmp_dir.path(), &content).is_empty());
+    }
+
+    #[test]
+    fn test_file_info_resyncs_after_garbage() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let mut content = vec![0x44, 0x4C, 0x54];
+        content.extend(stored_message(0).as_bytes());
+        content.extend(b"DLT\x01 but no valid header");
+        content.extend(stored_message(1).as_bytes());
+        content.extend(&stored_message(2).as_bytes()[..10]);
+        fs::write(&in_path, &content).expect("could not write dlt file");
+        let info = get_dlt_file_info(
+            &fs::File::open(&in_path).expect("could not open dlt file"),
+            None,
+        )
+        .expect("could not get file info");
+        let json = serde_json::to_value(&info).expect("could not serialize");
+        assert_eq!("APP", json["app_ids"][0][0]);
+        assert_eq!(2, json["app_ids"][0][1]["log_info"]);
+    }
+
+    fn time_window_filter(
+        from: Option<i64>,
+        to: Option<i64>,
+        time_base: Option<filtering::TimeBase>,
+    ) -> Option<filtering::DltFilterConfig> {
+        Some(filtering::DltFilterConfig {
+            from,
+            to,
+            time_base,
+            ..Default::default()
+        })
+    }
+
+    fn stored_messages(counters: &[u8]) -> Vec<u8> {
+        counters
+            .iter()
+            .flat_map(|c| stored_message(*c).as_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_index_storage_time_window() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        // storage time of message n is 1000 + n seconds
+        assert_eq!(
+            expected_lines(&[1, 2, 3]),
+            index_filtered_dlt_content(
+                tmp_dir.path(),
+                &stored_messages(&[0, 1, 2, 3, 4, 5]),
+                time_window_filter(Some(1_001_000), Some(1_003_000), None)
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_time_window_stops_after_end() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        assert_eq!(
+            expected_lines(&[0]),
+            index_filtered_dlt_content(
+                tmp_dir.path(),
+                &stored_messages(&[0, 4, 2]),
+                time_window_filter(None, Some(1_003_000), None)
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_time_window_unordered_storage_time() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        assert_eq!(
+            expected_lines(&[3, 2]),
+            index_filtered_dlt_content(
+                tmp_dir.path(),
+                &stored_messages(&[3, 0, 4, 2]),
+                time_window_filter(Some(1_002_000), Some(1_003_000), None)
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_uptime_window() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        // the ecu uptime of message n is n, it starts over with the second lifecycle
+        assert_eq!(
+            expected_lines(&[2, 3, 2]),
+            index_filtered_dlt_content(
+                tmp_dir.path(),
+                &stored_messages(&[1, 2, 3, 4, 0, 2]),
+                time_window_filter(Some(2), Some(3), Some(filtering::TimeBase::Uptime))
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_with_lifecycles() {
+        use indexer_base::utils::PLUGIN_ID_SENTINAL;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        // (counter, uptime in 0.1 ms), the ecu restarts before message 2
+        let messages: Vec<dlt::Message> = [(0, 20_000), (1, 30_000), (10, 5_000), (11, 15_000)]
+            .iter()
+            .map(|&(counter, uptime)| {
+                let mut msg = stored_message(counter);
+                msg.header.timestamp = Some(uptime);
+                msg
+            })
+            .collect();
+        let content: Vec<u8> = messages.iter().flat_map(dlt::Message::as_bytes).collect();
+        let lines = index_dlt_content_with(tmp_dir.path(), &content, None, true);
+        assert_eq!(messages.len(), lines.len());
+        let mut annotations = vec![];
+        for (msg, line) in messages.iter().zip(lines.iter()) {
+            let annotated = line.split(PLUGIN_ID_SENTINAL).next().expect("no message");
+            assert!(annotated.starts_with(&msg.to_string()));
+            let mut columns = annotated.rsplit(dlt::DLT_COLUMN_SENTINAL);
+            let time = columns.next().expect("no absolute time").to_string();
+            let id = columns.next().expect("no lifecycle id").to_string();
+            annotations.push((id, time));
+        }
+        // lifecycles start at storage time - uptime: 998 s and 1009.5 s
+        assert_eq!(
+            vec![
+                ("0".to_string(), "1000000".to_string()),
+                ("0".to_string(), "1001000".to_string()),
+                ("1".to_string(), "1010000".to_string()),
+                ("1".to_string(), "1011000".to_string()),
+            ],
+            annotations
+        );
+    }
+
+    fn file_info(dir: &Path, content: &[u8]) -> serde_json::Value {
+        let in_path = dir.join("trace.dlt");
+        fs::write(&in_path, content).expect("could not write dlt file");
+        let info = get_dlt_file_info(
+            &fs::File::open(&in_path).expect("could not open dlt file"),
+            None,
+        )
+        .expect("could not get file info");
+        serde_json::to_value(&info).expect("could not serialize")
+    }
+
+    #[test]
+    fn test_file_info_statistics() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let mut non_verbose = stored_message(5);
+        non_verbose.header.has_extended_header = false;
+        non_verbose.header.ecu_id = Some("ECU2".to_string());
+        non_verbose.extended_header = None;
+        non_verbose.payload = dlt::Payload::NonVerbose(1, vec![1, 2, 3]);
+        non_verbose.header.overall_length =
+            non_verbose.as_bytes_without_storage_header().len() as u16;
+        let mut content = stored_messages(&[0, 0, 1, 3]);
+        content.extend(non_verbose.as_bytes());
+        let json = file_info(tmp_dir.path(), &content);
+
+        assert_eq!(
+            serde_json::json!([["ECU1", "APP", "CTX", 4, 4], ["ECU2", "NONE", "NONE", 1, 0]]),
+            serde_json::Value::Array(
+                json["ecu_app_context_ids"]
+                    .as_array()
+                    .expect("array")
+                    .iter()
+                    .map(|ids| serde_json::json!([
+                        ids["ecu_id"],
+                        ids["app_id"],
+                        ids["context_id"],
+                        ids["count"],
+                        ids["levels"]["log_info"]
+                    ]))
+                    .collect()
+            )
+        );
+        assert_eq!(
+            serde_json::json!([
+                ["APP", {"first": 1_000_000, "last": 1_003_000}],
+                ["NONE", {"first": 1_005_000, "last": 1_005_000}]
+            ]),
+            json["app_id_times"]
+        );
+        assert_eq!(
+            serde_json::json!({"start": 1_000_000, "bucket_ms": 1000, "counts": [2, 1, 0, 1, 0, 1]}),
+            json["message_rate"]
+        );
+        assert_eq!(4, json["verbose"]);
+        assert_eq!(1, json["non_verbose"]);
+        assert_eq!(4, json["message_types"]["log"]);
+        assert_eq!(1, json["message_types"]["no_type"]);
+        assert_eq!(0, json["message_types"]["control"]);
+        assert_eq!(7, json["payload_sizes"]["min"]);
+        assert!(json["payload_sizes"]["max"].as_u64().expect("max") > 7);
+    }
+
+    #[test]
+    fn test_file_info_message_rate_buckets() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let json = file_info(tmp_dir.path(), &stored_messages(&[0, 1, 250]));
+        let rate = &json["message_rate"];
+        assert_eq!(3000, rate["bucket_ms"]);
+        let counts = rate["counts"].as_array().expect("counts");
+        assert_eq!(84, counts.len());
+        assert_eq!(2, counts[0]);
+        assert_eq!(1, counts[83]);
+    }
+
+    #[test]
+    fn test_file_info_without_messages() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let json = file_info(tmp_dir.path(), &[]);
+        assert!(json["message_rate"].is_null());
+        assert!(json["payload_sizes"].is_null());
+        assert_eq!(0, json["verbose"]);
+    }
 }
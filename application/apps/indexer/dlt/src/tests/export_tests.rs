@@ -0,0 +1,256 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_parse::dlt_message;
+    use crate::export::*;
+    use crate::fibex;
+    use crate::filtering;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    fn message(counter: u8, ecu: &str, level: dlt::LogLevel) -> dlt::Message {
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: 1_000 + u32::from(counter),
+                    microseconds: 500_000,
+                },
+                ecu_id: ecu.to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: counter % 2 == 0,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some(ecu.to_string()),
+                session_id: Some(7),
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 1,
+                message_type: dlt::MessageType::Log(level),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::StringType,
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::StringVal(format!("message {}", counter)),
+            }]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    fn messages() -> Vec<dlt::Message> {
+        (0..10)
+            .map(|i| {
+                let ecu = if i % 3 == 0 { "ECU1" } else { "ECU2" };
+                let level = if i % 2 == 0 {
+                    dlt::LogLevel::Error
+                } else {
+                    dlt::LogLevel::Debug
+                };
+                message(i, ecu, level)
+            })
+            .collect()
+    }
+
+    fn read_messages(path: &Path) -> Vec<dlt::Message> {
+        let content = fs::read(path).expect("could not read export");
+        let mut input = &content[..];
+        let mut res = vec![];
+        while !input.is_empty() {
+            let (rest, msg) = dlt_message(input, None, None, None).expect("invalid export");
+            res.push(msg.expect("no filter used"));
+            input = rest;
+        }
+        res
+    }
+
+    fn export(dir: &Path, config: ExportConfig) -> (usize, Vec<dlt::Message>) {
+        let in_path = dir.join("trace.dlt");
+        let out_path = dir.join("export.dlt");
+        let content: Vec<u8> = messages().iter().flat_map(|m| m.as_bytes()).collect();
+        fs::write(&in_path, content).expect("could not write dlt file");
+        let exported = export_dlt_file(
+            fs::File::open(&in_path).expect("could not open dlt file"),
+            &out_path,
+            config,
+            None,
+        )
+        .expect("export failed");
+        (exported, read_messages(&out_path))
+    }
+
+    #[test]
+    fn test_export_everything() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let (exported, exported_messages) = export(tmp_dir.path(), ExportConfig::default());
+        assert_eq!(10, exported);
+        assert_eq!(messages(), exported_messages);
+    }
+
+    #[test]
+    fn test_export_with_filter() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let config = ExportConfig {
+            filter: Some(filtering::DltFilterConfig {
+                min_log_level: Some(dlt::LEVEL_ERROR),
+                app_min_log_levels: None,
+                context_min_log_levels: None,
+                app_ids: None,
+                ecu_ids: Some(vec!["ECU1".to_string()]),
+                context_ids: None,
+                include_payload: None,
+                exclude_payload: None,
+                expression: None,
+                from: None,
+                to: None,
+                time_base: None,
+            }),
+            ..Default::default()
+        };
+        let (exported, exported_messages) = export(tmp_dir.path(), config);
+        let expected: Vec<dlt::Message> = messages()
+            .into_iter()
+            .filter(|m| m.header.message_counter % 6 == 0)
+            .collect();
+        assert_eq!(2, exported);
+        assert_eq!(expected, exported_messages);
+    }
+
+    #[test]
+    fn test_export_line_range() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let config = ExportConfig {
+            lines: Some(LineRange { first: 2, last: 4 }),
+            ..Default::default()
+        };
+        let (_, exported_messages) = export(tmp_dir.path(), config);
+        assert_eq!(messages()[2..=4].to_vec(), exported_messages);
+    }
+
+    #[test]
+    fn test_export_line_range_counts_filtered_lines() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let config = ExportConfig {
+            filter: Some(filtering::DltFilterConfig {
+                min_log_level: None,
+                app_min_log_levels: None,
+                context_min_log_levels: None,
+                app_ids: None,
+                ecu_ids: Some(vec!["ECU2".to_string()]),
+                context_ids: None,
+                include_payload: None,
+                exclude_payload: None,
+                expression: None,
+                from: None,
+                to: None,
+                time_base: None,
+            }),
+            lines: Some(LineRange { first: 1, last: 2 }),
+            ..Default::default()
+        };
+        let (_, exported_messages) = export(tmp_dir.path(), config);
+        let counters: Vec<u8> = exported_messages
+            .iter()
+            .map(|m| m.header.message_counter)
+            .collect();
+        // ECU2 messages are 1, 2, 4, 5, 7, 8
+        assert_eq!(vec![2, 4], counters);
+    }
+
+    #[test]
+    fn test_export_time_range() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let config = ExportConfig {
+            time: Some(TimeRange {
+                start: Some(1_003_500),
+                end: Some(1_005_000),
+            }),
+            ..Default::default()
+        };
+        let (_, exported_messages) = export(tmp_dir.path(), config);
+        assert_eq!(messages()[3..=4].to_vec(), exported_messages);
+    }
+
+    #[test]
+    fn test_export_invalid_line_range() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        fs::write(&in_path, b"").expect("could not write dlt file");
+        let res = export_dlt_file(
+            fs::File::open(&in_path).expect("could not open dlt file"),
+            &tmp_dir.path().join("export.dlt"),
+            ExportConfig {
+                lines: Some(LineRange { first: 5, last: 1 }),
+                ..Default::default()
+            },
+            None,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_export_non_verbose_with_fibex() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let fibex_path = tmp_dir.path().join("fibex.xml");
+        fs::write(&fibex_path, fibex::tests::FIBEX).expect("could not write fibex");
+        let raw: Vec<u8> = vec![
+            0x44, 0x4C, 0x54, 0x01, // storage header pattern "DLT" + 0x01
+            0x56, 0xA2, 0x91, 0x5C, 0x9C, 0x91, 0x0B, 0x00, // timestamp
+            0x45, 0x43, 0x55, 0x31, // storage header ecu id "ECU1"
+            0x24, // header type: version 1, with ecu id, little endian, no extended header
+            0x00, // message counter
+            0x00, 0x11, // length
+            0x45, 0x43, 0x55, 0x31, // ecu id "ECU1"
+            0x0A, 0x00, 0x00, 0x00, // message id 10
+            0xFB, 0xFF, 0xFF, 0xFF, // -5
+            0x01, // true
+        ];
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let out_path = tmp_dir.path().join("export.dlt");
+        fs::write(&in_path, &raw).expect("could not write dlt file");
+        let exported = export_dlt_file(
+            fs::File::open(&in_path).expect("could not open dlt file"),
+            &out_path,
+            ExportConfig {
+                filter: Some(filtering::DltFilterConfig {
+                    min_log_level: None,
+                    app_min_log_levels: None,
+                    context_min_log_levels: None,
+                    app_ids: Some(vec!["APP1".to_string()]),
+                    ecu_ids: None,
+                    context_ids: None,
+                    include_payload: None,
+                    exclude_payload: None,
+                    expression: None,
+                    from: None,
+                    to: None,
+                    time_base: None,
+                }),
+                ..Default::default()
+            },
+            Some(fibex::FibexConfig {
+                fibex_file_paths: vec![fibex_path.to_string_lossy().to_string()],
+            }),
+        )
+        .expect("export failed");
+        assert_eq!(1, exported);
+        // the extended header known from the fibex is not written
+        assert_eq!(raw, fs::read(&out_path).expect("could not read export"));
+    }
+}
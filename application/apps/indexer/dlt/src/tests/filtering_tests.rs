@@ -0,0 +1,517 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_parse::dlt_message;
+    use crate::filtering::expression::{FilterExpression, MAX_EXPRESSION_DEPTH};
+    use crate::filtering::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn message(text: &str) -> dlt::Message {
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: 1_000,
+                    microseconds: 0,
+                },
+                ecu_id: "ECU1".to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: false,
+                message_counter: 0,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: None,
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 2,
+                message_type: dlt::MessageType::Log(dlt::LogLevel::Info),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![
+                dlt::Argument {
+                    type_info: dlt::TypeInfo {
+                        kind: dlt::TypeInfoKind::StringType,
+                        coding: dlt::StringCoding::UTF8,
+                        has_variable_info: false,
+                        has_trace_info: false,
+                    },
+                    name: None,
+                    unit: None,
+                    trace_info: None,
+                    fixed_point: None,
+                    value: dlt::Value::StringVal(text.to_string()),
+                },
+                dlt::Argument {
+                    type_info: dlt::TypeInfo {
+                        kind: dlt::TypeInfoKind::Unsigned(dlt::TypeLength::BitLength32, false),
+                        coding: dlt::StringCoding::UTF8,
+                        has_variable_info: false,
+                        has_trace_info: false,
+                    },
+                    name: None,
+                    unit: None,
+                    trace_info: None,
+                    fixed_point: None,
+                    value: dlt::Value::U32(42),
+                },
+            ]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    fn pattern(pattern: &str, is_regex: bool, ignore_case: bool) -> PayloadPattern {
+        PayloadPattern {
+            pattern: pattern.to_string(),
+            is_regex,
+            ignore_case,
+        }
+    }
+
+    fn filter(
+        include_payload: Option<Vec<PayloadPattern>>,
+        exclude_payload: Option<Vec<PayloadPattern>>,
+    ) -> ProcessedDltFilterConfig {
+        process_filter_config(DltFilterConfig {
+            min_log_level: None,
+            app_min_log_levels: None,
+            context_min_log_levels: None,
+            app_ids: None,
+            ecu_ids: None,
+            context_ids: None,
+            include_payload,
+            exclude_payload,
+            expression: None,
+            from: None,
+            to: None,
+            time_base: None,
+        })
+        .expect("valid filter")
+    }
+
+    fn passes(filter: &ProcessedDltFilterConfig, text: &str) -> bool {
+        let bytes = message(text).as_bytes();
+        let (rest, msg) = dlt_message(&bytes, Some(filter), None, None).expect("should parse");
+        assert!(rest.is_empty());
+        msg.is_some()
+    }
+
+    #[test]
+    fn test_old_filter_config_format() {
+        let cfg: DltFilterConfig = serde_json::from_str(r#"{"min_log_level":3,"app_ids":["APP"]}"#)
+            .expect("old format should still be accepted");
+        assert!(cfg.include_payload.is_none());
+        assert!(cfg.exclude_payload.is_none());
+        let cfg: DltFilterConfig = serde_json::from_str(
+            r#"{"include_payload":[{"pattern":"watchdog"}],"exclude_payload":[{"pattern":"^a+$","is_regex":true,"ignore_case":true}]}"#,
+        )
+        .expect("payload patterns");
+        let include = cfg.include_payload.expect("include patterns");
+        assert!(!include[0].is_regex);
+        assert!(!include[0].ignore_case);
+        let exclude = cfg.exclude_payload.expect("exclude patterns");
+        assert!(exclude[0].is_regex);
+        assert!(exclude[0].ignore_case);
+    }
+
+    #[test]
+    fn test_include_substring() {
+        let f = filter(Some(vec![pattern("watchdog", false, false)]), None);
+        assert!(passes(&f, "the watchdog was triggered"));
+        assert!(!passes(&f, "the Watchdog was triggered"));
+        assert!(!passes(&f, "all fine"));
+        let f = filter(Some(vec![pattern("WatchDog", false, true)]), None);
+        assert!(passes(&f, "the watchdog was triggered"));
+        assert!(passes(&f, "WATCHDOG"));
+    }
+
+    #[test]
+    fn test_include_any_of_several_patterns() {
+        let f = filter(
+            Some(vec![
+                pattern("watchdog", false, false),
+                pattern("reset", false, false),
+            ]),
+            None,
+        );
+        assert!(passes(&f, "watchdog"));
+        assert!(passes(&f, "reset"));
+        assert!(!passes(&f, "neither"));
+    }
+
+    #[test]
+    fn test_regex_over_all_arguments() {
+        let f = filter(Some(vec![pattern(r"^temp: \d+ 42$", true, false)]), None);
+        assert!(passes(&f, "temp: 17"));
+        assert!(!passes(&f, "TEMP: 17"));
+        let f = filter(Some(vec![pattern(r"^temp: \d+", true, true)]), None);
+        assert!(passes(&f, "TEMP: 17"));
+    }
+
+    #[test]
+    fn test_exclude() {
+        let f = filter(
+            Some(vec![pattern("watchdog", false, false)]),
+            Some(vec![pattern("heartbeat", false, true)]),
+        );
+        assert!(passes(&f, "watchdog triggered"));
+        assert!(!passes(&f, "watchdog HEARTBEAT"));
+        let f = filter(None, Some(vec![pattern("noise", false, false)]));
+        assert!(passes(&f, "something"));
+        assert!(!passes(&f, "some noise"));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let res = process_filter_config(DltFilterConfig {
+            min_log_level: None,
+            app_min_log_levels: None,
+            context_min_log_levels: None,
+            app_ids: None,
+            ecu_ids: None,
+            context_ids: None,
+            include_payload: Some(vec![pattern("(unclosed", true, false)]),
+            exclude_payload: None,
+            expression: None,
+            from: None,
+            to: None,
+            time_base: None,
+        });
+        assert!(res.is_err());
+    }
+
+    fn log_message(ecu: &str, app_id: &str, level: dlt::LogLevel, text: &str) -> dlt::Message {
+        let mut msg = message(text);
+        msg.header.ecu_id = Some(ecu.to_string());
+        if let Some(ext) = msg.extended_header.as_mut() {
+            ext.application_id = app_id.to_string();
+            ext.message_type = dlt::MessageType::Log(level);
+        }
+        msg
+    }
+
+    fn expression(input: &str) -> FilterExpression {
+        FilterExpression::parse(input).expect("valid expression")
+    }
+
+    fn parse_error(input: &str) -> String {
+        format!(
+            "{}",
+            FilterExpression::parse(input).expect_err("expression should be invalid")
+        )
+    }
+
+    #[test]
+    fn test_expression_header_fields() {
+        let e = expression(r#"(ecu == "ECU1" && level <= WARN) || apid in ["DIAG","SYS"]"#);
+        assert!(e.matches(&log_message("ECU1", "APP", dlt::LogLevel::Error, "")));
+        assert!(e.matches(&log_message("ECU1", "APP", dlt::LogLevel::Warn, "")));
+        assert!(!e.matches(&log_message("ECU1", "APP", dlt::LogLevel::Info, "")));
+        assert!(!e.matches(&log_message("ECU2", "APP", dlt::LogLevel::Fatal, "")));
+        assert!(e.matches(&log_message("ECU2", "SYS", dlt::LogLevel::Verbose, "")));
+        assert!(e.matches(&log_message("ECU2", "DIAG", dlt::LogLevel::Info, "")));
+    }
+
+    #[test]
+    fn test_expression_precedence_and_negation() {
+        // && binds stronger than ||
+        let e = expression(r#"apid == "A" || apid == "B" && level == ERROR"#);
+        assert!(e.matches(&log_message("ECU1", "A", dlt::LogLevel::Info, "")));
+        assert!(!e.matches(&log_message("ECU1", "B", dlt::LogLevel::Info, "")));
+        assert!(e.matches(&log_message("ECU1", "B", dlt::LogLevel::Error, "")));
+        let e = expression(r#"!(apid == "A" || ecu != "ECU1")"#);
+        assert!(!e.matches(&log_message("ECU1", "A", dlt::LogLevel::Info, "")));
+        assert!(!e.matches(&log_message("ECU2", "B", dlt::LogLevel::Info, "")));
+        assert!(e.matches(&log_message("ECU1", "B", dlt::LogLevel::Info, "")));
+    }
+
+    #[test]
+    fn test_expression_numeric_fields() {
+        let mut msg = log_message("ECU1", "APP", dlt::LogLevel::Info, "");
+        msg.header.session_id = Some(17);
+        msg.header.message_counter = 200;
+        msg.header.timestamp = Some(12_345);
+        assert!(expression("session == 17 && counter > 0xC7").matches(&msg));
+        assert!(expression("timestamp >= 12345 && timestamp < 12346").matches(&msg));
+        assert!(expression("type == LOG && type != CONTROL").matches(&msg));
+        assert!(expression("level in [INFO, DEBUG]").matches(&msg));
+        // the storage header time is 1000 s after the epoch
+        assert!(expression("time == 1000000").matches(&msg));
+        assert!(expression(r#"time < "1970-01-01T00:16:41Z""#).matches(&msg));
+        assert!(!expression(r#"time > "1970-01-01T00:16:40Z""#).matches(&msg));
+    }
+
+    #[test]
+    fn test_expression_missing_fields() {
+        let mut msg = log_message("ECU1", "APP", dlt::LogLevel::Info, "");
+        msg.extended_header = None;
+        msg.header.session_id = None;
+        assert!(!expression("level <= VERBOSE").matches(&msg));
+        assert!(!expression(r#"apid != "APP""#).matches(&msg));
+        assert!(!expression("session == 0").matches(&msg));
+        assert!(expression("!(session == 0)").matches(&msg));
+    }
+
+    #[test]
+    fn test_expression_payload() {
+        let msg = log_message("ECU1", "APP", dlt::LogLevel::Info, "watchdog triggered");
+        assert!(expression(r#"payload contains "watchdog""#).matches(&msg));
+        assert!(expression(r#"payload ~ "^watch\\w+ trig.* 42$""#).matches(&msg));
+        assert!(expression(r#"payload matches "(?i)WATCHDOG""#).matches(&msg));
+        assert!(!expression(r#"payload contains "Watchdog""#).matches(&msg));
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        assert_eq!(
+            "invalid filter expression at column 1: unknown field 'apd' (known fields: ecu, apid, ctid, payload, level, type, session, counter, timestamp, time)",
+            parse_error(r#"apd == "A""#)
+        );
+        assert_eq!(
+            "invalid filter expression at column 9: unterminated string",
+            parse_error(r#"apid == "A"#)
+        );
+        assert_eq!(
+            "invalid filter expression at column 13: expected ')' but the expression ended",
+            parse_error(r#"(apid == "A""#)
+        );
+        assert_eq!(
+            "invalid filter expression at column 6: operator '<' can not be used with text field 'apid'",
+            parse_error(r#"apid < "A""#)
+        );
+        assert_eq!(
+            "invalid filter expression at column 10: unknown value 'LOUD' for this field",
+            parse_error("level <= LOUD")
+        );
+        assert_eq!(
+            "invalid filter expression at column 9: expected a string but found number 1",
+            parse_error("apid == 1")
+        );
+        assert_eq!(
+            "invalid filter expression at column 13: unexpected ')' after the end of the expression",
+            parse_error(r#"apid == "A" )"#)
+        );
+        assert_eq!(
+            "invalid filter expression at column 15: expected a field name but the expression ended",
+            parse_error(r#"apid == "A" &&"#)
+        );
+        assert!(parse_error(r#"payload ~ "(""#).contains("invalid regular expression"));
+        assert!(parse_error("level == 1 $").contains("unexpected character '$'"));
+    }
+
+    #[test]
+    fn test_expression_nesting_depth() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!(
+                r#"{}apid == "A"{}"#,
+                open.repeat(depth),
+                close.repeat(depth)
+            )
+        };
+        assert!(FilterExpression::parse(&nested("(", ")", MAX_EXPRESSION_DEPTH)).is_ok());
+        assert!(FilterExpression::parse(&nested("!", "", MAX_EXPRESSION_DEPTH)).is_ok());
+        assert_eq!(
+            format!(
+                "invalid filter expression at column {}: nested deeper than {} levels",
+                MAX_EXPRESSION_DEPTH + 1,
+                MAX_EXPRESSION_DEPTH
+            ),
+            parse_error(&nested("(", ")", MAX_EXPRESSION_DEPTH + 1))
+        );
+        // must not overflow the stack
+        assert!(parse_error(&nested("(", ")", 100_000)).contains("nested deeper"));
+        assert!(parse_error(&nested("!", "", 100_000)).contains("nested deeper"));
+        assert!(parse_error(&nested("!(", ")", 100_000)).contains("nested deeper"));
+        // long chains are not nested
+        let chain = vec![r#"apid == "A""#; 100_000].join(" && ");
+        let e = expression(&chain);
+        assert!(e.matches(&log_message("ECU1", "A", dlt::LogLevel::Info, "")));
+        assert!(!e.matches(&log_message("ECU1", "B", dlt::LogLevel::Info, "")));
+    }
+
+    #[test]
+    fn test_expression_in_filter_config() {
+        let f = process_filter_config(DltFilterConfig {
+            expression: Some(r#"payload contains "watchdog" && level <= ERROR"#.to_string()),
+            from: None,
+            to: None,
+            time_base: None,
+            ..Default::default()
+        })
+        .expect("valid filter");
+        let passes = |msg: dlt::Message| {
+            let bytes = msg.as_bytes();
+            let (_, msg) = dlt_message(&bytes, Some(&f), None, None).expect("should parse");
+            msg.is_some()
+        };
+        assert!(passes(log_message(
+            "ECU1",
+            "APP",
+            dlt::LogLevel::Error,
+            "watchdog"
+        )));
+        assert!(!passes(log_message(
+            "ECU1",
+            "APP",
+            dlt::LogLevel::Info,
+            "watchdog"
+        )));
+        assert!(!passes(log_message(
+            "ECU1",
+            "APP",
+            dlt::LogLevel::Error,
+            "fine"
+        )));
+        assert!(process_filter_config(DltFilterConfig {
+            expression: Some("apid ==".to_string()),
+            from: None,
+            to: None,
+            time_base: None,
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_read_filter_file_with_expression() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("filter");
+        fs::write(&path, "level <= WARN\n").expect("could not write filter");
+        let cfg = read_filter_options(&mut fs::File::open(&path).expect("could not open filter"))
+            .expect("could not read filter");
+        assert_eq!(Some("level <= WARN".to_string()), cfg.expression);
+        fs::write(&path, r#"{"app_ids":["APP"],"expression":"level <= WARN"}"#)
+            .expect("could not write filter");
+        let cfg = read_filter_options(&mut fs::File::open(&path).expect("could not open filter"))
+            .expect("could not read filter");
+        assert_eq!(Some("level <= WARN".to_string()), cfg.expression);
+        assert_eq!(Some(vec!["APP".to_string()]), cfg.app_ids);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let msg = message("text");
+        let f = process_filter_config(
+            serde_json::from_str(r#"{"from":1000000,"to":1000000}"#).expect("valid json"),
+        )
+        .expect("valid filter");
+        assert!(!f.is_outside_time_window(msg.storage_header.as_ref(), &msg.header));
+        assert_eq!(Some(1_000_000), f.storage_time_end());
+        let f =
+            process_filter_config(serde_json::from_str(r#"{"from":1000001}"#).expect("valid json"))
+                .expect("valid filter");
+        assert!(f.is_outside_time_window(msg.storage_header.as_ref(), &msg.header));
+        // without storage header there is no time to compare
+        assert!(f.is_outside_time_window(None, &msg.header));
+
+        let f = process_filter_config(
+            serde_json::from_str(r#"{"to":10,"time_base":"uptime"}"#).expect("valid json"),
+        )
+        .expect("valid filter");
+        assert_eq!(TimeBase::Uptime, f.time_base);
+        assert_eq!(None, f.storage_time_end());
+        let mut header = msg.header.clone();
+        header.timestamp = Some(10);
+        assert!(!f.is_outside_time_window(None, &header));
+        header.timestamp = Some(11);
+        assert!(f.is_outside_time_window(None, &header));
+    }
+
+    #[test]
+    fn test_invalid_time_window() {
+        let res = process_filter_config(DltFilterConfig {
+            from: Some(2),
+            to: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(
+            "invalid time window 2..1",
+            res.err().expect("should fail").to_string()
+        );
+    }
+
+    fn passes_level(
+        filter: &ProcessedDltFilterConfig,
+        app_id: &str,
+        context_id: &str,
+        level: dlt::LogLevel,
+    ) -> bool {
+        let mut msg = log_message("ECU1", app_id, level, "text");
+        if let Some(ext) = msg.extended_header.as_mut() {
+            ext.context_id = context_id.to_string();
+        }
+        let bytes = msg.as_bytes();
+        let (_, msg) = dlt_message(&bytes, Some(filter), None, None).expect("should parse");
+        msg.is_some()
+    }
+
+    #[test]
+    fn test_app_and_context_log_levels() {
+        let f = process_filter_config(
+            serde_json::from_str(
+                r#"{
+                    "min_log_level": 3,
+                    "app_min_log_levels": {"NAV": 5, "DIAG": 2},
+                    "context_min_log_levels": {"NAV": {"MAP": 6}, "DIAG": {"UDS": 4}}
+                }"#,
+            )
+            .expect("valid json"),
+        )
+        .expect("valid filter");
+        // global level
+        assert!(passes_level(&f, "APP", "CTX", dlt::LogLevel::Warn));
+        assert!(!passes_level(&f, "APP", "CTX", dlt::LogLevel::Info));
+        // application level
+        assert!(passes_level(&f, "NAV", "CTX", dlt::LogLevel::Debug));
+        assert!(!passes_level(&f, "NAV", "CTX", dlt::LogLevel::Verbose));
+        assert!(!passes_level(&f, "DIAG", "CTX", dlt::LogLevel::Warn));
+        // context level
+        assert!(passes_level(&f, "NAV", "MAP", dlt::LogLevel::Verbose));
+        assert!(passes_level(&f, "DIAG", "UDS", dlt::LogLevel::Info));
+        assert!(!passes_level(&f, "DIAG", "UDS", dlt::LogLevel::Debug));
+        // the context id alone does not select a level
+        assert!(!passes_level(&f, "APP", "MAP", dlt::LogLevel::Verbose));
+    }
+
+    #[test]
+    fn test_app_log_levels_without_global_level() {
+        let mut levels = HashMap::new();
+        levels.insert("NAV".to_string(), dlt::LEVEL_WARN);
+        let f = process_filter_config(DltFilterConfig {
+            app_min_log_levels: Some(levels),
+            ..Default::default()
+        })
+        .expect("valid filter");
+        assert!(!passes_level(&f, "NAV", "CTX", dlt::LogLevel::Info));
+        assert!(passes_level(&f, "APP", "CTX", dlt::LogLevel::Verbose));
+    }
+
+    #[test]
+    fn test_invalid_global_log_level_is_ignored() {
+        let f = process_filter_config(DltFilterConfig {
+            min_log_level: Some(dlt::LEVEL_DEBUG << 4),
+            ..Default::default()
+        })
+        .expect("valid filter");
+        assert!(passes_level(&f, "APP", "CTX", dlt::LogLevel::Verbose));
+    }
+
+    #[test]
+    fn test_invalid_context_log_level() {
+        let res = process_filter_config(
+            serde_json::from_str(r#"{"context_min_log_levels": {"NAV": {"MAP": 9}}}"#)
+                .expect("valid json"),
+        );
+        assert_eq!(
+            "invalid log level 9 for NAV/MAP",
+            res.err().expect("should fail").to_string()
+        );
+    }
+}
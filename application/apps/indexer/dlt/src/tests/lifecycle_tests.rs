@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_parse::get_dlt_file_info;
+    use crate::lifecycle::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempdir::TempDir;
+
+    /// message of `ecu` received at `storage_ms` with an uptime of `uptime_ms`
+    fn message(ecu: &str, storage_ms: i64, uptime_ms: Option<u32>) -> dlt::Message {
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: (storage_ms / 1000) as u32,
+                    microseconds: (storage_ms % 1000) as u32 * 1000,
+                },
+                ecu_id: "LOGR".to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: false,
+                version: 1,
+                big_endian: true,
+                message_counter: 0,
+                overall_length: 0,
+                ecu_id: Some(ecu.to_string()),
+                session_id: None,
+                timestamp: uptime_ms.map(|ms| ms * 10),
+            },
+            extended_header: None,
+            payload: dlt::Payload::NonVerbose(1, vec![]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    /// ECU1 starts at 100_000 ms and restarts at 200_000 ms, ECU2 starts at 50_000 ms
+    fn messages() -> Vec<dlt::Message> {
+        vec![
+            message("ECU1", 100_010, Some(10)),
+            message("ECU2", 100_050, Some(50_000)),
+            message("ECU1", 100_505, Some(500)),
+            // delayed in the logger
+            message("ECU1", 160_400, Some(60_000)),
+            message("ECU1", 200_003, Some(2)),
+            message("ECU2", 200_060, Some(150_005)),
+            message("ECU1", 200_301, Some(300)),
+        ]
+    }
+
+    fn detect(messages: &[dlt::Message]) -> (Vec<usize>, Vec<Lifecycle>) {
+        let mut detector = LifecycleDetector::new();
+        let ids = messages.iter().map(|m| detector.add_message(m)).collect();
+        (ids, detector.finish())
+    }
+
+    #[test]
+    fn test_detect_restart() {
+        let (ids, lifecycles) = detect(&messages());
+        assert_eq!(vec![0, 1, 0, 0, 2, 1, 2], ids);
+        assert_eq!(
+            vec![
+                Lifecycle {
+                    id: 0,
+                    ecu_id: "ECU1".to_string(),
+                    start: Some(100_000),
+                    end: Some(160_000),
+                    message_count: 3,
+                },
+                Lifecycle {
+                    id: 1,
+                    ecu_id: "ECU2".to_string(),
+                    start: Some(50_050),
+                    end: Some(200_055),
+                    message_count: 2,
+                },
+                Lifecycle {
+                    id: 2,
+                    ecu_id: "ECU1".to_string(),
+                    start: Some(200_001),
+                    end: Some(200_301),
+                    message_count: 2,
+                },
+            ],
+            lifecycles
+        );
+    }
+
+    #[test]
+    fn test_uptime_jitter_is_no_restart() {
+        let (ids, lifecycles) = detect(&[
+            message("ECU1", 10_000, Some(5_000)),
+            message("ECU1", 10_001, Some(4_200)),
+            message("ECU1", 10_002, None),
+            message("ECU1", 10_003, Some(5_001)),
+        ]);
+        assert_eq!(vec![0, 0, 0, 0], ids);
+        assert_eq!(1, lifecycles.len());
+        assert_eq!(4, lifecycles[0].message_count);
+        assert_eq!(Some(5_000), lifecycles[0].start);
+    }
+
+    #[test]
+    fn test_no_storage_time() {
+        let mut detector = LifecycleDetector::new();
+        detector.add(Some("ECU1"), Some(100_000), None);
+        detector.add(Some("ECU1"), Some(10), None);
+        detector.add(None, Some(10), None);
+        let lifecycles = detector.finish();
+        assert_eq!(3, lifecycles.len());
+        assert_eq!(None, lifecycles[0].start);
+        assert_eq!(None, lifecycles[1].end);
+        assert_eq!("NONE", lifecycles[2].ecu_id);
+    }
+
+    #[test]
+    fn test_annotate_messages() {
+        let messages = messages();
+        let (_, lifecycles) = detect(&messages);
+        let mut annotator = LifecycleAnnotator::new(&lifecycles);
+        let annotations: Vec<Annotation> = messages
+            .iter()
+            .map(|m| annotator.annotate_message(m).expect("known lifecycle"))
+            .collect();
+        let expected: Vec<(usize, i64)> = vec![
+            (0, 100_010),
+            (1, 100_050),
+            (0, 100_500),
+            (0, 160_000),
+            (2, 200_003),
+            (1, 200_055),
+            (2, 200_301),
+        ];
+        assert_eq!(
+            expected
+                .into_iter()
+                .map(|(lifecycle_id, time)| Annotation {
+                    lifecycle_id,
+                    absolute_time: Some(time),
+                })
+                .collect::<Vec<Annotation>>(),
+            annotations
+        );
+        let mut annotator = LifecycleAnnotator::new(&lifecycles);
+        assert_eq!(
+            Some(Annotation {
+                lifecycle_id: 0,
+                absolute_time: None
+            }),
+            annotator.annotate(Some("ECU1"), None)
+        );
+    }
+
+    #[test]
+    fn test_lifecycles_in_file_info() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        let content: Vec<u8> = messages().iter().flat_map(|m| m.as_bytes()).collect();
+        fs::write(&in_path, &content).expect("could not write dlt file");
+        let info = get_dlt_file_info(
+            &fs::File::open(&in_path).expect("could not open dlt file"),
+            None,
+        )
+        .expect("could not get file info");
+        let json = serde_json::to_value(&info).expect("could not serialize");
+        let (_, expected) = detect(&messages());
+        assert_eq!(
+            serde_json::to_value(&expected).expect("could not serialize"),
+            json["lifecycles"]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_parse::tests::argument_strategy;
+    use crate::dlt_parse::*;
+    use crate::filtering;
+    use crate::message_ref::*;
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn message(counter: u8, payload: dlt::Payload, big_endian: bool) -> dlt::Message {
+        let argument_count = match &payload {
+            dlt::Payload::Verbose(arguments) => arguments.len() as u8,
+            _ => 0,
+        };
+        let level = if counter % 2 == 0 {
+            dlt::LogLevel::Info
+        } else {
+            dlt::LogLevel::Error
+        };
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: 1_000 + u32::from(counter),
+                    microseconds: 0,
+                },
+                ecu_id: "ECU1".to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: Some(7),
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count,
+                message_type: dlt::MessageType::Log(level),
+                application_id: "APP".to_string(),
+                context_id: format!("C{}", counter % 3),
+            }),
+            payload,
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    fn text_message(counter: u8) -> dlt::Message {
+        message(
+            counter,
+            dlt::Payload::Verbose(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::StringType,
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::StringVal(format!("message {}", counter)),
+            }]),
+            counter % 2 == 0,
+        )
+    }
+
+    /// messages of different kinds with corrupted data in between
+    fn content() -> Vec<u8> {
+        let mut content = vec![];
+        for counter in 0..20u8 {
+            content.extend(text_message(counter).as_bytes());
+            if counter % 7 == 3 {
+                content.extend(b"garbage DLT in between");
+            }
+        }
+        let mut non_verbose = text_message(20);
+        non_verbose.header.has_extended_header = false;
+        non_verbose.extended_header = None;
+        non_verbose.payload = dlt::Payload::NonVerbose(1, vec![1, 2, 3]);
+        non_verbose.header.overall_length =
+            non_verbose.as_bytes_without_storage_header().len() as u16;
+        content.extend(non_verbose.as_bytes());
+        let truncated = text_message(21).as_bytes();
+        content.extend(&truncated[..truncated.len() - 3]);
+        content
+    }
+
+    proptest! {
+        #[test]
+        fn test_arguments_same_as_owned(
+            argument in argument_strategy(),
+            big_endian in any::<bool>()
+        ) {
+            let msg = message(1, dlt::Payload::Verbose(vec![argument.clone()]), big_endian);
+            let mut bytes = msg.as_bytes();
+            bytes.extend(b"----");
+            let (rest, msg_ref) = dlt_message_ref(&bytes).expect("could not parse message");
+            assert_eq!(b"----", rest);
+            let arguments: Vec<dlt::Argument> = msg_ref
+                .arguments()
+                .map(|a| a.expect("could not parse argument").to_argument())
+                .collect();
+            assert_eq!(vec![argument], arguments);
+            assert_eq!(msg, msg_ref.to_message().expect("could not decode message"));
+        }
+    }
+
+    #[test]
+    fn test_headers_same_as_owned() {
+        let msg = text_message(4);
+        let bytes = msg.as_bytes();
+        let (rest, msg_ref) = dlt_message_ref(&bytes).expect("could not parse message");
+        assert!(rest.is_empty());
+        assert_eq!(bytes.as_slice(), msg_ref.bytes);
+        assert_eq!(
+            msg.storage_header,
+            msg_ref
+                .storage_header
+                .as_ref()
+                .map(StorageHeaderRef::to_owned)
+        );
+        assert_eq!(msg.header, msg_ref.header.to_owned());
+        assert_eq!(msg.header.header_type(), msg_ref.header.header_type());
+        assert_eq!(
+            msg.extended_header,
+            msg_ref
+                .extended_header
+                .as_ref()
+                .map(ExtendedHeaderRef::to_owned)
+        );
+        assert_eq!(Some("ECU1"), msg_ref.ecu_id());
+        assert_eq!(Some(1_004_000), msg_ref.storage_time());
+        assert_eq!(Some(dlt::LogLevel::Info), msg_ref.log_level());
+        match msg_ref.arguments().next() {
+            Some(Ok(ArgumentRef {
+                value: ValueRef::StringVal(s),
+                ..
+            })) => assert_eq!("message 4", s),
+            other => panic!("unexpected argument: {:?}", other),
+        }
+    }
+
+    /// a verbose message with `depth` nested structs as its only argument
+    fn nested_struct_message(depth: usize) -> Vec<u8> {
+        let mut msg = message(1, dlt::Payload::Verbose(vec![]), true);
+        if let Some(h) = msg.extended_header.as_mut() {
+            h.argument_count = 1;
+        }
+        let mut bytes = msg.as_bytes();
+        bytes.extend(crate::dlt_parse::tests::nested_struct_bytes(depth));
+        let length = (bytes.len() - dlt::STORAGE_HEADER_LENGTH) as u16;
+        bytes[dlt::STORAGE_HEADER_LENGTH + 2..dlt::STORAGE_HEADER_LENGTH + 4]
+            .copy_from_slice(&length.to_be_bytes());
+        bytes
+    }
+
+    /// depth of the deepest struct reached by walking all fields
+    fn struct_depth(argument: ArgumentRef) -> usize {
+        match argument.value {
+            ValueRef::Struct(fields) => {
+                1 + fields
+                    .map(|f| struct_depth(f.expect("could not parse field")))
+                    .max()
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_deeply_nested_structs() {
+        let bytes = nested_struct_message(dlt::MAX_STRUCT_DEPTH);
+        let (_, msg_ref) = dlt_message_ref(&bytes).expect("could not parse message");
+        let argument = msg_ref
+            .arguments()
+            .next()
+            .expect("no argument")
+            .expect("could not parse argument");
+        assert_eq!(dlt::MAX_STRUCT_DEPTH, struct_depth(argument));
+
+        // fills a whole message, would overflow the stack without a limit
+        for depth in &[dlt::MAX_STRUCT_DEPTH + 1, 10_000] {
+            let bytes = nested_struct_message(*depth);
+            let (_, msg_ref) = dlt_message_ref(&bytes).expect("could not parse message");
+            match msg_ref.arguments().next() {
+                Some(Err(_)) => (),
+                other => panic!("expected an error, got {:?}", other),
+            }
+            assert!(msg_ref.to_message().is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        let mut bytes = text_message(0).as_bytes();
+        bytes[dlt::STORAGE_HEADER_LENGTH + 2] = 0;
+        bytes[dlt::STORAGE_HEADER_LENGTH + 3] = 2;
+        assert!(dlt_message_ref(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_mapped_messages_skip_corrupted_data() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("trace.dlt");
+        fs::write(&path, content()).expect("could not write dlt file");
+        let dlt_file = MappedDltFile::open(&path).expect("could not map file");
+        let counters: Vec<u8> = dlt_file
+            .messages()
+            .map(|msg| msg.header.message_counter)
+            .collect();
+        assert_eq!((0..=20).collect::<Vec<u8>>(), counters);
+    }
+
+    #[test]
+    fn test_mapped_empty_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("empty.dlt");
+        fs::write(&path, b"").expect("could not write dlt file");
+        let dlt_file = MappedDltFile::open(&path).expect("could not map file");
+        assert_eq!(0, dlt_file.messages().count());
+    }
+
+    #[test]
+    fn test_mapped_file_info_same_as_file_info() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("trace.dlt");
+        fs::write(&path, content()).expect("could not write dlt file");
+        let info = get_dlt_file_info(
+            &fs::File::open(&path).expect("could not open dlt file"),
+            None,
+        )
+        .expect("could not get file info");
+        let dlt_file = MappedDltFile::open(&path).expect("could not map file");
+        let mapped_info = get_mapped_dlt_file_info(&dlt_file, None).expect("could not get info");
+        assert_eq!(
+            serde_json::to_value(&info).expect("could not serialize"),
+            serde_json::to_value(&mapped_info).expect("could not serialize")
+        );
+    }
+
+    #[test]
+    fn test_filter_same_as_owned() {
+        let filters = vec![
+            filtering::DltFilterConfig {
+                min_log_level: Some(dlt::LEVEL_WARN),
+                ..Default::default()
+            },
+            filtering::DltFilterConfig {
+                context_ids: Some(vec!["C1".to_string()]),
+                ..Default::default()
+            },
+            filtering::DltFilterConfig {
+                from: Some(1_003_000),
+                to: Some(1_008_000),
+                ..Default::default()
+            },
+            filtering::DltFilterConfig {
+                include_payload: Some(vec![filtering::PayloadPattern {
+                    pattern: "message 1".to_string(),
+                    is_regex: false,
+                    ignore_case: false,
+                }]),
+                ..Default::default()
+            },
+        ];
+        let content = content();
+        let messages: Vec<MessageRef> = Messages::new(&content).collect();
+        for filter in filters {
+            let filter = filtering::process_filter_config(filter).expect("valid filter");
+            for msg in &messages {
+                let owned = dlt_message(msg.bytes, Some(&filter), None, None)
+                    .expect("could not parse message")
+                    .1;
+                assert_eq!(owned.is_none(), is_filtered_out(&filter, msg));
+            }
+        }
+    }
+}
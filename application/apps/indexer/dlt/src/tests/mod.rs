@@ -1,2 +1,9 @@
 #[macro_use]
 mod dlt_parse_tests;
+mod dlt_net_tests;
+mod export_tests;
+mod filtering_tests;
+mod lifecycle_tests;
+mod message_ref_tests;
+mod parallel_tests;
+mod pcap_tests;
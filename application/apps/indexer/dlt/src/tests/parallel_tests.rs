@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::dlt_parse::index_dlt_file;
+    use crate::filtering;
+    use crate::parallel::*;
+    use indexer_base::config::IndexingConfig;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    /// looks like two chained messages to the slice splitting
+    const FAKE_MESSAGES: &str =
+        "DLT\u{1}aaaaaaaaaaaa\u{20}a\u{0}\u{4}DLT\u{1}aaaaaaaaaaaa\u{20}a\u{0}\u{4}";
+
+    fn message(counter: u8, text: &str) -> dlt::Message {
+        let level = if counter % 3 == 0 {
+            dlt::LogLevel::Warn
+        } else {
+            dlt::LogLevel::Debug
+        };
+        let mut msg = dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds: 1_000 + u32::from(counter),
+                    microseconds: 0,
+                },
+                ecu_id: "ECU1".to_string(),
+            }),
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: counter % 2 == 0,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 1,
+                message_type: dlt::MessageType::Log(level),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::StringType,
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::StringVal(format!("{} {}", text, counter)),
+            }]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+
+    /// messages with corrupted data and fake storage headers in between
+    fn content() -> Vec<u8> {
+        let mut content = vec![];
+        for counter in 0..60u8 {
+            let text = if counter % 7 == 0 {
+                FAKE_MESSAGES
+            } else {
+                "message"
+            };
+            content.extend(message(counter, text).as_bytes());
+            if counter % 11 == 5 {
+                content.extend(b"garbage DLT in between");
+            }
+            if counter % 13 == 8 {
+                let mut corrupted = message(counter, "corrupted").as_bytes();
+                corrupted[dlt::STORAGE_HEADER_LENGTH + 2] = 0;
+                corrupted[dlt::STORAGE_HEADER_LENGTH + 3] = 2;
+                content.extend(corrupted);
+            }
+        }
+        let truncated = message(60, "truncated").as_bytes();
+        content.extend(&truncated[..truncated.len() - 5]);
+        content
+    }
+
+    fn indexing_config<'a>(in_path: &Path, out_path: &'a std::path::PathBuf) -> IndexingConfig<'a> {
+        IndexingConfig {
+            tag: "TAG",
+            chunk_size: 7,
+            in_file: fs::File::open(in_path).expect("could not open dlt file"),
+            zip_entry: None,
+            out_path,
+            append: false,
+            to_stdout: false,
+            index_map: None,
+            token_index: None,
+            follow: None,
+        }
+    }
+
+    /// index `content` serially and in slices, returns both outputs and chunks
+    fn index_both_ways(
+        content: &[u8],
+        filter: Option<filtering::DltFilterConfig>,
+        threads: usize,
+        slice_size: usize,
+    ) -> ((String, String), (String, String)) {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        fs::write(&in_path, content).expect("could not write dlt file");
+        let serial_out = tmp_dir.path().join("serial.out");
+        let serial_chunks = index_dlt_file(
+            indexing_config(&in_path, &serial_out),
+            filter.clone(),
+            0,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .expect("serial indexing failed");
+        let parallel_out = tmp_dir.path().join("parallel.out");
+        let parallel_chunks = index_dlt_file_in_slices(
+            indexing_config(&in_path, &parallel_out),
+            filter
+                .map(filtering::process_filter_config)
+                .transpose()
+                .expect("valid filter"),
+            0,
+            None,
+            None,
+            None,
+            None,
+            threads,
+            slice_size,
+        )
+        .expect("parallel indexing failed");
+        let output = |path| fs::read_to_string(path).expect("could not read output");
+        let chunks = |chunks| serde_json::to_string(&chunks).expect("could not serialize chunks");
+        (
+            (output(&serial_out), chunks(serial_chunks)),
+            (output(&parallel_out), chunks(parallel_chunks)),
+        )
+    }
+
+    #[test]
+    fn test_same_output_for_all_slice_sizes() {
+        let content = content();
+        for slice_size in &[1, 17, 64, 100, 333, 1000, 4096, 1_000_000] {
+            let (serial, parallel) = index_both_ways(&content, None, 3, *slice_size);
+            assert!(!serial.0.is_empty());
+            assert_eq!(serial, parallel, "slice size {}", slice_size);
+        }
+    }
+
+    #[test]
+    fn test_same_output_with_filter() {
+        let filter = filtering::DltFilterConfig {
+            min_log_level: Some(dlt::LEVEL_WARN),
+            ..Default::default()
+        };
+        let (serial, parallel) = index_both_ways(&content(), Some(filter), 4, 50);
+        assert_eq!(20, serial.0.lines().count());
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let (serial, parallel) = index_both_ways(&[], None, 2, 10);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_storage_time_window_falls_back_to_serial() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("trace.dlt");
+        fs::write(&in_path, content()).expect("could not write dlt file");
+        let out_path = tmp_dir.path().join("trace.out");
+        let filter = filtering::DltFilterConfig {
+            from: Some(1_010_000),
+            to: Some(1_019_000),
+            ..Default::default()
+        };
+        index_dlt_file_parallel(
+            indexing_config(&in_path, &out_path),
+            Some(filter),
+            0,
+            None,
+            None,
+            None,
+            None,
+            2,
+        )
+        .expect("indexing failed");
+        let output = fs::read_to_string(&out_path).expect("could not read output");
+        assert_eq!(10, output.lines().count());
+    }
+}
@@ -0,0 +1,326 @@
+#[cfg(test)]
+mod tests {
+    use crate::dlt;
+    use crate::pcap::*;
+    use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+    use indexer_base::config::IndexingConfig;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    fn message(counter: u8) -> dlt::Message {
+        let mut msg = dlt::Message {
+            storage_header: None,
+            header: dlt::StandardHeader {
+                has_extended_header: true,
+                version: 1,
+                big_endian: false,
+                message_counter: counter,
+                overall_length: 0,
+                ecu_id: Some("ECU1".to_string()),
+                session_id: None,
+                timestamp: Some(u32::from(counter)),
+            },
+            extended_header: Some(dlt::ExtendedHeader {
+                verbose: true,
+                argument_count: 1,
+                message_type: dlt::MessageType::Log(dlt::LogLevel::Info),
+                application_id: "APP".to_string(),
+                context_id: "CTX".to_string(),
+            }),
+            payload: dlt::Payload::Verbose(vec![dlt::Argument {
+                type_info: dlt::TypeInfo {
+                    kind: dlt::TypeInfoKind::StringType,
+                    coding: dlt::StringCoding::UTF8,
+                    has_variable_info: false,
+                    has_trace_info: false,
+                },
+                name: None,
+                unit: None,
+                trace_info: None,
+                fixed_point: None,
+                value: dlt::Value::StringVal(format!("message {}", counter)),
+            }]),
+        };
+        msg.header.overall_length = msg.as_bytes_without_storage_header().len() as u16;
+        msg
+    }
+    fn stored(msg: dlt::Message, seconds: u32, microseconds: u32) -> dlt::Message {
+        dlt::Message {
+            storage_header: Some(dlt::StorageHeader {
+                timestamp: dlt::DltTimeStamp {
+                    seconds,
+                    microseconds,
+                },
+                ecu_id: "ECU1".to_string(),
+            }),
+            ..msg
+        }
+    }
+
+    fn ipv4(protocol: u8, transport: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0x00];
+        packet
+            .write_u16::<BigEndian>(20 + transport.len() as u16)
+            .unwrap();
+        packet.extend_from_slice(&[0x00, 0x00, 0x40, 0x00, 0x40, protocol, 0x00, 0x00]);
+        packet.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 2]);
+        packet.extend_from_slice(transport);
+        packet
+    }
+    fn ethernet(ip_packet: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x02, 0, 0, 0, 0, 1, 0x02, 0, 0, 0, 0, 2];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(ip_packet);
+        frame
+    }
+    fn udp(payload: &[u8]) -> Vec<u8> {
+        udp_to_port(3490, payload)
+    }
+    fn udp_to_port(port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = vec![];
+        datagram.write_u16::<BigEndian>(50000).unwrap();
+        datagram.write_u16::<BigEndian>(port).unwrap();
+        datagram
+            .write_u16::<BigEndian>(8 + payload.len() as u16)
+            .unwrap();
+        datagram.write_u16::<BigEndian>(0).unwrap();
+        datagram.extend_from_slice(payload);
+        ethernet(&ipv4(17, &datagram))
+    }
+    fn tcp(sequence_number: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![];
+        segment.write_u16::<BigEndian>(3490).unwrap();
+        segment.write_u16::<BigEndian>(50000).unwrap();
+        segment.write_u32::<BigEndian>(sequence_number).unwrap();
+        segment.write_u32::<BigEndian>(0).unwrap(); // ack
+        segment.extend_from_slice(&[0x50, flags, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+        segment.extend_from_slice(payload);
+        ethernet(&ipv4(6, &segment))
+    }
+    fn pcap(packets: &[(u32, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut file = vec![];
+        file.write_u32::<LittleEndian>(0xA1B2_C3D4).unwrap();
+        file.write_u16::<LittleEndian>(2).unwrap();
+        file.write_u16::<LittleEndian>(4).unwrap();
+        file.extend_from_slice(&[0u8; 8]); // thiszone, sigfigs
+        file.write_u32::<LittleEndian>(65535).unwrap();
+        file.write_u32::<LittleEndian>(1).unwrap(); // ethernet
+        for (seconds, micros, data) in packets {
+            file.write_u32::<LittleEndian>(*seconds).unwrap();
+            file.write_u32::<LittleEndian>(*micros).unwrap();
+            file.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            file.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            file.extend_from_slice(data);
+        }
+        file
+    }
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut body = body.to_vec();
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+        let mut block = vec![];
+        let length = 12 + body.len() as u32;
+        block.write_u32::<BigEndian>(block_type).unwrap();
+        block.write_u32::<BigEndian>(length).unwrap();
+        block.extend_from_slice(&body);
+        block.write_u32::<BigEndian>(length).unwrap();
+        block
+    }
+    /// big endian pcapng with nanosecond resolution
+    fn pcapng(packets: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut file = vec![];
+        let mut section = vec![0x1A, 0x2B, 0x3C, 0x4D, 0, 1, 0, 0];
+        section.extend_from_slice(&[0xFF; 8]); // unknown section length
+        file.extend(pcapng_block(0x0A0D_0D0A, &section));
+        let mut interface = vec![0, 1, 0, 0, 0, 0, 0xFF, 0xFF];
+        interface.extend_from_slice(&[0, 9, 0, 1, 9, 0, 0, 0]); // if_tsresol = 10^-9
+        interface.extend_from_slice(&[0, 0, 0, 0]); // end of options
+        file.extend(pcapng_block(1, &interface));
+        // blocks we are not interested in are skipped
+        file.extend(pcapng_block(5, &[0u8; 12]));
+        for (ts, data) in packets {
+            let mut body = vec![];
+            body.write_u32::<BigEndian>(0).unwrap();
+            body.write_u32::<BigEndian>((ts >> 32) as u32).unwrap();
+            body.write_u32::<BigEndian>(*ts as u32).unwrap();
+            body.write_u32::<BigEndian>(data.len() as u32).unwrap();
+            body.write_u32::<BigEndian>(data.len() as u32).unwrap();
+            body.extend_from_slice(data);
+            file.extend(pcapng_block(6, &body));
+        }
+        file
+    }
+    fn extract_all(capture: &[u8]) -> Vec<dlt::Message> {
+        extract_from_ports(capture, DEFAULT_DLT_PORTS)
+    }
+    fn extract_from_ports(capture: &[u8], ports: &[u16]) -> Vec<dlt::Message> {
+        let mut reader = CaptureReader::new(capture).expect("invalid capture");
+        let mut extractor = MessageExtractor::new(None, None, ports);
+        let mut messages = vec![];
+        while let Some(packet) = reader.next_packet().expect("could not read packet") {
+            messages.extend(extractor.messages(&packet));
+        }
+        assert_eq!(capture.len(), reader.bytes_read());
+        messages
+    }
+
+    #[test]
+    fn test_udp_with_multiple_messages_per_datagram() {
+        let mut payload = message(0).as_bytes_without_storage_header();
+        payload.extend(message(1).as_bytes_without_storage_header());
+        let capture = pcap(&[(100, 5, udp(&payload)), (101, 6, udp(&[0x01, 0x02]))]);
+        assert_eq!(
+            vec![stored(message(0), 100, 5), stored(message(1), 100, 5)],
+            extract_all(&capture)
+        );
+    }
+
+    #[test]
+    fn test_only_dlt_ports_are_decoded() {
+        let payload = message(0).as_bytes_without_storage_header();
+        // e.g. dns, that happens to look like a dlt message
+        let capture = pcap(&[(100, 0, udp_to_port(53, &payload)), (101, 0, udp(&payload))]);
+        assert_eq!(vec![stored(message(0), 101, 0)], extract_all(&capture));
+        assert_eq!(
+            vec![stored(message(0), 100, 0)],
+            extract_from_ports(&capture, &[53])
+        );
+    }
+
+    #[test]
+    fn test_tcp_reassembly() {
+        let stream: Vec<u8> = (0..3)
+            .flat_map(|i| message(i).as_bytes_without_storage_header())
+            .collect();
+        let first_len = message(0).header.overall_length as usize;
+        let isn = 0xFFFF_FFF0u32; // sequence numbers wrap around
+        let seq = |offset: usize| isn.wrapping_add(1).wrapping_add(offset as u32);
+        let capture = pcap(&[
+            (1, 0, tcp(isn, 0x02, &[])), // SYN
+            (2, 0, tcp(seq(0), 0x18, &stream[..10])),
+            // out of order
+            (
+                3,
+                0,
+                tcp(seq(first_len + 5), 0x18, &stream[first_len + 5..]),
+            ),
+            // retransmission overlapping what we already have
+            (4, 0, tcp(seq(0), 0x18, &stream[..first_len + 5])),
+            (5, 0, tcp(seq(stream.len()), 0x11, &[])), // FIN
+        ]);
+        assert_eq!(
+            vec![
+                stored(message(0), 4, 0),
+                stored(message(1), 4, 0),
+                stored(message(2), 4, 0),
+            ],
+            extract_all(&capture)
+        );
+    }
+
+    #[test]
+    fn test_tcp_stream_with_corrupted_message() {
+        let mut stream = message(0).as_bytes_without_storage_header();
+        // standard header with a length that is too small for its headers
+        stream.extend_from_slice(&[0x35, 0x00, 0x00, 0x01]);
+        for i in 1..3 {
+            stream.extend(message(i).as_bytes_without_storage_header());
+        }
+        let capture = pcap(&[
+            (1, 0, tcp(0, 0x02, &[])), // SYN
+            (2, 0, tcp(1, 0x18, &stream)),
+        ]);
+        assert_eq!(
+            vec![
+                stored(message(0), 2, 0),
+                stored(message(1), 2, 0),
+                stored(message(2), 2, 0),
+            ],
+            extract_all(&capture)
+        );
+    }
+
+    #[test]
+    fn test_tcp_capture_starting_mid_stream() {
+        let stream: Vec<u8> = (0..2)
+            .flat_map(|i| message(i).as_bytes_without_storage_header())
+            .collect();
+        let capture = pcap(&[
+            (7, 0, tcp(5000, 0x18, &stream[..3])),
+            (8, 0, tcp(5003, 0x18, &stream[3..])),
+        ]);
+        assert_eq!(
+            vec![stored(message(0), 8, 0), stored(message(1), 8, 0)],
+            extract_all(&capture)
+        );
+    }
+
+    #[test]
+    fn test_pcapng_with_nanosecond_timestamps() {
+        let ts = 1_500_000_000u64 * 1_000_000_000 + 123_456_789;
+        let capture = pcapng(&[(ts, udp(&message(3).as_bytes_without_storage_header()))]);
+        assert_eq!(
+            vec![stored(message(3), 1_500_000_000, 123_456)],
+            extract_all(&capture)
+        );
+    }
+
+    #[test]
+    fn test_invalid_capture() {
+        assert!(CaptureReader::new(&b"DLT\x01 this is no capture"[..]).is_err());
+    }
+
+    #[test]
+    fn test_is_capture_file() {
+        assert!(is_capture_file(Path::new("trace.pcap")));
+        assert!(is_capture_file(Path::new("/tmp/trace.PCAPNG")));
+        assert!(!is_capture_file(Path::new("trace.dlt")));
+        assert!(!is_capture_file(Path::new("pcap")));
+    }
+
+    #[test]
+    fn test_index_pcap_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let capture_path = tmp_dir.path().join("trace.pcap");
+        let out_path = tmp_dir.path().join("trace.out");
+        let packets: Vec<(u32, u32, Vec<u8>)> = (0..5)
+            .map(|i| {
+                (
+                    100 + u32::from(i),
+                    0,
+                    udp(&message(i).as_bytes_without_storage_header()),
+                )
+            })
+            .collect();
+        fs::write(&capture_path, pcap(&packets)).expect("could not write capture");
+        let chunks = index_pcap_file(
+            IndexingConfig {
+                tag: "PCAP",
+                chunk_size: 2,
+                in_file: fs::File::open(&capture_path).expect("could not open capture"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+            DEFAULT_DLT_PORTS,
+        )
+        .expect("indexing failed");
+        assert_eq!(3, chunks.len());
+        let content = fs::read_to_string(&out_path).expect("could not read output");
+        assert_eq!(5, content.lines().count());
+        let expected_first_line = format!("{}", stored(message(0), 100, 0));
+        assert!(content.starts_with(&expected_first_line));
+    }
+}
@@ -9,6 +9,9 @@
 // Dissemination of this information or reproduction of this material
 // is strictly forbidden unless prior written permission is obtained
 // from E.S.R.Labs.
+use crate::index_map::IndexMapWriter;
+use crate::token_index::TokenIndexWriter;
+use failure::Error;
 use serde::{Deserialize, Serialize};
 use serde_json::Result;
 use std::fs;
@@ -31,6 +34,8 @@ pub struct ChunkFactory {
     last_line_current_chunk: usize,
     current_byte_index: usize,
     lines_in_chunk: usize,
+    index_writer: Option<IndexMapWriter>,
+    token_writer: Option<TokenIndexWriter>,
 }
 
 impl ChunkFactory {
@@ -46,6 +51,38 @@ impl ChunkFactory {
             current_byte_index: start_of_chunk_byte_index,
             last_line_current_chunk: 0,
             lines_in_chunk: 0,
+            index_writer: None,
+            token_writer: None,
+        }
+    }
+    /// also write all chunks (and line offsets) to an index map
+    pub fn with_index_writer(mut self, writer: IndexMapWriter) -> ChunkFactory {
+        self.index_writer = Some(writer);
+        self
+    }
+    /// also write the trigrams of every chunk to a token index
+    pub fn with_token_writer(mut self, writer: TokenIndexWriter) -> ChunkFactory {
+        self.token_writer = Some(writer);
+        self
+    }
+    /// true if the content of the lines has to be passed to `add_tokens`
+    pub fn indexes_tokens(&self) -> bool {
+        self.token_writer.is_some()
+    }
+    /// content of the next line (without tag and row number) for the token index
+    pub fn add_tokens(&mut self, content: &[u8]) {
+        if let Some(writer) = self.token_writer.as_mut() {
+            writer.add_line(content);
+        }
+    }
+    /// complete the index map and the token index, does nothing if none is written
+    pub fn finish_index(&mut self) -> std::result::Result<(), Error> {
+        if let Some(writer) = self.index_writer.take() {
+            writer.finish()?;
+        }
+        match self.token_writer.take() {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
         }
     }
     pub fn get_current_byte_index(&self) -> usize {
@@ -56,30 +93,47 @@ impl ChunkFactory {
         line_nr: usize,
         additional_bytes: usize,
     ) -> Option<Chunk> {
+        if let Some(writer) = self.index_writer.as_mut() {
+            writer.add_line(line_nr - 1, self.current_byte_index);
+        }
         self.current_byte_index += additional_bytes;
         self.lines_in_chunk += 1;
         // check if we need to construct a new mapping chunk
         if self.lines_in_chunk >= self.chunk_size {
-            self.last_line_current_chunk = line_nr;
-            let chunk = Chunk {
-                r: (
-                    self.last_line_current_chunk - self.lines_in_chunk,
-                    self.last_line_current_chunk - 1,
-                ),
-                b: (self.start_of_chunk_byte_index, self.current_byte_index),
-            };
-            if self.to_stdout {
-                if let Ok(c) = serde_json::to_string(&chunk) {
-                    println!("{}", c);
-                }
-            }
-
-            self.start_of_chunk_byte_index = self.current_byte_index + 1;
-            self.lines_in_chunk = 0;
-            return Some(chunk);
+            return Some(self.emit_chunk(line_nr));
         }
         None
     }
+    /// chunk for the lines added since the last chunk, the next lines start
+    /// a new one (used to report lines early when following a file)
+    pub fn create_pending_chunk(&mut self, line_nr: usize) -> Option<Chunk> {
+        if self.lines_in_chunk == 0 {
+            return None;
+        }
+        Some(self.emit_chunk(line_nr))
+    }
+    /// chunk for the lines since the last chunk up to `line_nr` (exclusive)
+    fn emit_chunk(&mut self, line_nr: usize) -> Chunk {
+        self.last_line_current_chunk = line_nr;
+        let chunk = Chunk {
+            r: (line_nr - self.lines_in_chunk, line_nr - 1),
+            b: (self.start_of_chunk_byte_index, self.current_byte_index),
+        };
+        if self.to_stdout {
+            if let Ok(c) = serde_json::to_string(&chunk) {
+                println!("{}", c);
+            }
+        }
+        if let Some(writer) = self.index_writer.as_mut() {
+            writer.add_chunk(&chunk);
+        }
+        if let Some(writer) = self.token_writer.as_mut() {
+            writer.add_chunk(&chunk);
+        }
+        self.start_of_chunk_byte_index = self.current_byte_index + 1;
+        self.lines_in_chunk = 0;
+        chunk
+    }
     pub fn create_last_chunk(&mut self, line_nr: usize, only_chunk: bool) -> Option<Chunk> {
         // only add junk if we produced any output lines
         if line_nr > 0 && self.start_of_chunk_byte_index != self.current_byte_index {
@@ -99,6 +153,12 @@ impl ChunkFactory {
                         serde_json::to_string(&chunk).expect("chunk could not be serialized")
                     );
                 }
+                if let Some(writer) = self.index_writer.as_mut() {
+                    writer.add_chunk(&chunk);
+                }
+                if let Some(writer) = self.token_writer.as_mut() {
+                    writer.add_chunk(&chunk);
+                }
                 return Some(chunk);
             }
         }
@@ -0,0 +1,211 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! transparent decompression of the files to index
+//!
+//! the format is detected by the magic bytes at the start of the file,
+//! gzip and xz files are decompressed while they are read. For zip archives
+//! a single entry is read (stored or deflated).
+use failure::{err_msg, Error};
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use xz2::read::XzDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZIP_MAGIC: &[u8] = &[b'P', b'K', 0x03, 0x04];
+const EMPTY_ZIP_MAGIC: &[u8] = &[b'P', b'K', 0x05, 0x06];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Zip,
+}
+
+/// detect the compression of `file` by its first bytes
+///
+/// the file is positioned at its start again afterwards
+pub fn detect_compression(file: &mut fs::File) -> Result<Option<Compression>, Error> {
+    let mut magic = [0u8; 6];
+    let mut read = 0;
+    while read < magic.len() {
+        match file.read(&mut magic[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    file.seek(SeekFrom::Start(0))?;
+    let magic = &magic[..read];
+    Ok(if magic.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if magic.starts_with(XZ_MAGIC) {
+        Some(Compression::Xz)
+    } else if magic.starts_with(ZIP_MAGIC) || magic.starts_with(EMPTY_ZIP_MAGIC) {
+        Some(Compression::Zip)
+    } else {
+        None
+    })
+}
+
+/// number of bytes read from the file so far
+#[derive(Debug, Clone, Default)]
+pub struct ConsumedBytes(Arc<AtomicUsize>);
+impl ConsumedBytes {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// counts the bytes that are read from the compressed file
+struct CountingReader<R> {
+    inner: R,
+    consumed: ConsumedBytes,
+}
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.consumed.0).fetch_add(n, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// the (decompressed) content of a file to index
+pub struct Input {
+    pub reader: Box<dyn Read + Send>,
+    pub progress: ReadProgress,
+}
+impl Input {
+    /// content that is read as it is
+    pub fn uncompressed(reader: Box<dyn Read + Send>) -> Input {
+        Input {
+            reader,
+            progress: ReadProgress {
+                compression: None,
+                consumed: ConsumedBytes::default(),
+            },
+        }
+    }
+}
+
+/// position in the file for progress reports
+#[derive(Debug, Clone)]
+pub struct ReadProgress {
+    pub compression: Option<Compression>,
+    consumed: ConsumedBytes,
+}
+impl ReadProgress {
+    /// for compressed files this is the number of compressed bytes consumed,
+    /// otherwise the processed bytes reported by the indexer
+    pub fn bytes(&self, processed_bytes: usize) -> usize {
+        match self.compression {
+            Some(_) => self.consumed.get(),
+            None => processed_bytes,
+        }
+    }
+}
+
+/// open the content of `file` for indexing
+///
+/// `zip_entry` selects the entry of a zip archive, it can be left out
+/// for archives with only one entry
+pub fn open_input(mut file: fs::File, zip_entry: Option<&str>) -> Result<Input, Error> {
+    let compression = detect_compression(&mut file)?;
+    let consumed = ConsumedBytes::default();
+    let reader: Box<dyn Read + Send> = match compression {
+        None => Box::new(file),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(CountingReader {
+            inner: file,
+            consumed: consumed.clone(),
+        })),
+        Some(Compression::Xz) => Box::new(XzDecoder::new_multi_decoder(CountingReader {
+            inner: file,
+            consumed: consumed.clone(),
+        })),
+        Some(Compression::Zip) => zip_entry_reader(file, zip_entry, &consumed)?,
+    };
+    Ok(Input {
+        reader,
+        progress: ReadProgress {
+            compression,
+            consumed,
+        },
+    })
+}
+
+/// names of the files in a zip archive (directories are left out)
+pub fn zip_entries(file: &fs::File) -> Result<Vec<String>, Error> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut names = vec![];
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if !entry.is_dir() {
+            names.push(entry.name().to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// reads the data of an entry directly from the file
+///
+/// the consumed bytes start at the offset of the entry so progress
+/// can be reported against the size of the whole archive
+fn zip_entry_reader(
+    mut file: fs::File,
+    zip_entry: Option<&str>,
+    consumed: &ConsumedBytes,
+) -> Result<Box<dyn Read + Send>, Error> {
+    let (data_start, compressed_size, method) = {
+        let mut archive = zip::ZipArchive::new(&file)?;
+        let name = match zip_entry {
+            Some(name) => name.to_string(),
+            None => {
+                let names = zip_entries(&file)?;
+                match names.as_slice() {
+                    [name] => name.clone(),
+                    [] => return Err(err_msg("zip archive contains no files")),
+                    _ => {
+                        return Err(err_msg(format!(
+                            "zip archive contains several files, choose one of: {}",
+                            names.join(", ")
+                        )))
+                    }
+                }
+            }
+        };
+        let entry = archive
+            .by_name(&name)
+            .map_err(|e| err_msg(format!("could not open {} in zip archive: {}", name, e)))?;
+        (
+            entry.data_start(),
+            entry.compressed_size(),
+            entry.compression(),
+        )
+    };
+    file.seek(SeekFrom::Start(data_start))?;
+    (consumed.0).store(data_start as usize, Ordering::Relaxed);
+    let data = CountingReader {
+        inner: file.take(compressed_size),
+        consumed: consumed.clone(),
+    };
+    match method {
+        zip::CompressionMethod::Stored => Ok(Box::new(data)),
+        zip::CompressionMethod::Deflated => Ok(Box::new(DeflateDecoder::new(data))),
+        other => Err(err_msg(format!(
+            "unsupported compression in zip archive: {:?}",
+            other
+        ))),
+    }
+}
@@ -9,6 +9,7 @@
 // Dissemination of this information or reproduction of this material
 // is strictly forbidden unless prior written permission is obtained
 // from E.S.R.Labs.
+use serde::{Deserialize, Serialize};
 use std::path;
 use std::fs;
 
@@ -17,7 +18,65 @@ pub struct IndexingConfig<'a> {
     pub tag: &'a str,
     pub chunk_size: usize,
     pub in_file: fs::File,
+    /// entry to index if `in_file` is a zip archive with more than one file
+    pub zip_entry: Option<&'a str>,
     pub out_path: &'a path::PathBuf,
     pub append: bool,
     pub to_stdout: bool,
+    /// also write a binary index map of the output
+    pub index_map: Option<IndexMapConfig<'a>>,
+    /// also write a token index of the output here (see `token_index`)
+    pub token_index: Option<&'a path::Path>,
+    /// keep indexing `in_file` while it grows, it is looked for at this path
+    /// again when it was rotated (see `follow`)
+    pub follow: Option<&'a path::Path>,
+}
+
+/// where to write the binary index map (see `index_map`)
+#[derive(Debug, Clone, Copy)]
+pub struct IndexMapConfig<'a> {
+    pub path: &'a path::Path,
+    /// include the byte offset of every line
+    pub line_offsets: bool,
+}
+
+/// inclusive time window (posix timestamps in ms)
+/// a missing bound leaves the window open on that side
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeWindow {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+impl TimeWindow {
+    pub fn contains(&self, time: i64) -> bool {
+        self.from.map_or(true, |from| time >= from) && !self.is_past_end(time)
+    }
+    /// true if `time` lies after the end of the window
+    pub fn is_past_end(&self, time: i64) -> bool {
+        self.to.map_or(false, |to| time > to)
+    }
+}
+
+/// detects when timestamps that have been ascending so far pass the end of
+/// a time window, from there on no later entry can fall into the window
+pub struct TimeWindowEnd {
+    end: i64,
+    last: Option<i64>,
+    ascending: bool,
+}
+impl TimeWindowEnd {
+    pub fn new(end: i64) -> TimeWindowEnd {
+        TimeWindowEnd {
+            end,
+            last: None,
+            ascending: true,
+        }
+    }
+    pub fn is_passed(&mut self, time: i64) -> bool {
+        if self.last.map_or(false, |last| time < last) {
+            self.ascending = false;
+        }
+        self.last = Some(time);
+        self.ascending && time > self.end
+    }
 }
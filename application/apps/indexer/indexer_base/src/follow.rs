@@ -0,0 +1,153 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! reading files that are still being written
+//!
+//! a `FollowReader` does not report the end of the file, a read that finds no
+//! new content fails with `io::ErrorKind::WouldBlock` instead. indexers flush
+//! what they have at that point, `wait` for the file to grow and read again.
+//! if the file was truncated or another file took its place (log rotation),
+//! reading continues from the start of the file that is now at the path. The
+//! read that switches files fails with a `WouldBlock` error as well, but one
+//! that `is_reopened`, so content that was cut off at the end of the old file
+//! is not joined with the start of the new one.
+use crate::compression;
+use failure::{err_msg, Error};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// how long to wait before looking for new content again
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// device and inode, used to detect that the file at the path was replaced
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+/// without inodes a rotation is only noticed if the new file is smaller
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// error payload of the read that switched to the file now at the path
+#[derive(Debug)]
+struct Reopened;
+impl fmt::Display for Reopened {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "reading continues with another file")
+    }
+}
+impl error::Error for Reopened {}
+
+pub struct FollowReader {
+    path: PathBuf,
+    file: fs::File,
+    id: Option<(u64, u64)>,
+    position: u64,
+}
+impl FollowReader {
+    /// follow `file` which was opened from `path`
+    ///
+    /// compressed files can not be followed
+    pub fn new(mut file: fs::File, path: &Path) -> Result<FollowReader, Error> {
+        if let Some(compression) = compression::detect_compression(&mut file)? {
+            return Err(err_msg(format!(
+                "can not follow {:?}, it is compressed ({:?})",
+                path, compression
+            )));
+        }
+        let id = file_id(&file.metadata()?);
+        Ok(FollowReader {
+            path: path.to_path_buf(),
+            file,
+            id,
+            position: 0,
+        })
+    }
+    /// start over if the file at the path is not the one we read anymore
+    ///
+    /// only called when all of the current file was read, so nothing that
+    /// was written before a rotation is lost
+    fn reopen_if_replaced(&mut self) -> io::Result<bool> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // moved away and not created again yet
+            Err(_) => return Ok(false),
+        };
+        let id = file_id(&metadata);
+        if id == self.id && metadata.len() >= self.position {
+            return Ok(false);
+        }
+        self.file = fs::File::open(&self.path)?;
+        self.id = file_id(&self.file.metadata()?);
+        self.position = 0;
+        Ok(true)
+    }
+}
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.file.read(buf)?;
+        if n > 0 {
+            self.position += n as u64;
+            return Ok(n);
+        }
+        if self.reopen_if_replaced()? {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, Reopened));
+        }
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+}
+
+/// open `file` (found at `path`) for indexing in follow mode
+pub fn open_input(file: fs::File, path: &Path) -> Result<compression::Input, Error> {
+    Ok(compression::Input::uncompressed(Box::new(
+        FollowReader::new(file, path)?,
+    )))
+}
+
+/// true if `e` only means that there is no new content yet
+pub fn is_waiting(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock
+}
+
+/// true if `e` means that the next read starts with another file, a line or
+/// message that was cut off at the end of the previous file is incomplete
+pub fn is_reopened(e: &io::Error) -> bool {
+    e.get_ref().map_or(false, |inner| inner.is::<Reopened>())
+}
+
+/// wait for new content, returns true if the shutdown channel fired
+///
+/// a disconnected channel counts as shutdown, without a channel this only
+/// sleeps
+pub fn wait(shutdown_receiver: Option<&mpsc::Receiver<()>>) -> bool {
+    match shutdown_receiver {
+        Some(rx) => match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(_) | Err(RecvTimeoutError::Disconnected) => true,
+            Err(RecvTimeoutError::Timeout) => false,
+        },
+        None => {
+            std::thread::sleep(POLL_INTERVAL);
+            false
+        }
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! binary, seekable replacement for the json chunk map
+//!
+//! layout (all numbers little endian):
+//!
+//! | bytes | content                                                  |
+//! |-------|----------------------------------------------------------|
+//! | 8     | magic `CHIPIDX\0`                                        |
+//! | 4     | format version                                           |
+//! | 4     | flags (bit 0: line offset table present)                 |
+//! | 24    | source fingerprint: size, modification time (ms), hash   |
+//! | 8     | number of chunks                                         |
+//! | 8     | number of line offsets                                   |
+//! | 8     | line number of the first line offset                     |
+//! | 32 * n| chunks: first row, last row, first byte, last byte       |
+//! | 8 * m | byte offset of each line in the output                   |
+use crate::chunks::Chunk;
+use crate::config::IndexMapConfig;
+use failure::{err_msg, Error};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+pub const MAGIC: &[u8; 8] = b"CHIPIDX\0";
+pub const VERSION: u32 = 1;
+pub const HEADER_LENGTH: u64 = 64;
+pub const CHUNK_RECORD_LENGTH: u64 = 32;
+const LINE_OFFSET_LENGTH: u64 = 8;
+const FLAG_LINE_OFFSETS: u32 = 1;
+/// number of bytes at the start of the source that go into the fingerprint
+const FINGERPRINT_HEAD_LENGTH: usize = 64 * 1024;
+
+/// identifies the file an index was created from
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SourceFingerprint {
+    pub size: u64,
+    /// modification time in ms since the epoch
+    pub modified: u64,
+    /// FNV-1a hash of the first bytes of the file
+    pub head_hash: u64,
+}
+impl SourceFingerprint {
+    /// fingerprint of `file`, which is positioned at its start afterwards
+    pub fn of_file(file: &fs::File) -> Result<SourceFingerprint, Error> {
+        let metadata = file.metadata()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut head = vec![];
+        let mut reader = file;
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .take(FINGERPRINT_HEAD_LENGTH as u64)
+            .read_to_end(&mut head)?;
+        reader.seek(SeekFrom::Start(0))?;
+        Ok(SourceFingerprint {
+            size: metadata.len(),
+            modified,
+            head_hash: fnv1a(&head),
+        })
+    }
+    pub fn of_path(path: &Path) -> Result<SourceFingerprint, Error> {
+        SourceFingerprint::of_file(&fs::File::open(path)?)
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    flags: u32,
+    fingerprint: SourceFingerprint,
+    chunk_count: u64,
+    line_count: u64,
+    first_line: u64,
+}
+impl Header {
+    fn has_line_offsets(&self) -> bool {
+        self.flags & FLAG_LINE_OFFSETS != 0
+    }
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.flags.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.size.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.modified.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.head_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.chunk_count.to_le_bytes());
+        bytes.extend_from_slice(&self.line_count.to_le_bytes());
+        bytes.extend_from_slice(&self.first_line.to_le_bytes());
+        bytes
+    }
+    fn read<R: Read>(reader: &mut R) -> Result<Header, Error> {
+        let mut bytes = [0u8; HEADER_LENGTH as usize];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|_| err_msg("index map too short"))?;
+        if &bytes[..8] != MAGIC {
+            return Err(err_msg("not a chipmunk index map"));
+        }
+        let u32_at = |pos: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&bytes[pos..pos + 4]);
+            u32::from_le_bytes(b)
+        };
+        let u64_at = |pos: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&bytes[pos..pos + 8]);
+            u64::from_le_bytes(b)
+        };
+        let version = u32_at(8);
+        if version != VERSION {
+            return Err(err_msg(format!(
+                "unsupported index map version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        Ok(Header {
+            flags: u32_at(12),
+            fingerprint: SourceFingerprint {
+                size: u64_at(16),
+                modified: u64_at(24),
+                head_hash: u64_at(32),
+            },
+            chunk_count: u64_at(40),
+            line_count: u64_at(48),
+            first_line: u64_at(56),
+        })
+    }
+    fn line_table_start(&self) -> u64 {
+        HEADER_LENGTH + self.chunk_count * CHUNK_RECORD_LENGTH
+    }
+}
+
+fn chunk_record(chunk: &Chunk) -> [u8; CHUNK_RECORD_LENGTH as usize] {
+    let mut record = [0u8; CHUNK_RECORD_LENGTH as usize];
+    let values = [chunk.r.0, chunk.r.1, chunk.b.0, chunk.b.1];
+    for (i, v) in values.iter().enumerate() {
+        record[i * 8..(i + 1) * 8].copy_from_slice(&(*v as u64).to_le_bytes());
+    }
+    record
+}
+
+fn read_u64s<R: Read>(reader: &mut R, values: &mut [u64]) -> io::Result<()> {
+    let mut b = [0u8; 8];
+    for v in values.iter_mut() {
+        reader.read_exact(&mut b)?;
+        *v = u64::from_le_bytes(b);
+    }
+    Ok(())
+}
+
+/// writes an index map while the output is produced
+///
+/// chunk records go straight to the index file, line offsets are collected
+/// in a temporary file and moved behind the records by `finish`
+pub struct IndexMapWriter {
+    path: PathBuf,
+    records: BufWriter<fs::File>,
+    lines: Option<BufWriter<fs::File>>,
+    lines_path: PathBuf,
+    header: Header,
+    /// first io error, reported by `finish`
+    error: Option<io::Error>,
+}
+impl IndexMapWriter {
+    pub fn create(
+        path: &Path,
+        fingerprint: SourceFingerprint,
+        line_offsets: bool,
+    ) -> Result<IndexMapWriter, Error> {
+        let header = Header {
+            flags: if line_offsets { FLAG_LINE_OFFSETS } else { 0 },
+            fingerprint,
+            chunk_count: 0,
+            line_count: 0,
+            first_line: 0,
+        };
+        let mut file = fs::File::create(path)?;
+        file.write_all(&header.as_bytes())?;
+        IndexMapWriter::with_file(path, file, header)
+    }
+    /// continue an existing index map (for output that is appended to)
+    ///
+    /// the line offsets are only kept if they were written before as well,
+    /// a missing index map is created
+    pub fn append(
+        path: &Path,
+        fingerprint: SourceFingerprint,
+        line_offsets: bool,
+    ) -> Result<IndexMapWriter, Error> {
+        if !path.exists() {
+            return IndexMapWriter::create(path, fingerprint, line_offsets);
+        }
+        let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+        let old_header = Header::read(&mut file)?;
+        let keep_lines =
+            line_offsets && (old_header.has_line_offsets() || old_header.chunk_count == 0);
+        let mut header = old_header;
+        header.fingerprint = fingerprint;
+        if keep_lines {
+            // set before the writer is created so that it gets a line table
+            header.flags |= FLAG_LINE_OFFSETS;
+        } else {
+            header.flags &= !FLAG_LINE_OFFSETS;
+        }
+        if !keep_lines || !old_header.has_line_offsets() {
+            header.line_count = 0;
+        }
+        let mut writer = IndexMapWriter::with_file(path, file, header)?;
+        if let Some(lines) = writer.lines.as_mut() {
+            let mut old = fs::File::open(path)?;
+            old.seek(SeekFrom::Start(old_header.line_table_start()))?;
+            io::copy(&mut old.take(header.line_count * LINE_OFFSET_LENGTH), lines)?;
+        }
+        let file = writer.records.get_mut();
+        file.set_len(old_header.line_table_start())?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(writer)
+    }
+    fn with_file(path: &Path, file: fs::File, header: Header) -> Result<IndexMapWriter, Error> {
+        let lines_path = path.with_extension("lines.tmp");
+        let lines = if header.has_line_offsets() {
+            Some(BufWriter::new(fs::File::create(&lines_path)?))
+        } else {
+            None
+        };
+        Ok(IndexMapWriter {
+            path: path.to_path_buf(),
+            records: BufWriter::new(file),
+            lines,
+            lines_path,
+            header,
+            error: None,
+        })
+    }
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        if self.error.is_none() {
+            match self.records.write_all(&chunk_record(chunk)) {
+                Ok(()) => self.header.chunk_count += 1,
+                Err(e) => self.error = Some(e),
+            }
+        }
+    }
+    /// byte offset of output line `line_nr`, lines have to be added in order
+    pub fn add_line(&mut self, line_nr: usize, offset: usize) {
+        if let (Some(lines), None) = (self.lines.as_mut(), &self.error) {
+            if self.header.line_count == 0 {
+                self.header.first_line = line_nr as u64;
+            }
+            match lines.write_all(&(offset as u64).to_le_bytes()) {
+                Ok(()) => self.header.line_count += 1,
+                Err(e) => self.error = Some(e),
+            }
+        }
+    }
+    /// move the line offsets behind the chunks and write the final header
+    pub fn finish(mut self) -> Result<(), Error> {
+        if let Some(e) = self.error.take() {
+            return Err(err_msg(format!(
+                "could not write index map {:?}: {}",
+                self.path, e
+            )));
+        }
+        if let Some(lines) = self.lines.take() {
+            lines.into_inner().map_err(|e| e.into_error())?;
+            io::copy(&mut fs::File::open(&self.lines_path)?, &mut self.records)?;
+            fs::remove_file(&self.lines_path)?;
+        }
+        self.records.flush()?;
+        let file = self.records.get_mut();
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&self.header.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// create a writer as configured, `source` is fingerprinted
+pub fn writer_for(
+    config: &IndexMapConfig,
+    source: &fs::File,
+    append: bool,
+) -> Result<IndexMapWriter, Error> {
+    let fingerprint = SourceFingerprint::of_file(source)?;
+    if append {
+        IndexMapWriter::append(config.path, fingerprint, config.line_offsets)
+    } else {
+        IndexMapWriter::create(config.path, fingerprint, config.line_offsets)
+    }
+}
+
+/// write all `chunks` as an index map
+pub fn write_index_map(
+    chunks: &[Chunk],
+    path: &Path,
+    fingerprint: SourceFingerprint,
+) -> Result<(), Error> {
+    let mut writer = IndexMapWriter::create(path, fingerprint, false)?;
+    for chunk in chunks {
+        writer.add_chunk(chunk);
+    }
+    writer.finish()
+}
+
+/// convert a json chunk map (as written by `serialize_chunks`)
+///
+/// the fingerprint is left empty if the source file is not given
+pub fn migrate_json_map(
+    json_path: &Path,
+    index_path: &Path,
+    source: Option<&Path>,
+) -> Result<usize, Error> {
+    let reader = BufReader::new(fs::File::open(json_path)?);
+    let chunks: Vec<Chunk> = serde_json::from_reader(reader)?;
+    let fingerprint = match source {
+        Some(path) => SourceFingerprint::of_path(path)?,
+        None => SourceFingerprint::default(),
+    };
+    write_index_map(&chunks, index_path, fingerprint)?;
+    Ok(chunks.len())
+}
+
+/// reads chunks and line offsets of an index map on demand
+pub struct IndexMap {
+    file: BufReader<fs::File>,
+    header: Header,
+}
+impl IndexMap {
+    pub fn open(path: &Path) -> Result<IndexMap, Error> {
+        let mut file = BufReader::new(fs::File::open(path)?);
+        let header = Header::read(&mut file)?;
+        let expected_len = header.line_table_start() + header.line_count * LINE_OFFSET_LENGTH;
+        if file.get_ref().metadata()?.len() < expected_len {
+            return Err(err_msg(format!("index map {:?} is truncated", path)));
+        }
+        Ok(IndexMap { file, header })
+    }
+    pub fn fingerprint(&self) -> SourceFingerprint {
+        self.header.fingerprint
+    }
+    /// true if the index map was created from the file at `source`
+    pub fn matches_source(&self, source: &Path) -> bool {
+        SourceFingerprint::of_path(source).map_or(false, |f| f == self.header.fingerprint)
+    }
+    pub fn chunk_count(&self) -> usize {
+        self.header.chunk_count as usize
+    }
+    pub fn has_line_offsets(&self) -> bool {
+        self.header.has_line_offsets()
+    }
+    pub fn chunk(&mut self, index: usize) -> Result<Option<Chunk>, Error> {
+        if index >= self.chunk_count() {
+            return Ok(None);
+        }
+        self.file.seek(SeekFrom::Start(
+            HEADER_LENGTH + index as u64 * CHUNK_RECORD_LENGTH,
+        ))?;
+        let mut values = [0u64; 4];
+        read_u64s(&mut self.file, &mut values)?;
+        Ok(Some(Chunk {
+            r: (values[0] as usize, values[1] as usize),
+            b: (values[2] as usize, values[3] as usize),
+        }))
+    }
+    /// all chunks in order
+    pub fn chunks(&mut self) -> Result<Vec<Chunk>, Error> {
+        let mut chunks = Vec::with_capacity(self.chunk_count());
+        for i in 0..self.chunk_count() {
+            if let Some(chunk) = self.chunk(i)? {
+                chunks.push(chunk);
+            }
+        }
+        Ok(chunks)
+    }
+    /// index of the chunk that contains `line_nr` (binary search over the records)
+    pub fn chunk_index_of_line(&mut self, line_nr: usize) -> Result<Option<usize>, Error> {
+        let (mut low, mut high) = (0usize, self.chunk_count());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let chunk = match self.chunk(mid)? {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            if line_nr < chunk.r.0 {
+                high = mid;
+            } else if line_nr > chunk.r.1 {
+                low = mid + 1;
+            } else {
+                return Ok(Some(mid));
+            }
+        }
+        Ok(None)
+    }
+    pub fn chunk_of_line(&mut self, line_nr: usize) -> Result<Option<Chunk>, Error> {
+        match self.chunk_index_of_line(line_nr)? {
+            Some(index) => self.chunk(index),
+            None => Ok(None),
+        }
+    }
+    /// byte offset of `line_nr` in the output
+    ///
+    /// exact if the line offset table is present, otherwise only known for
+    /// the first line of a chunk
+    pub fn byte_offset(&mut self, line_nr: usize) -> Result<Option<usize>, Error> {
+        let line_nr = line_nr as u64;
+        let header = self.header;
+        if header.has_line_offsets()
+            && line_nr >= header.first_line
+            && line_nr - header.first_line < header.line_count
+        {
+            self.file.seek(SeekFrom::Start(
+                header.line_table_start() + (line_nr - header.first_line) * LINE_OFFSET_LENGTH,
+            ))?;
+            let mut offset = [0u64];
+            read_u64s(&mut self.file, &mut offset)?;
+            return Ok(Some(offset[0] as usize));
+        }
+        // following chunks start one byte after the end of the previous one,
+        // the line itself starts right at that end
+        match self.chunk_index_of_line(line_nr as usize)? {
+            Some(0) => Ok(self
+                .chunk(0)?
+                .filter(|chunk| chunk.r.0 == line_nr as usize)
+                .map(|chunk| chunk.b.0)),
+            Some(index) => match (self.chunk(index)?, self.chunk(index - 1)?) {
+                (Some(chunk), Some(previous)) if chunk.r.0 == line_nr as usize => {
+                    Ok(Some(previous.b.1))
+                }
+                _ => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// read the chunks of a json or a binary map
+pub fn read_chunks(path: &Path) -> Result<Vec<Chunk>, Error> {
+    let mut file = fs::File::open(path)?;
+    let mut magic = [0u8; 8];
+    let is_binary = file.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+    if is_binary {
+        IndexMap::open(path)?.chunks()
+    } else {
+        file.seek(SeekFrom::Start(0))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
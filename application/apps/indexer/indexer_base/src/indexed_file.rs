@@ -0,0 +1,207 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! random access to the lines of an indexed output file
+//!
+//! every output line looks like
+//! `<content>\u{3}<tag>\u{3}\u{2}<row>\u{2}` and the processor adds
+//! `<timestamp>\u{2}` when it is asked to detect timestamps
+use crate::chunks::Chunk;
+use crate::index_map::{self, IndexMap};
+use crate::utils::{is_newline, PLUGIN_ID_SENTINAL, ROW_NUMBER_SENTINAL};
+use failure::{err_msg, Error};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// one line of the output split into its parts
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedLine {
+    pub content: String,
+    pub tag: String,
+    pub row: usize,
+    /// posix timestamp in ms, processor output has 0 if none was detected
+    pub timestamp: Option<i64>,
+}
+
+pub fn parse_line(line: &str) -> Result<IndexedLine, Error> {
+    let invalid = || err_msg(format!("not an indexed line: {:?}", line));
+    let line = line.trim_end_matches(is_newline);
+    let tag_end = line.rfind(PLUGIN_ID_SENTINAL).ok_or_else(invalid)?;
+    let tag_start = line[..tag_end]
+        .rfind(PLUGIN_ID_SENTINAL)
+        .ok_or_else(invalid)?;
+    let mut numbers = line[tag_end + 1..]
+        .trim_start_matches(ROW_NUMBER_SENTINAL)
+        .split(ROW_NUMBER_SENTINAL);
+    let row = numbers
+        .next()
+        .and_then(|row| row.parse().ok())
+        .ok_or_else(invalid)?;
+    let timestamp = match numbers.next() {
+        Some("") | None => None,
+        Some(ts) => Some(ts.parse().map_err(|_| invalid())?),
+    };
+    Ok(IndexedLine {
+        content: line[..tag_start].to_string(),
+        tag: line[tag_start + 1..tag_end].to_string(),
+        row,
+        timestamp,
+    })
+}
+
+/// an output file together with its chunk map
+pub struct IndexedFile {
+    reader: BufReader<fs::File>,
+    chunks: Vec<Chunk>,
+    /// only kept if it contains the offsets of all lines
+    index_map: Option<IndexMap>,
+}
+impl IndexedFile {
+    /// `map_path` can be a json chunk map or a binary index map
+    pub fn open(out_path: &Path, map_path: &Path) -> Result<IndexedFile, Error> {
+        let chunks = index_map::read_chunks(map_path)?;
+        let index_map = IndexMap::open(map_path)
+            .ok()
+            .filter(IndexMap::has_line_offsets);
+        Ok(IndexedFile {
+            reader: BufReader::new(fs::File::open(out_path)?),
+            chunks,
+            index_map,
+        })
+    }
+    pub fn with_chunks(out_path: &Path, chunks: Vec<Chunk>) -> Result<IndexedFile, Error> {
+        Ok(IndexedFile {
+            reader: BufReader::new(fs::File::open(out_path)?),
+            chunks,
+            index_map: None,
+        })
+    }
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+    /// line numbers covered by the chunk map
+    pub fn line_range(&self) -> Range<usize> {
+        match (self.chunks.first(), self.chunks.last()) {
+            (Some(first), Some(last)) => first.r.0..last.r.1 + 1,
+            _ => 0..0,
+        }
+    }
+    pub fn line_count(&self) -> usize {
+        let range = self.line_range();
+        range.end - range.start
+    }
+    pub fn get_line(&mut self, line_nr: usize) -> Result<Option<IndexedLine>, Error> {
+        self.lines_from(line_nr)?.next().transpose()
+    }
+    /// the lines of `range` that are part of the output
+    pub fn get_lines(&mut self, range: Range<usize>) -> Result<Vec<IndexedLine>, Error> {
+        let count = range.end.saturating_sub(range.start);
+        self.lines_from(range.start)?.take(count).collect()
+    }
+    pub fn lines(&mut self) -> Result<Lines<'_>, Error> {
+        let start = self.line_range().start;
+        self.lines_from(start)
+    }
+    /// all lines starting at `line_nr`
+    pub fn lines_from(&mut self, line_nr: usize) -> Result<Lines<'_>, Error> {
+        let end = self.line_range().end;
+        let (offset, skip) = match self.seek_position(line_nr)? {
+            Some(position) => position,
+            None => {
+                return Ok(Lines {
+                    reader: &mut self.reader,
+                    line_nr,
+                    end: line_nr,
+                    buf: vec![],
+                })
+            }
+        };
+        self.reader.seek(SeekFrom::Start(offset as u64))?;
+        let mut lines = Lines {
+            reader: &mut self.reader,
+            line_nr,
+            end,
+            buf: vec![],
+        };
+        for _ in 0..skip {
+            if !lines.read_line()? {
+                lines.end = line_nr;
+                break;
+            }
+        }
+        Ok(lines)
+    }
+    /// byte offset to start reading at and the number of lines to skip from there
+    fn seek_position(&mut self, line_nr: usize) -> Result<Option<(usize, usize)>, Error> {
+        if let Some(index_map) = self.index_map.as_mut() {
+            if let Some(offset) = index_map.byte_offset(line_nr)? {
+                return Ok(Some((offset, 0)));
+            }
+        }
+        let index = match self.chunks.binary_search_by(|chunk| {
+            if line_nr < chunk.r.0 {
+                Ordering::Greater
+            } else if line_nr > chunk.r.1 {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        }) {
+            Ok(index) => index,
+            Err(_) => return Ok(None),
+        };
+        // following chunks start one byte after the end of the previous one,
+        // their first line starts right at that end
+        let offset = match index {
+            0 => self.chunks[0].b.0,
+            _ => self.chunks[index - 1].b.1,
+        };
+        Ok(Some((offset, line_nr - self.chunks[index].r.0)))
+    }
+}
+
+/// consecutive lines of an `IndexedFile`
+pub struct Lines<'a> {
+    reader: &'a mut BufReader<fs::File>,
+    line_nr: usize,
+    end: usize,
+    buf: Vec<u8>,
+}
+impl<'a> Lines<'a> {
+    fn read_line(&mut self) -> Result<bool, Error> {
+        self.buf.clear();
+        Ok(self.reader.read_until(b'\n', &mut self.buf)? > 0)
+    }
+}
+impl<'a> Iterator for Lines<'a> {
+    type Item = Result<IndexedLine, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.line_nr >= self.end {
+            return None;
+        }
+        match self.read_line() {
+            Ok(true) => {
+                self.line_nr += 1;
+                Some(parse_line(&String::from_utf8_lossy(&self.buf)))
+            }
+            // output is shorter than its chunk map
+            Ok(false) => None,
+            Err(e) => {
+                self.end = self.line_nr;
+                Some(Err(e))
+            }
+        }
+    }
+}
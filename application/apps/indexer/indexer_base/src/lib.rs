@@ -10,10 +10,15 @@
 // is strictly forbidden unless prior written permission is obtained
 // from E.S.R.Labs.
 pub mod chunks;
+pub mod compression;
 pub mod config;
 pub mod error_reporter;
+pub mod follow;
+pub mod index_map;
+pub mod indexed_file;
 pub mod progress;
 pub mod timedline;
+pub mod token_index;
 pub mod utils;
 
 #[cfg(test)]
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use crate::compression::*;
+    use flate2::write::GzEncoder;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+    use xz2::write::XzEncoder;
+    use zip::write::FileOptions;
+
+    fn content(text: &str) -> Vec<u8> {
+        (0..2000)
+            .flat_map(|i| format!("{} line {}\n", text, i).into_bytes())
+            .collect()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(data).expect("could not compress");
+        encoder.finish().expect("could not compress")
+    }
+
+    fn xz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = XzEncoder::new(vec![], 6);
+        encoder.write_all(data).expect("could not compress");
+        encoder.finish().expect("could not compress")
+    }
+
+    fn zip_archive(path: &Path, entries: &[(&str, zip::CompressionMethod, &[u8])]) {
+        let mut writer =
+            zip::ZipWriter::new(fs::File::create(path).expect("could not create archive"));
+        writer
+            .add_directory("logs/", FileOptions::default())
+            .expect("could not add directory");
+        for (name, method, data) in entries {
+            writer
+                .start_file(*name, FileOptions::default().compression_method(*method))
+                .expect("could not add entry");
+            writer.write_all(data).expect("could not write entry");
+        }
+        writer.finish().expect("could not finish archive");
+    }
+
+    fn write(dir: &TempDir, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, data).expect("could not write file");
+        path
+    }
+
+    /// the decompressed content and the progress after reading all of it
+    fn read_input(path: &Path, zip_entry: Option<&str>) -> (Vec<u8>, usize) {
+        let file = fs::File::open(path).expect("could not open file");
+        let mut input = open_input(file, zip_entry).expect("could not open input");
+        let mut data = vec![];
+        input
+            .reader
+            .read_to_end(&mut data)
+            .expect("could not read input");
+        let progress = input.progress.bytes(data.len());
+        (data, progress)
+    }
+
+    fn detect(path: &Path) -> Option<Compression> {
+        let mut file = fs::File::open(path).expect("could not open file");
+        detect_compression(&mut file).expect("could not detect compression")
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let data = content("plain");
+        assert_eq!(None, detect(&write(&tmp_dir, "plain.log", &data)));
+        assert_eq!(None, detect(&write(&tmp_dir, "empty.log", b"")));
+        assert_eq!(
+            Some(Compression::Gzip),
+            detect(&write(&tmp_dir, "a.gz", &gzip(&data)))
+        );
+        assert_eq!(
+            Some(Compression::Xz),
+            detect(&write(&tmp_dir, "a.xz", &xz(&data)))
+        );
+        let zip_path = tmp_dir.path().join("a.zip");
+        zip_archive(
+            &zip_path,
+            &[("a.log", zip::CompressionMethod::Stored, &data)],
+        );
+        assert_eq!(Some(Compression::Zip), detect(&zip_path));
+    }
+
+    #[test]
+    fn test_plain_file_is_read_as_is() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let data = content("plain");
+        let (read, progress) = read_input(&write(&tmp_dir, "plain.log", &data), None);
+        assert_eq!(data, read);
+        assert_eq!(data.len(), progress);
+    }
+
+    #[test]
+    fn test_gzip_with_several_members() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let first = content("first");
+        let second = content("second");
+        let mut compressed = gzip(&first);
+        compressed.extend(gzip(&second));
+        let (read, progress) = read_input(&write(&tmp_dir, "a.gz", &compressed), None);
+        assert_eq!([first, second].concat(), read);
+        assert_eq!(compressed.len(), progress);
+    }
+
+    #[test]
+    fn test_xz() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let data = content("xz");
+        let compressed = xz(&data);
+        let (read, progress) = read_input(&write(&tmp_dir, "a.xz", &compressed), None);
+        assert_eq!(data, read);
+        assert_eq!(compressed.len(), progress);
+    }
+
+    #[test]
+    fn test_zip_entries() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let stored = content("stored");
+        let deflated = content("deflated");
+        let path = tmp_dir.path().join("logs.zip");
+        zip_archive(
+            &path,
+            &[
+                ("logs/stored.log", zip::CompressionMethod::Stored, &stored),
+                (
+                    "logs/deflated.log",
+                    zip::CompressionMethod::Deflated,
+                    &deflated,
+                ),
+            ],
+        );
+        let file = fs::File::open(&path).expect("could not open archive");
+        assert_eq!(
+            vec!["logs/stored.log", "logs/deflated.log"],
+            zip_entries(&file).expect("could not list entries")
+        );
+        assert_eq!(stored, read_input(&path, Some("logs/stored.log")).0);
+        let (read, progress) = read_input(&path, Some("logs/deflated.log"));
+        assert_eq!(deflated, read);
+        // the progress is the end of the entry in the archive
+        assert!(progress > stored.len());
+        assert!(progress < fs::metadata(&path).expect("no metadata").len() as usize);
+
+        let open = |entry| open_input(fs::File::open(&path).expect("could not open"), entry);
+        assert!(open(None).is_err());
+        assert!(open(Some("missing.log")).is_err());
+    }
+
+    #[test]
+    fn test_zip_with_single_entry() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let data = content("single");
+        let path = tmp_dir.path().join("single.zip");
+        zip_archive(
+            &path,
+            &[("single.log", zip::CompressionMethod::Deflated, &data)],
+        );
+        assert_eq!(data, read_input(&path, None).0);
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunks::ChunkFactory;
+    use crate::follow::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::Path;
+    use std::sync::mpsc;
+    use tempdir::TempDir;
+
+    /// everything that can be read until the reader has to wait,
+    /// a switch to another file is marked with "|"
+    fn read_available(reader: &mut FollowReader) -> String {
+        let mut content = vec![];
+        let mut buf = [0u8; 4];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(n) => content.extend_from_slice(&buf[..n]),
+                Err(ref e) if is_reopened(e) => content.push(b'|'),
+                Err(ref e) if is_waiting(e) => break,
+                Err(e) => panic!("could not read: {}", e),
+            }
+        }
+        String::from_utf8(content).expect("not utf8")
+    }
+
+    fn append(path: &Path, content: &str) {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .expect("could not open file")
+            .write_all(content.as_bytes())
+            .expect("could not append");
+    }
+
+    #[test]
+    fn test_follow_growing_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.log");
+        fs::write(&path, "one\n").expect("could not write file");
+        let file = fs::File::open(&path).expect("could not open file");
+        let mut reader = FollowReader::new(file, &path).expect("could not follow");
+        assert_eq!("one\n", read_available(&mut reader));
+        assert_eq!("", read_available(&mut reader));
+        append(&path, "two\nthr");
+        assert_eq!("two\nthr", read_available(&mut reader));
+        append(&path, "ee\n");
+        assert_eq!("ee\n", read_available(&mut reader));
+    }
+
+    #[test]
+    fn test_follow_truncated_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.log");
+        fs::write(&path, "one\ntwo\n").expect("could not write file");
+        let file = fs::File::open(&path).expect("could not open file");
+        let mut reader = FollowReader::new(file, &path).expect("could not follow");
+        assert_eq!("one\ntwo\n", read_available(&mut reader));
+        fs::write(&path, "3\n").expect("could not truncate file");
+        assert_eq!("|3\n", read_available(&mut reader));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotated_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.log");
+        let rotated = tmp_dir.path().join("test.log.1");
+        fs::write(&path, "one\n").expect("could not write file");
+        let file = fs::File::open(&path).expect("could not open file");
+        let mut reader = FollowReader::new(file, &path).expect("could not follow");
+        assert_eq!("one\n", read_available(&mut reader));
+
+        fs::rename(&path, &rotated).expect("could not rotate");
+        // written before the new file was created
+        append(&rotated, "two\n");
+        assert_eq!("two\n", read_available(&mut reader));
+        // a new file that is bigger than what was read so far
+        fs::write(&path, "three\nfour\nfive\n").expect("could not write file");
+        append(&rotated, "late\n");
+        assert_eq!("late\n|three\nfour\nfive\n", read_available(&mut reader));
+        append(&path, "six\n");
+        assert_eq!("six\n", read_available(&mut reader));
+    }
+
+    #[test]
+    fn test_compressed_files_are_not_followed() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.log.gz");
+        fs::write(&path, [0x1f, 0x8b, 0, 0]).expect("could not write file");
+        let file = fs::File::open(&path).expect("could not open file");
+        assert!(FollowReader::new(file, &path).is_err());
+    }
+
+    #[test]
+    fn test_wait_for_shutdown() {
+        let (tx, rx) = mpsc::channel();
+        assert!(!wait(Some(&rx)));
+        tx.send(()).expect("could not send");
+        assert!(wait(Some(&rx)));
+        drop(tx);
+        assert!(wait(Some(&rx)));
+    }
+
+    #[test]
+    fn test_pending_chunks() {
+        let mut factory = ChunkFactory::new(3, false, 0);
+        assert!(factory.create_pending_chunk(0).is_none());
+        assert!(factory.create_chunk_if_needed(1, 10).is_none());
+        assert!(factory.create_chunk_if_needed(2, 10).is_none());
+        let pending = factory.create_pending_chunk(2).expect("no pending chunk");
+        assert_eq!(((0, 1), (0, 20)), (pending.r, pending.b));
+        assert!(factory.create_pending_chunk(2).is_none());
+        // the next chunk starts after the pending one
+        assert!(factory.create_chunk_if_needed(3, 10).is_none());
+        assert!(factory.create_chunk_if_needed(4, 10).is_none());
+        let full = factory
+            .create_chunk_if_needed(5, 10)
+            .expect("no full chunk");
+        assert_eq!(((2, 4), (21, 50)), (full.r, full.b));
+        assert!(factory.create_last_chunk(5, false).is_none());
+    }
+}
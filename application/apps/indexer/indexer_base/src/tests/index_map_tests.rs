@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunks::{serialize_chunks, Chunk, ChunkFactory};
+    use crate::index_map::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    /// feeds lines of varying length through a chunk factory that writes an index map,
+    /// returns the chunks and the offsets of all lines
+    fn index_lines(
+        writer: IndexMapWriter,
+        first_line: usize,
+        start_byte: usize,
+        line_count: usize,
+    ) -> (Vec<Chunk>, Vec<usize>) {
+        let mut factory = ChunkFactory::new(3, false, start_byte).with_index_writer(writer);
+        let mut chunks = vec![];
+        let mut offsets = vec![];
+        let mut line_nr = first_line;
+        for i in 0..line_count {
+            offsets.push(factory.get_current_byte_index());
+            line_nr += 1;
+            if let Some(chunk) = factory.create_chunk_if_needed(line_nr, 10 + i % 7) {
+                chunks.push(chunk);
+            }
+        }
+        if let Some(chunk) = factory.create_last_chunk(line_nr, chunks.is_empty()) {
+            chunks.push(chunk);
+        }
+        factory.finish_index().expect("could not finish index map");
+        (chunks, offsets)
+    }
+
+    fn fingerprint(size: u64) -> SourceFingerprint {
+        SourceFingerprint {
+            size,
+            modified: 1_568_000_000_000,
+            head_hash: 42,
+        }
+    }
+
+    fn assert_same_chunks(expected: &[Chunk], actual: &[Chunk]) {
+        let ranges = |chunks: &[Chunk]| -> Vec<((usize, usize), (usize, usize))> {
+            chunks.iter().map(|c| (c.r, c.b)).collect()
+        };
+        assert_eq!(ranges(expected), ranges(actual));
+    }
+
+    #[test]
+    fn test_written_by_chunk_factory() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.idx");
+        let writer = IndexMapWriter::create(&path, fingerprint(100), true).expect("no writer");
+        let (chunks, offsets) = index_lines(writer, 0, 0, 10);
+
+        let mut index_map = IndexMap::open(&path).expect("could not open index map");
+        assert_eq!(fingerprint(100), index_map.fingerprint());
+        assert!(index_map.has_line_offsets());
+        assert_eq!(4, index_map.chunk_count());
+        assert_same_chunks(&chunks, &index_map.chunks().expect("no chunks"));
+        for (line_nr, offset) in offsets.iter().enumerate() {
+            assert_eq!(
+                Some(*offset),
+                index_map.byte_offset(line_nr).expect("lookup failed")
+            );
+        }
+        assert_eq!(None, index_map.byte_offset(10).expect("lookup failed"));
+    }
+
+    #[test]
+    fn test_lookup_without_line_offsets() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.idx");
+        let writer = IndexMapWriter::create(&path, fingerprint(100), false).expect("no writer");
+        let (chunks, offsets) = index_lines(writer, 0, 0, 100);
+
+        let mut index_map = IndexMap::open(&path).expect("could not open index map");
+        assert!(!index_map.has_line_offsets());
+        for (line_nr, offset) in offsets.iter().enumerate() {
+            let index = index_map
+                .chunk_index_of_line(line_nr)
+                .expect("lookup failed")
+                .expect("line not found");
+            assert_eq!(line_nr / 3, index);
+            let expected_offset = if chunks[index].r.0 == line_nr {
+                Some(*offset)
+            } else {
+                None
+            };
+            assert_eq!(
+                expected_offset,
+                index_map.byte_offset(line_nr).expect("lookup failed")
+            );
+        }
+        assert!(index_map
+            .chunk_of_line(100)
+            .expect("lookup failed")
+            .is_none());
+    }
+
+    #[test]
+    fn test_append() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.idx");
+        let writer = IndexMapWriter::create(&path, fingerprint(1), true).expect("no writer");
+        let (mut chunks, mut offsets) = index_lines(writer, 0, 0, 7);
+        let out_size = chunks.last().expect("no chunks").b.1;
+
+        let writer = IndexMapWriter::append(&path, fingerprint(2), true).expect("no writer");
+        let (appended_chunks, appended_offsets) = index_lines(writer, 7, out_size, 5);
+        chunks.extend(appended_chunks);
+        offsets.extend(appended_offsets);
+
+        let mut index_map = IndexMap::open(&path).expect("could not open index map");
+        assert_eq!(fingerprint(2), index_map.fingerprint());
+        assert_same_chunks(&chunks, &index_map.chunks().expect("no chunks"));
+        for (line_nr, offset) in offsets.iter().enumerate() {
+            assert_eq!(
+                Some(*offset),
+                index_map.byte_offset(line_nr).expect("lookup failed")
+            );
+        }
+        assert!(!tmp_dir.path().join("test.map.lines.tmp").exists());
+    }
+
+    #[test]
+    fn test_append_drops_incomplete_line_offsets() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.idx");
+        let writer = IndexMapWriter::create(&path, fingerprint(1), false).expect("no writer");
+        let (chunks, _) = index_lines(writer, 0, 0, 7);
+        let out_size = chunks.last().expect("no chunks").b.1;
+        let writer = IndexMapWriter::append(&path, fingerprint(2), true).expect("no writer");
+        index_lines(writer, 7, out_size, 5);
+
+        let index_map = IndexMap::open(&path).expect("could not open index map");
+        assert!(!index_map.has_line_offsets());
+        assert_eq!(5, index_map.chunk_count());
+    }
+
+    #[test]
+    fn test_append_line_offsets_to_empty_map() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.idx");
+        IndexMapWriter::create(&path, fingerprint(1), false)
+            .expect("no writer")
+            .finish()
+            .expect("could not finish index map");
+        let writer = IndexMapWriter::append(&path, fingerprint(2), true).expect("no writer");
+        let (chunks, offsets) = index_lines(writer, 0, 0, 5);
+
+        let mut index_map = IndexMap::open(&path).expect("could not open index map");
+        assert!(index_map.has_line_offsets());
+        assert_same_chunks(&chunks, &index_map.chunks().expect("no chunks"));
+        for (line_nr, offset) in offsets.iter().enumerate() {
+            assert_eq!(
+                Some(*offset),
+                index_map.byte_offset(line_nr).expect("lookup failed")
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate_json_map() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let source = tmp_dir.path().join("test.log");
+        fs::write(&source, "some\nlog\nlines\n").expect("could not write source");
+        let json_path = tmp_dir.path().join("test.log.map.json");
+        let index_path = tmp_dir.path().join("test.log.map.idx");
+        let chunks = vec![
+            Chunk {
+                r: (0, 499),
+                b: (0, 41_234),
+            },
+            Chunk {
+                r: (500, 612),
+                b: (41_235, 50_001),
+            },
+        ];
+        serialize_chunks(&chunks, &json_path).expect("could not write json map");
+
+        assert_eq!(
+            2,
+            migrate_json_map(&json_path, &index_path, Some(&source)).expect("migration failed")
+        );
+        assert_same_chunks(&chunks, &read_chunks(&json_path).expect("no json chunks"));
+        assert_same_chunks(
+            &chunks,
+            &read_chunks(&index_path).expect("no binary chunks"),
+        );
+
+        let mut index_map = IndexMap::open(&index_path).expect("could not open index map");
+        assert!(index_map.matches_source(&source));
+        assert_eq!(
+            Some(41_234),
+            index_map.byte_offset(500).expect("lookup failed")
+        );
+        fs::write(&source, "other\nlog\nlines\n").expect("could not write source");
+        assert!(!index_map.matches_source(&source));
+    }
+
+    #[test]
+    fn test_invalid_index_maps() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let open = |name: &str, content: &[u8]| {
+            let path = tmp_dir.path().join(name);
+            fs::write(&path, content).expect("could not write index map");
+            IndexMap::open(Path::new(&path))
+        };
+        assert!(open("empty.idx", b"").is_err());
+        assert!(open("json.idx", b"[{\"r\":[0,1],\"b\":[0,10]}]").is_err());
+
+        let path = tmp_dir.path().join("valid.idx");
+        let writer = IndexMapWriter::create(&path, fingerprint(1), true).expect("no writer");
+        index_lines(writer, 0, 0, 7);
+        let valid = fs::read(&path).expect("could not read index map");
+
+        let mut other_version = valid.clone();
+        other_version[8] = 99;
+        assert!(open("version.idx", &other_version).is_err());
+        assert!(open("truncated.idx", &valid[..valid.len() - 1]).is_err());
+        assert!(open("valid_copy.idx", &valid).is_ok());
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunks::{Chunk, ChunkFactory};
+    use crate::indexed_file::*;
+    use crate::utils;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn line(content: &str, tag: &str, row: usize, timestamp: Option<i64>) -> IndexedLine {
+        IndexedLine {
+            content: content.to_string(),
+            tag: tag.to_string(),
+            row,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            line("some content", "TAG", 12, None),
+            parse_line("some content\u{3}TAG\u{3}\u{2}12\u{2}\n").expect("not parsed")
+        );
+        assert_eq!(
+            line("2019-07-30 09:44:09 x", "TAG", 0, Some(1_564_479_849_000)),
+            parse_line("2019-07-30 09:44:09 x\u{3}TAG\u{3}\u{2}0\u{2}1564479849000\u{2}")
+                .expect("not parsed")
+        );
+        // the content itself may contain the separators of dlt messages
+        assert_eq!(
+            line("a\u{4}b\u{5}c", "dlt", 3, None),
+            parse_line("a\u{4}b\u{5}c\u{3}dlt\u{3}\u{2}3\u{2}").expect("not parsed")
+        );
+        assert!(parse_line("no sentinels at all").is_err());
+        assert!(parse_line("content\u{3}TAG\u{3}\u{2}x\u{2}").is_err());
+        assert!(parse_line("content\u{3}TAG\u{3}\u{2}1\u{2}ts\u{2}").is_err());
+    }
+
+    /// writes `count` tagged lines and returns their chunks
+    fn write_output(path: &std::path::Path, count: usize) -> Vec<Chunk> {
+        let mut out = vec![];
+        let mut factory = ChunkFactory::new(4, false, 0);
+        let mut chunks = vec![];
+        for i in 0..count {
+            let len = utils::create_tagged_line("T", &mut out, &format!("line {}", i), i, true)
+                .expect("could not write line");
+            if let Some(chunk) = factory.create_chunk_if_needed(i + 1, len) {
+                chunks.push(chunk);
+            }
+        }
+        chunks.extend(factory.create_last_chunk(count, chunks.is_empty()));
+        fs::write(path, out).expect("could not write output");
+        chunks
+    }
+
+    #[test]
+    fn test_get_lines() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("test.out");
+        let chunks = write_output(&out_path, 10);
+        let mut file = IndexedFile::with_chunks(&out_path, chunks).expect("could not open");
+        assert_eq!(0..10, file.line_range());
+        for i in (0..10).rev() {
+            assert_eq!(
+                Some(line(&format!("line {}", i), "T", i, None)),
+                file.get_line(i).expect("could not read line")
+            );
+        }
+        assert_eq!(None, file.get_line(10).expect("could not read line"));
+        let rows =
+            |lines: Vec<IndexedLine>| -> Vec<usize> { lines.iter().map(|l| l.row).collect() };
+        assert_eq!(
+            vec![3, 4, 5, 6],
+            rows(file.get_lines(3..7).expect("could not read lines"))
+        );
+        assert_eq!(
+            vec![8, 9],
+            rows(file.get_lines(8..20).expect("could not read lines"))
+        );
+        let all: Vec<IndexedLine> = file
+            .lines()
+            .expect("no lines")
+            .collect::<Result<_, _>>()
+            .expect("could not read lines");
+        assert_eq!((0..10).collect::<Vec<usize>>(), rows(all));
+    }
+
+    #[test]
+    fn test_output_shorter_than_chunks() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let out_path = tmp_dir.path().join("test.out");
+        let chunks = write_output(&out_path, 10);
+        write_output(&out_path, 6);
+        let mut file = IndexedFile::with_chunks(&out_path, chunks).expect("could not open");
+        assert_eq!(6, file.lines().expect("no lines").count());
+        assert_eq!(None, file.get_line(7).expect("could not read line"));
+    }
+}
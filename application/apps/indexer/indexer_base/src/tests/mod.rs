@@ -1,2 +1,7 @@
 #[macro_use]
 mod utils_tests;
+mod compression_tests;
+mod follow_tests;
+mod index_map_tests;
+mod indexed_file_tests;
+mod token_index_tests;
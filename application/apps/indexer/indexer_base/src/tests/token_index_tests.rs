@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunks::{Chunk, ChunkFactory};
+    use crate::index_map::SourceFingerprint;
+    use crate::token_index::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempdir::TempDir;
+
+    /// feeds `lines` through a chunk factory that writes a token index
+    fn index_lines(writer: TokenIndexWriter, start_byte: usize, lines: &[&str]) -> Vec<Chunk> {
+        let mut factory = ChunkFactory::new(2, false, start_byte).with_token_writer(writer);
+        let mut chunks = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            factory.add_tokens(line.as_bytes());
+            if let Some(chunk) = factory.create_chunk_if_needed(i + 1, line.len() + 1) {
+                chunks.push(chunk);
+            }
+        }
+        if let Some(chunk) = factory.create_last_chunk(lines.len(), chunks.is_empty()) {
+            chunks.push(chunk);
+        }
+        factory
+            .finish_index()
+            .expect("could not finish token index");
+        chunks
+    }
+
+    fn fingerprint(size: u64) -> SourceFingerprint {
+        SourceFingerprint {
+            size,
+            modified: 1_568_000_000_000,
+            head_hash: 42,
+        }
+    }
+
+    fn writer_for_test(path: &std::path::Path, append: bool) -> TokenIndexWriter {
+        if append {
+            TokenIndexWriter::append(path, fingerprint(1), 256).expect("no writer")
+        } else {
+            TokenIndexWriter::create(path, fingerprint(1), 256).expect("no writer")
+        }
+    }
+
+    const LINES: [&str; 5] = [
+        "connection established",
+        "sending request",
+        "Request TIMEOUT after 30s",
+        "connection closed",
+        "shutting down",
+    ];
+
+    #[test]
+    fn test_candidate_chunks() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.tokens");
+        let chunks = index_lines(writer_for_test(&path, false), 0, &LINES);
+        assert_eq!(3, chunks.len());
+
+        let mut index = TokenIndex::open(&path).expect("could not open token index");
+        assert_eq!(fingerprint(1), index.fingerprint());
+        assert!(index.matches_output(&chunks, chunks[2].b.1 as u64));
+        assert!(!index.matches_output(&chunks, chunks[2].b.1 as u64 + 1));
+        assert!(!index.matches_output(&chunks[..2], chunks[2].b.1 as u64));
+
+        let mut candidates = |terms: &[&str]| {
+            let alternatives: Vec<Vec<u32>> =
+                terms.iter().map(|t| trigrams(t.as_bytes())).collect();
+            index
+                .candidate_chunks(&alternatives)
+                .expect("lookup failed")
+        };
+        assert_eq!(vec![1], candidates(&["timeout"]));
+        // ascii letters are folded
+        assert_eq!(vec![0, 1], candidates(&["REQUEST"]));
+        assert_eq!(vec![0, 1], candidates(&["connection"]));
+        assert_eq!(vec![1, 2], candidates(&["timeout", "shutting"]));
+        assert_eq!(Vec::<usize>::new(), candidates(&["no such thing"]));
+        // nothing known about the query, all chunks have to be searched
+        assert_eq!(vec![0, 1, 2], candidates(&["xx"]));
+        assert_eq!(vec![0, 1, 2], candidates(&["no such thing", ""]));
+    }
+
+    #[test]
+    fn test_append() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.tokens");
+        let mut chunks = index_lines(writer_for_test(&path, false), 0, &LINES[..3]);
+        let out_size = chunks.last().expect("no chunks").b.1;
+
+        // filter length of the existing index is kept
+        let writer = TokenIndexWriter::append(&path, fingerprint(2), 4096).expect("no writer");
+        chunks.extend(index_lines(writer, out_size, &LINES[3..]));
+
+        let mut index = TokenIndex::open(&path).expect("could not open token index");
+        assert_eq!(fingerprint(2), index.fingerprint());
+        assert_eq!(3, index.chunk_count());
+        let out_size = chunks.last().expect("no chunks").b.1 as u64;
+        assert!(index.matches_output(&chunks, out_size));
+        assert_eq!(
+            vec![2],
+            index
+                .candidate_chunks(&[trigrams(b"shutting")])
+                .expect("lookup failed")
+        );
+        assert_eq!(
+            (HEADER_LENGTH + 3 * 256) as usize,
+            fs::metadata(&path).expect("no token index").len() as usize
+        );
+    }
+
+    #[test]
+    fn test_append_replaces_invalid_index() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("test.map.tokens");
+        fs::write(&path, b"garbage").expect("could not write token index");
+        let chunks = index_lines(writer_for_test(&path, true), 0, &LINES);
+        let index = TokenIndex::open(&path).expect("could not open token index");
+        assert!(index.matches_output(&chunks, chunks[2].b.1 as u64));
+    }
+
+    #[test]
+    fn test_invalid_token_indices() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let path = tmp_dir.path().join("valid.tokens");
+        index_lines(writer_for_test(&path, false), 0, &LINES);
+        let valid = fs::read(&path).expect("could not read token index");
+        let open = |name: &str, content: &[u8]| {
+            let path = tmp_dir.path().join(name);
+            fs::write(&path, content).expect("could not write token index");
+            TokenIndex::open(&path)
+        };
+        let mut other_version = valid.clone();
+        other_version[8] = 99;
+        assert!(open("version.tokens", &other_version).is_err());
+        assert!(open("truncated.tokens", &valid[..valid.len() - 1]).is_err());
+        assert!(open("empty.tokens", b"").is_err());
+        assert!(open("valid_copy.tokens", &valid).is_ok());
+    }
+
+    #[test]
+    fn test_filter_length_for() {
+        assert_eq!(256, filter_length_for(1));
+        assert_eq!(4096, filter_length_for(500));
+        assert_eq!(64 * 1024, filter_length_for(1_000_000));
+    }
+}
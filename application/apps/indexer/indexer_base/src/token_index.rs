@@ -0,0 +1,323 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! trigrams of every chunk of the output, lets searches skip chunks
+//!
+//! each chunk gets a bloom filter of the (ascii lowercased) trigrams of the
+//! content of its lines, a chunk can only contain a match if all trigrams
+//! that the match requires are in its filter. the file lives next to the
+//! chunk map (see `path_for`).
+//!
+//! layout (all numbers little endian):
+//!
+//! | bytes | content                                                  |
+//! |-------|----------------------------------------------------------|
+//! | 8     | magic `CHIPTOK\0`                                        |
+//! | 4     | format version                                           |
+//! | 4     | length of one filter in bytes                            |
+//! | 24    | source fingerprint: size, modification time (ms), hash   |
+//! | 8     | number of chunks                                         |
+//! | 8     | size of the output when the last chunk was added         |
+//! | f * n | one filter per chunk                                     |
+use crate::chunks::Chunk;
+use crate::index_map::SourceFingerprint;
+use failure::{err_msg, Error};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const MAGIC: &[u8; 8] = b"CHIPTOK\0";
+pub const VERSION: u32 = 1;
+pub const HEADER_LENGTH: u64 = 56;
+const MIN_FILTER_LENGTH: usize = 256;
+const MAX_FILTER_LENGTH: usize = 64 * 1024;
+
+/// where the token index for the chunk map at `map_path` is stored
+pub fn path_for(map_path: &Path) -> PathBuf {
+    map_path.with_extension("tokens")
+}
+
+/// filter length that keeps false positives low for chunks of `chunk_size` lines
+pub fn filter_length_for(chunk_size: usize) -> usize {
+    (chunk_size * 8)
+        .clamp(MIN_FILTER_LENGTH, MAX_FILTER_LENGTH)
+        .next_power_of_two()
+}
+
+fn trigram_at(window: &[u8]) -> u32 {
+    window.iter().fold(0u32, |trigram, b| {
+        (trigram << 8) | u32::from(b.to_ascii_lowercase())
+    })
+}
+
+/// all distinct trigrams of `text`, ascii letters are lowercased
+pub fn trigrams(text: &[u8]) -> Vec<u32> {
+    let mut trigrams: Vec<u32> = text.windows(3).map(trigram_at).collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+/// the two bits of the filter that are set for `trigram`
+fn bit_positions(trigram: u32, filter_bits: usize) -> [usize; 2] {
+    let hash = u64::from(trigram).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    [
+        (hash >> 32) as usize % filter_bits,
+        (hash & 0xffff_ffff) as usize % filter_bits,
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    filter_length: u32,
+    fingerprint: SourceFingerprint,
+    chunk_count: u64,
+    out_size: u64,
+}
+impl Header {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.filter_length.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.size.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.modified.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.head_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.chunk_count.to_le_bytes());
+        bytes.extend_from_slice(&self.out_size.to_le_bytes());
+        bytes
+    }
+    fn read<R: Read>(reader: &mut R) -> Result<Header, Error> {
+        let mut bytes = [0u8; HEADER_LENGTH as usize];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|_| err_msg("token index too short"))?;
+        if &bytes[..8] != MAGIC {
+            return Err(err_msg("not a chipmunk token index"));
+        }
+        let u32_at = |pos: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&bytes[pos..pos + 4]);
+            u32::from_le_bytes(b)
+        };
+        let u64_at = |pos: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&bytes[pos..pos + 8]);
+            u64::from_le_bytes(b)
+        };
+        let version = u32_at(8);
+        if version != VERSION {
+            return Err(err_msg(format!(
+                "unsupported token index version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        let filter_length = u32_at(12);
+        if filter_length == 0 {
+            return Err(err_msg("token index without filters"));
+        }
+        Ok(Header {
+            filter_length,
+            fingerprint: SourceFingerprint {
+                size: u64_at(16),
+                modified: u64_at(24),
+                head_hash: u64_at(32),
+            },
+            chunk_count: u64_at(40),
+            out_size: u64_at(48),
+        })
+    }
+    fn file_length(&self) -> u64 {
+        HEADER_LENGTH + self.chunk_count * u64::from(self.filter_length)
+    }
+}
+
+/// collects the trigrams of the current chunk while the output is produced
+pub struct TokenIndexWriter {
+    path: PathBuf,
+    file: BufWriter<fs::File>,
+    header: Header,
+    filter: Vec<u8>,
+    /// first io error, reported by `finish`
+    error: Option<io::Error>,
+}
+impl TokenIndexWriter {
+    pub fn create(
+        path: &Path,
+        fingerprint: SourceFingerprint,
+        filter_length: usize,
+    ) -> Result<TokenIndexWriter, Error> {
+        let header = Header {
+            filter_length: filter_length as u32,
+            fingerprint,
+            chunk_count: 0,
+            out_size: 0,
+        };
+        let mut file = fs::File::create(path)?;
+        file.write_all(&header.as_bytes())?;
+        Ok(TokenIndexWriter::with_file(path, file, header))
+    }
+    /// continue an existing token index (for output that is appended to)
+    ///
+    /// the filter length of the existing index is kept. if there is none (or
+    /// it is unreadable) a new one is created, it will not match the chunk map
+    /// of the whole output then and searches ignore it
+    pub fn append(
+        path: &Path,
+        fingerprint: SourceFingerprint,
+        filter_length: usize,
+    ) -> Result<TokenIndexWriter, Error> {
+        if !path.exists() {
+            return TokenIndexWriter::create(path, fingerprint, filter_length);
+        }
+        let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+        let mut header = match Header::read(&mut file) {
+            Ok(header) => header,
+            Err(_) => return TokenIndexWriter::create(path, fingerprint, filter_length),
+        };
+        header.fingerprint = fingerprint;
+        file.set_len(header.file_length())?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(TokenIndexWriter::with_file(path, file, header))
+    }
+    fn with_file(path: &Path, file: fs::File, header: Header) -> TokenIndexWriter {
+        TokenIndexWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            filter: vec![0; header.filter_length as usize],
+            header,
+            error: None,
+        }
+    }
+    /// content of a line of the current chunk (without tag and row number)
+    pub fn add_line(&mut self, content: &[u8]) {
+        let filter_bits = self.filter.len() * 8;
+        for window in content.windows(3) {
+            for bit in bit_positions(trigram_at(window), filter_bits).iter() {
+                self.filter[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+    }
+    /// all lines added since the last chunk belong to `chunk`
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        if self.error.is_none() {
+            match self.file.write_all(&self.filter) {
+                Ok(()) => {
+                    self.header.chunk_count += 1;
+                    self.header.out_size = chunk.b.1 as u64;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        for b in self.filter.iter_mut() {
+            *b = 0;
+        }
+    }
+    /// write the final header
+    pub fn finish(mut self) -> Result<(), Error> {
+        if let Some(e) = self.error.take() {
+            return Err(err_msg(format!(
+                "could not write token index {:?}: {}",
+                self.path, e
+            )));
+        }
+        self.file.flush()?;
+        let file = self.file.get_mut();
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&self.header.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// create a writer for chunks of `chunk_size` lines, `source` is fingerprinted
+pub fn writer_for(
+    path: &Path,
+    source: &fs::File,
+    append: bool,
+    chunk_size: usize,
+) -> Result<TokenIndexWriter, Error> {
+    let fingerprint = SourceFingerprint::of_file(source)?;
+    let filter_length = filter_length_for(chunk_size);
+    if append {
+        TokenIndexWriter::append(path, fingerprint, filter_length)
+    } else {
+        TokenIndexWriter::create(path, fingerprint, filter_length)
+    }
+}
+
+/// reads the filters of a token index
+pub struct TokenIndex {
+    file: BufReader<fs::File>,
+    header: Header,
+}
+impl TokenIndex {
+    pub fn open(path: &Path) -> Result<TokenIndex, Error> {
+        let mut file = BufReader::new(fs::File::open(path)?);
+        let header = Header::read(&mut file)?;
+        if file.get_ref().metadata()?.len() < header.file_length() {
+            return Err(err_msg(format!("token index {:?} is truncated", path)));
+        }
+        Ok(TokenIndex { file, header })
+    }
+    pub fn fingerprint(&self) -> SourceFingerprint {
+        self.header.fingerprint
+    }
+    /// true if the token index was created from the file at `source`
+    pub fn matches_source(&self, source: &Path) -> bool {
+        SourceFingerprint::of_path(source).map_or(false, |f| f == self.header.fingerprint)
+    }
+    pub fn chunk_count(&self) -> usize {
+        self.header.chunk_count as usize
+    }
+    /// true if the index covers exactly `chunks` of an output of `out_size` bytes
+    pub fn matches_output(&self, chunks: &[Chunk], out_size: u64) -> bool {
+        self.chunk_count() == chunks.len()
+            && self.header.out_size == out_size
+            && chunks
+                .last()
+                .map_or(out_size == 0, |c| c.b.1 as u64 == out_size)
+    }
+    /// indices of the chunks that may contain a match
+    ///
+    /// `alternatives` are the trigrams of each query, a chunk is a candidate if
+    /// its filter contains all trigrams of at least one of them
+    pub fn candidate_chunks(&mut self, alternatives: &[Vec<u32>]) -> Result<Vec<usize>, Error> {
+        if alternatives.iter().any(Vec::is_empty) {
+            return Ok((0..self.chunk_count()).collect());
+        }
+        let filter_bits = self.header.filter_length as usize * 8;
+        let positions: Vec<Vec<[usize; 2]>> = alternatives
+            .iter()
+            .map(|trigrams| {
+                trigrams
+                    .iter()
+                    .map(|t| bit_positions(*t, filter_bits))
+                    .collect()
+            })
+            .collect();
+        self.file.seek(SeekFrom::Start(HEADER_LENGTH))?;
+        let mut filter = vec![0u8; self.header.filter_length as usize];
+        let mut candidates = vec![];
+        for index in 0..self.chunk_count() {
+            self.file.read_exact(&mut filter)?;
+            let is_set = |bit: usize| filter[bit / 8] & (1 << (bit % 8)) != 0;
+            if positions
+                .iter()
+                .any(|bits| bits.iter().all(|b| is_set(b[0]) && is_set(b[1])))
+            {
+                candidates.push(index);
+            }
+        }
+        Ok(candidates)
+    }
+}
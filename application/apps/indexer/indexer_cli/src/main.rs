@@ -13,12 +13,16 @@ extern crate processor;
 extern crate indexer_base;
 extern crate dlt;
 extern crate merging;
+extern crate search;
 extern crate chrono;
 extern crate dirs;
 
 use indexer_base::chunks::{serialize_chunks, Chunk};
-use indexer_base::config::IndexingConfig;
+use indexer_base::compression;
+use indexer_base::config::{IndexMapConfig, IndexingConfig, TimeWindow};
 use indexer_base::error_reporter::*;
+use indexer_base::index_map;
+use indexer_base::token_index;
 
 #[macro_use]
 extern crate clap;
@@ -124,6 +128,18 @@ fn main() {
                         .short("w")
                         .help("add timestamp info if available"),
                 )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("TIME")
+                        .help("only index lines with a timestamp at or after TIME (posix ms or RFC 3339)"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("TIME")
+                        .help("only index lines with a timestamp at or before TIME (posix ms or RFC 3339)"),
+                )
                 .arg(
                     Arg::with_name("append")
                         .short("a")
@@ -135,6 +151,40 @@ fn main() {
                         .short("s")
                         .long("stdout")
                         .help("put out chunk information on stdout"),
+                )
+                .arg(
+                    Arg::with_name("binary_map")
+                        .long("binary-map")
+                        .help("also write a binary index map to <INPUT>.map.idx"),
+                )
+                .arg(
+                    Arg::with_name("line_offsets")
+                        .long("line-offsets")
+                        .requires("binary_map")
+                        .help("store the byte offset of every line in the binary index map"),
+                )
+                .arg(
+                    Arg::with_name("tokens")
+                        .long("tokens")
+                        .help("also write a token index to <INPUT>.map.tokens to speed up searches"),
+                )
+                .arg(
+                    Arg::with_name("follow")
+                        .long("follow")
+                        .conflicts_with_all(&["entry", "all_entries"])
+                        .help("keep indexing the input while it grows (until killed), survives truncation and rotation"),
+                )
+                .arg(
+                    Arg::with_name("entry")
+                        .long("entry")
+                        .value_name("NAME")
+                        .help("file to index if the input is a zip archive")
+                        .conflicts_with("all_entries"),
+                )
+                .arg(
+                    Arg::with_name("all_entries")
+                        .long("all-entries")
+                        .help("index all files of a zip archive, tagged with <TAG>:<file name>"),
                 ),
         )
         .subcommand(
@@ -246,6 +296,96 @@ fn main() {
                         .long("file"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("search in an indexed file")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .help("the indexed file to search in")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("expression")
+                        .short("e")
+                        .long("expression")
+                        .value_name("EXPRESSION")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required_unless("queries")
+                        .help("regular expression to search for (can be given more than once)"),
+                )
+                .arg(
+                    Arg::with_name("queries")
+                        .long("queries")
+                        .value_name("QUERIES")
+                        .help("json file with a list of queries ({pattern, literal, ignore_case})"),
+                )
+                .arg(
+                    Arg::with_name("literal")
+                        .short("l")
+                        .long("literal")
+                        .help("match the expressions as plain text"),
+                )
+                .arg(
+                    Arg::with_name("ignore_case")
+                        .long("ignore-case")
+                        .help("ignore case when matching the expressions"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .value_name("OUT")
+                        .help("Output file for the matching lines, \"<input>.search\" if not present"),
+                )
+                .arg(
+                    Arg::with_name("chunk_size")
+                        .short("c")
+                        .long("chunk_size")
+                        .help("How many lines should be in a chunk (used for access later)")
+                        .required(false)
+                        .default_value("500"),
+                )
+                .arg(
+                    Arg::with_name("stdout")
+                        .short("s")
+                        .long("stdout")
+                        .help("put out chunk information on stdout"),
+                )
+                .arg(
+                    Arg::with_name("matches")
+                        .short("m")
+                        .long("matches")
+                        .help("put out every match (row and matching queries) on stdout"),
+                )
+                .arg(
+                    Arg::with_name("binary_map")
+                        .long("binary-map")
+                        .help("also write a binary index map to <OUT>.map.idx"),
+                )
+                .arg(
+                    Arg::with_name("line_offsets")
+                        .long("line-offsets")
+                        .requires("binary_map")
+                        .help("store the byte offset of every line in the binary index map"),
+                )
+                .arg(
+                    Arg::with_name("map")
+                        .long("map")
+                        .value_name("MAP")
+                        .help("chunk map of the input, a token index next to it is used to skip chunks"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .long("source")
+                        .value_name("SOURCE")
+                        .requires("map")
+                        .help("ignore the token index if it was not created from this file"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("dlt")
                 .about("handling dtl input")
@@ -253,7 +393,7 @@ fn main() {
                     Arg::with_name("input")
                         .short("i")
                         .long("input")
-                        .help("the DLT file to parse")
+                        .help("the DLT file to parse (or a .pcap/.pcapng capture containing DLT)")
                         .required(true)
                         .index(1),
                 )
@@ -299,13 +439,117 @@ fn main() {
                         .short("f")
                         .long("filter")
                         .value_name("FILTER_CONFIG")
-                        .help("json file that defines dlt filter settings"),
+                        .help("json file that defines dlt filter settings (or just a filter expression)"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("TIME")
+                        .help("only index messages stored at or after TIME (posix ms or RFC 3339)"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("TIME")
+                        .help("only index messages stored at or before TIME (posix ms or RFC 3339)"),
+                )
+                .arg(
+                    Arg::with_name("fibex")
+                        .short("x")
+                        .long("fibex")
+                        .value_name("FIBEX")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("fibex file used to decode non-verbose messages"),
                 )
                 .arg(
                     Arg::with_name("stdout")
                         .short("s")
                         .long("stdout")
                         .help("put out chunk information on stdout"),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .short("j")
+                        .long("threads")
+                        .value_name("THREADS")
+                        .help("number of threads used to parse the file (0 for one per cpu)")
+                        .required(false)
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("binary_map")
+                        .long("binary-map")
+                        .help("also write a binary index map to <INPUT>.map.idx"),
+                )
+                .arg(
+                    Arg::with_name("line_offsets")
+                        .long("line-offsets")
+                        .requires("binary_map")
+                        .help("store the byte offset of every line in the binary index map"),
+                )
+                .arg(
+                    Arg::with_name("tokens")
+                        .long("tokens")
+                        .help("also write a token index to <INPUT>.map.tokens to speed up searches"),
+                )
+                .arg(
+                    Arg::with_name("follow")
+                        .long("follow")
+                        .conflicts_with_all(&["entry", "all_entries"])
+                        .help("keep indexing the input while it grows (until killed), survives truncation and rotation"),
+                )
+                .arg(
+                    Arg::with_name("lifecycles")
+                        .long("lifecycles")
+                        .conflicts_with("follow")
+                        .help("append the ecu lifecycle id and the reconstructed absolute time (posix ms) to every message"),
+                )
+                .arg(
+                    Arg::with_name("dlt_port")
+                        .long("dlt-port")
+                        .value_name("PORT")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .default_value("3490")
+                        .help("udp/tcp port of the dlt traffic in a .pcap/.pcapng capture, other traffic is ignored"),
+                )
+                .arg(
+                    Arg::with_name("entry")
+                        .long("entry")
+                        .value_name("NAME")
+                        .help("file to index if the input is a zip archive")
+                        .conflicts_with("all_entries"),
+                )
+                .arg(
+                    Arg::with_name("all_entries")
+                        .long("all-entries")
+                        .help("index all files of a zip archive, tagged with <TAG>:<file name>"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-map")
+                .about("convert a json chunk map into a binary index map")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .help("json chunk map (<file>.map.json)")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .value_name("OUT")
+                        .help("binary index map (defaults to <file>.map.idx)"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .long("source")
+                        .value_name("SOURCE")
+                        .help("indexed file the fingerprint is taken from"),
                 ),
         )
         .subcommand(
@@ -319,6 +563,155 @@ fn main() {
                         .required(true)
                         .index(1),
                 )
+                .arg(
+                    Arg::with_name("fibex")
+                        .short("x")
+                        .long("fibex")
+                        .value_name("FIBEX")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("fibex file used to decode non-verbose messages"),
+                )
+                .arg(
+                    Arg::with_name("stdout")
+                        .short("s")
+                        .long("stdout")
+                        .help("put out chunk information on stdout"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dlt-export")
+                .about("write selected dlt messages to a new dlt file")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .help("the DLT file to export from")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .value_name("OUT")
+                        .help("the DLT file to write")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("filter_config")
+                        .short("f")
+                        .long("filter")
+                        .value_name("FILTER_CONFIG")
+                        .help("json file that defines dlt filter settings (or just a filter expression)"),
+                )
+                .arg(
+                    Arg::with_name("from_line")
+                        .long("from-line")
+                        .value_name("LINE")
+                        .help("first line to export (0 based, counting the filtered lines)"),
+                )
+                .arg(
+                    Arg::with_name("to_line")
+                        .long("to-line")
+                        .value_name("LINE")
+                        .help("last line to export (0 based, counting the filtered lines)"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("TIME")
+                        .help("export messages stored at or after TIME (posix ms or RFC 3339)"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("TIME")
+                        .help("export messages stored at or before TIME (posix ms or RFC 3339)"),
+                )
+                .arg(
+                    Arg::with_name("fibex")
+                        .short("x")
+                        .long("fibex")
+                        .value_name("FIBEX")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("fibex file used to decode non-verbose messages"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dlt-udp")
+                .about("receive dlt messages over udp (multicast)")
+                .arg(
+                    Arg::with_name("bind")
+                        .short("b")
+                        .long("bind")
+                        .help("local address to receive on, e.g. 0.0.0.0:3490")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("multicast")
+                        .short("m")
+                        .long("multicast")
+                        .value_name("GROUP")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("multicast group to join"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .long("interface")
+                        .value_name("INTERFACE")
+                        .requires("multicast")
+                        .help("interface used to join the multicast groups (ipv4 address or ipv6 interface index)"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .value_name("TAG")
+                        .help("tag for each log entry")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("chunk_size")
+                        .short("c")
+                        .long("chunk_size")
+                        .help("How many lines should be in a chunk (used for access later)")
+                        .required(false)
+                        .default_value("500"),
+                )
+                .arg(
+                    Arg::with_name("append")
+                        .short("a")
+                        .long("append")
+                        .help("append to file if exists"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("out")
+                        .value_name("OUT")
+                        .help("Output file")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("filter_config")
+                        .short("f")
+                        .long("filter")
+                        .value_name("FILTER_CONFIG")
+                        .help("json file that defines dlt filter settings (or just a filter expression)"),
+                )
+                .arg(
+                    Arg::with_name("fibex")
+                        .short("x")
+                        .long("fibex")
+                        .value_name("FIBEX")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("fibex file used to decode non-verbose messages"),
+                )
                 .arg(
                     Arg::with_name("stdout")
                         .short("s")
@@ -342,8 +735,16 @@ fn main() {
         handle_dlt_subcommand(matches, start, use_stderr_for_status_updates)
     } else if let Some(matches) = matches.subcommand_matches("dlt-stats") {
         handle_dlt_stats_subcommand(matches, start, use_stderr_for_status_updates)
+    } else if let Some(matches) = matches.subcommand_matches("dlt-export") {
+        handle_dlt_export_subcommand(matches, start, use_stderr_for_status_updates)
+    } else if let Some(matches) = matches.subcommand_matches("dlt-udp") {
+        handle_dlt_udp_subcommand(matches)
     } else if let Some(matches) = matches.subcommand_matches("discover") {
         handle_discover_subcommand(matches)
+    } else if let Some(matches) = matches.subcommand_matches("migrate-map") {
+        handle_migrate_map_subcommand(matches)
+    } else if let Some(matches) = matches.subcommand_matches("search") {
+        handle_search_subcommand(matches, start, use_stderr_for_status_updates)
     }
 
     fn handle_index_subcommand(
@@ -362,15 +763,20 @@ fn main() {
             );
             let mapping_out_path: path::PathBuf =
                 path::PathBuf::from(file.to_string() + ".map.json");
-            let chunk_size = value_t_or_exit!(matches.value_of("chunk_size"), usize);
-
-            let f = match fs::File::open(&file) {
-                Ok(file) => file,
-                Err(_) => {
-                    report_error(format!("could not open {}", file));
-                    std::process::exit(2)
-                }
+            let index_map_path = path::PathBuf::from(file.to_string() + ".map.idx");
+            let index_map = index_map_args(matches, &index_map_path);
+            let token_index_path = token_index::path_for(&mapping_out_path);
+            let token_index = if matches.is_present("tokens") {
+                Some(token_index_path.as_path())
+            } else {
+                None
             };
+            let follow = if matches.is_present("follow") {
+                Some(path::Path::new(file))
+            } else {
+                None
+            };
+            let chunk_size = value_t_or_exit!(matches.value_of("chunk_size"), usize);
 
             let source_file_size = if status_updates {
                 Some(match fs::metadata(file) {
@@ -386,25 +792,42 @@ fn main() {
             let append: bool = matches.is_present("append");
             let stdout: bool = matches.is_present("stdout");
             let timestamps: bool = matches.is_present("timestamp");
+            let time_window = time_window_args(matches);
             let (tx, _rx): (
                 Sender<IndexingProgress<Chunk>>,
                 Receiver<IndexingProgress<Chunk>>,
             ) = std::sync::mpsc::channel();
 
-            match processor::processor::create_index_and_mapping(
-                IndexingConfig {
-                    tag,
-                    chunk_size,
-                    in_file: f,
-                    out_path: &out_path,
-                    append,
-                    to_stdout: stdout,
-                },
-                timestamps,
-                source_file_size,
-                Some(tx),
-                None,
-            ) {
+            let entries = entries_to_index(matches, path::Path::new(file), tag);
+            let res = index_entries(&entries, append, |entry, tag, append| {
+                let f = match fs::File::open(&file) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        report_error(format!("could not open {}", file));
+                        std::process::exit(2)
+                    }
+                };
+                processor::processor::create_index_and_mapping(
+                    IndexingConfig {
+                        tag,
+                        chunk_size,
+                        in_file: f,
+                        zip_entry: entry,
+                        out_path: &out_path,
+                        append,
+                        to_stdout: stdout,
+                        index_map,
+                        token_index,
+                        follow,
+                    },
+                    timestamps,
+                    time_window,
+                    source_file_size,
+                    Some(tx.clone()),
+                    None,
+                )
+            });
+            match res {
                 Err(why) => {
                     report_error(format!("couldn't process: {}", why));
                     std::process::exit(2)
@@ -576,28 +999,49 @@ fn main() {
             }
         }
     }
+    fn fibex_config(matches: &clap::ArgMatches) -> Option<dlt::fibex::FibexConfig> {
+        matches
+            .values_of("fibex")
+            .map(|paths| dlt::fibex::FibexConfig {
+                fibex_file_paths: paths.map(|p| p.to_string()).collect(),
+            })
+    }
+    fn dlt_filter_config(matches: &clap::ArgMatches) -> Option<dlt::filtering::DltFilterConfig> {
+        match matches.value_of("filter_config") {
+            Some(filter_config_file_name) => {
+                let config_path = path::PathBuf::from(filter_config_file_name);
+                let mut cnf_file = match fs::File::open(&config_path) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        report_error(format!("could not open filter config {:?}", config_path));
+                        std::process::exit(2)
+                    }
+                };
+                match dlt::filtering::read_filter_options(&mut cnf_file) {
+                    Ok(filter_config) => Some(filter_config),
+                    Err(e) => {
+                        report_error(format!("invalid filter config {:?}: {}", config_path, e));
+                        std::process::exit(2)
+                    }
+                }
+            }
+            None => None,
+        }
+    }
     fn handle_dlt_subcommand(
         matches: &clap::ArgMatches,
         start: std::time::Instant,
         status_updates: bool,
     ) {
         if let (Some(file_name), Some(tag)) = (matches.value_of("input"), matches.value_of("tag")) {
-            let filter_conf: Option<dlt::filtering::DltFilterConfig> = match matches
-                .value_of("filter_config")
-            {
-                Some(filter_config_file_name) => {
-                    let config_path = path::PathBuf::from(filter_config_file_name);
-                    let mut cnf_file = match fs::File::open(&config_path) {
-                        Ok(file) => file,
-                        Err(_) => {
-                            report_error(format!("could not open filter config {:?}", config_path));
-                            std::process::exit(2)
-                        }
-                    };
-                    dlt::filtering::read_filter_options(&mut cnf_file).ok()
-                }
-                None => None,
-            };
+            let mut filter_conf = dlt_filter_config(matches);
+            if let Some(window) = time_window_args(matches) {
+                // replaces a time window of the filter config
+                let conf = filter_conf.get_or_insert_with(Default::default);
+                conf.from = window.from;
+                conf.to = window.to;
+                conf.time_base = Some(dlt::filtering::TimeBase::Storage);
+            }
             let append: bool = matches.is_present("append");
             let stdout: bool = matches.is_present("stdout");
             let source_file_size = if status_updates {
@@ -620,37 +1064,75 @@ fn main() {
             let file_path = path::PathBuf::from(file_name);
             let mapping_out_path: path::PathBuf =
                 path::PathBuf::from(file_name.to_string() + ".map.json");
-            let f = match fs::File::open(&file_path) {
-                Ok(file) => file,
-                Err(_) => {
-                    report_error(format!("could not open {:?}", file_path));
-                    std::process::exit(2)
-                }
+            let index_map_path = path::PathBuf::from(file_name.to_string() + ".map.idx");
+            let index_map = index_map_args(matches, &index_map_path);
+            let token_index_path = token_index::path_for(&mapping_out_path);
+            let token_index = if matches.is_present("tokens") {
+                Some(token_index_path.as_path())
+            } else {
+                None
+            };
+            let follow = if matches.is_present("follow") {
+                Some(file_path.as_path())
+            } else {
+                None
             };
-
             let (tx, _rx): (
                 Sender<IndexingProgress<Chunk>>,
                 Receiver<IndexingProgress<Chunk>>,
             ) = std::sync::mpsc::channel();
             let chunk_size = value_t_or_exit!(matches.value_of("chunk_size"), usize);
-            match dlt::dlt_parse::create_index_and_mapping_dlt(
-                IndexingConfig {
+            let threads = value_t_or_exit!(matches.value_of("threads"), usize);
+            let lifecycles = matches.is_present("lifecycles");
+            let dlt_ports = values_t_or_exit!(matches.values_of("dlt_port"), u16);
+            let entries = entries_to_index(matches, &file_path, tag);
+            let res = index_entries(&entries, append, |entry, tag, append| {
+                let f = match fs::File::open(&file_path) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        report_error(format!("could not open {:?}", file_path));
+                        std::process::exit(2)
+                    }
+                };
+                let indexing_config = IndexingConfig {
                     tag,
                     chunk_size,
                     in_file: f,
+                    zip_entry: entry,
                     out_path: &out_path,
                     append,
                     to_stdout: stdout,
-                },
-                source_file_size,
-                filter_conf,
-                Some(tx),
-                None,
-                // dlt::filtering::DltFilterConfig {
-                //     min_log_level: verbosity_log_level,
-                //     components: None,
-                // },
-            ) {
+                    index_map,
+                    token_index,
+                    follow,
+                };
+                if dlt::pcap::is_capture_file(&file_path) {
+                    if lifecycles {
+                        report_warning("lifecycles are not annotated for captures");
+                    }
+                    dlt::pcap::index_pcap_file(
+                        indexing_config,
+                        filter_conf.clone(),
+                        source_file_size,
+                        Some(tx.clone()),
+                        None,
+                        fibex_config(matches),
+                        &dlt_ports,
+                    )
+                } else {
+                    dlt::dlt_parse::create_index_and_mapping_dlt(
+                        indexing_config,
+                        source_file_size,
+                        filter_conf.clone(),
+                        Some(tx.clone()),
+                        None,
+                        fibex_config(matches),
+                        threads,
+                        lifecycles,
+                    )
+                }
+            });
+            match res {
                 Err(why) => {
                     report_error(format!("couldn't process: {}", why));
                     std::process::exit(2)
@@ -770,6 +1252,332 @@ fn main() {
         }
     }
 
+    /// entries of a zip archive to index with their tags
+    ///
+    /// the one given with `--entry` or all files with `--all-entries`
+    /// (tagged "<tag>:<file name>"), for other inputs just the file itself
+    fn entries_to_index(
+        matches: &clap::ArgMatches,
+        file_path: &path::Path,
+        tag: &str,
+    ) -> Vec<(Option<String>, String)> {
+        if let Some(entry) = matches.value_of("entry") {
+            return vec![(Some(entry.to_string()), tag.to_string())];
+        }
+        if matches.is_present("all_entries") {
+            let zip_entries = fs::File::open(file_path)
+                .map_err(failure::Error::from)
+                .and_then(|mut f| match compression::detect_compression(&mut f)? {
+                    Some(compression::Compression::Zip) => compression::zip_entries(&f).map(Some),
+                    _ => Ok(None),
+                });
+            match zip_entries {
+                Ok(Some(names)) => {
+                    return names
+                        .into_iter()
+                        .map(|name| {
+                            let entry_tag = format!("{}:{}", tag, name);
+                            (Some(name), entry_tag)
+                        })
+                        .collect()
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    report_error(format!("could not read entries of {:?}: {}", file_path, e));
+                    std::process::exit(2)
+                }
+            }
+        }
+        vec![(None, tag.to_string())]
+    }
+    /// index the entries one after the other into the same output
+    fn index_entries<F>(
+        entries: &[(Option<String>, String)],
+        append: bool,
+        mut index: F,
+    ) -> std::result::Result<Vec<Chunk>, failure::Error>
+    where
+        F: FnMut(Option<&str>, &str, bool) -> std::result::Result<Vec<Chunk>, failure::Error>,
+    {
+        let mut chunks = vec![];
+        for (i, (entry, tag)) in entries.iter().enumerate() {
+            chunks.extend(index(entry.as_ref().map(String::as_str), tag, append || i > 0)?);
+        }
+        Ok(chunks)
+    }
+    fn index_map_args<'a>(
+        matches: &clap::ArgMatches,
+        path: &'a path::Path,
+    ) -> Option<IndexMapConfig<'a>> {
+        if matches.is_present("binary_map") {
+            Some(IndexMapConfig {
+                path,
+                line_offsets: matches.is_present("line_offsets"),
+            })
+        } else {
+            None
+        }
+    }
+    fn handle_migrate_map_subcommand(matches: &clap::ArgMatches) {
+        let json_path =
+            path::PathBuf::from(matches.value_of("input").expect("input must be present"));
+        let index_path = match matches.value_of("output") {
+            Some(out) => path::PathBuf::from(out),
+            None => json_path.with_extension("idx"),
+        };
+        let source = matches.value_of("source").map(path::Path::new);
+        match index_map::migrate_json_map(&json_path, &index_path, source) {
+            Ok(chunk_count) => println!("wrote {} chunks to {:?}", chunk_count, index_path),
+            Err(e) => {
+                report_error(format!("could not migrate {:?}: {}", json_path, e));
+                std::process::exit(2)
+            }
+        }
+    }
+    fn search_queries(matches: &clap::ArgMatches) -> Vec<search::searcher::SearchQuery> {
+        let mut queries: Vec<search::searcher::SearchQuery> = match matches.value_of("queries") {
+            Some(queries_path) => match fs::read_to_string(queries_path)
+                .map_err(failure::Error::from)
+                .and_then(|content| Ok(serde_json::from_str(&content)?))
+            {
+                Ok(queries) => queries,
+                Err(e) => {
+                    report_error(format!("could not read queries {}: {}", queries_path, e));
+                    std::process::exit(2)
+                }
+            },
+            None => vec![],
+        };
+        if let Some(expressions) = matches.values_of("expression") {
+            queries.extend(expressions.map(|pattern| search::searcher::SearchQuery {
+                pattern: pattern.to_string(),
+                literal: matches.is_present("literal"),
+                ignore_case: matches.is_present("ignore_case"),
+            }));
+        }
+        queries
+    }
+    fn handle_search_subcommand(
+        matches: &clap::ArgMatches,
+        start: std::time::Instant,
+        status_updates: bool,
+    ) {
+        let file_name = matches.value_of("input").expect("input must be present");
+        let in_path = path::PathBuf::from(file_name);
+        let fallback_out = file_name.to_string() + ".search";
+        let out_path = path::PathBuf::from(
+            matches
+                .value_of("output")
+                .unwrap_or_else(|| fallback_out.as_str()),
+        );
+        let mapping_out_path =
+            path::PathBuf::from(out_path.to_string_lossy().to_string() + ".map.json");
+        let chunk_size = value_t_or_exit!(matches.value_of("chunk_size"), usize);
+        let queries = search_queries(matches);
+        let print_matches = matches.is_present("matches");
+        let (tx, rx): (
+            Sender<IndexingProgress<search::searcher::SearchItem>>,
+            Receiver<IndexingProgress<search::searcher::SearchItem>>,
+        ) = std::sync::mpsc::channel();
+        let search_thread = {
+            let in_path = in_path.clone();
+            let out_path = out_path.clone();
+            let queries = queries.clone();
+            let index_map_path =
+                path::PathBuf::from(out_path.to_string_lossy().to_string() + ".map.idx");
+            let binary_map = matches.is_present("binary_map");
+            let line_offsets = matches.is_present("line_offsets");
+            let to_stdout = matches.is_present("stdout");
+            let map_path = matches.value_of("map").map(path::PathBuf::from);
+            let source_path = matches.value_of("source").map(path::PathBuf::from);
+            std::thread::spawn(move || {
+                search::searcher::search_indexed_file(
+                    search::searcher::SearchConfig {
+                        in_path: &in_path,
+                        out_path: &out_path,
+                        queries: &queries,
+                        chunk_size,
+                        to_stdout,
+                        index_map: if binary_map {
+                            Some(IndexMapConfig {
+                                path: &index_map_path,
+                                line_offsets,
+                            })
+                        } else {
+                            None
+                        },
+                        map_path: map_path.as_ref().map(path::PathBuf::as_path),
+                        source_path: source_path.as_ref().map(path::PathBuf::as_path),
+                    },
+                    Some(tx),
+                    None,
+                )
+            })
+        };
+        for event in rx.iter() {
+            if let IndexingProgress::GotItem {
+                item: search::searcher::SearchItem::Match(m),
+            } = event
+            {
+                if print_matches {
+                    println!("{}", serde_json::to_string(&m).unwrap_or_default());
+                }
+            }
+        }
+        match search_thread.join() {
+            Ok(Ok(result)) => {
+                let _ = serialize_chunks(&result.chunks, &mapping_out_path);
+                if status_updates {
+                    for (query, hits) in queries.iter().zip(result.hits.iter()) {
+                        eprintln!("{} matches for {:?}", hits, query.pattern);
+                    }
+                    duration_report(start, format!("searching {:?}", in_path));
+                }
+            }
+            Ok(Err(why)) => {
+                report_error(format!("couldn't search: {}", why));
+                std::process::exit(2)
+            }
+            Err(_) => {
+                report_error("search thread panicked");
+                std::process::exit(2)
+            }
+        }
+    }
+    fn time_arg(matches: &clap::ArgMatches, name: &str) -> Option<i64> {
+        matches
+            .value_of(name)
+            .map(|value| match value.parse::<i64>() {
+                Ok(posix_ms) => posix_ms,
+                Err(_) => match chrono::DateTime::parse_from_rfc3339(value) {
+                    Ok(datetime) => datetime.timestamp_millis(),
+                    Err(e) => {
+                        report_error(format!("invalid time {}: {}", value, e));
+                        std::process::exit(2)
+                    }
+                },
+            })
+    }
+    fn time_window_args(matches: &clap::ArgMatches) -> Option<TimeWindow> {
+        match (time_arg(matches, "from"), time_arg(matches, "to")) {
+            (None, None) => None,
+            (from, to) => Some(TimeWindow { from, to }),
+        }
+    }
+    fn handle_dlt_export_subcommand(
+        matches: &clap::ArgMatches,
+        start: std::time::Instant,
+        status_updates: bool,
+    ) {
+        if let (Some(file_name), Some(out)) =
+            (matches.value_of("input"), matches.value_of("output"))
+        {
+            let file_path = path::PathBuf::from(file_name);
+            let f = match fs::File::open(&file_path) {
+                Ok(file) => file,
+                Err(_) => {
+                    report_error(format!("could not open {:?}", file_path));
+                    std::process::exit(2)
+                }
+            };
+            let lines = if matches.is_present("from_line") || matches.is_present("to_line") {
+                let first = if matches.is_present("from_line") {
+                    value_t_or_exit!(matches.value_of("from_line"), usize)
+                } else {
+                    0
+                };
+                let last = if matches.is_present("to_line") {
+                    value_t_or_exit!(matches.value_of("to_line"), usize)
+                } else {
+                    usize::MAX
+                };
+                Some(dlt::export::LineRange { first, last })
+            } else {
+                None
+            };
+            let time = if matches.is_present("from") || matches.is_present("to") {
+                Some(dlt::export::TimeRange {
+                    start: time_arg(matches, "from"),
+                    end: time_arg(matches, "to"),
+                })
+            } else {
+                None
+            };
+            let config = dlt::export::ExportConfig {
+                filter: dlt_filter_config(matches),
+                lines,
+                time,
+            };
+            match dlt::export::export_dlt_file(
+                f,
+                &path::PathBuf::from(out),
+                config,
+                fibex_config(matches),
+            ) {
+                Err(why) => {
+                    report_error(format!("couldn't export: {}", why));
+                    std::process::exit(2)
+                }
+                Ok(exported) => {
+                    if status_updates {
+                        duration_report(start, format!("exporting {} messages", exported));
+                    }
+                }
+            }
+            std::process::exit(0)
+        }
+    }
+
+    fn handle_dlt_udp_subcommand(matches: &clap::ArgMatches) {
+        if let (Some(bind_addr), Some(tag), Some(out)) = (
+            matches.value_of("bind"),
+            matches.value_of("tag"),
+            matches.value_of("output"),
+        ) {
+            let interface = matches.value_of("interface").map(|i| i.to_string());
+            let source = dlt::dlt_net::UdpSourceConfig {
+                bind_addr: bind_addr.to_string(),
+                multicast: matches
+                    .values_of("multicast")
+                    .map(|groups| {
+                        groups
+                            .map(|multiaddr| dlt::dlt_net::MulticastInfo {
+                                multiaddr: multiaddr.to_string(),
+                                interface: interface.clone(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            };
+            let out_path = path::PathBuf::from(out);
+            let mapping_out_path: path::PathBuf = path::PathBuf::from(out.to_string() + ".map.json");
+            let chunk_size = value_t_or_exit!(matches.value_of("chunk_size"), usize);
+            match dlt::dlt_net::index_from_udp(
+                &source,
+                dlt::dlt_net::SocketIndexingConfig {
+                    tag,
+                    chunk_size,
+                    out_path: &out_path,
+                    append: matches.is_present("append"),
+                    to_stdout: matches.is_present("stdout"),
+                },
+                dlt_filter_config(matches),
+                None,
+                None,
+                fibex_config(matches),
+            ) {
+                Err(why) => {
+                    report_error(format!("couldn't process: {}", why));
+                    std::process::exit(2)
+                }
+                Ok(chunks) => {
+                    let _ = serialize_chunks(&chunks, &mapping_out_path);
+                }
+            }
+            std::process::exit(0)
+        }
+    }
+
     fn handle_dlt_stats_subcommand(
         matches: &clap::ArgMatches,
         start: std::time::Instant,
@@ -784,7 +1592,7 @@ fn main() {
                 std::process::exit(2)
             }
         };
-        match dlt::dlt_parse::get_dlt_file_info(&f) {
+        match dlt::dlt_parse::get_dlt_file_info(&f, fibex_config(matches)) {
             Err(why) => {
                 report_error(format!("couldn't collect statistics: {}", why));
                 std::process::exit(2)
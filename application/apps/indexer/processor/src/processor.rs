@@ -13,9 +13,13 @@
 use crate::parse;
 use failure::{err_msg, Error};
 use indexer_base::chunks::{Chunk, ChunkFactory};
-use indexer_base::config::IndexingConfig;
+use indexer_base::compression;
+use indexer_base::config::{IndexingConfig, TimeWindow, TimeWindowEnd};
+use indexer_base::index_map;
 use indexer_base::error_reporter::*;
+use indexer_base::follow;
 use indexer_base::progress::*;
+use indexer_base::token_index;
 use indexer_base::utils;
 use parse::detect_timestamp_in_string;
 use std::sync::mpsc::{self, TryRecvError};
@@ -28,6 +32,7 @@ const REPORT_PROGRESS_LINE_BLOCK: usize = 1_000_000;
 pub fn create_index_and_mapping(
     config: IndexingConfig,
     parse_timestamps: bool,
+    time_window: Option<TimeWindow>,
     source_file_size: Option<usize>,
     update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
     shutdown_receiver: Option<mpsc::Receiver<()>>,
@@ -46,16 +51,20 @@ pub fn create_index_and_mapping(
         config,
         initial_line_nr,
         parse_timestamps,
+        time_window,
         source_file_size,
         update_channel,
         shutdown_receiver,
     )
 }
 
+/// lines are only indexed if their timestamp lies inside of `time_window`,
+/// lines without a timestamp belong to the last line that had one
 pub fn index_file(
     config: IndexingConfig,
     initial_line_nr: usize,
     timestamps: bool,
+    time_window: Option<TimeWindow>,
     source_file_size: Option<usize>,
     update_channel: Option<mpsc::Sender<IndexingProgress<Chunk>>>,
     shutdown_receiver: Option<mpsc::Receiver<()>>,
@@ -67,15 +76,67 @@ pub fn index_file(
     let mut chunks = vec![];
     let mut chunk_factory =
         ChunkFactory::new(config.chunk_size, config.to_stdout, current_out_file_size);
+    if let Some(index_map) = &config.index_map {
+        chunk_factory = chunk_factory.with_index_writer(index_map::writer_for(
+            index_map,
+            &config.in_file,
+            config.append,
+        )?);
+    }
+    if let Some(token_index) = config.token_index {
+        chunk_factory = chunk_factory.with_token_writer(token_index::writer_for(
+            token_index,
+            &config.in_file,
+            config.append,
+            config.chunk_size,
+        )?);
+    }
 
-    let mut reader = BufReader::new(config.in_file);
+    let compression::Input { reader, progress } = match config.follow {
+        Some(path) => follow::open_input(config.in_file, path)?,
+        None => compression::open_input(config.in_file, config.zip_entry)?,
+    };
+    let mut reader = BufReader::new(reader);
     let mut line_nr = initial_line_nr;
     let mut buf_writer = BufWriter::with_capacity(10 * 1024 * 1024, out_file);
 
     let mut buf = vec![];
     let mut processed_bytes = 0usize;
     let mut stopped = false;
-    while let Ok(len) = reader.read_until(b'\n', &mut buf) {
+    let mut in_time_window = time_window.is_none();
+    let mut time_window_end = time_window.and_then(|w| w.to).map(TimeWindowEnd::new);
+    loop {
+        let len = match reader.read_until(b'\n', &mut buf) {
+            // a line that was cut off while following is completed first
+            Ok(_) => buf.len(),
+            Err(ref e) if follow::is_reopened(e) => {
+                if buf.is_empty() {
+                    continue;
+                }
+                // the rest of a line that was cut off at the end of the replaced
+                // file will never come, index what we have as a complete line
+                buf.push(b'\n');
+                buf.len()
+            }
+            Err(ref e) if follow::is_waiting(e) => {
+                // all of the file is indexed, report the lines so far
+                buf_writer.flush()?;
+                if let Some(chunk) = chunk_factory.create_pending_chunk(line_nr) {
+                    update_channel.as_ref().map(|c| {
+                        c.send(IndexingProgress::GotItem {
+                            item: chunk.clone(),
+                        })
+                    });
+                    chunks.push(chunk);
+                }
+                if stopped || follow::wait(shutdown_receiver.as_ref()) {
+                    info!("stopped following {:?}", config.follow);
+                    break;
+                }
+                continue;
+            }
+            Err(_) => break,
+        };
         if stopped {
             info!("we where stopped in indexer",);
             break;
@@ -90,13 +151,27 @@ pub fn index_file(
             break;
         };
         let additional_bytes: usize;
+        let detected_ts = if trimmed_len != 0 && (timestamps || time_window.is_some()) {
+            detect_timestamp_in_string(trimmed_line, None)
+                .ok()
+                .map(|(time, _, _)| time)
+        } else {
+            None
+        };
+        if let (Some(window), Some(ts)) = (&time_window, detected_ts) {
+            if time_window_end
+                .as_mut()
+                .map_or(false, |end| end.is_passed(ts))
+            {
+                info!("end of time window reached at line {}", line_nr);
+                break;
+            }
+            in_time_window = window.contains(ts);
+        }
         // only use non-empty lines, others will be dropped
-        if trimmed_len != 0 {
+        if trimmed_len != 0 && in_time_window {
             if timestamps {
-                let ts = match detect_timestamp_in_string(trimmed_line, None) {
-                    Ok((time, _, _)) => time,
-                    Err(_) => 0,
-                };
+                let ts = detected_ts.unwrap_or(0);
                 if had_newline {
                     writeln!(
                         buf_writer,
@@ -167,6 +242,7 @@ pub fn index_file(
                 );
             }
             line_nr += 1;
+            chunk_factory.add_tokens(trimmed_line.as_bytes());
 
             match chunk_factory.create_chunk_if_needed(line_nr, additional_bytes) {
                 Some(chunk) => {
@@ -199,14 +275,14 @@ pub fn index_file(
                 utils::report_progress(
                     line_nr,
                     chunk_factory.get_current_byte_index(),
-                    processed_bytes,
+                    progress.bytes(processed_bytes),
                     file_size,
                     REPORT_PROGRESS_LINE_BLOCK,
                 );
                 if line_nr % REPORT_PROGRESS_LINE_BLOCK == 0 {
                     update_channel.as_ref().map(|c| {
                         c.send(IndexingProgress::Progress {
-                            ticks: (processed_bytes, file_size),
+                            ticks: (progress.bytes(processed_bytes), file_size),
                         })
                     });
                 }
@@ -214,7 +290,9 @@ pub fn index_file(
         }
         buf = vec![];
     }
-    if stopped {
+    // when following a file, shutting down is the regular end
+    if stopped && config.follow.is_none() {
+        chunk_factory.finish_index()?;
         if let Some(tx) = update_channel {
             debug!("sending IndexingProgress::Stopped");
             tx.send(IndexingProgress::Stopped)?;
@@ -230,6 +308,7 @@ pub fn index_file(
             });
             chunks.push(chunk);
         }
+        chunk_factory.finish_index()?;
         match chunks.last() {
             Some(last_chunk) => {
                 let last_expected_byte_index =
@@ -4,8 +4,12 @@ mod tests {
     extern crate rand;
     extern crate tempdir;
     use crate::processor::*;
-    use indexer_base::chunks::Chunk;
-    use indexer_base::config::IndexingConfig;
+    use indexer_base::chunks::{serialize_chunks, Chunk};
+    use crate::parse::detect_timestamp_in_string;
+    use indexer_base::config::{IndexMapConfig, IndexingConfig, TimeWindow};
+    use indexer_base::index_map::IndexMap;
+    use indexer_base::indexed_file::IndexedFile;
+    use indexer_base::utils;
     use pretty_assertions::assert_eq;
     use std::fs;
     use std::fs::File;
@@ -31,11 +35,16 @@ mod tests {
                 tag: tag_name,
                 chunk_size: chunksize,
                 in_file: f,
+                zip_entry: None,
                 out_path: &out_file_path,
                 append: tmp_file_name.is_some(),
                 to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
             },
             false,
+            None,
             Some(source_file_size),
             None,
             None,
@@ -90,11 +99,16 @@ mod tests {
                 tag: "tag",
                 chunk_size: 1,
                 in_file: empty_file,
+                zip_entry: None,
                 out_path: &out_path,
                 append: false,
                 to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
             },
             false,
+            None,
             Some(source_file_size),
             None,
             None,
@@ -116,11 +130,16 @@ mod tests {
                 tag: "tag",
                 chunk_size: 1,
                 in_file: nonempty_file,
+                zip_entry: None,
                 out_path: &out_path,
                 append: true,
                 to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
             },
             false,
+            None,
             Some(source_file_size),
             None,
             None,
@@ -183,6 +202,106 @@ mod tests {
         assert_eq!(2, chunks.len());
         assert_eq!(content.len(), size_of_all_chunks(&chunks));
     }
+    fn index_time_window(test_content: &str, time_window: TimeWindow) -> Vec<String> {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let test_file_path = tmp_dir.path().join("tmpTestFile.txt");
+        let out_file_path = tmp_dir.path().join("tmpTestFile.txt.out");
+        fs::write(&test_file_path, test_content).expect("testfile could not be written");
+        create_index_and_mapping(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 1,
+                in_file: File::open(&test_file_path).unwrap(),
+                zip_entry: None,
+                out_path: &out_file_path,
+                append: false,
+                to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
+            },
+            true,
+            Some(time_window),
+            None,
+            None,
+            None,
+        )
+        .expect("could not index file");
+        fs::read_to_string(out_file_path)
+            .expect("could not read file")
+            .lines()
+            .map(|line| {
+                line.split(utils::PLUGIN_ID_SENTINAL)
+                    .next()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+    fn time_of(line: &str) -> i64 {
+        detect_timestamp_in_string(line, None)
+            .expect("no timestamp found")
+            .0
+    }
+    #[test]
+    fn test_index_time_window() {
+        let content = "2019-07-30 09:38:01.000 +00:00 before\n\
+                       2019-07-30 09:38:02.000 +00:00 first\n\
+                       \tcontinued\n\
+                       2019-07-30 09:38:03.000 +00:00 last\n\
+                       2019-07-30 09:38:04.000 +00:00 after\n\
+                       \tcontinued after\n";
+        let lines = index_time_window(
+            content,
+            TimeWindow {
+                from: Some(time_of("2019-07-30 09:38:02.000 +00:00")),
+                to: Some(time_of("2019-07-30 09:38:03.000 +00:00")),
+            },
+        );
+        assert_eq!(
+            vec![
+                "2019-07-30 09:38:02.000 +00:00 first",
+                "\tcontinued",
+                "2019-07-30 09:38:03.000 +00:00 last"
+            ],
+            lines
+        );
+    }
+    #[test]
+    fn test_index_time_window_stops_after_end() {
+        let content = "2019-07-30 09:38:02.000 +00:00 first\n\
+                       2019-07-30 09:38:04.000 +00:00 after\n\
+                       2019-07-30 09:38:03.000 +00:00 not read anymore\n";
+        let lines = index_time_window(
+            content,
+            TimeWindow {
+                from: None,
+                to: Some(time_of("2019-07-30 09:38:03.000 +00:00")),
+            },
+        );
+        assert_eq!(vec!["2019-07-30 09:38:02.000 +00:00 first"], lines);
+    }
+    #[test]
+    fn test_index_time_window_unordered_timestamps() {
+        let content = "2019-07-30 09:38:03.000 +00:00 first\n\
+                       2019-07-30 09:38:01.000 +00:00 earlier\n\
+                       2019-07-30 09:38:04.000 +00:00 after\n\
+                       2019-07-30 09:38:02.000 +00:00 inside again\n";
+        let lines = index_time_window(
+            content,
+            TimeWindow {
+                from: Some(time_of("2019-07-30 09:38:02.000 +00:00")),
+                to: Some(time_of("2019-07-30 09:38:03.000 +00:00")),
+            },
+        );
+        assert_eq!(
+            vec![
+                "2019-07-30 09:38:03.000 +00:00 first",
+                "2019-07-30 09:38:02.000 +00:00 inside again"
+            ],
+            lines
+        );
+    }
     fn size_of_all_chunks(chunks: &[Chunk]) -> usize {
         let res = chunks
             .iter()
@@ -220,11 +339,16 @@ mod tests {
                 tag: "TAG",
                 chunk_size: 1,
                 in_file,
+                zip_entry: None,
                 out_path: &out_file_path,
                 append: append_use_case,
                 to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
             },
             false,
+            None,
             Some(in_file_size),
             None,
             None,
@@ -242,4 +366,302 @@ mod tests {
         assert_eq!(expected_content.trim_end(), out_file_content.trim_end());
         assert_eq!(true, chunks_fit_together(&chunks), "chunks need to fit");
     }
+
+    #[test]
+    fn test_index_map_with_line_offsets() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("source.log");
+        let out_path = tmp_dir.path().join("source.log.out");
+        let index_path = tmp_dir.path().join("source.log.map.idx");
+        let content: String = (0..50)
+            .map(|i| format!("line {} {}\n", i, "x".repeat(i % 9)))
+            .collect();
+        fs::write(&in_path, &content).expect("testfile could not be written");
+        let index = |append: bool| {
+            create_index_and_mapping(
+                IndexingConfig {
+                    tag: "TAG",
+                    chunk_size: 7,
+                    in_file: File::open(&in_path).expect("could not open source"),
+                    zip_entry: None,
+                    out_path: &out_path,
+                    append,
+                    to_stdout: false,
+                    index_map: Some(IndexMapConfig {
+                        path: &index_path,
+                        line_offsets: true,
+                    }),
+                    token_index: None,
+                    follow: None,
+                },
+                false,
+                None,
+                None,
+                None,
+                None,
+            )
+            .expect("could not index file")
+        };
+        let mut chunks = index(false);
+        chunks.extend(index(true));
+
+        let mut index_map = IndexMap::open(&index_path).expect("could not open index map");
+        assert!(index_map.matches_source(&in_path));
+        let stored: Vec<((usize, usize), (usize, usize))> = index_map
+            .chunks()
+            .expect("could not read chunks")
+            .iter()
+            .map(|c| (c.r, c.b))
+            .collect();
+        let expected: Vec<((usize, usize), (usize, usize))> =
+            chunks.iter().map(|c| (c.r, c.b)).collect();
+        assert_eq!(expected, stored);
+        let output = fs::read(&out_path).expect("could not read output");
+        let mut offset = 0;
+        for (line_nr, line) in output.split(|b| *b == b'\n').take(100).enumerate() {
+            assert_eq!(
+                Some(offset),
+                index_map.byte_offset(line_nr).expect("lookup failed"),
+                "offset of line {}",
+                line_nr
+            );
+            offset += line.len() + 1;
+        }
+        assert_eq!(None, index_map.byte_offset(100).expect("lookup failed"));
+    }
+
+    #[test]
+    fn test_indexed_file_of_processor_output() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("source.log");
+        let out_path = tmp_dir.path().join("source.log.out");
+        let json_map_path = tmp_dir.path().join("source.log.map.json");
+        let index_map_path = tmp_dir.path().join("source.log.map.idx");
+        let source_lines: Vec<String> = (0..40)
+            .map(|i| format!("2019-07-30 09:{:02}:09.000 +02:00 entry number {}", i, i))
+            .collect();
+        fs::write(&in_path, source_lines.join("\n")).expect("testfile could not be written");
+        let chunks = index_file(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 6,
+                in_file: File::open(&in_path).expect("could not open source"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: Some(IndexMapConfig {
+                    path: &index_map_path,
+                    line_offsets: true,
+                }),
+                token_index: None,
+                follow: None,
+            },
+            0,
+            true,
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("could not index file");
+        serialize_chunks(&chunks, &json_map_path).expect("could not write json map");
+
+        for map_path in &[&json_map_path, &index_map_path] {
+            let mut file = IndexedFile::open(&out_path, map_path).expect("could not open output");
+            assert_eq!(40, file.line_count());
+            for row in &[39, 0, 17, 6, 5, 12] {
+                let line = file
+                    .get_line(*row)
+                    .expect("could not read line")
+                    .expect("line is missing");
+                assert_eq!(source_lines[*row], line.content);
+                assert_eq!("TAG", line.tag);
+                assert_eq!(*row, line.row);
+                let (expected_ts, _, _) =
+                    detect_timestamp_in_string(&source_lines[*row], None).expect("no timestamp");
+                assert_eq!(Some(expected_ts), line.timestamp);
+            }
+            let lines = file.get_lines(10..14).expect("could not read lines");
+            let contents: Vec<&str> = lines.iter().map(|l| l.content.as_str()).collect();
+            assert_eq!(source_lines[10..14].to_vec(), contents);
+            let rows: Vec<usize> = file
+                .lines_from(35)
+                .expect("no lines")
+                .map(|l| l.expect("could not read line").row)
+                .collect();
+            assert_eq!(vec![35, 36, 37, 38, 39], rows);
+            assert!(file.get_line(40).expect("could not read line").is_none());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_growing_and_rotated_file() {
+        use indexer_base::indexed_file::parse_line;
+        use indexer_base::progress::IndexingProgress;
+        use std::io::Write;
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("source.log");
+        let out_path = tmp_dir.path().join("source.log.out");
+        let map_path = tmp_dir.path().join("source.log.map.idx");
+        fs::write(&in_path, "a\nb\n").expect("testfile could not be written");
+        let append = |content: &str| {
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&in_path)
+                .expect("could not open source")
+                .write_all(content.as_bytes())
+                .expect("could not append");
+        };
+
+        let (update_tx, update_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let indexer = {
+            let (in_path, out_path, map_path) =
+                (in_path.clone(), out_path.clone(), map_path.clone());
+            std::thread::spawn(move || {
+                index_file(
+                    IndexingConfig {
+                        tag: "TAG",
+                        chunk_size: 100,
+                        in_file: File::open(&in_path).expect("could not open source"),
+                        zip_entry: None,
+                        out_path: &out_path,
+                        append: false,
+                        to_stdout: false,
+                        index_map: Some(IndexMapConfig {
+                            path: &map_path,
+                            line_offsets: true,
+                        }),
+                        token_index: None,
+                        follow: Some(&in_path),
+                    },
+                    0,
+                    false,
+                    None,
+                    None,
+                    Some(update_tx),
+                    Some(shutdown_rx),
+                )
+            })
+        };
+        let next_rows = || match update_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(IndexingProgress::GotItem { item }) => item.r,
+            _ => panic!("no chunk while following"),
+        };
+        assert_eq!((0, 1), next_rows());
+        // the cut off line is only indexed when it is complete
+        append("c\npart");
+        assert_eq!((2, 2), next_rows());
+        append("ial\n");
+        assert_eq!((3, 3), next_rows());
+        fs::rename(&in_path, tmp_dir.path().join("source.log.1")).expect("could not rotate");
+        fs::write(&in_path, "d\ne\n").expect("could not write rotated file");
+        assert_eq!((4, 5), next_rows());
+
+        shutdown_tx.send(()).expect("could not shut down");
+        let chunks = indexer
+            .join()
+            .expect("indexer panicked")
+            .expect("could not follow file");
+        assert_eq!(4, chunks.len());
+        match update_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(IndexingProgress::Finished) => (),
+            _ => panic!("following did not finish"),
+        }
+        let lines: Vec<(String, usize)> = fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(|l| {
+                let line = parse_line(l).expect("not an indexed line");
+                (line.content, line.row)
+            })
+            .collect();
+        let expected: Vec<(String, usize)> = vec!["a", "b", "c", "partial", "d", "e"]
+            .into_iter()
+            .enumerate()
+            .map(|(row, content)| (content.to_string(), row))
+            .collect();
+        assert_eq!(expected, lines);
+        let mut file = IndexedFile::open(&out_path, &map_path).expect("could not open output");
+        assert_eq!(6, file.line_count());
+        let line = file
+            .get_line(3)
+            .expect("could not read line")
+            .expect("line is missing");
+        assert_eq!("partial", line.content);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotated_file_with_unterminated_line() {
+        use indexer_base::indexed_file::parse_line;
+        use indexer_base::progress::IndexingProgress;
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("source.log");
+        let out_path = tmp_dir.path().join("source.log.out");
+        fs::write(&in_path, "a\nb").expect("testfile could not be written");
+
+        let (update_tx, update_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let indexer = {
+            let (in_path, out_path) = (in_path.clone(), out_path.clone());
+            std::thread::spawn(move || {
+                index_file(
+                    IndexingConfig {
+                        tag: "TAG",
+                        chunk_size: 100,
+                        in_file: File::open(&in_path).expect("could not open source"),
+                        zip_entry: None,
+                        out_path: &out_path,
+                        append: false,
+                        to_stdout: false,
+                        index_map: None,
+                        token_index: None,
+                        follow: Some(&in_path),
+                    },
+                    0,
+                    false,
+                    None,
+                    None,
+                    Some(update_tx),
+                    Some(shutdown_rx),
+                )
+            })
+        };
+        let next_rows = || match update_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(IndexingProgress::GotItem { item }) => item.r,
+            _ => panic!("no chunk while following"),
+        };
+        assert_eq!((0, 0), next_rows());
+        // the last line of the rotated file is never completed
+        fs::rename(&in_path, tmp_dir.path().join("source.log.1")).expect("could not rotate");
+        fs::write(&in_path, "c\nd\n").expect("could not write rotated file");
+        assert_eq!((1, 3), next_rows());
+
+        shutdown_tx.send(()).expect("could not shut down");
+        indexer
+            .join()
+            .expect("indexer panicked")
+            .expect("could not follow file");
+        let lines: Vec<(String, usize)> = fs::read_to_string(&out_path)
+            .expect("could not read output")
+            .lines()
+            .map(|l| {
+                let line = parse_line(l).expect("not an indexed line");
+                (line.content, line.row)
+            })
+            .collect();
+        let expected: Vec<(String, usize)> = vec!["a", "b", "c", "d"]
+            .into_iter()
+            .enumerate()
+            .map(|(row, content)| (content.to_string(), row))
+            .collect();
+        assert_eq!(expected, lines);
+    }
 }
@@ -0,0 +1,118 @@
+#[macro_use]
+extern crate criterion;
+extern crate search;
+
+use criterion::Criterion;
+use indexer_base::config::{IndexMapConfig, IndexingConfig};
+use indexer_base::token_index;
+use search::searcher::{search_indexed_file, SearchConfig, SearchQuery};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+
+const LINE_COUNT: usize = 500_000;
+
+fn source_line(i: usize) -> String {
+    let level = ["INFO", "DEBUG", "WARN", "ERROR"][i % 4];
+    if i % 100_000 == 99_999 {
+        format!(
+            "2019-09-10 10:00:00.000 {} watchdog: task {} starved",
+            level, i
+        )
+    } else {
+        format!(
+            "2019-09-10 10:00:00.000 {} worker {} processed request {} in {}ms",
+            level,
+            i % 16,
+            i,
+            i % 997
+        )
+    }
+}
+
+/// index a generated log with the processor, returns output and index map
+fn indexed_output(dir: &Path) -> (PathBuf, PathBuf) {
+    let in_path = dir.join("bench.log");
+    let content: Vec<String> = (0..LINE_COUNT).map(source_line).collect();
+    fs::write(&in_path, content.join("\n")).expect("could not write source");
+    let out_path = dir.join("bench.log.out");
+    let map_path = dir.join("bench.log.map.idx");
+    processor::processor::index_file(
+        IndexingConfig {
+            tag: "TAG",
+            chunk_size: 500,
+            in_file: fs::File::open(&in_path).expect("could not open source"),
+            zip_entry: None,
+            out_path: &out_path,
+            append: false,
+            to_stdout: false,
+            index_map: Some(IndexMapConfig {
+                path: &map_path,
+                line_offsets: false,
+            }),
+            token_index: Some(&token_index::path_for(&map_path)),
+            follow: None,
+        },
+        0,
+        false,
+        None,
+        None,
+        None,
+        None,
+    )
+    .expect("could not index source");
+    (out_path, map_path)
+}
+
+fn search(in_path: &Path, results: &PathBuf, query: &SearchQuery, map_path: Option<&Path>) {
+    search_indexed_file(
+        SearchConfig {
+            in_path,
+            out_path: results,
+            queries: std::slice::from_ref(query),
+            chunk_size: 500,
+            to_stdout: false,
+            index_map: None,
+            map_path,
+            source_path: None,
+        },
+        None,
+        None,
+    )
+    .expect("search failed");
+}
+
+fn search_benchmark(c: &mut Criterion) {
+    let tmp_dir = TempDir::new("search_bench").expect("could not create temp dir");
+    let (out_path, map_path) = indexed_output(tmp_dir.path());
+    let results = tmp_dir.path().join("bench.search");
+    let queries = [
+        ("rare literal", SearchQuery::literal("starved")),
+        ("rare regex", SearchQuery::regex(r"task \d+ starved")),
+        (
+            "literal of common trigrams",
+            SearchQuery::literal("request 4242"),
+        ),
+        ("everywhere", SearchQuery::literal("processed")),
+    ];
+    for (name, query) in queries.iter() {
+        let (paths, query_full) = ((out_path.clone(), results.clone()), query.clone());
+        c.bench_function(&format!("search {} (full scan)", name), move |b| {
+            b.iter(|| search(&paths.0, &paths.1, &query_full, None))
+        });
+        let (paths, query) = (
+            (out_path.clone(), results.clone(), map_path.clone()),
+            query.clone(),
+        );
+        c.bench_function(&format!("search {} (token index)", name), move |b| {
+            b.iter(|| search(&paths.0, &paths.1, &query, Some(&paths.2)))
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = search_benchmark
+}
+criterion_main!(benches);
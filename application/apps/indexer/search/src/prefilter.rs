@@ -0,0 +1,150 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! trigrams that every match of a query contains
+//!
+//! they are looked up in the token index to skip chunks. the extraction is
+//! conservative: if nothing is known about a query (e.g. `a.*|b`) it yields
+//! no trigrams and every chunk is searched.
+use crate::searcher::SearchQuery;
+use indexer_base::token_index;
+use regex_syntax::hir::{Hir, HirKind, Literal, RepetitionKind, RepetitionRange};
+use regex_syntax::Parser;
+use std::collections::BTreeSet;
+
+/// what is known about the matches of an expression
+struct Required {
+    /// the expression matches exactly this text
+    exact: Option<Vec<u8>>,
+    /// trigrams every match contains
+    trigrams: BTreeSet<u32>,
+}
+impl Required {
+    fn exact(text: Vec<u8>) -> Required {
+        Required {
+            exact: Some(text),
+            trigrams: BTreeSet::new(),
+        }
+    }
+    fn unknown() -> Required {
+        Required {
+            exact: None,
+            trigrams: BTreeSet::new(),
+        }
+    }
+    fn all_trigrams(self) -> BTreeSet<u32> {
+        let mut trigrams = self.trigrams;
+        if let Some(text) = self.exact {
+            trigrams.extend(token_index::trigrams(&text));
+        }
+        trigrams
+    }
+}
+
+fn required(hir: &Hir) -> Required {
+    match hir.kind() {
+        // zero width, does not interrupt a literal
+        HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => Required::exact(vec![]),
+        HirKind::Literal(Literal::Unicode(c)) => {
+            let mut bytes = [0u8; 4];
+            Required::exact(c.encode_utf8(&mut bytes).as_bytes().to_vec())
+        }
+        HirKind::Literal(Literal::Byte(b)) => Required::exact(vec![*b]),
+        HirKind::Class(_) => Required::unknown(),
+        HirKind::Group(group) => required(&group.hir),
+        HirKind::Repetition(repetition) => {
+            let min = match repetition.kind {
+                RepetitionKind::ZeroOrOne | RepetitionKind::ZeroOrMore => 0,
+                RepetitionKind::OneOrMore => 1,
+                RepetitionKind::Range(RepetitionRange::Exactly(n))
+                | RepetitionKind::Range(RepetitionRange::AtLeast(n))
+                | RepetitionKind::Range(RepetitionRange::Bounded(n, _)) => n,
+            };
+            if min == 0 {
+                Required::unknown()
+            } else {
+                Required {
+                    exact: None,
+                    trigrams: required(&repetition.hir).all_trigrams(),
+                }
+            }
+        }
+        HirKind::Concat(hirs) => {
+            let mut trigrams = BTreeSet::new();
+            let mut run: Vec<u8> = vec![];
+            let mut is_exact = true;
+            for hir in hirs {
+                let sub = required(hir);
+                match sub.exact {
+                    Some(text) => run.extend(text),
+                    None => {
+                        is_exact = false;
+                        trigrams.extend(token_index::trigrams(&run));
+                        trigrams.extend(sub.trigrams);
+                        run.clear();
+                    }
+                }
+            }
+            if is_exact {
+                Required {
+                    exact: Some(run),
+                    trigrams,
+                }
+            } else {
+                trigrams.extend(token_index::trigrams(&run));
+                Required {
+                    exact: None,
+                    trigrams,
+                }
+            }
+        }
+        HirKind::Alternation(hirs) => {
+            let mut branches = hirs.iter().map(|hir| required(hir).all_trigrams());
+            let first = branches.next().unwrap_or_default();
+            Required {
+                exact: None,
+                trigrams: branches.fold(first, |common, branch| {
+                    common.intersection(&branch).cloned().collect()
+                }),
+            }
+        }
+    }
+}
+
+/// true if ignoring case can only match what the token index folds, i.e. ascii
+/// letters. unicode case folding also maps `k` to the kelvin sign and `s` to
+/// the long s.
+fn folds_like_ascii(trigram: u32) -> bool {
+    trigram.to_be_bytes()[1..]
+        .iter()
+        .all(|b| b.is_ascii() && *b != b'k' && *b != b's')
+}
+
+/// trigrams (as stored in the token index) that every match of `query` contains
+pub fn query_trigrams(query: &SearchQuery) -> Vec<u32> {
+    let trigrams = if query.literal {
+        token_index::trigrams(query.pattern.as_bytes())
+    } else {
+        match Parser::new().parse(&query.pattern) {
+            Ok(hir) => required(&hir).all_trigrams().into_iter().collect(),
+            Err(_) => vec![],
+        }
+    };
+    if query.ignore_case {
+        trigrams
+            .into_iter()
+            .filter(|t| folds_like_ascii(*t))
+            .collect()
+    } else {
+        trigrams
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright (c) 2019 E.S.R.Labs. All rights reserved.
+//
+// NOTICE:  All information contained herein is, and remains
+// the property of E.S.R.Labs and its suppliers, if any.
+// The intellectual and technical concepts contained herein are
+// proprietary to E.S.R.Labs and its suppliers and may be covered
+// by German and Foreign Patents, patents in process, and are protected
+// by trade secret or copyright law.
+// Dissemination of this information or reproduction of this material
+// is strictly forbidden unless prior written permission is obtained
+// from E.S.R.Labs.
+
+//! search in indexed output files
+//!
+//! all queries are run at once, every line that matches at least one of
+//! them is copied to the results file (so it keeps its original row number)
+//! and reported together with the queries it matched
+use crate::prefilter;
+use failure::{err_msg, Error};
+use indexer_base::chunks::{Chunk, ChunkFactory};
+use indexer_base::config::IndexMapConfig;
+use indexer_base::index_map;
+use indexer_base::progress::IndexingProgress;
+use indexer_base::token_index::{self, TokenIndex};
+use indexer_base::utils::{self, PLUGIN_ID_SENTINAL, ROW_NUMBER_SENTINAL};
+use regex::bytes::{RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path;
+use std::sync::mpsc::{self, TryRecvError};
+
+const REPORT_PROGRESS_LINE_BLOCK: usize = 1_000_000;
+const SHUTDOWN_CHECK_LINE_BLOCK: usize = 10_000;
+const MAX_BUFFER_CAPACITY: usize = 10 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// match the pattern as plain text instead of a regular expression
+    #[serde(default)]
+    pub literal: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+impl SearchQuery {
+    pub fn regex(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern.to_string(),
+            literal: false,
+            ignore_case: false,
+        }
+    }
+    pub fn literal(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern.to_string(),
+            literal: true,
+            ignore_case: false,
+        }
+    }
+    fn expression(&self) -> String {
+        let pattern = if self.literal {
+            regex::escape(&self.pattern)
+        } else {
+            self.pattern.clone()
+        };
+        if self.ignore_case {
+            format!("(?i){}", pattern)
+        } else {
+            pattern
+        }
+    }
+}
+
+/// one set that runs all queries in a single pass over a line
+pub fn query_set(queries: &[SearchQuery]) -> Result<RegexSet, Error> {
+    if queries.is_empty() {
+        return Err(err_msg("no search queries given"));
+    }
+    RegexSetBuilder::new(queries.iter().map(SearchQuery::expression))
+        .unicode(true)
+        .build()
+        .map_err(|e| err_msg(format!("invalid search query: {}", e)))
+}
+
+#[derive(Debug)]
+pub struct SearchConfig<'a> {
+    /// indexed output to search in
+    pub in_path: &'a path::Path,
+    /// the matching lines are written here
+    pub out_path: &'a path::PathBuf,
+    pub queries: &'a [SearchQuery],
+    pub chunk_size: usize,
+    pub to_stdout: bool,
+    /// also write a binary index map of the results
+    pub index_map: Option<IndexMapConfig<'a>>,
+    /// chunk map of `in_path`, a token index stored next to it lets the
+    /// search skip chunks that cannot contain a match
+    pub map_path: Option<&'a path::Path>,
+    /// the token index is ignored if it was not created from this file
+    pub source_path: Option<&'a path::Path>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// row number of the line in the searched output
+    pub row: usize,
+    /// line number in the results file
+    pub line: usize,
+    /// indices of the queries that matched
+    pub queries: Vec<usize>,
+}
+
+/// what is reported while searching
+#[derive(Debug, Clone)]
+pub enum SearchItem {
+    Match(SearchMatch),
+    /// chunk of the results file
+    Chunk(Chunk),
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResult {
+    /// chunks of the results file
+    pub chunks: Vec<Chunk>,
+    pub matches: usize,
+    /// number of matching lines per query
+    pub hits: Vec<usize>,
+}
+
+/// content and row number of an output line
+///
+/// lines without sentinels (plain text) are searched completely,
+/// `line_index` is used as their row number
+fn split_line(line: &[u8], line_index: usize) -> (&[u8], usize) {
+    let plugin_sentinal = PLUGIN_ID_SENTINAL as u8;
+    let tag_end = match line.iter().rposition(|b| *b == plugin_sentinal) {
+        Some(i) => i,
+        None => return (line, line_index),
+    };
+    let tag_start = match line[..tag_end].iter().rposition(|b| *b == plugin_sentinal) {
+        Some(i) => i,
+        None => return (line, line_index),
+    };
+    let row = line[tag_end + 1..]
+        .iter()
+        .skip_while(|b| **b == ROW_NUMBER_SENTINAL as u8)
+        .take_while(|b| b.is_ascii_digit())
+        .fold(None, |row: Option<usize>, b| {
+            Some(row.unwrap_or(0) * 10 + usize::from(b - b'0'))
+        });
+    (&line[..tag_start], row.unwrap_or(line_index))
+}
+
+fn stop_requested(shutdown_receiver: Option<&mpsc::Receiver<()>>) -> bool {
+    match shutdown_receiver.map(mpsc::Receiver::try_recv) {
+        // Shutdown if we have received a command or if there is
+        // nothing to send it.
+        Some(Ok(_)) | Some(Err(TryRecvError::Disconnected)) => {
+            info!("shutdown received in search");
+            true
+        }
+        // No shutdown command, continue
+        Some(Err(TryRecvError::Empty)) | None => false,
+    }
+}
+
+/// part of the searched file, `first_line` is the index of its first line
+struct ScanRange {
+    start: u64,
+    length: u64,
+    first_line: usize,
+}
+
+/// the chunks of `config.in_path` that may contain a match
+///
+/// `None` if there is no token index next to the chunk map or if it does
+/// not belong to the searched file (anymore)
+fn candidate_ranges(config: &SearchConfig, in_size: u64) -> Result<Option<Vec<ScanRange>>, Error> {
+    let map_path = match config.map_path {
+        Some(map_path) => map_path,
+        None => return Ok(None),
+    };
+    let token_path = token_index::path_for(map_path);
+    if !token_path.exists() {
+        return Ok(None);
+    }
+    let mut index = match TokenIndex::open(&token_path) {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("ignoring token index {:?}: {}", token_path, e);
+            return Ok(None);
+        }
+    };
+    let chunks = index_map::read_chunks(map_path)?;
+    if !index.matches_output(&chunks, in_size)
+        || config
+            .source_path
+            .map_or(false, |source| !index.matches_source(source))
+    {
+        warn!("token index {:?} is outdated", token_path);
+        return Ok(None);
+    }
+    let alternatives: Vec<Vec<u32>> = config
+        .queries
+        .iter()
+        .map(prefilter::query_trigrams)
+        .collect();
+    let mut ranges: Vec<ScanRange> = vec![];
+    for i in index.candidate_chunks(&alternatives)? {
+        // following chunks start one byte after the end of the previous one,
+        // their first line starts right at that end
+        let start = if i == 0 {
+            chunks[0].b.0
+        } else {
+            chunks[i - 1].b.1
+        } as u64;
+        let end = chunks[i].b.1 as u64;
+        match ranges.last_mut() {
+            Some(last) if last.start + last.length == start => last.length = end - last.start,
+            _ => ranges.push(ScanRange {
+                start,
+                length: end - start,
+                first_line: chunks[i].r.0,
+            }),
+        }
+    }
+    debug!(
+        "token index leaves {} of {} bytes in {} ranges",
+        ranges.iter().map(|r| r.length).sum::<u64>(),
+        in_size,
+        ranges.len()
+    );
+    Ok(Some(ranges))
+}
+
+/// search all lines of `config.in_path`
+///
+/// every match and every chunk of the results file is sent to `update_channel`
+pub fn search_indexed_file(
+    config: SearchConfig,
+    update_channel: Option<mpsc::Sender<IndexingProgress<SearchItem>>>,
+    shutdown_receiver: Option<mpsc::Receiver<()>>,
+) -> Result<SearchResult, Error> {
+    let set = query_set(config.queries)?;
+    let in_file = fs::File::open(config.in_path)?;
+    let source_file_size = in_file.metadata()?.len() as usize;
+    let (out_file, _) = utils::get_out_file_and_size(false, config.out_path)?;
+    let mut chunk_factory = ChunkFactory::new(config.chunk_size, config.to_stdout, 0);
+    if let Some(index_map) = &config.index_map {
+        chunk_factory =
+            chunk_factory.with_index_writer(index_map::writer_for(index_map, &in_file, false)?);
+    }
+    let (ranges, buffer_capacity) = match candidate_ranges(&config, source_file_size as u64)? {
+        Some(ranges) => {
+            // every seek drops the content of the buffer, so it should not
+            // be much larger than the ranges that are read
+            let average = ranges.iter().map(|r| r.length).sum::<u64>() as usize
+                / std::cmp::max(ranges.len(), 1);
+            (ranges, average.clamp(64 * 1024, MAX_BUFFER_CAPACITY))
+        }
+        None => (
+            vec![ScanRange {
+                start: 0,
+                length: u64::MAX,
+                first_line: 0,
+            }],
+            MAX_BUFFER_CAPACITY,
+        ),
+    };
+    let mut reader = BufReader::with_capacity(buffer_capacity, in_file);
+    let mut buf_writer = BufWriter::with_capacity(10 * 1024 * 1024, out_file);
+
+    let mut result = SearchResult {
+        hits: vec![0; config.queries.len()],
+        ..Default::default()
+    };
+    let send = |item: SearchItem| {
+        update_channel
+            .as_ref()
+            .map(|c| c.send(IndexingProgress::GotItem { item }));
+    };
+    let mut buf = vec![];
+    let mut searched_lines = 0usize;
+    let mut stopped = false;
+    'ranges: for range in ranges {
+        reader.seek(SeekFrom::Start(range.start))?;
+        let mut range_reader = (&mut reader).take(range.length);
+        let mut processed_bytes = range.start as usize;
+        let mut line_index = range.first_line;
+        loop {
+            buf.clear();
+            let len = range_reader.read_until(b'\n', &mut buf)?;
+            if len == 0 {
+                break;
+            }
+            processed_bytes += len;
+            let line = &buf[..buf.len()
+                - buf
+                    .iter()
+                    .rev()
+                    .take_while(|b| **b == b'\n' || **b == b'\r')
+                    .count()];
+            let (content, row) = split_line(line, line_index);
+            if set.is_match(content) {
+                let queries: Vec<usize> = set.matches(content).into_iter().collect();
+                for q in &queries {
+                    result.hits[*q] += 1;
+                }
+                buf_writer.write_all(line)?;
+                buf_writer.write_all(b"\n")?;
+                send(SearchItem::Match(SearchMatch {
+                    row,
+                    line: result.matches,
+                    queries,
+                }));
+                result.matches += 1;
+                if let Some(chunk) =
+                    chunk_factory.create_chunk_if_needed(result.matches, line.len() + 1)
+                {
+                    buf_writer.flush()?;
+                    send(SearchItem::Chunk(chunk.clone()));
+                    result.chunks.push(chunk);
+                }
+            }
+            line_index += 1;
+            searched_lines += 1;
+            if searched_lines % SHUTDOWN_CHECK_LINE_BLOCK == 0
+                && stop_requested(shutdown_receiver.as_ref())
+            {
+                stopped = true;
+                break 'ranges;
+            }
+            if searched_lines % REPORT_PROGRESS_LINE_BLOCK == 0 {
+                update_channel.as_ref().map(|c| {
+                    c.send(IndexingProgress::Progress {
+                        ticks: (processed_bytes, source_file_size),
+                    })
+                });
+            }
+        }
+    }
+    buf_writer.flush()?;
+    if let Some(chunk) = chunk_factory.create_last_chunk(result.matches, result.chunks.is_empty()) {
+        send(SearchItem::Chunk(chunk.clone()));
+        result.chunks.push(chunk);
+    }
+    chunk_factory.finish_index()?;
+    debug!(
+        "search done: {} matches in {} lines (stopped: {})",
+        result.matches, searched_lines, stopped
+    );
+    if let Some(tx) = update_channel {
+        tx.send(if stopped {
+            IndexingProgress::Stopped
+        } else {
+            IndexingProgress::Finished
+        })?;
+    }
+    Ok(result)
+}
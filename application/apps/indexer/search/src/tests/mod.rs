@@ -0,0 +1,2 @@
+mod prefilter_tests;
+mod searcher_tests;
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use crate::prefilter::*;
+    use crate::searcher::SearchQuery;
+    use indexer_base::token_index::trigrams;
+    use pretty_assertions::assert_eq;
+
+    fn regex_trigrams(pattern: &str) -> Vec<u32> {
+        query_trigrams(&SearchQuery::regex(pattern))
+    }
+
+    fn all_trigrams(parts: &[&str]) -> Vec<u32> {
+        let mut expected: Vec<u32> = parts.iter().flat_map(|p| trigrams(p.as_bytes())).collect();
+        expected.sort_unstable();
+        expected.dedup();
+        expected
+    }
+
+    #[test]
+    fn test_literal_queries() {
+        assert_eq!(
+            trigrams(b"a.b(c"),
+            query_trigrams(&SearchQuery::literal("a.b(c"))
+        );
+        assert!(query_trigrams(&SearchQuery::literal("ab")).is_empty());
+    }
+
+    #[test]
+    fn test_regex_queries() {
+        assert_eq!(all_trigrams(&["timeout"]), regex_trigrams("timeout"));
+        assert_eq!(all_trigrams(&["error"]), regex_trigrams(r"^error\b"));
+        // classes and optional parts interrupt the literal
+        assert_eq!(
+            all_trigrams(&["error", "code"]),
+            regex_trigrams(r"error\s+code")
+        );
+        assert_eq!(all_trigrams(&["abc", "def"]), regex_trigrams("abc(x)?def"));
+        assert_eq!(all_trigrams(&["abc", "xyz"]), regex_trigrams("abc(xyz)+"));
+        assert!(regex_trigrams("a.*b").is_empty());
+        assert!(regex_trigrams("(abc)*").is_empty());
+        // only what all alternatives have in common
+        assert_eq!(
+            all_trigrams(&["connect"]),
+            regex_trigrams("connect|connection|reconnecting")
+        );
+        assert!(regex_trigrams("abc|xyz").is_empty());
+        assert!(regex_trigrams("(?i)abc").is_empty());
+    }
+
+    #[test]
+    fn test_ignore_case_queries() {
+        let query = SearchQuery {
+            pattern: "Disk full".to_string(),
+            literal: false,
+            ignore_case: true,
+        };
+        // `s` and `k` also match non ascii characters when ignoring case
+        assert_eq!(all_trigrams(&[" fu", "ful", "ull"]), query_trigrams(&query));
+        let query = SearchQuery {
+            pattern: "grüße".to_string(),
+            literal: true,
+            ignore_case: true,
+        };
+        assert!(query_trigrams(&query).is_empty());
+    }
+}
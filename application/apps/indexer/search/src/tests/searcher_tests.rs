@@ -0,0 +1,419 @@
+#[cfg(test)]
+mod tests {
+    use crate::searcher::*;
+    use indexer_base::chunks::Chunk;
+    use indexer_base::config::{IndexMapConfig, IndexingConfig};
+    use indexer_base::indexed_file::IndexedFile;
+    use indexer_base::progress::IndexingProgress;
+    use indexer_base::token_index;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use tempdir::TempDir;
+
+    /// index `lines` with the processor and return the path of the output
+    fn indexed_output(dir: &Path, lines: &[String]) -> PathBuf {
+        let in_path = dir.join("source.log");
+        let out_path = dir.join("source.log.out");
+        fs::write(&in_path, lines.join("\n")).expect("could not write source");
+        processor::processor::index_file(
+            IndexingConfig {
+                tag: "TAG",
+                chunk_size: 5,
+                in_file: fs::File::open(&in_path).expect("could not open source"),
+                zip_entry: None,
+                out_path: &out_path,
+                append: false,
+                to_stdout: false,
+                index_map: None,
+                token_index: None,
+                follow: None,
+            },
+            0,
+            false,
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("could not index source");
+        out_path
+    }
+
+    struct Search {
+        result: SearchResult,
+        matches: Vec<SearchMatch>,
+        chunks: Vec<Chunk>,
+        stopped: bool,
+    }
+
+    fn search(
+        in_path: &Path,
+        out_path: &PathBuf,
+        queries: &[SearchQuery],
+        shutdown_receiver: Option<mpsc::Receiver<()>>,
+    ) -> Search {
+        let (tx, rx) = mpsc::channel();
+        let result = search_indexed_file(
+            SearchConfig {
+                in_path,
+                out_path,
+                queries,
+                chunk_size: 3,
+                to_stdout: false,
+                index_map: None,
+                map_path: None,
+                source_path: None,
+            },
+            Some(tx),
+            shutdown_receiver,
+        )
+        .expect("search failed");
+        let mut search = Search {
+            result,
+            matches: vec![],
+            chunks: vec![],
+            stopped: false,
+        };
+        for event in rx.iter() {
+            match event {
+                IndexingProgress::GotItem {
+                    item: SearchItem::Match(m),
+                } => search.matches.push(m),
+                IndexingProgress::GotItem {
+                    item: SearchItem::Chunk(c),
+                } => search.chunks.push(c),
+                IndexingProgress::Stopped => search.stopped = true,
+                _ => (),
+            }
+        }
+        search
+    }
+
+    fn source_lines() -> Vec<String> {
+        (0..30)
+            .map(|i| match i % 5 {
+                0 => format!("{} ERROR code {}", i, 100 + i),
+                1 => format!("{} warn: a.b.c", i),
+                2 => format!("{} info axbxc", i),
+                3 => format!("{} WARN error 7", i),
+                _ => format!("{} debug TAG", i),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multiple_queries() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let lines = source_lines();
+        let in_path = indexed_output(tmp_dir.path(), &lines);
+        let out_path = tmp_dir.path().join("results.out");
+        let queries = vec![
+            SearchQuery::regex(r"error \d+"),
+            SearchQuery::literal("a.b"),
+            SearchQuery {
+                pattern: "warn".to_string(),
+                literal: true,
+                ignore_case: true,
+            },
+        ];
+        let search = search(&in_path, &out_path, &queries, None);
+
+        let expected: Vec<(usize, Vec<usize>)> = (0..30)
+            .filter_map(|row| match row % 5 {
+                1 => Some((row, vec![1, 2])),
+                3 => Some((row, vec![0, 2])),
+                _ => None,
+            })
+            .collect();
+        let found: Vec<(usize, Vec<usize>)> = search
+            .matches
+            .iter()
+            .map(|m| (m.row, m.queries.clone()))
+            .collect();
+        assert_eq!(expected, found);
+        assert_eq!(
+            (0..12).collect::<Vec<usize>>(),
+            search
+                .matches
+                .iter()
+                .map(|m| m.line)
+                .collect::<Vec<usize>>()
+        );
+        assert_eq!(12, search.result.matches);
+        assert_eq!(vec![6, 6, 12], search.result.hits);
+        assert!(!search.stopped);
+
+        let ranges =
+            |chunks: &[Chunk]| -> Vec<(usize, usize)> { chunks.iter().map(|c| c.r).collect() };
+        assert_eq!(ranges(&search.result.chunks), ranges(&search.chunks));
+        assert_eq!(
+            vec![(0, 2), (3, 5), (6, 8), (9, 11)],
+            ranges(&search.chunks)
+        );
+        let mut results = IndexedFile::with_chunks(&out_path, search.result.chunks)
+            .expect("could not open results");
+        for (line_nr, (row, _)) in expected.iter().enumerate().rev() {
+            let line = results
+                .get_line(line_nr)
+                .expect("could not read result")
+                .expect("result is missing");
+            assert_eq!(*row, line.row);
+            assert_eq!(lines[*row], line.content);
+            assert_eq!("TAG", line.tag);
+        }
+    }
+
+    #[test]
+    fn test_tag_and_row_are_not_searched() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let lines: Vec<String> = (0..10).map(|i| format!("line {}", i)).collect();
+        let in_path = indexed_output(tmp_dir.path(), &lines);
+        let out_path = tmp_dir.path().join("results.out");
+        let search = search(
+            &in_path,
+            &out_path,
+            &[SearchQuery::literal("TAG"), SearchQuery::regex("7$")],
+            None,
+        );
+        assert_eq!(
+            vec![7],
+            search.matches.iter().map(|m| m.row).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0, 1], search.result.hits);
+    }
+
+    #[test]
+    fn test_search_plain_file() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("plain.log");
+        fs::write(&in_path, "first\r\nsecond\nthird match\nmatch").expect("could not write");
+        let out_path = tmp_dir.path().join("results.out");
+        let search = search(&in_path, &out_path, &[SearchQuery::literal("match")], None);
+        assert_eq!(
+            vec![2, 3],
+            search.matches.iter().map(|m| m.row).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "third match\nmatch\n",
+            fs::read_to_string(&out_path).expect("could not read results")
+        );
+    }
+
+    #[test]
+    fn test_empty_results() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = indexed_output(tmp_dir.path(), &source_lines());
+        let out_path = tmp_dir.path().join("results.out");
+        let search = search(
+            &in_path,
+            &out_path,
+            &[SearchQuery::literal("nowhere")],
+            None,
+        );
+        assert_eq!(0, search.result.matches);
+        assert!(search.result.chunks.is_empty());
+        assert_eq!(0, fs::metadata(&out_path).expect("no results").len());
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = indexed_output(tmp_dir.path(), &source_lines());
+        let out_path = tmp_dir.path().join("results.out");
+        let run = |queries: &[SearchQuery]| {
+            search_indexed_file(
+                SearchConfig {
+                    in_path: &in_path,
+                    out_path: &out_path,
+                    queries,
+                    chunk_size: 3,
+                    to_stdout: false,
+                    index_map: None,
+                    map_path: None,
+                    source_path: None,
+                },
+                None,
+                None,
+            )
+        };
+        assert!(run(&[]).is_err());
+        assert!(run(&[SearchQuery::regex("a(b")]).is_err());
+        assert!(run(&[SearchQuery::literal("a(b")]).is_ok());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let in_path = tmp_dir.path().join("large.log");
+        let content: String = (0..50_000).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&in_path, content).expect("could not write");
+        let out_path = tmp_dir.path().join("results.out");
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        shutdown_tx.send(()).expect("could not send shutdown");
+        let search = search(
+            &in_path,
+            &out_path,
+            &[SearchQuery::regex("line")],
+            Some(shutdown_rx),
+        );
+        assert!(search.stopped);
+        assert!(search.result.matches < 50_000);
+        assert_eq!(search.result.matches, search.matches.len());
+    }
+
+    /// index all `sources` one after the other into the same output, with a
+    /// binary index map and a token index next to it
+    ///
+    /// returns the paths of the output and of the index map
+    fn indexed_output_with_tokens(dir: &Path, sources: &[Vec<String>]) -> (PathBuf, PathBuf) {
+        let out_path = dir.join("source.log.out");
+        let map_path = dir.join("source.log.map.idx");
+        let token_path = token_index::path_for(&map_path);
+        for (i, lines) in sources.iter().enumerate() {
+            let in_path = dir.join(format!("source_{}.log", i));
+            fs::write(&in_path, lines.join("\n")).expect("could not write source");
+            processor::processor::create_index_and_mapping(
+                IndexingConfig {
+                    tag: "TAG",
+                    chunk_size: 5,
+                    in_file: fs::File::open(&in_path).expect("could not open source"),
+                    zip_entry: None,
+                    out_path: &out_path,
+                    append: i > 0,
+                    to_stdout: false,
+                    index_map: Some(IndexMapConfig {
+                        path: &map_path,
+                        line_offsets: false,
+                    }),
+                    token_index: Some(&token_path),
+                    follow: None,
+                },
+                false,
+                None,
+                None,
+                None,
+                None,
+            )
+            .expect("could not index source");
+        }
+        (out_path, map_path)
+    }
+
+    /// rows of all matches
+    fn search_rows(
+        in_path: &Path,
+        queries: &[SearchQuery],
+        map_path: Option<&Path>,
+        source_path: Option<&Path>,
+    ) -> Vec<usize> {
+        let (tx, rx) = mpsc::channel();
+        let out_path = in_path.with_extension("search");
+        search_indexed_file(
+            SearchConfig {
+                in_path,
+                out_path: &out_path,
+                queries,
+                chunk_size: 3,
+                to_stdout: false,
+                index_map: None,
+                map_path,
+                source_path,
+            },
+            Some(tx),
+            None,
+        )
+        .expect("search failed");
+        rx.iter()
+            .filter_map(|event| match event {
+                IndexingProgress::GotItem {
+                    item: SearchItem::Match(m),
+                } => Some(m.row),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_token_index_finds_the_same_matches() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let appended: Vec<String> = (0..12)
+            .map(|i| format!("{} appended line {}", i, if i == 7 { "needle" } else { "" }))
+            .collect();
+        let (out_path, map_path) =
+            indexed_output_with_tokens(tmp_dir.path(), &[source_lines(), appended]);
+        let queries = vec![
+            SearchQuery::literal("needle"),
+            SearchQuery::regex(r"ERROR code 1[0-2]\d"),
+            SearchQuery {
+                pattern: "WARN".to_string(),
+                literal: true,
+                ignore_case: true,
+            },
+            SearchQuery::regex("nowhere|never"),
+        ];
+        for query in queries {
+            let query = [query];
+            let expected = search_rows(&out_path, &query, None, None);
+            assert!(!expected.is_empty() || query[0].pattern == "nowhere|never");
+            assert_eq!(
+                expected,
+                search_rows(&out_path, &query, Some(&map_path), None),
+                "query: {:?}",
+                query
+            );
+        }
+        // row numbers continue in the appended part
+        assert_eq!(
+            vec![37],
+            search_rows(
+                &out_path,
+                &[SearchQuery::literal("needle")],
+                Some(&map_path),
+                Some(&tmp_dir.path().join("source_1.log")),
+            )
+        );
+    }
+
+    #[test]
+    fn test_token_index_skips_chunks() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let (out_path, map_path) = indexed_output_with_tokens(tmp_dir.path(), &[source_lines()]);
+        // change the output behind the back of the token index (same size), the
+        // chunks that did not contain the term before are not searched anymore
+        let output = fs::read_to_string(&out_path).expect("could not read output");
+        fs::write(&out_path, output.replace("axbxc", "zzzzz")).expect("could not write output");
+        let query = [SearchQuery::literal("zzzzz")];
+        assert_eq!(6, search_rows(&out_path, &query, None, None).len());
+        assert!(search_rows(&out_path, &query, Some(&map_path), None).is_empty());
+
+        // the token index is only used if it belongs to the given source
+        let source_path = tmp_dir.path().join("source_0.log");
+        assert!(search_rows(&out_path, &query, Some(&map_path), Some(&source_path)).is_empty());
+        fs::write(&source_path, "changed").expect("could not write source");
+        assert_eq!(
+            6,
+            search_rows(&out_path, &query, Some(&map_path), Some(&source_path)).len()
+        );
+    }
+
+    #[test]
+    fn test_outdated_token_index_is_ignored() {
+        let tmp_dir = TempDir::new("test_dir").expect("could not create temp dir");
+        let (out_path, map_path) = indexed_output_with_tokens(tmp_dir.path(), &[source_lines()]);
+        let mut output = fs::read_to_string(&out_path).expect("could not read output");
+        output.push_str("\nneedle");
+        fs::write(&out_path, output).expect("could not write output");
+        assert_eq!(
+            1,
+            search_rows(
+                &out_path,
+                &[SearchQuery::literal("needle")],
+                Some(&map_path),
+                None
+            )
+            .len()
+        );
+    }
+}